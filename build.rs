@@ -0,0 +1,18 @@
+// Checks that the usb.ids snapshot is present when the `embedded-usb-ids`
+// feature asks for it. The snapshot is only in the git repository, so
+// without this check a build from the published package would fail with a
+// bare "couldn't read data/usb.ids" error.
+
+use std::path::Path;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=data");
+    println!("cargo:rustc-check-cfg=cfg(usb_ids_missing)");
+
+    if std::env::var_os("CARGO_FEATURE_EMBEDDED_USB_IDS").is_some()
+        && !Path::new("data/usb.ids").is_file()
+    {
+        println!("cargo:rustc-cfg=usb_ids_missing");
+    }
+}
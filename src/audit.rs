@@ -0,0 +1,441 @@
+//! Tamper-evident, hash-chained audit log.
+//!
+//! Each line of an audit log is an [`AuditRecord`]: the event exactly as serialised, a sequence number, the hash of
+//! the previous record and a SHA-256 hash over the previous hash, the sequence number and the event. Editing,
+//! inserting, removing or reordering a record breaks the chain from that point on, which [`verify_log`] reports.
+//!
+//! With the `signing` feature, every Nth record can also carry an Ed25519 signature over its hash. Because each hash
+//! covers everything before it, a valid signature vouches for the whole log up to that checkpoint, so the log cannot
+//! be rewritten from scratch by someone without the key either. Verifying with the public key and the checkpoint
+//! interval the log was written with also requires every checkpoint to be signed, so signatures cannot simply be
+//! dropped from a rewritten log. Keys are stored as 64 hexadecimal characters (the 32-byte secret seed, or the public
+//! key).
+
+use crate::device_info::UsbDeviceInfo;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+/// Previous hash of the first record in a log.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Default number of records between signed checkpoints.
+pub const DEFAULT_CHECKPOINT_INTERVAL: u64 = 100;
+
+/// One line of an audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Position in the log, starting at 1
+    pub seq: u64,
+    /// Hash of the previous record, or [`GENESIS_HASH`] for the first
+    pub prev_hash: String,
+    /// The event, kept byte for byte as it was hashed
+    pub event: Box<RawValue>,
+    /// SHA-256 of this record, see [`chain_hash`]
+    pub hash: String,
+    /// Ed25519 signature over `hash` (checkpoint records only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// Computes a record hash: SHA-256 over the previous hash, the sequence
+/// number and the event JSON, separated by newlines, as lowercase hex.
+///
+/// # Examples
+///
+/// ```
+/// use usbwatch_rs::audit::{chain_hash, GENESIS_HASH};
+///
+/// let first = chain_hash(GENESIS_HASH, 1, r#"{"device_name":"USB Storage"}"#);
+/// assert_eq!(first.len(), 64);
+/// assert_ne!(first, chain_hash(GENESIS_HASH, 2, r#"{"device_name":"USB Storage"}"#));
+/// ```
+pub fn chain_hash(prev_hash: &str, seq: u64, event_json: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(prev_hash.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(seq.to_string().as_bytes());
+    hasher.update(b"\n");
+    hasher.update(event_json.as_bytes());
+    to_hex(&hasher.finalize())
+}
+
+/// Appends events to an audit log, continuing the chain of an existing file.
+pub struct AuditLog {
+    file: File,
+    seq: u64,
+    last_hash: String,
+    #[cfg(feature = "signing")]
+    signer: Option<(ed25519_dalek::SigningKey, u64)>,
+}
+
+impl AuditLog {
+    /// Opens (or creates) an audit log for appending.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened, or if its last line is
+    /// not an audit record the chain can continue from.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let mut seq = 0;
+        let mut last_hash = GENESIS_HASH.to_string();
+        if path.exists() {
+            let reader = BufReader::new(
+                File::open(path)
+                    .map_err(|e| format!("Failed to open audit log '{}': {e}", path.display()))?,
+            );
+            let mut last_line = None;
+            for line in reader.lines() {
+                let line = line?;
+                if !line.trim().is_empty() {
+                    last_line = Some(line);
+                }
+            }
+            if let Some(line) = last_line {
+                let record: AuditRecord = serde_json::from_str(&line).map_err(|e| {
+                    format!(
+                        "Cannot continue audit log '{}': last line is not a record: {e}",
+                        path.display()
+                    )
+                })?;
+                seq = record.seq;
+                last_hash = record.hash;
+            }
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Failed to open audit log '{}': {e}", path.display()))?;
+        Ok(Self {
+            file,
+            seq,
+            last_hash,
+            #[cfg(feature = "signing")]
+            signer: None,
+        })
+    }
+
+    /// Signs every `interval`th record with `secret_key`, a 32-byte Ed25519
+    /// secret seed.
+    #[cfg(feature = "signing")]
+    pub fn with_signing_key(mut self, secret_key: &[u8; 32], interval: u64) -> Self {
+        self.signer = Some((
+            ed25519_dalek::SigningKey::from_bytes(secret_key),
+            interval.max(1),
+        ));
+        self
+    }
+
+    /// Appends an event and returns the written record.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialisation or the write fails.
+    pub fn append(
+        &mut self,
+        device_info: &UsbDeviceInfo,
+    ) -> Result<AuditRecord, Box<dyn std::error::Error>> {
+        let event = serde_json::to_string(device_info)?;
+        let seq = self.seq + 1;
+        let hash = chain_hash(&self.last_hash, seq, &event);
+
+        #[allow(unused_mut)]
+        let mut signature = None;
+        #[cfg(feature = "signing")]
+        if let Some((key, interval)) = &self.signer {
+            if seq.is_multiple_of(*interval) {
+                use ed25519_dalek::Signer;
+                signature = Some(to_hex(&key.sign(hash.as_bytes()).to_bytes()));
+            }
+        }
+
+        let record = AuditRecord {
+            seq,
+            prev_hash: std::mem::replace(&mut self.last_hash, hash.clone()),
+            event: RawValue::from_string(event)?,
+            hash,
+            signature,
+        };
+        writeln!(self.file, "{}", serde_json::to_string(&record)?)?;
+        self.file.flush()?;
+        self.seq = seq;
+        Ok(record)
+    }
+}
+
+/// Where and why an audit log's chain is broken.
+#[derive(Debug, Clone, Serialize)]
+pub struct BrokenLink {
+    /// Line number in the file, starting at 1
+    pub line: usize,
+    /// Sequence number of the record, if it could be parsed
+    pub seq: Option<u64>,
+    /// What is wrong with the record
+    pub reason: String,
+}
+
+/// Result of verifying an audit log.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VerifyReport {
+    /// Records verified before the first broken link
+    pub records: u64,
+    /// Checkpoint signatures verified with the public key
+    pub signatures: u64,
+    /// Checkpoint signatures seen but not verified (no public key given)
+    pub unchecked_signatures: u64,
+    /// Records after the last verified checkpoint, which only the chain
+    /// protects (set when a public key is given)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unsigned_tail: Option<u64>,
+    /// Hash of the last valid record
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_hash: Option<String>,
+    /// The first broken link, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub broken: Option<BrokenLink>,
+}
+
+impl VerifyReport {
+    /// Returns whether the whole log verified; with a public key, at least
+    /// one checkpoint signature must cover it.
+    pub fn is_valid(&self) -> bool {
+        self.broken.is_none() && !self.is_unsigned()
+    }
+
+    /// Returns whether a public key was given but no signature covers the log.
+    fn is_unsigned(&self) -> bool {
+        self.signatures == 0 && self.unsigned_tail.unwrap_or(0) > 0
+    }
+
+    /// Renders the report as plain text.
+    pub fn render_text(&self) -> String {
+        let mut out = String::new();
+        match &self.broken {
+            None if self.is_unsigned() => {
+                let _ = writeln!(
+                    out,
+                    "UNSIGNED: {} record(s), chain intact but no checkpoint signature covers it",
+                    self.records
+                );
+            }
+            None => {
+                let _ = writeln!(out, "OK: {} record(s), chain intact", self.records);
+            }
+            Some(broken) => {
+                let _ = writeln!(
+                    out,
+                    "BROKEN at line {}{}: {}",
+                    broken.line,
+                    broken
+                        .seq
+                        .map(|seq| format!(" (seq {seq})"))
+                        .unwrap_or_default(),
+                    broken.reason
+                );
+                let _ = writeln!(out, "{} record(s) verified before the break", self.records);
+            }
+        }
+        if self.signatures > 0 {
+            let _ = writeln!(out, "{} checkpoint signature(s) verified", self.signatures);
+            if let Some(tail) = self.unsigned_tail.filter(|tail| *tail > 0) {
+                let _ = writeln!(
+                    out,
+                    "{tail} record(s) after the last checkpoint are protected by the chain only"
+                );
+            }
+        }
+        if self.unchecked_signatures > 0 {
+            let _ = writeln!(
+                out,
+                "{} checkpoint signature(s) not checked (no public key given)",
+                self.unchecked_signatures
+            );
+        }
+        if let Some(hash) = &self.last_hash {
+            let _ = writeln!(out, "Last hash: {hash}");
+        }
+        out
+    }
+}
+
+/// Verifies an audit log read line by line, stopping at the first broken
+/// link.
+///
+/// When `public_key` is given, checkpoint signatures are verified too, and
+/// every `checkpoint_interval`th record must carry one; otherwise signatures
+/// are counted as unchecked.
+///
+/// # Errors
+///
+/// Returns an error if the input cannot be read, the public key is invalid or
+/// one is given without the `signing` feature; a broken chain is reported in
+/// the returned [`VerifyReport`].
+pub fn verify_log<R: BufRead>(
+    reader: R,
+    public_key: Option<&[u8; 32]>,
+    checkpoint_interval: u64,
+) -> Result<VerifyReport, Box<dyn std::error::Error>> {
+    #[cfg(feature = "signing")]
+    let verifying_key = public_key
+        .map(ed25519_dalek::VerifyingKey::from_bytes)
+        .transpose()
+        .map_err(|e| format!("Invalid public key: {e}"))?;
+    #[cfg(not(feature = "signing"))]
+    if public_key.is_some() {
+        return Err("Verifying checkpoint signatures requires the signing feature".into());
+    }
+    let checkpoint_interval = checkpoint_interval.max(1);
+
+    let mut report = VerifyReport {
+        unsigned_tail: public_key.map(|_| 0),
+        ..Default::default()
+    };
+    let mut prev_hash = GENESIS_HASH.to_string();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let broken = |seq: Option<u64>, reason: String| BrokenLink {
+            line: index + 1,
+            seq,
+            reason,
+        };
+
+        let record: AuditRecord = match serde_json::from_str(&line) {
+            Ok(record) => record,
+            Err(e) => {
+                report.broken = Some(broken(None, format!("not an audit record: {e}")));
+                break;
+            }
+        };
+        let seq = Some(record.seq);
+        let expected_seq = report.records + 1;
+
+        let problem = if record.seq != expected_seq {
+            Some(format!(
+                "sequence number {} where {expected_seq} was expected",
+                record.seq
+            ))
+        } else if record.prev_hash != prev_hash {
+            Some("previous hash does not match the preceding record".to_string())
+        } else if record.hash != chain_hash(&record.prev_hash, record.seq, record.event.get()) {
+            Some("hash does not match the record's content".to_string())
+        } else {
+            None
+        };
+        if let Some(reason) = problem {
+            report.broken = Some(broken(seq, reason));
+            break;
+        }
+
+        if let Some(signature) = &record.signature {
+            #[cfg(feature = "signing")]
+            if let Some(key) = &verifying_key {
+                if let Err(reason) = verify_signature(key, &record.hash, signature) {
+                    report.broken = Some(broken(seq, reason));
+                    break;
+                }
+                report.signatures += 1;
+                report.unsigned_tail = Some(0);
+            } else {
+                report.unchecked_signatures += 1;
+            }
+            #[cfg(not(feature = "signing"))]
+            {
+                let _ = signature;
+                report.unchecked_signatures += 1;
+            }
+        } else if public_key.is_some() && record.seq.is_multiple_of(checkpoint_interval) {
+            // A rewritten log could otherwise just leave the signatures out
+            report.broken = Some(broken(
+                seq,
+                format!("checkpoint record is not signed (every {checkpoint_interval} records)"),
+            ));
+            break;
+        }
+
+        report.records += 1;
+        if let Some(tail) = &mut report.unsigned_tail {
+            if record.signature.is_none() {
+                *tail += 1;
+            }
+        }
+        prev_hash = record.hash;
+        report.last_hash = Some(prev_hash.clone());
+    }
+    Ok(report)
+}
+
+#[cfg(feature = "signing")]
+fn verify_signature(
+    key: &ed25519_dalek::VerifyingKey,
+    hash: &str,
+    signature: &str,
+) -> Result<(), String> {
+    let bytes: [u8; 64] = from_hex(signature)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("malformed checkpoint signature")?;
+    key.verify_strict(
+        hash.as_bytes(),
+        &ed25519_dalek::Signature::from_bytes(&bytes),
+    )
+    .map_err(|_| "checkpoint signature is not valid for the public key".to_string())
+}
+
+/// Generates a new Ed25519 key pair, returning the secret seed and the
+/// public key.
+#[cfg(feature = "signing")]
+pub fn generate_keypair() -> ([u8; 32], [u8; 32]) {
+    let key = ed25519_dalek::SigningKey::generate(&mut rand_core::OsRng);
+    (key.to_bytes(), key.verifying_key().to_bytes())
+}
+
+/// Reads a 32-byte key stored as hexadecimal text.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or does not hold 64 hex digits.
+pub fn load_key<P: AsRef<Path>>(path: P) -> Result<[u8; 32], Box<dyn std::error::Error>> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read key '{}': {e}", path.display()))?;
+    from_hex(text.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| format!("Key '{}' is not 64 hexadecimal digits", path.display()).into())
+}
+
+/// Formats bytes as lowercase hex.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut out, byte| {
+        let _ = write!(out, "{byte:02x}");
+        out
+    })
+}
+
+/// Parses hex text into bytes.
+///
+/// # Errors
+///
+/// Returns an error if the text has an odd length or a non-hex digit.
+pub fn from_hex(text: &str) -> Result<Vec<u8>, String> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return Err(format!("Invalid hex '{text}'"));
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&text[i..i + 2], 16).map_err(|_| format!("Invalid hex '{text}'"))
+        })
+        .collect()
+}
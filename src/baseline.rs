@@ -0,0 +1,301 @@
+//! Known-device baselines and drift detection.
+//!
+//! A [`Baseline`] is a snapshot of the devices attached to a machine, saved as JSON with the same serialization as
+//! events. Comparing a later scan against it with [`Baseline::diff`] yields a [`DriftReport`] of added, removed and
+//! changed devices (for example a different serial number in the same port), and [`tag_task`] marks live events as
+//! known or unknown.
+
+use crate::device_info::UsbDeviceInfo;
+use crate::usb_class::ClassCode;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::{Arc, PoisonError, RwLock};
+use tokio::sync::mpsc;
+
+/// Whether an event's device is part of the baseline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BaselineStatus {
+    /// The device matches a baseline device
+    Known,
+    /// The device is not in the baseline
+    Unknown,
+}
+
+impl fmt::Display for BaselineStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BaselineStatus::Known => write!(f, "known"),
+            BaselineStatus::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// A saved set of attached devices.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Baseline {
+    /// When the baseline was captured
+    pub created_at: DateTime<Utc>,
+    /// Devices attached at capture time
+    pub devices: Vec<UsbDeviceInfo>,
+}
+
+/// A device present in both the baseline and the current scan whose identity
+/// differs.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceChange {
+    /// The device as recorded in the baseline
+    pub baseline: UsbDeviceInfo,
+    /// The device as currently attached
+    pub current: UsbDeviceInfo,
+    /// Names of the fields that differ (e.g. `serial_number`, `port_path`)
+    pub fields: Vec<String>,
+}
+
+/// Differences between a baseline and the currently attached devices.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DriftReport {
+    /// Devices that are attached but not in the baseline
+    pub added: Vec<UsbDeviceInfo>,
+    /// Baseline devices that are no longer attached
+    pub removed: Vec<UsbDeviceInfo>,
+    /// Devices whose identity changed
+    pub changed: Vec<DeviceChange>,
+}
+
+impl Baseline {
+    /// Creates a baseline from the currently attached devices.
+    pub fn new(devices: Vec<UsbDeviceInfo>) -> Self {
+        Self {
+            created_at: Utc::now(),
+            devices,
+        }
+    }
+
+    /// Loads a baseline from a JSON file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid baseline.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read baseline '{}': {e}", path.display()))?;
+        serde_json::from_str(&json)
+            .map_err(|e| format!("Invalid baseline '{}': {e}", path.display()).into())
+    }
+
+    /// Saves the baseline as pretty-printed JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json + "\n")
+            .map_err(|e| format!("Failed to write baseline '{}': {e}", path.display()))?;
+        Ok(())
+    }
+
+    /// Returns whether a device is part of the baseline.
+    ///
+    /// A device is known if a baseline device has the same vendor ID, product
+    /// ID and serial number. Devices without a serial number must also be in
+    /// the same port, since nothing else tells identical devices apart.
+    pub fn is_known(&self, device: &UsbDeviceInfo) -> bool {
+        self.devices.iter().any(|known| {
+            known.device_key() == device.device_key()
+                && (device.serial_number.is_some() || known.port_path == device.port_path)
+        })
+    }
+
+    /// Returns the baseline status of a device.
+    pub fn status(&self, device: &UsbDeviceInfo) -> BaselineStatus {
+        if self.is_known(device) {
+            BaselineStatus::Known
+        } else {
+            BaselineStatus::Unknown
+        }
+    }
+
+    /// Compares the baseline with the currently attached devices.
+    ///
+    /// Devices are paired by port first, so a different device in a known
+    /// port is reported as changed; the remaining devices are paired by
+    /// VID:PID:serial, so a device that moved port is reported as changed
+    /// too. Whatever is left is added or removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use usbwatch_rs::baseline::Baseline;
+    /// use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+    ///
+    /// let mut drive = UsbDeviceInfo::new(
+    ///     "USB Storage".to_string(),
+    ///     "0781".to_string(),
+    ///     "5583".to_string(),
+    ///     Some("A1".to_string()),
+    ///     DeviceEventType::Connected,
+    /// );
+    /// drive.port_path = Some("1-1".to_string());
+    /// let baseline = Baseline::new(vec![drive.clone()]);
+    ///
+    /// drive.serial_number = Some("B2".to_string());
+    /// let report = baseline.diff(&[drive]);
+    /// assert_eq!(report.changed[0].fields, vec!["serial_number"]);
+    /// assert!(report.has_drift());
+    /// ```
+    pub fn diff(&self, current: &[UsbDeviceInfo]) -> DriftReport {
+        let mut report = DriftReport::default();
+        let mut remaining: Vec<&UsbDeviceInfo> = current.iter().collect();
+        let mut unpaired = Vec::new();
+
+        for known in &self.devices {
+            let paired = known.port_path.as_ref().and_then(|port| {
+                remaining
+                    .iter()
+                    .position(|device| device.port_path.as_ref() == Some(port))
+            });
+            match paired {
+                Some(index) => report.compare(known, remaining.remove(index)),
+                None => unpaired.push(known),
+            }
+        }
+
+        for known in unpaired {
+            match remaining
+                .iter()
+                .position(|device| device.device_key() == known.device_key())
+            {
+                Some(index) => report.compare(known, remaining.remove(index)),
+                None => report.removed.push(known.clone()),
+            }
+        }
+
+        report.added = remaining.into_iter().cloned().collect();
+        report
+    }
+}
+
+impl DriftReport {
+    /// Returns whether the attached devices differ from the baseline.
+    pub fn has_drift(&self) -> bool {
+        !self.added.is_empty() || !self.removed.is_empty() || !self.changed.is_empty()
+    }
+
+    fn compare(&mut self, baseline: &UsbDeviceInfo, current: &UsbDeviceInfo) {
+        let fields = identity_differences(baseline, current);
+        if !fields.is_empty() {
+            self.changed.push(DeviceChange {
+                baseline: baseline.clone(),
+                current: current.clone(),
+                fields,
+            });
+        }
+    }
+
+    /// Renders the report as plain text, one line per difference.
+    pub fn render_text(&self) -> String {
+        if !self.has_drift() {
+            return "No drift: attached devices match the baseline\n".to_string();
+        }
+        let describe = |device: &UsbDeviceInfo| {
+            format!(
+                "{} ({}:{}, serial {}, port {})",
+                device.device_name,
+                device.vendor_id,
+                device.product_id,
+                device.serial_number.as_deref().unwrap_or("-"),
+                device.port_path.as_deref().unwrap_or("-")
+            )
+        };
+
+        let mut out = String::new();
+        for device in &self.added {
+            let _ = writeln!(out, "+ added    {}", describe(device));
+        }
+        for device in &self.removed {
+            let _ = writeln!(out, "- removed  {}", describe(device));
+        }
+        for change in &self.changed {
+            let _ = writeln!(out, "~ changed  {}", describe(&change.baseline));
+            let _ = writeln!(
+                out,
+                "           now {} [{}]",
+                describe(&change.current),
+                change.fields.join(", ")
+            );
+        }
+        let _ = writeln!(
+            out,
+            "Drift: {} added, {} removed, {} changed",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        );
+        out
+    }
+}
+
+/// Lists the identity fields that differ between two records of a device.
+fn identity_differences(baseline: &UsbDeviceInfo, current: &UsbDeviceInfo) -> Vec<String> {
+    let interface_classes = |device: &UsbDeviceInfo| -> Vec<ClassCode> {
+        device
+            .interfaces
+            .iter()
+            .map(|interface| interface.class)
+            .collect()
+    };
+
+    let mut fields = Vec::new();
+    let mut check = |name: &str, same: bool| {
+        if !same {
+            fields.push(name.to_string());
+        }
+    };
+    check("vendor_id", baseline.vendor_id == current.vendor_id);
+    check("product_id", baseline.product_id == current.product_id);
+    check(
+        "serial_number",
+        baseline.serial_number == current.serial_number,
+    );
+    check("port_path", baseline.port_path == current.port_path);
+    check(
+        "device_class",
+        baseline.device_class == current.device_class,
+    );
+    check(
+        "interfaces",
+        interface_classes(baseline) == interface_classes(current),
+    );
+    fields
+}
+
+/// Tags each event as known or unknown to the baseline and passes it on.
+///
+/// The baseline is shared so it can be replaced while the task runs (e.g. on
+/// a daemon reload); each event is tagged against the current baseline.
+///
+/// Runs until the input channel closes or the output channel's receiver is
+/// dropped.
+pub async fn tag_task(
+    mut rx: mpsc::Receiver<UsbDeviceInfo>,
+    tx: mpsc::Sender<UsbDeviceInfo>,
+    baseline: Arc<RwLock<Baseline>>,
+) {
+    while let Some(mut event) = rx.recv().await {
+        let status = baseline
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .status(&event);
+        event.baseline = Some(status);
+        if tx.send(event).await.is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,281 @@
+//! The agent side: streams events to a collector.
+
+use super::{read_frame, write_frame, Connection, Frame};
+use crate::device_info::UsbDeviceInfo;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+
+/// Default number of unacknowledged events an agent keeps.
+pub const DEFAULT_BUFFER_SIZE: usize = 10_000;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// How long a stopping agent keeps trying to deliver buffered events.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Collector and buffering of an agent.
+#[derive(Clone)]
+pub struct AgentOptions {
+    /// Collector address, `host:port`
+    pub upstream: String,
+    /// Host name the collector tags the events with
+    pub host: String,
+    /// Number of unacknowledged events kept while the collector is
+    /// unreachable; the oldest are dropped beyond it
+    pub buffer_size: usize,
+    /// Connect with TLS, optionally presenting a client certificate
+    #[cfg(feature = "tls")]
+    pub tls: Option<super::ClientTls>,
+}
+
+impl AgentOptions {
+    /// Creates options with the default buffer size and no TLS.
+    pub fn new(upstream: impl Into<String>, host: impl Into<String>) -> Self {
+        Self {
+            upstream: upstream.into(),
+            host: host.into(),
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+}
+
+/// Async task that streams each event from `rx` to a collector and forwards
+/// it to `tx`.
+///
+/// Events are buffered while the collector is unreachable and sent once the
+/// connection is re-established. When `rx` closes or `tx` is dropped, the
+/// task gives the collector a few seconds to acknowledge what is still
+/// buffered before it ends.
+pub async fn agent_task(
+    mut rx: mpsc::Receiver<UsbDeviceInfo>,
+    tx: mpsc::Sender<UsbDeviceInfo>,
+    options: AgentOptions,
+) {
+    let (uplink_tx, uplink_rx) = mpsc::unbounded_channel();
+    let uplink = tokio::spawn(Uplink::new(&options).run(uplink_rx, options));
+    while let Some(event) = rx.recv().await {
+        let _ = uplink_tx.send(event.clone());
+        if tx.send(event).await.is_err() {
+            break;
+        }
+    }
+    drop(uplink_tx);
+    let _ = tokio::time::timeout(FLUSH_TIMEOUT, uplink).await;
+}
+
+/// Events waiting for acknowledgement, numbered within one session.
+struct Uplink {
+    session: String,
+    pending: VecDeque<(u64, UsbDeviceInfo)>,
+    next_seq: u64,
+    capacity: usize,
+    dropped: u64,
+    closed: bool,
+}
+
+impl Uplink {
+    fn new(options: &AgentOptions) -> Self {
+        let now = chrono::Utc::now();
+        Self {
+            session: now.timestamp_nanos_opt().unwrap_or_default().to_string(),
+            pending: VecDeque::new(),
+            next_seq: 1,
+            capacity: options.buffer_size.max(1),
+            dropped: 0,
+            closed: false,
+        }
+    }
+
+    /// Buffers `event`, dropping the oldest one if the buffer is full.
+    fn push(&mut self, event: UsbDeviceInfo) -> u64 {
+        if self.pending.len() >= self.capacity {
+            self.pending.pop_front();
+            self.dropped += 1;
+        }
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.pending.push_back((seq, event));
+        seq
+    }
+
+    /// Waits for `future` while buffering the events that arrive meanwhile.
+    async fn buffer_while<F: Future>(
+        &mut self,
+        events: &mut mpsc::UnboundedReceiver<UsbDeviceInfo>,
+        future: F,
+    ) -> F::Output {
+        tokio::pin!(future);
+        loop {
+            tokio::select! {
+                output = &mut future => return output,
+                event = events.recv(), if !self.closed => match event {
+                    Some(event) => {
+                        self.push(event);
+                    }
+                    None => self.closed = true,
+                },
+            }
+        }
+    }
+
+    async fn run(
+        mut self,
+        mut events: mpsc::UnboundedReceiver<UsbDeviceInfo>,
+        options: AgentOptions,
+    ) {
+        let mut delay = MIN_RECONNECT_DELAY;
+        let mut warned = false;
+        loop {
+            if self.closed && self.pending.is_empty() {
+                return;
+            }
+            let connection = self.buffer_while(&mut events, connect(&options)).await;
+            let result = match connection {
+                Ok(connection) => {
+                    delay = MIN_RECONNECT_DELAY;
+                    if warned {
+                        eprintln!("Reconnected to collector '{}'", options.upstream);
+                        warned = false;
+                    }
+                    if self.dropped > 0 {
+                        eprintln!(
+                            "Agent buffer was full; dropped {} events while the collector was unreachable",
+                            self.dropped
+                        );
+                        self.dropped = 0;
+                    }
+                    match self.stream(connection, &mut events, &options).await {
+                        Ok(()) => return,
+                        // Reconnect straight away after losing an established connection
+                        Err(e) => {
+                            eprintln!("Connection to collector '{}' lost: {e}", options.upstream);
+                            continue;
+                        }
+                    }
+                }
+                Err(e) => e,
+            };
+            if self.closed {
+                eprintln!(
+                    "Failed to deliver {} events to collector '{}': {result}",
+                    self.pending.len(),
+                    options.upstream
+                );
+                return;
+            }
+            // Report each outage once rather than every attempt
+            if !warned {
+                eprintln!(
+                    "Failed to connect to collector '{}', buffering events: {result}",
+                    options.upstream
+                );
+                warned = true;
+            }
+            self.buffer_while(&mut events, tokio::time::sleep(delay))
+                .await;
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    }
+
+    /// Sends the buffered and new events over `connection` until the events
+    /// channel closes and everything is acknowledged.
+    async fn stream(
+        &mut self,
+        connection: Box<dyn Connection>,
+        events: &mut mpsc::UnboundedReceiver<UsbDeviceInfo>,
+        options: &AgentOptions,
+    ) -> io::Result<()> {
+        let (mut reader, mut writer) = tokio::io::split(connection);
+        let (ack_tx, mut ack_rx) = mpsc::unbounded_channel();
+        let acks = tokio::spawn(async move {
+            loop {
+                let ack = match read_frame(&mut reader).await {
+                    Ok(Some(Frame::Ack { seq })) => Ok(seq),
+                    Ok(Some(_)) => Err("unexpected frame".to_string()),
+                    Ok(None) => Err("closed by the collector".to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+                let failed = ack.is_err();
+                if ack_tx.send(ack).is_err() || failed {
+                    return;
+                }
+            }
+        });
+
+        let result = async {
+            let hello = Frame::Hello {
+                host: options.host.clone(),
+                session: self.session.clone(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            };
+            write_frame(&mut writer, &hello).await?;
+            for (seq, event) in &self.pending {
+                send_event(&mut writer, *seq, event).await?;
+            }
+            loop {
+                if self.closed && self.pending.is_empty() {
+                    return Ok(());
+                }
+                tokio::select! {
+                    event = events.recv(), if !self.closed => match event {
+                        Some(event) => {
+                            let seq = self.push(event);
+                            if let Some((_, event)) = self.pending.back() {
+                                send_event(&mut writer, seq, event).await?;
+                            }
+                        }
+                        None => self.closed = true,
+                    },
+                    ack = ack_rx.recv() => match ack {
+                        Some(Ok(seq)) => {
+                            while self.pending.front().is_some_and(|(pending, _)| *pending <= seq) {
+                                self.pending.pop_front();
+                            }
+                        }
+                        Some(Err(e)) => return Err(io::Error::new(io::ErrorKind::ConnectionAborted, e)),
+                        None => return Err(io::ErrorKind::ConnectionAborted.into()),
+                    },
+                }
+            }
+        }
+        .await;
+        acks.abort();
+        result
+    }
+}
+
+async fn send_event<W: tokio::io::AsyncWrite + Unpin>(
+    writer: &mut W,
+    seq: u64,
+    event: &UsbDeviceInfo,
+) -> io::Result<()> {
+    let frame = Frame::Event {
+        seq,
+        event: Box::new(event.clone()),
+    };
+    write_frame(writer, &frame).await
+}
+
+/// Connects to the collector, with TLS if configured.
+async fn connect(options: &AgentOptions) -> io::Result<Box<dyn Connection>> {
+    let connect = async {
+        let stream = TcpStream::connect(&options.upstream).await?;
+        stream.set_nodelay(true)?;
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &options.tls {
+            return Ok(Box::new(tls.connect(stream).await?) as Box<dyn Connection>);
+        }
+        Ok(Box::new(stream) as Box<dyn Connection>)
+    };
+    tokio::time::timeout(CONNECT_TIMEOUT, connect)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "connection timed out"))?
+}
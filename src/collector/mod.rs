@@ -0,0 +1,269 @@
+//! Central collection of events from many hosts.
+//!
+//! `usbwatch agent --upstream host:port` runs the normal monitoring pipeline and streams every event to a collector;
+//! `usbwatch collector --listen addr` receives the events of all its agents and writes them through the usual
+//! [`Logger`](crate::logger::Logger) outputs, with [`origin`](crate::device_info::UsbDeviceInfo::origin) set to the
+//! agent's host name.
+//!
+//! ## Wire format
+//!
+//! Each frame is a 4-byte big-endian length followed by that many bytes of JSON holding a [`Frame`]. An agent opens
+//! with `hello`, then sends its events numbered from 1; the collector acknowledges each one:
+//!
+//! ```text
+//! agent     -> {"type":"hello","host":"ws-042","session":"1760774400123456789","version":"0.4.8"}
+//! agent     -> {"type":"event","seq":1,"event":{"device_name":"USB Storage",...}}
+//! collector -> {"type":"ack","seq":1}
+//! ```
+//!
+//! Agents keep events until they are acknowledged and resend them after reconnecting, so delivery is at least once;
+//! the collector skips events of the same session it has already seen. Without TLS anyone who can reach the
+//! collector can submit events and the traffic is readable, so outside a trusted network use the `tls` feature, and
+//! client certificates to restrict which agents may connect. With client certificates, the host in an agent's hello
+//! must be one of the DNS names or the common name of its certificate, so an agent cannot log events as another host.
+
+use crate::device_info::UsbDeviceInfo;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+mod agent;
+#[cfg(feature = "tls")]
+mod tls;
+
+pub use agent::*;
+#[cfg(feature = "tls")]
+pub use tls::*;
+
+/// Largest frame accepted, in bytes.
+pub const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+/// How long a new connection may take to finish the TLS handshake and send
+/// its hello before it is dropped.
+const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pause after a failed accept, which is usually a shortage of file
+/// descriptors that retrying at once would not fix.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(500);
+
+/// A message exchanged between agent and collector.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Frame {
+    /// First frame sent by an agent
+    Hello {
+        /// Host name the agent's events are tagged with
+        host: String,
+        /// Identifies one run of the agent; sequence numbers restart with each session
+        session: String,
+        /// usbwatch version of the agent
+        version: String,
+    },
+    /// A device event
+    Event {
+        /// Position of the event in the session, starting at 1
+        seq: u64,
+        /// The event
+        event: Box<UsbDeviceInfo>,
+    },
+    /// Acknowledges all events of the session up to `seq`
+    Ack {
+        /// Sequence number of the last event received
+        seq: u64,
+    },
+}
+
+/// Writes one length-prefixed frame.
+///
+/// # Errors
+///
+/// Returns an error if serialisation or writing fails.
+pub async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, frame: &Frame) -> io::Result<()> {
+    let json = serde_json::to_vec(frame)?;
+    if json.len() > MAX_FRAME_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Frame of {} bytes exceeds the limit", json.len()),
+        ));
+    }
+    writer.write_all(&(json.len() as u32).to_be_bytes()).await?;
+    writer.write_all(&json).await?;
+    writer.flush().await
+}
+
+/// Reads one length-prefixed frame, or `None` if the peer closed the
+/// connection between frames.
+///
+/// # Errors
+///
+/// Returns an error if reading fails, the frame is too large or it is not a
+/// valid [`Frame`].
+pub async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Option<Frame>> {
+    let mut length = [0; 4];
+    match reader.read_exact(&mut length).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_FRAME_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Frame of {length} bytes exceeds the limit"),
+        ));
+    }
+    let mut json = vec![0; length];
+    reader.read_exact(&mut json).await?;
+    Ok(Some(serde_json::from_slice(&json)?))
+}
+
+/// A byte stream to or from a peer, with or without TLS.
+trait Connection: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Connection for T {}
+
+/// Options of a collector.
+#[derive(Clone, Default)]
+pub struct CollectorOptions {
+    /// Serve TLS, optionally requiring client certificates
+    #[cfg(feature = "tls")]
+    pub tls: Option<ServerTls>,
+}
+
+/// Last sequence number seen per agent host, with the session it belongs to.
+type Sessions = Arc<Mutex<HashMap<String, (String, u64)>>>;
+
+/// Accepts agent connections on `listener` and sends their events, tagged
+/// with the agent's host, to `tx`, until the task is aborted.
+///
+/// Failed accepts (for example when out of file descriptors) are logged and
+/// retried after a pause.
+pub async fn serve(
+    listener: TcpListener,
+    tx: mpsc::Sender<UsbDeviceInfo>,
+    options: CollectorOptions,
+) {
+    let sessions = Sessions::default();
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                eprintln!("Failed to accept agent connection: {e}");
+                tokio::time::sleep(ACCEPT_BACKOFF).await;
+                continue;
+            }
+        };
+        let tx = tx.clone();
+        let sessions = sessions.clone();
+        let options = options.clone();
+        tokio::spawn(async move {
+            let _ = stream.set_nodelay(true);
+            #[cfg(feature = "tls")]
+            let (connection, cert_names): (Box<dyn Connection>, _) = match &options.tls {
+                Some(tls) => match tokio::time::timeout(HELLO_TIMEOUT, tls.accept(stream)).await {
+                    Ok(Ok(stream)) => {
+                        let names = client_cert_names(&stream);
+                        (Box::new(stream), names)
+                    }
+                    Ok(Err(e)) => {
+                        eprintln!("TLS handshake with {peer} failed: {e}");
+                        return;
+                    }
+                    Err(_) => {
+                        eprintln!("TLS handshake with {peer} timed out");
+                        return;
+                    }
+                },
+                None => (Box::new(stream), None),
+            };
+            #[cfg(not(feature = "tls"))]
+            let (connection, cert_names): (Box<dyn Connection>, _) = {
+                let _ = options;
+                (Box::new(stream), None)
+            };
+            if let Err(e) = handle_agent(connection, peer, cert_names, tx, sessions).await {
+                eprintln!("Agent connection from {peer} failed: {e}");
+            }
+        });
+    }
+}
+
+/// Receives the events of one agent. `cert_names` are the names in its client
+/// certificate, which the host in its hello must match.
+async fn handle_agent(
+    connection: Box<dyn Connection>,
+    peer: SocketAddr,
+    cert_names: Option<Vec<String>>,
+    tx: mpsc::Sender<UsbDeviceInfo>,
+    sessions: Sessions,
+) -> io::Result<()> {
+    let (mut reader, mut writer) = tokio::io::split(connection);
+    // Connections that never say hello would otherwise be kept open for good
+    let hello = tokio::time::timeout(HELLO_TIMEOUT, read_frame(&mut reader))
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "no hello frame received"))??;
+    let (host, session) = match hello {
+        Some(Frame::Hello { host, session, .. }) => (host, session),
+        Some(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "expected a hello frame",
+            ))
+        }
+        None => return Ok(()),
+    };
+    if let Some(names) = &cert_names {
+        if !names.iter().any(|name| name.eq_ignore_ascii_case(&host)) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "host '{host}' is not named in the client certificate ({})",
+                    names.join(", ")
+                ),
+            ));
+        }
+    }
+    eprintln!("Agent '{host}' connected from {peer}");
+
+    loop {
+        let (seq, mut event) = match read_frame(&mut reader).await? {
+            Some(Frame::Event { seq, event }) => (seq, *event),
+            Some(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "expected an event frame",
+                ))
+            }
+            None => {
+                eprintln!("Agent '{host}' disconnected");
+                return Ok(());
+            }
+        };
+        let new = {
+            let mut sessions = sessions.lock().unwrap_or_else(PoisonError::into_inner);
+            let last = sessions
+                .entry(host.clone())
+                .or_insert_with(|| (session.clone(), 0));
+            if last.0 != session {
+                *last = (session.clone(), 0);
+            }
+            let new = seq > last.1;
+            last.1 = last.1.max(seq);
+            new
+        };
+        // Events resent after a reconnect are acknowledged but not logged twice
+        if new {
+            event.origin = Some(host.clone());
+            if tx.send(event).await.is_err() {
+                return Ok(());
+            }
+        }
+        write_frame(&mut writer, &Frame::Ack { seq }).await?;
+    }
+}
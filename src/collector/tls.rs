@@ -0,0 +1,165 @@
+//! TLS and mutual TLS between agent and collector.
+
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::crypto::{ring, CryptoProvider};
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore, ServerConfig};
+use tokio_rustls::{client, server, TlsAcceptor, TlsConnector};
+use x509_parser::extensions::GeneralName;
+
+/// TLS settings of an agent.
+#[derive(Clone)]
+pub struct ClientTls {
+    connector: TlsConnector,
+    server_name: ServerName<'static>,
+}
+
+impl ClientTls {
+    /// Trusts the collector certificates issued by the CA in the PEM file
+    /// `ca`, and presents the PEM certificate chain and key in `identity`
+    /// for mutual TLS. `server_name` must match the collector's certificate.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file cannot be read or parsed.
+    pub fn new(
+        ca: &Path,
+        identity: Option<(&Path, &Path)>,
+        server_name: &str,
+    ) -> Result<Self, String> {
+        let builder = ClientConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| format!("Failed to configure TLS: {e}"))?
+            .with_root_certificates(load_roots(ca)?);
+        let config = match identity {
+            Some((cert, key)) => builder
+                .with_client_auth_cert(load_certs(cert)?, load_key(key)?)
+                .map_err(|e| format!("Invalid client certificate '{}': {e}", cert.display()))?,
+            None => builder.with_no_client_auth(),
+        };
+        let server_name = ServerName::try_from(server_name.to_string())
+            .map_err(|e| format!("Invalid TLS server name '{server_name}': {e}"))?;
+        Ok(Self {
+            connector: TlsConnector::from(Arc::new(config)),
+            server_name,
+        })
+    }
+
+    pub(crate) async fn connect(
+        &self,
+        stream: TcpStream,
+    ) -> io::Result<client::TlsStream<TcpStream>> {
+        self.connector
+            .connect(self.server_name.clone(), stream)
+            .await
+    }
+}
+
+/// TLS settings of a collector.
+#[derive(Clone)]
+pub struct ServerTls {
+    acceptor: TlsAcceptor,
+}
+
+impl ServerTls {
+    /// Serves the PEM certificate chain and key in `cert` and `key`. With
+    /// `client_ca`, only agents presenting a certificate issued by that CA
+    /// may connect.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file cannot be read or parsed.
+    pub fn new(cert: &Path, key: &Path, client_ca: Option<&Path>) -> Result<Self, String> {
+        let builder = ServerConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| format!("Failed to configure TLS: {e}"))?;
+        let builder = match client_ca {
+            Some(ca) => {
+                let verifier = WebPkiClientVerifier::builder_with_provider(
+                    Arc::new(load_roots(ca)?),
+                    provider(),
+                )
+                .build()
+                .map_err(|e| format!("Invalid client CA '{}': {e}", ca.display()))?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let config = builder
+            .with_single_cert(load_certs(cert)?, load_key(key)?)
+            .map_err(|e| format!("Invalid certificate '{}': {e}", cert.display()))?;
+        Ok(Self {
+            acceptor: TlsAcceptor::from(Arc::new(config)),
+        })
+    }
+
+    pub(crate) async fn accept(
+        &self,
+        stream: TcpStream,
+    ) -> io::Result<server::TlsStream<TcpStream>> {
+        self.acceptor.accept(stream).await
+    }
+}
+
+/// Returns the DNS names and common names of the client certificate the
+/// agent presented on `stream`, or `None` without mutual TLS.
+pub(crate) fn client_cert_names(stream: &server::TlsStream<TcpStream>) -> Option<Vec<String>> {
+    let cert = stream.get_ref().1.peer_certificates()?.first()?;
+    // The certificate was verified during the handshake, so it parses
+    let Ok((_, cert)) = x509_parser::parse_x509_certificate(cert) else {
+        return Some(Vec::new());
+    };
+    let mut names: Vec<String> = match cert.subject_alternative_name() {
+        Ok(Some(san)) => san
+            .value
+            .general_names
+            .iter()
+            .filter_map(|name| match name {
+                GeneralName::DNSName(name) => Some(name.to_string()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    names.extend(
+        cert.subject()
+            .iter_common_name()
+            .filter_map(|name| name.as_str().ok())
+            .map(str::to_string),
+    );
+    Some(names)
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(ring::default_provider())
+}
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to read certificates from '{}': {e}", path.display()))?;
+    if certs.is_empty() {
+        return Err(format!("No certificates found in '{}'", path.display()));
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &Path) -> Result<PrivateKeyDer<'static>, String> {
+    PrivateKeyDer::from_pem_file(path)
+        .map_err(|e| format!("Failed to read private key from '{}': {e}", path.display()))
+}
+
+fn load_roots(path: &Path) -> Result<RootCertStore, String> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(path)? {
+        roots
+            .add(cert)
+            .map_err(|e| format!("Invalid CA certificate in '{}': {e}", path.display()))?;
+    }
+    Ok(roots)
+}
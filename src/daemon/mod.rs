@@ -0,0 +1,378 @@
+//! Daemon mode: a query and control API on a Unix domain socket.
+//!
+//! `usbwatch daemon` runs the normal monitoring pipeline and keeps a [`DaemonState`] with the currently attached
+//! devices and the recent event history, so other tools can ask one daemon per host instead of each scanning sysfs.
+//! The state is platform-independent and also backs the HTTP API of `usbwatch serve`; the socket server and
+//! [`DaemonClient`] are only available on Unix.
+//!
+//! ## Protocol
+//!
+//! Newline-delimited JSON in the style of JSON-RPC. Each request is one line with a `method`, optional `params` and
+//! an optional `id` that is echoed back; each response is one line with either `result` or `error`:
+//!
+//! ```text
+//! -> {"id":1,"method":"list"}
+//! <- {"id":1,"result":[{"device_name":"USB Storage","vendor_id":"0781",...}]}
+//! -> {"id":2,"method":"history","params":{"vendor_id":"0781","limit":5}}
+//! <- {"id":2,"result":[...]}
+//! -> {"id":3,"method":"subscribe"}
+//! <- {"id":3,"result":{"subscribed":true}}
+//! <- {"event":{"device_name":"USB Storage",...}}
+//! ```
+//!
+//! | Method      | Params                   | Result                                                 |
+//! |-------------|--------------------------|--------------------------------------------------------|
+//! | `list`      | -                        | Currently attached devices                             |
+//! | `subscribe` | -                        | Acknowledgement, then one `{"event":...}` line per event (`{"lagged":n}` if the client fell behind) |
+//! | `stats`     | -                        | [`DaemonStats`]                                        |
+//! | `reload`    | -                        | Message describing what was reloaded                   |
+//! | `history`   | [`HistoryQuery`] fields  | Matching events, newest first                          |
+//!
+//! A subscribed connection only streams events; open another connection for further requests.
+
+use crate::device_info::{DeviceEventType, UsbDeviceInfo};
+use crate::history::HistoryQuery;
+use crate::metrics::Metrics;
+use crate::stats::EventStats;
+#[cfg(feature = "sqlite")]
+use crate::store::EventStore;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::sync::{broadcast, mpsc};
+
+#[cfg(unix)]
+mod socket;
+#[cfg(unix)]
+pub use socket::*;
+
+/// Default number of recent events kept in memory for `history` and `stats`.
+pub const DEFAULT_HISTORY_SIZE: usize = 10_000;
+
+/// Events buffered per subscriber before it is reported as lagging.
+const SUBSCRIBER_BUFFER: usize = 256;
+
+/// A request line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Request {
+    /// Echoed back in the response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    /// Operation: `list`, `subscribe`, `stats`, `reload` or `history`
+    pub method: String,
+    /// Operation parameters
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub params: Value,
+}
+
+/// A response line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Response {
+    /// The request's `id`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    /// Result of a successful request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    /// Error message of a failed request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Response {
+    pub(crate) fn from_result(id: Option<Value>, result: Result<Value, String>) -> Self {
+        match result {
+            Ok(result) => Self {
+                id,
+                result: Some(result),
+                error: None,
+            },
+            Err(error) => Self {
+                id,
+                result: None,
+                error: Some(error),
+            },
+        }
+    }
+}
+
+/// A line sent to subscribed clients.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamMessage {
+    /// A device event
+    Event(Box<UsbDeviceInfo>),
+    /// Number of events the client missed because it read too slowly
+    Lagged(u64),
+}
+
+/// Result of the `stats` method.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonStats {
+    /// When the daemon started
+    pub started_at: DateTime<Utc>,
+    /// Seconds since the daemon started
+    pub uptime_secs: i64,
+    /// Number of currently attached devices
+    pub connected_devices: usize,
+    /// Number of subscribed clients
+    pub subscribers: usize,
+    /// Events seen since the daemon started, including ones filtered out
+    pub events_total: u64,
+    /// Statistics over the in-memory event history
+    pub history: EventStats,
+}
+
+/// Callback that re-reads the daemon's configuration, returning a summary.
+pub type ReloadFn = Box<dyn Fn() -> Result<String, String> + Send + Sync>;
+
+/// Attached devices, recent history and subscribers of a running daemon.
+pub struct DaemonState {
+    started_at: DateTime<Utc>,
+    devices: Mutex<BTreeMap<String, UsbDeviceInfo>>,
+    history: Mutex<VecDeque<UsbDeviceInfo>>,
+    history_size: usize,
+    metrics: Arc<Metrics>,
+    events: broadcast::Sender<UsbDeviceInfo>,
+    reload: Option<ReloadFn>,
+    #[cfg(feature = "sqlite")]
+    store: Option<Mutex<EventStore>>,
+}
+
+impl DaemonState {
+    /// Creates an empty state that keeps the last `history_size` events.
+    pub fn new(history_size: usize) -> Self {
+        Self {
+            started_at: Utc::now(),
+            devices: Mutex::new(BTreeMap::new()),
+            history: Mutex::new(VecDeque::new()),
+            history_size,
+            metrics: Arc::new(Metrics::new()),
+            events: broadcast::channel(SUBSCRIBER_BUFFER).0,
+            reload: None,
+            #[cfg(feature = "sqlite")]
+            store: None,
+        }
+    }
+
+    /// Sets the callback run by the `reload` method.
+    pub fn with_reload<F>(mut self, reload: F) -> Self
+    where
+        F: Fn() -> Result<String, String> + Send + Sync + 'static,
+    {
+        self.reload = Some(Box::new(reload));
+        self
+    }
+
+    /// Answers `history` from an SQLite event store instead of the in-memory
+    /// history.
+    #[cfg(feature = "sqlite")]
+    pub fn with_store(mut self, store: EventStore) -> Self {
+        self.store = Some(Mutex::new(store));
+        self
+    }
+
+    /// Records an event: updates the attached devices and the history, and
+    /// sends it to subscribers.
+    ///
+    /// Events are not counted in [`metrics`](Self::metrics) here, since that
+    /// happens before the pipeline filters them.
+    pub fn record(&self, event: &UsbDeviceInfo) {
+        track_devices(
+            &mut self.devices.lock().unwrap_or_else(PoisonError::into_inner),
+            event,
+        );
+        {
+            let mut history = self.history.lock().unwrap_or_else(PoisonError::into_inner);
+            history.push_back(event.clone());
+            while history.len() > self.history_size {
+                history.pop_front();
+            }
+        }
+        // Sending only fails when nobody is subscribed
+        let _ = self.events.send(event.clone());
+    }
+
+    /// Returns the currently attached devices.
+    pub fn devices(&self) -> Vec<UsbDeviceInfo> {
+        self.devices
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the metrics collected for this state's events.
+    pub fn metrics(&self) -> &Arc<Metrics> {
+        &self.metrics
+    }
+
+    /// Returns the attached devices identified by `id`.
+    ///
+    /// `id` is a tracking key (`VID:PID:serial`) or port path, which select
+    /// at most one device, or a `VID:PID` pair or serial number, which may
+    /// match several.
+    pub fn find_devices(&self, id: &str) -> Vec<UsbDeviceInfo> {
+        let devices = self.devices.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(device) = devices.get(id) {
+            return vec![device.clone()];
+        }
+        if let Some(device) = devices
+            .values()
+            .find(|device| device.port_path.as_deref() == Some(id))
+        {
+            return vec![device.clone()];
+        }
+        devices
+            .values()
+            .filter(|device| {
+                let vid_pid = format!("{}:{}", device.vendor_id, device.product_id);
+                vid_pid.eq_ignore_ascii_case(id) || device.serial_number.as_deref() == Some(id)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the daemon's statistics.
+    pub fn stats(&self) -> DaemonStats {
+        let history: Vec<UsbDeviceInfo> = self
+            .history
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .cloned()
+            .collect();
+        DaemonStats {
+            started_at: self.started_at,
+            uptime_secs: (Utc::now() - self.started_at).num_seconds(),
+            connected_devices: self
+                .devices
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .len(),
+            subscribers: self.events.receiver_count(),
+            events_total: self.metrics.events_total(),
+            history: EventStats::from_events(&history),
+        }
+    }
+
+    /// Returns past events matching `query`, newest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the event store query fails.
+    pub fn history(&self, query: &HistoryQuery) -> Result<Vec<UsbDeviceInfo>, String> {
+        #[cfg(feature = "sqlite")]
+        if let Some(store) = &self.store {
+            return store
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .query(query)
+                .map_err(|e| e.to_string());
+        }
+        Ok(query.apply(
+            self.history
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter(),
+        ))
+    }
+
+    /// Like [`history`](Self::history), but runs the query on the blocking
+    /// thread pool so a slow event store does not stall the runtime.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the event store query fails.
+    pub async fn history_blocking(
+        self: Arc<Self>,
+        query: HistoryQuery,
+    ) -> Result<Vec<UsbDeviceInfo>, String> {
+        tokio::task::spawn_blocking(move || self.history(&query))
+            .await
+            .map_err(|e| format!("History query failed: {e}"))?
+    }
+
+    /// Subscribes to the events recorded from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<UsbDeviceInfo> {
+        self.events.subscribe()
+    }
+
+    /// Handles a request other than `subscribe`.
+    pub fn handle(&self, request: &Request) -> Response {
+        let result = match request.method.as_str() {
+            "list" => to_value(self.devices()),
+            "stats" => to_value(self.stats()),
+            "reload" => match &self.reload {
+                Some(reload) => reload().map(Value::String),
+                None => Err("Nothing to reload".to_string()),
+            },
+            "history" => {
+                let query = if request.params.is_null() {
+                    Ok(HistoryQuery::default())
+                } else {
+                    serde_json::from_value(request.params.clone())
+                        .map_err(|e| format!("Invalid history parameters: {e}"))
+                };
+                query
+                    .and_then(|query| self.history(&query))
+                    .and_then(to_value)
+            }
+            "subscribe" => Err("subscribe must be handled by the connection".to_string()),
+            method => Err(format!("Unknown method '{method}'")),
+        };
+        Response::from_result(request.id.clone(), result)
+    }
+
+    /// Like [`handle`](Self::handle), but runs on the blocking thread pool,
+    /// since `history` may query the event store and `reload` reads files.
+    pub async fn handle_blocking(self: Arc<Self>, request: Request) -> Response {
+        let id = request.id.clone();
+        tokio::task::spawn_blocking(move || self.handle(&request))
+            .await
+            .unwrap_or_else(|e| Response::from_result(id, Err(format!("Request failed: {e}"))))
+    }
+}
+
+/// Updates the attached `devices`, keyed by tracking key, for `event`.
+pub(crate) fn track_devices(devices: &mut BTreeMap<String, UsbDeviceInfo>, event: &UsbDeviceInfo) {
+    // Collapsed hub events carry the devices behind the hub as children
+    for device in std::iter::once(event).chain(&event.children) {
+        match event.event_type {
+            DeviceEventType::Connected => {
+                let mut device = device.clone();
+                device.children.clear();
+                devices.insert(device.device_key(), device);
+            }
+            DeviceEventType::Disconnected => {
+                devices.remove(&device.device_key());
+            }
+            DeviceEventType::Flapping
+            | DeviceEventType::SuspiciousDevice
+            | DeviceEventType::Degraded => {}
+        }
+    }
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|e| e.to_string())
+}
+
+/// Async task that records each event from `rx` in `state` and forwards it to `tx`.
+///
+/// The task ends when `rx` closes or `tx` is dropped.
+pub async fn record_task(
+    mut rx: mpsc::Receiver<UsbDeviceInfo>,
+    tx: mpsc::Sender<UsbDeviceInfo>,
+    state: Arc<DaemonState>,
+) {
+    while let Some(event) = rx.recv().await {
+        state.record(&event);
+        if tx.send(event).await.is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! The daemon's Unix domain socket: server and client.
+
+use super::{DaemonState, Request, Response, StreamMessage};
+use crate::device_info::UsbDeviceInfo;
+use crate::metrics::Metrics;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{broadcast, mpsc};
+
+/// Default path of the daemon's socket.
+pub const DEFAULT_SOCKET_PATH: &str = "/run/usbwatch.sock";
+
+/// Default permissions of the socket: owner and group may connect.
+pub const DEFAULT_SOCKET_MODE: u32 = 0o660;
+
+/// Ownership and permissions of the daemon's socket.
+#[derive(Debug, Clone)]
+pub struct SocketOptions {
+    /// Permission bits (e.g. `0o660`)
+    pub mode: u32,
+    /// Group that owns the socket, by name or numeric ID
+    pub group: Option<String>,
+}
+
+impl Default for SocketOptions {
+    fn default() -> Self {
+        Self {
+            mode: DEFAULT_SOCKET_MODE,
+            group: None,
+        }
+    }
+}
+
+/// Parses octal permission bits such as `660` or `0o600`.
+///
+/// # Errors
+///
+/// Returns an error if the value is not an octal number up to `0o777`.
+pub fn parse_socket_mode(mode: &str) -> Result<u32, String> {
+    u32::from_str_radix(mode.trim_start_matches("0o"), 8)
+        .ok()
+        .filter(|mode| *mode <= 0o777)
+        .ok_or_else(|| format!("Invalid socket mode '{mode}': expected octal such as 660"))
+}
+
+/// Binds the daemon's socket, replacing a stale socket file and applying the
+/// requested permissions.
+///
+/// # Errors
+///
+/// Returns an error if another daemon is listening on the path, the socket
+/// cannot be bound, or its permissions or group cannot be set.
+pub fn bind(
+    path: &Path,
+    options: &SocketOptions,
+) -> Result<UnixListener, Box<dyn std::error::Error>> {
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            return Err(format!("Another daemon is listening on '{}'", path.display()).into());
+        }
+        std::fs::remove_file(path)
+            .map_err(|e| format!("Failed to remove stale socket '{}': {e}", path.display()))?;
+    }
+
+    let listener = UnixListener::bind(path)
+        .map_err(|e| format!("Failed to bind socket '{}': {e}", path.display()))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(options.mode))
+        .map_err(|e| format!("Failed to set permissions on '{}': {e}", path.display()))?;
+    if let Some(group) = &options.group {
+        std::os::unix::fs::chown(path, None, Some(resolve_group(group)?))
+            .map_err(|e| format!("Failed to set group of '{}': {e}", path.display()))?;
+    }
+    Ok(listener)
+}
+
+/// Resolves a group name or numeric ID to a group ID.
+fn resolve_group(group: &str) -> Result<u32, String> {
+    if let Ok(gid) = group.parse() {
+        return Ok(gid);
+    }
+    let name = std::ffi::CString::new(group).map_err(|_| format!("Invalid group '{group}'"))?;
+    // SAFETY: getgrnam returns null or a pointer to a static entry, which is read before any other call
+    let entry = unsafe { libc::getgrnam(name.as_ptr()) };
+    if entry.is_null() {
+        return Err(format!("Unknown group '{group}'"));
+    }
+    // SAFETY: entry was checked to be non-null
+    Ok(unsafe { (*entry).gr_gid })
+}
+
+/// Accepts clients on `listener` and answers their requests until the task
+/// is aborted.
+pub async fn serve(listener: UnixListener, state: Arc<DaemonState>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let state = state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, state).await {
+                        if e.kind() != std::io::ErrorKind::BrokenPipe {
+                            eprintln!("Daemon client error: {e}");
+                        }
+                    }
+                });
+            }
+            Err(e) => eprintln!("Failed to accept daemon client: {e}"),
+        }
+    }
+}
+
+async fn handle_connection(stream: UnixStream, state: Arc<DaemonState>) -> std::io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request: Request = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                let response = Response::from_result(None, Err(format!("Invalid request: {e}")));
+                write_line(&mut writer, &response).await?;
+                continue;
+            }
+        };
+
+        if request.method == "subscribe" {
+            let events = state.subscribe();
+            let ack = Response::from_result(request.id, Ok(json!({ "subscribed": true })));
+            write_line(&mut writer, &ack).await?;
+            return stream_events(events, state.metrics().clone(), writer, lines).await;
+        }
+        let response = state.clone().handle_blocking(request).await;
+        write_line(&mut writer, &response).await?;
+    }
+    Ok(())
+}
+
+/// Streams events to a subscribed client until it disconnects.
+async fn stream_events(
+    mut events: broadcast::Receiver<UsbDeviceInfo>,
+    metrics: Arc<Metrics>,
+    mut writer: OwnedWriteHalf,
+    mut lines: Lines<BufReader<OwnedReadHalf>>,
+) -> std::io::Result<()> {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => write_line(&mut writer, &StreamMessage::Event(Box::new(event))).await?,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    metrics.record_dropped(missed);
+                    write_line(&mut writer, &StreamMessage::Lagged(missed)).await?;
+                }
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+            // Anything the client sends is ignored; end of input means it went away
+            line = lines.next_line() => {
+                if line?.is_none() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+async fn write_line<T: Serialize>(writer: &mut OwnedWriteHalf, message: &T) -> std::io::Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    writer.write_all(&line).await
+}
+
+/// Client for a running daemon.
+pub struct DaemonClient {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+    next_id: u64,
+}
+
+impl DaemonClient {
+    /// Connects to the daemon listening on `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if nothing is listening on the socket or it cannot be
+    /// accessed.
+    pub async fn connect(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let stream = UnixStream::connect(path).await.map_err(|e| {
+            format!(
+                "Failed to connect to the daemon at '{}': {e}",
+                path.display()
+            )
+        })?;
+        let (reader, writer) = stream.into_split();
+        Ok(Self {
+            lines: BufReader::new(reader).lines(),
+            writer,
+            next_id: 1,
+        })
+    }
+
+    async fn send(
+        &mut self,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, Box<dyn std::error::Error>> {
+        let id = Value::from(self.next_id);
+        self.next_id += 1;
+        let request = Request {
+            id: Some(id.clone()),
+            method: method.to_string(),
+            params,
+        };
+        write_line(&mut self.writer, &request).await?;
+
+        while let Some(line) = self.lines.next_line().await? {
+            let response: Response = serde_json::from_str(&line)?;
+            if response.id.as_ref() != Some(&id) {
+                continue;
+            }
+            return match (response.result, response.error) {
+                (_, Some(error)) => Err(error.into()),
+                (Some(result), None) => Ok(result),
+                (None, None) => Ok(Value::Null),
+            };
+        }
+        Err("The daemon closed the connection".into())
+    }
+
+    /// Calls a method other than `subscribe` and returns its result.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection fails or the daemon reports one.
+    pub async fn call(
+        &mut self,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, Box<dyn std::error::Error>> {
+        if method == "subscribe" {
+            return Err("Use DaemonClient::subscribe to subscribe".into());
+        }
+        self.send(method, params).await
+    }
+
+    /// Subscribes to the event stream and forwards events to `tx` until the
+    /// daemon closes the connection or `tx` is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the subscription is refused or the connection fails.
+    pub async fn subscribe(
+        mut self,
+        tx: mpsc::Sender<UsbDeviceInfo>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.send("subscribe", Value::Null).await?;
+        while let Some(line) = self.lines.next_line().await? {
+            match serde_json::from_str(&line)? {
+                StreamMessage::Event(event) => {
+                    if tx.send(*event).await.is_err() {
+                        return Ok(());
+                    }
+                }
+                StreamMessage::Lagged(missed) => {
+                    eprintln!("Missed {missed} event(s): reading too slowly");
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,298 @@
+//! Debouncing and flap detection for USB device events.
+//!
+//! Flaky cables and hubs can make a device connect and disconnect many times in quick succession. The
+//! [`Debouncer`] sits between the [`UsbWatcher`](crate::watcher::UsbWatcher) and the logger and can:
+//!
+//! - hold back events until a device has been stable for a configurable time, reporting only the net change
+//! - collapse a burst of changes into a single [`DeviceEventType::Flapping`] event carrying the change count
+//! - raise an alert, a `Flapping` event with a reason, when a device changes state more often than a threshold per
+//!   minute
+//!
+//! Devices are tracked by [`UsbDeviceInfo::device_key`] (`VID:PID:serial`).
+
+use crate::device_info::{DeviceEventType, UsbDeviceInfo};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+/// Window over which the flap rate is measured.
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Configuration for the [`Debouncer`].
+#[derive(Debug, Clone, Default)]
+pub struct DebounceConfig {
+    /// How long a device must be stable before its events are released.
+    /// Zero passes events through immediately.
+    pub stable_for: Duration,
+    /// Collapse a burst of more than one change into a single `Flapping` event
+    pub collapse: bool,
+    /// Raise an alert when a device changes state more than this many times per minute
+    pub alert_per_minute: Option<usize>,
+}
+
+/// Output of the [`Debouncer`].
+#[derive(Debug, Clone)]
+pub enum DebounceOutput {
+    /// An event to pass on downstream
+    Event(Box<UsbDeviceInfo>),
+    /// A `Flapping` event with a reason, raised when a device changes state
+    /// more often than the alert threshold
+    Alert(Box<UsbDeviceInfo>),
+}
+
+struct Burst {
+    last: UsbDeviceInfo,
+    changes: u32,
+    deadline: Instant,
+}
+
+/// Per-device debouncing state machine.
+///
+/// The debouncer is driven explicitly with timestamps so it can be tested
+/// without real time passing; [`debounce_task`] drives it from a channel.
+pub struct Debouncer {
+    config: DebounceConfig,
+    bursts: HashMap<String, Burst>,
+    /// Last state reported per device; devices missing from it are disconnected
+    reported: HashMap<String, DeviceEventType>,
+    changes: HashMap<String, VecDeque<Instant>>,
+    alerting: HashSet<String>,
+}
+
+impl Debouncer {
+    /// Creates a debouncer with the given configuration.
+    pub fn new(config: DebounceConfig) -> Self {
+        Self {
+            config,
+            bursts: HashMap::new(),
+            reported: HashMap::new(),
+            changes: HashMap::new(),
+            alerting: HashSet::new(),
+        }
+    }
+
+    /// Feeds an event observed at `now` into the debouncer.
+    ///
+    /// Returns any outputs that are ready immediately: the event itself when
+    /// debouncing is disabled or it is not a connect/disconnect, and
+    /// flap-rate alerts.
+    pub fn push(&mut self, event: UsbDeviceInfo, now: Instant) -> Vec<DebounceOutput> {
+        // Notices such as suspicious device warnings are never held back
+        if !event.event_type.is_state_change() {
+            return vec![DebounceOutput::Event(Box::new(event))];
+        }
+
+        let key = event.device_key();
+        let mut outputs = self.track_rate(&key, &event, now);
+
+        if self.config.stable_for.is_zero() {
+            self.set_reported(key, &event.event_type);
+            outputs.push(DebounceOutput::Event(Box::new(event)));
+            return outputs;
+        }
+
+        let deadline = now + self.config.stable_for;
+        match self.bursts.get_mut(&key) {
+            Some(burst) => {
+                burst.last = event;
+                burst.changes += 1;
+                burst.deadline = deadline;
+            }
+            None => {
+                self.bursts.insert(
+                    key,
+                    Burst {
+                        last: event,
+                        changes: 1,
+                        deadline,
+                    },
+                );
+            }
+        }
+        outputs
+    }
+
+    /// Releases the bursts of devices that have been stable until `now`.
+    pub fn poll(&mut self, now: Instant) -> Vec<DebounceOutput> {
+        let settled: Vec<String> = self
+            .bursts
+            .iter()
+            .filter(|(_, burst)| burst.deadline <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        let mut outputs = Vec::new();
+        for key in settled {
+            if let Some(burst) = self.bursts.remove(&key) {
+                outputs.extend(self.settle(key, burst));
+            }
+        }
+        outputs
+    }
+
+    /// Releases all pending bursts regardless of their deadline.
+    pub fn flush(&mut self) -> Vec<DebounceOutput> {
+        let mut outputs = Vec::new();
+        for (key, burst) in std::mem::take(&mut self.bursts) {
+            outputs.extend(self.settle(key, burst));
+        }
+        outputs
+    }
+
+    /// Returns the earliest time at which a pending burst settles.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.bursts.values().map(|burst| burst.deadline).min()
+    }
+
+    fn settle(&mut self, key: String, burst: Burst) -> Vec<DebounceOutput> {
+        let mut outputs = Vec::new();
+
+        if self.config.collapse && burst.changes > 1 {
+            let mut flapping = burst.last.clone();
+            flapping.event_type = DeviceEventType::Flapping;
+            flapping.flap_count = Some(burst.changes);
+            flapping.connected_at = None;
+            flapping.duration = None;
+            outputs.push(DebounceOutput::Event(Box::new(flapping)));
+        }
+
+        // Only report the settled state if it differs from what was last reported, so a
+        // device that comes and goes before it was ever reported leaves no orphan disconnect
+        let reported = self
+            .reported
+            .get(&key)
+            .unwrap_or(&DeviceEventType::Disconnected);
+        if *reported != burst.last.event_type {
+            self.set_reported(key, &burst.last.event_type);
+            outputs.push(DebounceOutput::Event(Box::new(burst.last)));
+        }
+        outputs
+    }
+
+    fn set_reported(&mut self, key: String, event_type: &DeviceEventType) {
+        // Disconnected devices are forgotten, so the map only holds attached ones
+        if *event_type == DeviceEventType::Disconnected {
+            self.reported.remove(&key);
+        } else {
+            self.reported.insert(key, event_type.clone());
+        }
+    }
+
+    fn track_rate(
+        &mut self,
+        key: &str,
+        event: &UsbDeviceInfo,
+        now: Instant,
+    ) -> Vec<DebounceOutput> {
+        let Some(threshold) = self.config.alert_per_minute else {
+            return Vec::new();
+        };
+
+        // Forget devices that have not changed within the window, such as ones unplugged for good
+        self.changes.retain(|_, changes| {
+            changes
+                .back()
+                .is_some_and(|last| now.duration_since(*last) <= RATE_WINDOW)
+        });
+        let changes = &self.changes;
+        self.alerting.retain(|key| changes.contains_key(key));
+
+        let changes = self.changes.entry(key.to_string()).or_default();
+        changes.push_back(now);
+        while changes
+            .front()
+            .is_some_and(|first| now.duration_since(*first) > RATE_WINDOW)
+        {
+            changes.pop_front();
+        }
+        let rate = changes.len();
+
+        if rate > threshold {
+            if self.alerting.insert(key.to_string()) {
+                let mut alert = event.flapping_event(format!(
+                    "{rate} state changes in the last minute (threshold {threshold})"
+                ));
+                alert.flap_count = Some(rate as u32);
+                return vec![DebounceOutput::Alert(Box::new(alert))];
+            }
+        } else {
+            self.alerting.remove(key);
+        }
+        Vec::new()
+    }
+}
+
+/// Async task that debounces events from `rx` and forwards them to `tx`.
+///
+/// Alerts are forwarded as soon as they are raised, ahead of the events
+/// being held back. When `rx` closes, pending bursts are flushed and the
+/// task ends.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use tokio::sync::mpsc;
+/// use usbwatch_rs::debounce::{debounce_task, DebounceConfig};
+/// use usbwatch_rs::UsbWatcher;
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let (watcher_tx, watcher_rx) = mpsc::channel(100);
+/// let (tx, mut rx) = mpsc::channel(100);
+/// let config = DebounceConfig {
+///     stable_for: Duration::from_millis(3000),
+///     collapse: true,
+///     alert_per_minute: Some(10),
+/// };
+/// tokio::spawn(debounce_task(watcher_rx, tx, config));
+///
+/// let watcher = UsbWatcher::new(watcher_tx)?;
+/// tokio::spawn(async move { watcher.start_monitoring().await.ok() });
+/// while let Some(event) = rx.recv().await {
+///     println!("{}", event);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn debounce_task(
+    mut rx: mpsc::Receiver<UsbDeviceInfo>,
+    tx: mpsc::Sender<UsbDeviceInfo>,
+    config: DebounceConfig,
+) {
+    let mut debouncer = Debouncer::new(config);
+
+    loop {
+        let outputs = match debouncer.next_deadline() {
+            Some(deadline) => tokio::select! {
+                event = rx.recv() => match event {
+                    Some(event) => debouncer.push(event, Instant::now()),
+                    None => break,
+                },
+                _ = tokio::time::sleep_until(deadline) => debouncer.poll(Instant::now()),
+            },
+            None => match rx.recv().await {
+                Some(event) => debouncer.push(event, Instant::now()),
+                None => break,
+            },
+        };
+        if forward(&tx, outputs).await.is_err() {
+            return;
+        }
+    }
+
+    let _ = forward(&tx, debouncer.flush()).await;
+}
+
+async fn forward(
+    tx: &mpsc::Sender<UsbDeviceInfo>,
+    outputs: Vec<DebounceOutput>,
+) -> Result<(), mpsc::error::SendError<UsbDeviceInfo>> {
+    for output in outputs {
+        match output {
+            DebounceOutput::Event(event) | DebounceOutput::Alert(event) => tx.send(*event).await?,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,746 @@
+//! USB descriptor parsing.
+//!
+//! Decodes the raw descriptor blobs the kernel exposes in sysfs (`/sys/bus/usb/devices/X/descriptors` and
+//! `bos_descriptors`) without going through libusb. The device descriptor is followed by every configuration
+//! descriptor with its interfaces, endpoints, interface associations, HID and class-specific descriptors; the
+//! parsed [`DescriptorTree`] can be rendered in a layout similar to `lsusb -v`.
+
+use crate::usb_class::ClassCode;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+
+/// Descriptor type codes.
+const DEVICE: u8 = 0x01;
+const CONFIGURATION: u8 = 0x02;
+const INTERFACE: u8 = 0x04;
+const ENDPOINT: u8 = 0x05;
+const INTERFACE_ASSOCIATION: u8 = 0x0b;
+const BOS: u8 = 0x0f;
+const DEVICE_CAPABILITY: u8 = 0x10;
+const HID: u8 = 0x21;
+
+/// All descriptors of a device.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DescriptorTree {
+    /// Device descriptor
+    pub device: DeviceDescriptor,
+    /// Configuration descriptors with their interfaces
+    pub configurations: Vec<ConfigurationDescriptor>,
+    /// Binary device object store, if the device provides one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bos: Option<BosDescriptor>,
+}
+
+/// Standard device descriptor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceDescriptor {
+    /// USB specification release (`bcdUSB`, e.g. "2.00")
+    pub usb_version: String,
+    /// Device class, subclass and protocol
+    pub class: ClassCode,
+    /// Maximum packet size for endpoint zero
+    pub max_packet_size0: u8,
+    /// Vendor ID
+    pub vendor_id: u16,
+    /// Product ID
+    pub product_id: u16,
+    /// Device release number (`bcdDevice`, e.g. "1.00")
+    pub device_version: String,
+    /// Index of the manufacturer string descriptor
+    pub manufacturer_index: u8,
+    /// Index of the product string descriptor
+    pub product_index: u8,
+    /// Index of the serial number string descriptor
+    pub serial_number_index: u8,
+    /// Number of possible configurations
+    pub num_configurations: u8,
+}
+
+/// Standard configuration descriptor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigurationDescriptor {
+    /// Value used to select this configuration
+    pub value: u8,
+    /// Index of the configuration string descriptor
+    pub string_index: u8,
+    /// Raw `bmAttributes`
+    pub attributes: u8,
+    /// Whether the device is self-powered in this configuration
+    pub self_powered: bool,
+    /// Whether the device supports remote wakeup
+    pub remote_wakeup: bool,
+    /// Maximum bus power draw in mA
+    pub max_power_ma: u16,
+    /// Interface association descriptors
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub associations: Vec<InterfaceAssociation>,
+    /// Interface descriptors, one per alternate setting
+    pub interfaces: Vec<InterfaceDescriptor>,
+    /// Other descriptors that precede the first interface
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra: Vec<RawDescriptor>,
+}
+
+/// Interface association descriptor (IAD), grouping interfaces into one function.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InterfaceAssociation {
+    /// First interface of the function
+    pub first_interface: u8,
+    /// Number of contiguous interfaces in the function
+    pub interface_count: u8,
+    /// Function class, subclass and protocol
+    pub class: ClassCode,
+    /// Index of the function string descriptor
+    pub string_index: u8,
+}
+
+/// Standard interface descriptor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InterfaceDescriptor {
+    /// Interface number
+    pub number: u8,
+    /// Alternate setting
+    pub alternate_setting: u8,
+    /// Interface class, subclass and protocol
+    pub class: ClassCode,
+    /// Index of the interface string descriptor
+    pub string_index: u8,
+    /// HID descriptor, for HID interfaces
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hid: Option<HidDescriptor>,
+    /// Endpoint descriptors
+    pub endpoints: Vec<EndpointDescriptor>,
+    /// Class-specific and unrecognised descriptors
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra: Vec<RawDescriptor>,
+}
+
+/// Endpoint transfer type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransferType {
+    /// Control transfers
+    Control,
+    /// Isochronous transfers
+    Isochronous,
+    /// Bulk transfers
+    Bulk,
+    /// Interrupt transfers
+    Interrupt,
+}
+
+/// Endpoint direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    /// Device to host
+    In,
+    /// Host to device
+    Out,
+}
+
+/// Standard endpoint descriptor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EndpointDescriptor {
+    /// Raw `bEndpointAddress`
+    pub address: u8,
+    /// Endpoint number
+    pub number: u8,
+    /// Transfer direction
+    pub direction: Direction,
+    /// Transfer type
+    pub transfer_type: TransferType,
+    /// Raw `bmAttributes`
+    pub attributes: u8,
+    /// Maximum packet size in bytes
+    pub max_packet_size: u16,
+    /// Additional transactions per microframe (high-speed isochronous and interrupt endpoints)
+    pub transactions_per_microframe: u8,
+    /// Polling interval (`bInterval`)
+    pub interval: u8,
+    /// SuperSpeed companion and class-specific endpoint descriptors
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra: Vec<RawDescriptor>,
+}
+
+/// HID class descriptor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HidDescriptor {
+    /// HID specification release (`bcdHID`, e.g. "1.11")
+    pub hid_version: String,
+    /// Country code of localised hardware (0 if not localised)
+    pub country_code: u8,
+    /// Class descriptors available from the device, as (type, length) pairs;
+    /// type 0x22 is the report descriptor
+    pub descriptors: Vec<(u8, u16)>,
+}
+
+/// Binary device object store (BOS) descriptor.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BosDescriptor {
+    /// Device capability descriptors
+    pub capabilities: Vec<DeviceCapability>,
+}
+
+/// Device capability descriptor from the BOS.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceCapability {
+    /// Capability type (`bDevCapabilityType`)
+    pub capability_type: u8,
+    /// Capability-specific data following the type byte
+    pub data: Vec<u8>,
+}
+
+/// A descriptor this module does not decode.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RawDescriptor {
+    /// Descriptor type (`bDescriptorType`)
+    pub descriptor_type: u8,
+    /// Descriptor bytes following the length and type
+    pub data: Vec<u8>,
+}
+
+impl DescriptorTree {
+    /// Parses the contents of a sysfs `descriptors` file.
+    ///
+    /// The blob must start with the device descriptor. A BOS descriptor is
+    /// decoded if present in the blob; on Linux it is usually exposed
+    /// separately in `bos_descriptors`, see [`BosDescriptor::parse`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data does not start with a device descriptor,
+    /// a descriptor is truncated or a standard descriptor is shorter than the
+    /// specification requires.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use usbwatch_rs::descriptors::DescriptorTree;
+    ///
+    /// let data = [
+    ///     // Device: USB 2.00, per-interface class, 0781:5583, one configuration
+    ///     18, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 64, 0x81, 0x07, 0x83, 0x55, 0x00, 0x01, 1, 2, 3, 1,
+    ///     // Configuration 1, bus powered, 224 mA
+    ///     9, 0x02, 32, 0, 1, 1, 0, 0x80, 112,
+    ///     // Interface 0: mass storage, SCSI, Bulk-Only
+    ///     9, 0x04, 0, 0, 2, 0x08, 0x06, 0x50, 0,
+    ///     // Bulk IN 0x81 and OUT 0x02, 512 bytes
+    ///     7, 0x05, 0x81, 0x02, 0x00, 0x02, 0,
+    ///     7, 0x05, 0x02, 0x02, 0x00, 0x02, 0,
+    /// ];
+    /// let tree = DescriptorTree::parse(&data).unwrap();
+    /// assert_eq!(tree.device.vendor_id, 0x0781);
+    /// assert_eq!(tree.configurations[0].max_power_ma, 224);
+    /// assert_eq!(tree.configurations[0].interfaces[0].endpoints.len(), 2);
+    /// ```
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        let mut descriptors = split_descriptors(data)?.into_iter();
+
+        let (device, super_speed) = match descriptors.next() {
+            Some(desc) if desc[1] == DEVICE => (parse_device(desc)?, word(desc, 2) >= 0x0300),
+            _ => return Err("Descriptor data does not start with a device descriptor".to_string()),
+        };
+
+        let mut configurations: Vec<ConfigurationDescriptor> = Vec::new();
+        let mut bos: Option<BosDescriptor> = None;
+
+        for desc in descriptors {
+            match desc[1] {
+                CONFIGURATION => configurations.push(parse_configuration(desc, super_speed)?),
+                BOS => bos = Some(BosDescriptor::default()),
+                DEVICE_CAPABILITY => {
+                    if let Some(bos) = &mut bos {
+                        bos.capabilities.push(parse_capability(desc)?);
+                    }
+                }
+                _ => {
+                    let Some(config) = configurations.last_mut() else {
+                        continue;
+                    };
+                    match desc[1] {
+                        INTERFACE => config.interfaces.push(parse_interface(desc)?),
+                        INTERFACE_ASSOCIATION => config.associations.push(parse_association(desc)?),
+                        ENDPOINT => match config.interfaces.last_mut() {
+                            Some(interface) => interface.endpoints.push(parse_endpoint(desc)?),
+                            None => config.extra.push(raw(desc)),
+                        },
+                        HID => match config.interfaces.last_mut() {
+                            Some(interface) if interface.endpoints.is_empty() => {
+                                interface.hid = Some(parse_hid(desc)?)
+                            }
+                            Some(interface) => interface.extra.push(raw(desc)),
+                            None => config.extra.push(raw(desc)),
+                        },
+                        _ => match config.interfaces.last_mut() {
+                            Some(interface) => match interface.endpoints.last_mut() {
+                                Some(endpoint) => endpoint.extra.push(raw(desc)),
+                                None => interface.extra.push(raw(desc)),
+                            },
+                            None => config.extra.push(raw(desc)),
+                        },
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            device,
+            configurations,
+            bos,
+        })
+    }
+
+    /// Renders the tree as indented text, similar to `lsusb -v`.
+    pub fn render_text(&self) -> String {
+        let mut out = String::new();
+        let d = &self.device;
+        let _ = writeln!(out, "Device Descriptor:");
+        field(&mut out, 1, "bcdUSB", &d.usb_version);
+        class_fields(&mut out, 1, "bDevice", &d.class);
+        field(&mut out, 1, "bMaxPacketSize0", d.max_packet_size0);
+        field(&mut out, 1, "idVendor", format!("0x{:04x}", d.vendor_id));
+        field(&mut out, 1, "idProduct", format!("0x{:04x}", d.product_id));
+        field(&mut out, 1, "bcdDevice", &d.device_version);
+        field(&mut out, 1, "iManufacturer", d.manufacturer_index);
+        field(&mut out, 1, "iProduct", d.product_index);
+        field(&mut out, 1, "iSerial", d.serial_number_index);
+        field(&mut out, 1, "bNumConfigurations", d.num_configurations);
+
+        for config in &self.configurations {
+            let _ = writeln!(out, "  Configuration Descriptor:");
+            field(&mut out, 2, "bNumInterfaces", config.interface_count());
+            field(&mut out, 2, "bConfigurationValue", config.value);
+            field(&mut out, 2, "iConfiguration", config.string_index);
+            let mut attributes = format!("0x{:02x}", config.attributes);
+            if config.self_powered {
+                attributes.push_str(" Self Powered");
+            } else {
+                attributes.push_str(" (Bus Powered)");
+            }
+            if config.remote_wakeup {
+                attributes.push_str(" Remote Wakeup");
+            }
+            field(&mut out, 2, "bmAttributes", attributes);
+            field(
+                &mut out,
+                2,
+                "MaxPower",
+                format!("{}mA", config.max_power_ma),
+            );
+            raw_fields(&mut out, 2, &config.extra);
+
+            for association in &config.associations {
+                let _ = writeln!(out, "    Interface Association:");
+                field(&mut out, 3, "bFirstInterface", association.first_interface);
+                field(&mut out, 3, "bInterfaceCount", association.interface_count);
+                class_fields(&mut out, 3, "bFunction", &association.class);
+                field(&mut out, 3, "iFunction", association.string_index);
+            }
+
+            for interface in &config.interfaces {
+                let _ = writeln!(out, "    Interface Descriptor:");
+                field(&mut out, 3, "bInterfaceNumber", interface.number);
+                field(
+                    &mut out,
+                    3,
+                    "bAlternateSetting",
+                    interface.alternate_setting,
+                );
+                field(&mut out, 3, "bNumEndpoints", interface.endpoints.len());
+                class_fields(&mut out, 3, "bInterface", &interface.class);
+                field(&mut out, 3, "iInterface", interface.string_index);
+
+                if let Some(hid) = &interface.hid {
+                    let _ = writeln!(out, "      HID Device Descriptor:");
+                    field(&mut out, 4, "bcdHID", &hid.hid_version);
+                    field(&mut out, 4, "bCountryCode", hid.country_code);
+                    field(&mut out, 4, "bNumDescriptors", hid.descriptors.len());
+                    for (descriptor_type, length) in &hid.descriptors {
+                        let name = if *descriptor_type == 0x22 {
+                            " Report"
+                        } else {
+                            ""
+                        };
+                        field(
+                            &mut out,
+                            4,
+                            "bDescriptorType",
+                            format!("0x{descriptor_type:02x}{name}"),
+                        );
+                        field(&mut out, 4, "wDescriptorLength", length);
+                    }
+                }
+                raw_fields(&mut out, 3, &interface.extra);
+
+                for endpoint in &interface.endpoints {
+                    let _ = writeln!(out, "      Endpoint Descriptor:");
+                    field(
+                        &mut out,
+                        4,
+                        "bEndpointAddress",
+                        format!(
+                            "0x{:02x}  EP {} {}",
+                            endpoint.address,
+                            endpoint.number,
+                            match endpoint.direction {
+                                Direction::In => "IN",
+                                Direction::Out => "OUT",
+                            }
+                        ),
+                    );
+                    field(
+                        &mut out,
+                        4,
+                        "bmAttributes",
+                        format!("0x{:02x} {:?}", endpoint.attributes, endpoint.transfer_type),
+                    );
+                    let packet_size = if endpoint.transactions_per_microframe > 0 {
+                        format!(
+                            "{} bytes, {}x",
+                            endpoint.max_packet_size,
+                            endpoint.transactions_per_microframe + 1
+                        )
+                    } else {
+                        format!("{} bytes", endpoint.max_packet_size)
+                    };
+                    field(&mut out, 4, "wMaxPacketSize", packet_size);
+                    field(&mut out, 4, "bInterval", endpoint.interval);
+                    raw_fields(&mut out, 4, &endpoint.extra);
+                }
+            }
+        }
+
+        if let Some(bos) = &self.bos {
+            out.push_str(&bos.render_text());
+        }
+        out
+    }
+}
+
+impl ConfigurationDescriptor {
+    /// Returns the number of distinct interfaces, ignoring alternate settings.
+    pub fn interface_count(&self) -> usize {
+        let mut numbers: Vec<u8> = self.interfaces.iter().map(|i| i.number).collect();
+        numbers.sort_unstable();
+        numbers.dedup();
+        numbers.len()
+    }
+}
+
+impl BosDescriptor {
+    /// Parses the contents of a sysfs `bos_descriptors` file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data does not start with a BOS descriptor or a
+    /// capability descriptor is truncated.
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        let mut descriptors = split_descriptors(data)?.into_iter();
+        match descriptors.next() {
+            Some(desc) if desc[1] == BOS => {}
+            _ => return Err("Descriptor data does not start with a BOS descriptor".to_string()),
+        }
+        let capabilities = descriptors
+            .filter(|desc| desc[1] == DEVICE_CAPABILITY)
+            .map(parse_capability)
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self { capabilities })
+    }
+
+    /// Renders the capabilities as indented text, similar to `lsusb -v`.
+    pub fn render_text(&self) -> String {
+        let mut out = String::from("Binary Object Store Descriptor:\n");
+        field(&mut out, 1, "bNumDeviceCaps", self.capabilities.len());
+        for capability in &self.capabilities {
+            let _ = writeln!(out, "  {}:", capability.name());
+            field(
+                &mut out,
+                2,
+                "bDevCapabilityType",
+                capability.capability_type,
+            );
+            match capability.describe() {
+                Some(description) => field(&mut out, 2, "Details", description),
+                None => field(&mut out, 2, "Data", hex(&capability.data)),
+            }
+        }
+        out
+    }
+}
+
+impl DeviceCapability {
+    /// Returns the name of the capability type.
+    pub fn name(&self) -> &'static str {
+        match self.capability_type {
+            0x01 => "Wireless USB",
+            0x02 => "USB 2.0 Extension",
+            0x03 => "SuperSpeed USB",
+            0x04 => "Container ID",
+            0x05 => "Platform",
+            0x06 => "Power Delivery",
+            0x07 => "Battery Info",
+            0x08 => "PD Consumer Port",
+            0x09 => "PD Provider Port",
+            0x0a => "SuperSpeedPlus USB",
+            0x0b => "Precision Time Measurement",
+            0x0c => "Wireless USB Ext",
+            0x0d => "Billboard",
+            0x0e => "Authentication",
+            0x0f => "Billboard Ex",
+            0x10 => "Configuration Summary",
+            _ => "Unknown Capability",
+        }
+    }
+
+    /// Decodes well-known capabilities into a short description.
+    pub fn describe(&self) -> Option<String> {
+        match self.capability_type {
+            // bmAttributes (u32): bit 1 is Link Power Management
+            0x02 if self.data.len() >= 4 => {
+                let attributes =
+                    u32::from_le_bytes([self.data[0], self.data[1], self.data[2], self.data[3]]);
+                Some(if attributes & 0x02 != 0 {
+                    "Link Power Management (LPM) supported".to_string()
+                } else {
+                    "Link Power Management (LPM) not supported".to_string()
+                })
+            }
+            // bmAttributes, wSpeedsSupported, ...
+            0x03 if self.data.len() >= 3 => {
+                let speeds = u16::from_le_bytes([self.data[1], self.data[2]]);
+                let names: Vec<&str> = [
+                    (0x01, "Low Speed"),
+                    (0x02, "Full Speed"),
+                    (0x04, "High Speed"),
+                    (0x08, "SuperSpeed (5Gbps)"),
+                ]
+                .iter()
+                .filter(|(bit, _)| speeds & bit != 0)
+                .map(|(_, name)| *name)
+                .collect();
+                Some(format!("Speeds supported: {}", names.join(", ")))
+            }
+            // bReserved, ContainerID (16 bytes)
+            0x04 if self.data.len() >= 17 => {
+                let id = &self.data[1..17];
+                Some(format!(
+                    "{}-{}-{}-{}-{}",
+                    hex_compact(&id[0..4]),
+                    hex_compact(&id[4..6]),
+                    hex_compact(&id[6..8]),
+                    hex_compact(&id[8..10]),
+                    hex_compact(&id[10..16])
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Splits a descriptor blob into individual descriptors using `bLength`.
+fn split_descriptors(data: &[u8]) -> Result<Vec<&[u8]>, String> {
+    let mut descriptors = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let length = data[offset] as usize;
+        if length < 2 {
+            return Err(format!(
+                "Invalid descriptor length {length} at offset {offset}"
+            ));
+        }
+        if offset + length > data.len() {
+            return Err(format!(
+                "Descriptor at offset {offset} is truncated ({length} bytes declared, {} available)",
+                data.len() - offset
+            ));
+        }
+        descriptors.push(&data[offset..offset + length]);
+        offset += length;
+    }
+    Ok(descriptors)
+}
+
+fn require(desc: &[u8], min: usize, name: &str) -> Result<(), String> {
+    if desc.len() < min {
+        return Err(format!(
+            "{name} descriptor too short: {} bytes, expected {min}",
+            desc.len()
+        ));
+    }
+    Ok(())
+}
+
+fn word(desc: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([desc[offset], desc[offset + 1]])
+}
+
+/// Formats a binary-coded decimal version such as 0x0210 as "2.10".
+fn bcd(value: u16) -> String {
+    format!("{:x}.{:02x}", value >> 8, value & 0xff)
+}
+
+fn parse_device(desc: &[u8]) -> Result<DeviceDescriptor, String> {
+    require(desc, 18, "Device")?;
+    Ok(DeviceDescriptor {
+        usb_version: bcd(word(desc, 2)),
+        class: ClassCode::new(desc[4], desc[5], desc[6]),
+        max_packet_size0: desc[7],
+        vendor_id: word(desc, 8),
+        product_id: word(desc, 10),
+        device_version: bcd(word(desc, 12)),
+        manufacturer_index: desc[14],
+        product_index: desc[15],
+        serial_number_index: desc[16],
+        num_configurations: desc[17],
+    })
+}
+
+fn parse_configuration(desc: &[u8], super_speed: bool) -> Result<ConfigurationDescriptor, String> {
+    require(desc, 9, "Configuration")?;
+    // bMaxPower is in 2 mA units, or 8 mA units when operating at SuperSpeed
+    let power_unit = if super_speed { 8 } else { 2 };
+    Ok(ConfigurationDescriptor {
+        value: desc[5],
+        string_index: desc[6],
+        attributes: desc[7],
+        self_powered: desc[7] & 0x40 != 0,
+        remote_wakeup: desc[7] & 0x20 != 0,
+        max_power_ma: desc[8] as u16 * power_unit,
+        associations: Vec::new(),
+        interfaces: Vec::new(),
+        extra: Vec::new(),
+    })
+}
+
+fn parse_interface(desc: &[u8]) -> Result<InterfaceDescriptor, String> {
+    require(desc, 9, "Interface")?;
+    Ok(InterfaceDescriptor {
+        number: desc[2],
+        alternate_setting: desc[3],
+        class: ClassCode::new(desc[5], desc[6], desc[7]),
+        string_index: desc[8],
+        hid: None,
+        endpoints: Vec::new(),
+        extra: Vec::new(),
+    })
+}
+
+fn parse_association(desc: &[u8]) -> Result<InterfaceAssociation, String> {
+    require(desc, 8, "Interface association")?;
+    Ok(InterfaceAssociation {
+        first_interface: desc[2],
+        interface_count: desc[3],
+        class: ClassCode::new(desc[4], desc[5], desc[6]),
+        string_index: desc[7],
+    })
+}
+
+fn parse_endpoint(desc: &[u8]) -> Result<EndpointDescriptor, String> {
+    require(desc, 7, "Endpoint")?;
+    let address = desc[2];
+    let attributes = desc[3];
+    let packet_size = word(desc, 4);
+    Ok(EndpointDescriptor {
+        address,
+        number: address & 0x0f,
+        direction: if address & 0x80 != 0 {
+            Direction::In
+        } else {
+            Direction::Out
+        },
+        transfer_type: match attributes & 0x03 {
+            0 => TransferType::Control,
+            1 => TransferType::Isochronous,
+            2 => TransferType::Bulk,
+            _ => TransferType::Interrupt,
+        },
+        attributes,
+        max_packet_size: packet_size & 0x07ff,
+        transactions_per_microframe: ((packet_size >> 11) & 0x03) as u8,
+        interval: desc[6],
+        extra: Vec::new(),
+    })
+}
+
+fn parse_hid(desc: &[u8]) -> Result<HidDescriptor, String> {
+    require(desc, 6, "HID")?;
+    let count = desc[5] as usize;
+    require(desc, 6 + count * 3, "HID")?;
+    let descriptors = (0..count)
+        .map(|i| {
+            let offset = 6 + i * 3;
+            (desc[offset], word(desc, offset + 1))
+        })
+        .collect();
+    Ok(HidDescriptor {
+        hid_version: bcd(word(desc, 2)),
+        country_code: desc[4],
+        descriptors,
+    })
+}
+
+fn parse_capability(desc: &[u8]) -> Result<DeviceCapability, String> {
+    require(desc, 3, "Device capability")?;
+    Ok(DeviceCapability {
+        capability_type: desc[2],
+        data: desc[3..].to_vec(),
+    })
+}
+
+fn raw(desc: &[u8]) -> RawDescriptor {
+    RawDescriptor {
+        descriptor_type: desc[1],
+        data: desc[2..].to_vec(),
+    }
+}
+
+fn field(out: &mut String, depth: usize, name: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(out, "{:indent$}{name:<20} {value}", "", indent = depth * 2);
+}
+
+fn class_fields(out: &mut String, depth: usize, prefix: &str, class: &ClassCode) {
+    let usb_class = class.usb_class();
+    field(
+        out,
+        depth,
+        &format!("{prefix}Class"),
+        format!("0x{:02x} {}", class.class, usb_class.label()),
+    );
+    field(
+        out,
+        depth,
+        &format!("{prefix}SubClass"),
+        format!("0x{:02x}", class.subclass),
+    );
+    let refinement = class
+        .refinement()
+        .map(|r| format!(" {r}"))
+        .unwrap_or_default();
+    field(
+        out,
+        depth,
+        &format!("{prefix}Protocol"),
+        format!("0x{:02x}{refinement}", class.protocol),
+    );
+}
+
+fn raw_fields(out: &mut String, depth: usize, descriptors: &[RawDescriptor]) {
+    for descriptor in descriptors {
+        field(
+            out,
+            depth,
+            &format!("Descriptor 0x{:02x}", descriptor.descriptor_type),
+            hex(&descriptor.data),
+        );
+    }
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn hex_compact(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{b:02x}")).collect()
+}
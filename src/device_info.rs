@@ -3,6 +3,12 @@
 //! Core data structures for representing USB device information and events in the usbwatch monitoring system.
 //! Supports Linux, Windows, and macOS device handles and event types.
 
+use crate::baseline::BaselineStatus;
+use crate::descriptors::DescriptorTree;
+use crate::hid::HidClass;
+use crate::metadata::{monotonic_now_ns, EventMetadata};
+use crate::policy::PolicyDecision;
+use crate::usb_class::{ClassCode, UsbClass};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -75,15 +81,87 @@ pub struct UsbDeviceInfo {
     pub product_id: String,
     /// Optional serial number of the device
     pub serial_number: Option<String>,
+    /// Vendor name from the USB ID database
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor_name: Option<String>,
+    /// Product name from the USB ID database
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub product_name: Option<String>,
+    /// Device class name from the USB ID database
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class_name: Option<String>,
+    /// Class, subclass and protocol from the device descriptor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_class: Option<ClassCode>,
+    /// Interfaces of the active configuration
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interfaces: Vec<UsbInterfaceInfo>,
+    /// Decoded USB descriptors, when requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub descriptors: Option<DescriptorTree>,
     /// UTC timestamp when the event occurred
     pub timestamp: DateTime<Utc>,
-    /// Type of device event (connected or disconnected)
+    /// Monotonic clock reading taken with `timestamp`, in nanoseconds (Unix); reported as
+    /// `metadata.monotonic_ns`
+    #[serde(skip)]
+    pub monotonic_ns: Option<u64>,
+    /// Type of device event (connected, disconnected or flapping)
     pub event_type: DeviceEventType,
+    /// UTC timestamp when the device was connected (disconnect events only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connected_at: Option<DateTime<Utc>>,
+    /// How long the device was connected, in seconds (disconnect events only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+    /// Number of state changes collapsed into this event (flapping events only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flap_count: Option<u32>,
+    /// Physical port path (e.g. "1-1.2" on Linux, "usb1" for a root hub)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port_path: Option<String>,
+    /// Authorisation policy decision (connect events, when a policy is configured)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<PolicyDecision>,
+    /// Whether the device is in the known-device baseline (when monitoring with a baseline)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<BaselineStatus>,
+    /// Sequence number and host identity (when monitoring with --metadata)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<EventMetadata>,
+    /// Host of the agent that sent the event (events received by a collector)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    /// Why the device was flagged (suspicious device events only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Devices behind this hub that changed state in the same scan, when hub events are collapsed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<UsbDeviceInfo>,
     /// Platform-specific device handle for advanced operations
     #[serde(skip)]
     pub device_handle: DeviceHandle,
 }
 
+/// Information about one interface of a USB device.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsbInterfaceInfo {
+    /// Interface identifier (e.g. "1-1.2:1.0" on Linux)
+    pub id: String,
+    /// Interface number (`bInterfaceNumber`)
+    pub number: u8,
+    /// Class, subclass and protocol from the interface descriptor
+    pub class: ClassCode,
+    /// Interface class name from the USB ID database
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class_name: Option<String>,
+    /// Name of the kernel driver bound to the interface
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub driver: Option<String>,
+    /// Application collections from the HID report descriptor (HID interfaces only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hid_classes: Vec<HidClass>,
+}
+
 /// Types of USB device events that can be monitored.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum DeviceEventType {
@@ -91,6 +169,23 @@ pub enum DeviceEventType {
     Connected,
     /// Device was disconnected from the system
     Disconnected,
+    /// Device connected and disconnected repeatedly in a short burst
+    Flapping,
+    /// Newly connected device has a suspicious combination of functions
+    SuspiciousDevice,
+    /// Newly connected device is running slower than it supports
+    Degraded,
+}
+
+impl DeviceEventType {
+    /// Returns whether this event reports a change in the device's
+    /// connection state, as opposed to a notice about the device.
+    pub fn is_state_change(&self) -> bool {
+        matches!(
+            self,
+            DeviceEventType::Connected | DeviceEventType::Disconnected
+        )
+    }
 }
 
 impl UsbDeviceInfo {
@@ -133,8 +228,25 @@ impl UsbDeviceInfo {
             vendor_id,
             product_id,
             serial_number,
+            vendor_name: None,
+            product_name: None,
+            class_name: None,
+            device_class: None,
+            interfaces: Vec::new(),
+            descriptors: None,
             timestamp: Utc::now(),
+            monotonic_ns: monotonic_now_ns(),
             event_type,
+            connected_at: None,
+            duration: None,
+            flap_count: None,
+            port_path: None,
+            policy: None,
+            baseline: None,
+            metadata: None,
+            origin: None,
+            reason: None,
+            children: Vec::new(),
             device_handle: DeviceHandle::Unknown,
         }
     }
@@ -186,17 +298,250 @@ impl UsbDeviceInfo {
             vendor_id,
             product_id,
             serial_number,
+            vendor_name: None,
+            product_name: None,
+            class_name: None,
+            device_class: None,
+            interfaces: Vec::new(),
+            descriptors: None,
             timestamp: Utc::now(),
+            monotonic_ns: monotonic_now_ns(),
             event_type,
+            connected_at: None,
+            duration: None,
+            flap_count: None,
+            port_path: None,
+            policy: None,
+            baseline: None,
+            metadata: None,
+            origin: None,
+            reason: None,
+            children: Vec::new(),
             device_handle,
         }
     }
 
+    /// Returns the key used to track this device across events.
+    ///
+    /// The key has the form `VID:PID:serial`, with `unknown` in place of a
+    /// missing serial number.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use usbwatch_rs::device_info::{UsbDeviceInfo, DeviceEventType};
+    ///
+    /// let device = UsbDeviceInfo::new(
+    ///     "USB Storage".to_string(),
+    ///     "0781".to_string(),
+    ///     "5583".to_string(),
+    ///     None,
+    ///     DeviceEventType::Connected,
+    /// );
+    /// assert_eq!(device.device_key(), "0781:5583:unknown");
+    /// ```
+    pub fn device_key(&self) -> String {
+        format!(
+            "{}:{}:{}",
+            self.vendor_id,
+            self.product_id,
+            self.serial_number.as_deref().unwrap_or("unknown")
+        )
+    }
+
+    /// Returns the USB classes this device implements.
+    ///
+    /// The device class is included unless it defers to the interfaces
+    /// (`per-interface`, or `miscellaneous` for composite devices), followed
+    /// by each distinct interface class.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use usbwatch_rs::device_info::{UsbDeviceInfo, DeviceEventType};
+    /// use usbwatch_rs::usb_class::{ClassCode, UsbClass};
+    ///
+    /// let mut device = UsbDeviceInfo::new(
+    ///     "USB Hub".to_string(),
+    ///     "05e3".to_string(),
+    ///     "0610".to_string(),
+    ///     None,
+    ///     DeviceEventType::Connected,
+    /// );
+    /// device.device_class = Some(ClassCode::new(0x09, 0x00, 0x02));
+    /// assert_eq!(device.classes(), vec![UsbClass::Hub]);
+    /// ```
+    pub fn classes(&self) -> Vec<UsbClass> {
+        let mut classes = Vec::new();
+        if let Some(code) = &self.device_class {
+            let class = code.usb_class();
+            if !matches!(class, UsbClass::PerInterface | UsbClass::Miscellaneous) {
+                classes.push(class);
+            }
+        }
+        for interface in &self.interfaces {
+            let class = interface.class.usb_class();
+            if !classes.contains(&class) {
+                classes.push(class);
+            }
+        }
+        classes
+    }
+
+    /// Describes the device and interface classes, e.g.
+    /// `HID (Boot Keyboard), Mass Storage (SCSI, Bulk-Only)`.
+    ///
+    /// Returns `None` when no class information is known.
+    pub fn class_summary(&self) -> Option<String> {
+        let mut descriptions: Vec<String> = Vec::new();
+        if let Some(code) = &self.device_class {
+            if !matches!(
+                code.usb_class(),
+                UsbClass::PerInterface | UsbClass::Miscellaneous
+            ) || self.interfaces.is_empty()
+            {
+                descriptions.push(code.to_string());
+            }
+        }
+        for interface in &self.interfaces {
+            let description = interface.class.to_string();
+            if !descriptions.contains(&description) {
+                descriptions.push(description);
+            }
+        }
+        (!descriptions.is_empty()).then(|| descriptions.join(", "))
+    }
+
+    /// Returns the HID application collections across all interfaces.
+    pub fn hid_classes(&self) -> Vec<HidClass> {
+        let mut classes = Vec::new();
+        for class in self.interfaces.iter().flat_map(|i| &i.hid_classes) {
+            if !classes.contains(class) {
+                classes.push(*class);
+            }
+        }
+        classes
+    }
+
+    /// Returns whether the device can type keystrokes, judged by its HID
+    /// report descriptors or, failing that, a boot keyboard interface.
+    pub fn has_keyboard(&self) -> bool {
+        self.interfaces.iter().any(|interface| {
+            interface.hid_classes.contains(&HidClass::Keyboard)
+                || (interface.hid_classes.is_empty() && interface.class.is_boot_keyboard())
+        })
+    }
+
+    /// Explains why the device looks like a keystroke injector, if it does.
+    ///
+    /// A device that presents both mass storage and a keyboard is a classic
+    /// BadUSB pattern: a "flash drive" that types commands when plugged in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo, UsbInterfaceInfo};
+    /// use usbwatch_rs::hid::HidClass;
+    /// use usbwatch_rs::usb_class::ClassCode;
+    ///
+    /// let interface = |number, class, hid_classes| UsbInterfaceInfo {
+    ///     id: format!("1-1:1.{number}"),
+    ///     number,
+    ///     class,
+    ///     class_name: None,
+    ///     driver: None,
+    ///     hid_classes,
+    /// };
+    /// let mut device = UsbDeviceInfo::new(
+    ///     "Flash Drive".to_string(),
+    ///     "1234".to_string(),
+    ///     "5678".to_string(),
+    ///     None,
+    ///     DeviceEventType::Connected,
+    /// );
+    /// device.interfaces = vec![
+    ///     interface(0, ClassCode::new(0x08, 0x06, 0x50), vec![]),
+    ///     interface(1, ClassCode::new(0x03, 0x00, 0x00), vec![HidClass::Keyboard]),
+    /// ];
+    /// assert!(device.suspicious_reason().is_some());
+    /// ```
+    pub fn suspicious_reason(&self) -> Option<String> {
+        let classes = self.classes();
+        if classes.contains(&UsbClass::MassStorage) && self.has_keyboard() {
+            return Some(
+                "Composite device combines mass storage with a keyboard interface".to_string(),
+            );
+        }
+        None
+    }
+
+    /// Returns a `SuspiciousDevice` event for this device if it looks like a
+    /// keystroke injector.
+    pub fn suspicious_event(&self) -> Option<UsbDeviceInfo> {
+        let reason = self.suspicious_reason()?;
+        Some(self.notice(DeviceEventType::SuspiciousDevice, reason))
+    }
+
+    /// Returns a `Degraded` event for this device, e.g. with a reason from
+    /// [`LinkInfo::degraded_reason`](crate::link::LinkInfo::degraded_reason).
+    pub fn degraded_event(&self, reason: String) -> UsbDeviceInfo {
+        self.notice(DeviceEventType::Degraded, reason)
+    }
+
+    /// Returns a `Flapping` event for this device explaining why it was
+    /// raised, e.g. a flap-rate alert.
+    pub fn flapping_event(&self, reason: String) -> UsbDeviceInfo {
+        self.notice(DeviceEventType::Flapping, reason)
+    }
+
+    /// Returns a copy of this record as a notice of the given type.
+    fn notice(&self, event_type: DeviceEventType, reason: String) -> UsbDeviceInfo {
+        let mut event = self.clone();
+        event.event_type = event_type;
+        event.reason = Some(reason);
+        event.connected_at = None;
+        event.duration = None;
+        event
+    }
+
+    /// Turns this record into a disconnect event stamped with the current time.
+    ///
+    /// If the connection time is known, `connected_at` and `duration` are
+    /// filled in so the event records how long the device was plugged in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use usbwatch_rs::device_info::{UsbDeviceInfo, DeviceEventType};
+    ///
+    /// let mut device = UsbDeviceInfo::new(
+    ///     "USB Storage".to_string(),
+    ///     "0781".to_string(),
+    ///     "5583".to_string(),
+    ///     None,
+    ///     DeviceEventType::Connected,
+    /// );
+    /// let connected_at = device.timestamp - chrono::Duration::seconds(30);
+    /// device.mark_disconnected(Some(connected_at));
+    ///
+    /// assert_eq!(device.event_type, DeviceEventType::Disconnected);
+    /// assert!(device.duration.unwrap() >= 30.0);
+    /// ```
+    pub fn mark_disconnected(&mut self, connected_at: Option<DateTime<Utc>>) {
+        self.event_type = DeviceEventType::Disconnected;
+        self.timestamp = Utc::now();
+        self.monotonic_ns = monotonic_now_ns();
+        self.connected_at = connected_at;
+        self.duration = connected_at
+            .map(|since| (self.timestamp - since).num_milliseconds().max(0) as f64 / 1000.0);
+    }
+
     /// Formats the device information as a human-readable string.
     ///
     /// Returns a formatted string suitable for console output or log files.
-    /// The format includes timestamp, event type, device name, VID/PID, and
-    /// optional serial number.
+    /// The format includes timestamp, event type, device name, VID/PID,
+    /// optional serial number and, for disconnects, how long the device was
+    /// connected.
     ///
     /// # Examples
     ///
@@ -218,6 +563,9 @@ impl UsbDeviceInfo {
         let event_str = match self.event_type {
             DeviceEventType::Connected => "CONNECTED",
             DeviceEventType::Disconnected => "DISCONNECTED",
+            DeviceEventType::Flapping => "FLAPPING",
+            DeviceEventType::SuspiciousDevice => "SUSPICIOUS",
+            DeviceEventType::Degraded => "DEGRADED",
         };
 
         let serial_str = self
@@ -226,16 +574,87 @@ impl UsbDeviceInfo {
             .map(|s| format!(" Serial: {s}"))
             .unwrap_or_default();
 
+        let duration_str = self
+            .duration
+            .map(|d| format!(" Connected for: {}", crate::report::format_duration(d)))
+            .unwrap_or_default();
+
+        let flap_str = self
+            .flap_count
+            .map(|n| format!(" Changes: {n}"))
+            .unwrap_or_default();
+
+        let policy_str = self
+            .policy
+            .as_ref()
+            .map(|p| format!(" Policy: {p}"))
+            .unwrap_or_default();
+
+        let baseline_str = self
+            .baseline
+            .map(|b| format!(" Baseline: {b}"))
+            .unwrap_or_default();
+
+        let metadata_str = self
+            .metadata
+            .as_ref()
+            .map(|m| match &m.hostname {
+                Some(host) => format!(" Seq: {} Host: {host}", m.seq),
+                None => format!(" Seq: {}", m.seq),
+            })
+            .unwrap_or_default();
+
+        let origin_str = self
+            .origin
+            .as_ref()
+            .map(|o| format!(" Origin: {o}"))
+            .unwrap_or_default();
+
+        let reason_str = self
+            .reason
+            .as_ref()
+            .map(|r| format!(" Reason: {r}"))
+            .unwrap_or_default();
+
+        let children_str = if self.children.is_empty() {
+            String::new()
+        } else {
+            format!(" Children: [{}]", self.children_summary())
+        };
+
         format!(
-            "[{}] {} - {} (VID: {}, PID: {}){}",
+            "[{}] {} - {} (VID: {}, PID: {}){}{}{}{}{}{}{}{}{}",
             self.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
             event_str,
             self.device_name,
             self.vendor_id,
             self.product_id,
-            serial_str
+            serial_str,
+            duration_str,
+            flap_str,
+            policy_str,
+            baseline_str,
+            reason_str,
+            metadata_str,
+            origin_str,
+            children_str
         )
     }
+
+    /// Summarises the collapsed child devices as a comma-separated list of
+    /// names with VID:PID.
+    pub fn children_summary(&self) -> String {
+        self.children
+            .iter()
+            .map(|child| {
+                format!(
+                    "{} ({}:{})",
+                    child.device_name, child.vendor_id, child.product_id
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
 }
 
 impl AsDeviceHandle for UsbDeviceInfo {
@@ -255,6 +674,26 @@ impl std::fmt::Display for DeviceEventType {
         match self {
             DeviceEventType::Connected => write!(f, "Connected"),
             DeviceEventType::Disconnected => write!(f, "Disconnected"),
+            DeviceEventType::Flapping => write!(f, "Flapping"),
+            DeviceEventType::SuspiciousDevice => write!(f, "SuspiciousDevice"),
+            DeviceEventType::Degraded => write!(f, "Degraded"),
+        }
+    }
+}
+
+impl std::str::FromStr for DeviceEventType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "connected" | "connect" => Ok(DeviceEventType::Connected),
+            "disconnected" | "disconnect" => Ok(DeviceEventType::Disconnected),
+            "flapping" => Ok(DeviceEventType::Flapping),
+            "suspiciousdevice" | "suspicious-device" | "suspicious" => {
+                Ok(DeviceEventType::SuspiciousDevice)
+            }
+            "degraded" => Ok(DeviceEventType::Degraded),
+            _ => Err(format!("Unknown event type '{s}'")),
         }
     }
 }
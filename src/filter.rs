@@ -0,0 +1,112 @@
+//! Event filtering.
+//!
+//! An [`EventFilter`] selects which device events are passed on to the logger. Filters are applied as a stage
+//! in the event pipeline by [`filter_task`], so they work the same for live monitoring and replayed logs.
+
+use crate::device_info::{DeviceEventType, UsbDeviceInfo};
+use crate::usb_class::UsbClass;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+/// Criteria that device events must meet to be reported.
+///
+/// An empty filter matches every event; otherwise an event must meet every
+/// criterion that is set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventFilter {
+    /// Only match devices implementing one of these classes, on the device or any interface
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub classes: Vec<UsbClass>,
+    /// Only match devices with this vendor ID (hex, case-insensitive)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vendor_id: Option<String>,
+    /// Only match devices with this product ID (hex, case-insensitive)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub product_id: Option<String>,
+    /// Only match devices with this serial number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub serial_number: Option<String>,
+    /// Only match devices on this port or behind it (e.g. `1-2` matches `1-2` and `1-2.4`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<String>,
+    /// Only match these event types
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub event_types: Vec<DeviceEventType>,
+}
+
+impl EventFilter {
+    /// Returns whether the filter has no criteria.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns whether an event matches the filter.
+    ///
+    /// Collapsed hub events match if the hub or any of its children match.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+    /// use usbwatch_rs::filter::EventFilter;
+    /// use usbwatch_rs::usb_class::{ClassCode, UsbClass};
+    ///
+    /// let mut device = UsbDeviceInfo::new(
+    ///     "USB Storage".to_string(),
+    ///     "0781".to_string(),
+    ///     "5583".to_string(),
+    ///     None,
+    ///     DeviceEventType::Connected,
+    /// );
+    /// device.device_class = Some(ClassCode::new(0x08, 0x06, 0x50));
+    ///
+    /// let filter = EventFilter {
+    ///     classes: vec![UsbClass::MassStorage],
+    ///     ..Default::default()
+    /// };
+    /// assert!(filter.matches(&device));
+    /// ```
+    pub fn matches(&self, event: &UsbDeviceInfo) -> bool {
+        let device_matches = (self.classes.is_empty()
+            || event
+                .classes()
+                .iter()
+                .any(|class| self.classes.contains(class)))
+            && matches_id(self.vendor_id.as_deref(), &event.vendor_id)
+            && matches_id(self.product_id.as_deref(), &event.product_id)
+            && self
+                .serial_number
+                .as_ref()
+                .is_none_or(|serial| event.serial_number.as_ref() == Some(serial))
+            && self.port.as_deref().is_none_or(|port| {
+                event.port_path.as_deref().is_some_and(|path| {
+                    path == port
+                        || path
+                            .strip_prefix(port)
+                            .is_some_and(|rest| rest.starts_with('.'))
+                })
+            })
+            && (self.event_types.is_empty() || self.event_types.contains(&event.event_type));
+        device_matches || event.children.iter().any(|child| self.matches(child))
+    }
+}
+
+fn matches_id(wanted: Option<&str>, id: &str) -> bool {
+    wanted.is_none_or(|wanted| wanted.eq_ignore_ascii_case(id))
+}
+
+/// Async task that forwards the events from `rx` that match `filter` to `tx`.
+///
+/// The task ends when `rx` closes or `tx` is dropped.
+pub async fn filter_task(
+    mut rx: mpsc::Receiver<UsbDeviceInfo>,
+    tx: mpsc::Sender<UsbDeviceInfo>,
+    filter: EventFilter,
+) {
+    while let Some(event) = rx.recv().await {
+        if filter.matches(&event) && tx.send(event).await.is_err() {
+            return;
+        }
+    }
+}
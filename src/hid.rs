@@ -0,0 +1,167 @@
+//! HID report descriptor analysis.
+//!
+//! A HID interface describes what it can do in its report descriptor (exposed by Linux as `report_descriptor`
+//! under the HID device in sysfs). The top-level application collections tell us whether an interface can type
+//! keystrokes, move a pointer, send consumer control keys or only exchange vendor-defined reports, regardless of
+//! what the interface descriptor's boot protocol claims. This is the basis for spotting keystroke injectors
+//! (BadUSB) that pose as storage devices.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Usage page for generic desktop controls.
+const GENERIC_DESKTOP: u16 = 0x01;
+/// Usage page for consumer controls.
+const CONSUMER: u16 = 0x0c;
+/// Start of the vendor-defined usage page range.
+const VENDOR_DEFINED_MIN: u16 = 0xff00;
+
+/// Kind of top-level HID application collection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HidClass {
+    /// Keyboard or keypad (can inject keystrokes)
+    Keyboard,
+    /// Mouse or other pointer
+    Mouse,
+    /// Joystick
+    Joystick,
+    /// Game pad
+    Gamepad,
+    /// Consumer control (media keys, volume)
+    ConsumerControl,
+    /// System control (power, sleep)
+    SystemControl,
+    /// Vendor-defined usage page
+    VendorDefined,
+    /// Any other application collection
+    Other,
+}
+
+impl HidClass {
+    /// Classifies an application collection by its usage page and usage.
+    pub fn from_usage(usage_page: u16, usage: u16) -> Self {
+        match (usage_page, usage) {
+            (GENERIC_DESKTOP, 0x06 | 0x07) => HidClass::Keyboard,
+            (GENERIC_DESKTOP, 0x01 | 0x02) => HidClass::Mouse,
+            (GENERIC_DESKTOP, 0x04) => HidClass::Joystick,
+            (GENERIC_DESKTOP, 0x05) => HidClass::Gamepad,
+            (GENERIC_DESKTOP, 0x80) => HidClass::SystemControl,
+            (CONSUMER, 0x01) => HidClass::ConsumerControl,
+            (page, _) if page >= VENDOR_DEFINED_MIN => HidClass::VendorDefined,
+            _ => HidClass::Other,
+        }
+    }
+}
+
+impl fmt::Display for HidClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            HidClass::Keyboard => "keyboard",
+            HidClass::Mouse => "mouse",
+            HidClass::Joystick => "joystick",
+            HidClass::Gamepad => "gamepad",
+            HidClass::ConsumerControl => "consumer-control",
+            HidClass::SystemControl => "system-control",
+            HidClass::VendorDefined => "vendor-defined",
+            HidClass::Other => "other",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Classifies a HID report descriptor by its top-level application collections.
+///
+/// Each distinct kind is listed once, in the order it appears.
+///
+/// # Errors
+///
+/// Returns an error if an item runs past the end of the descriptor.
+///
+/// # Examples
+///
+/// ```
+/// use usbwatch_rs::hid::{classify_report_descriptor, HidClass};
+///
+/// // Usage Page (Generic Desktop), Usage (Keyboard), Collection (Application), ..., End Collection
+/// let keyboard = [0x05, 0x01, 0x09, 0x06, 0xa1, 0x01, 0x05, 0x07, 0x19, 0xe0, 0xc0];
+/// assert_eq!(classify_report_descriptor(&keyboard).unwrap(), vec![HidClass::Keyboard]);
+/// ```
+pub fn classify_report_descriptor(descriptor: &[u8]) -> Result<Vec<HidClass>, String> {
+    let mut classes = Vec::new();
+    let mut usage_page: u16 = 0;
+    let mut page_stack: Vec<u16> = Vec::new();
+    let mut usages: Vec<(u16, u16)> = Vec::new();
+    let mut depth = 0usize;
+    let mut offset = 0;
+
+    while offset < descriptor.len() {
+        let prefix = descriptor[offset];
+
+        // Long items carry no usage information we need
+        if prefix == 0xfe {
+            let size = *descriptor
+                .get(offset + 1)
+                .ok_or("Truncated long item in report descriptor")? as usize;
+            offset += 3 + size;
+            if offset > descriptor.len() {
+                return Err("Truncated long item in report descriptor".to_string());
+            }
+            continue;
+        }
+
+        let size = match prefix & 0x03 {
+            3 => 4,
+            n => n as usize,
+        };
+        let data = descriptor
+            .get(offset + 1..offset + 1 + size)
+            .ok_or_else(|| format!("Truncated item at offset {offset} in report descriptor"))?;
+        let value = data
+            .iter()
+            .rev()
+            .fold(0u32, |acc, byte| (acc << 8) | *byte as u32);
+        offset += 1 + size;
+
+        // Prefix without the size bits: tag and type
+        match prefix & 0xfc {
+            // Global: Usage Page
+            0x04 => usage_page = value as u16,
+            // Global: Push / Pop
+            0xa4 => page_stack.push(usage_page),
+            0xb4 => usage_page = page_stack.pop().unwrap_or(usage_page),
+            // Local: Usage, with an optional usage page in the upper 16 bits
+            0x08 => {
+                let page = if size == 4 {
+                    (value >> 16) as u16
+                } else {
+                    usage_page
+                };
+                usages.push((page, value as u16));
+            }
+            // Main: Collection
+            0xa0 => {
+                // Collection type 0x01 is Application
+                if depth == 0 && value == 0x01 {
+                    let (page, usage) = usages.first().copied().unwrap_or((usage_page, 0));
+                    let class = HidClass::from_usage(page, usage);
+                    if !classes.contains(&class) {
+                        classes.push(class);
+                    }
+                }
+                depth += 1;
+                usages.clear();
+            }
+            // Main: End Collection
+            0xc0 => {
+                depth = depth.saturating_sub(1);
+                usages.clear();
+            }
+            // Main: Input, Output, Feature clear the local state
+            0x80 | 0x90 | 0xb0 => usages.clear(),
+            _ => {}
+        }
+    }
+
+    Ok(classes)
+}
@@ -0,0 +1,130 @@
+//! Queries over past USB device events.
+//!
+//! [`HistoryQuery`] describes which past events to return. It is answered by the SQLite
+//! [`EventStore`](crate::store::EventStore) (`sqlite` feature) and by the daemon's in-memory history, which uses
+//! [`HistoryQuery::apply`].
+
+use crate::device_info::{DeviceEventType, UsbDeviceInfo};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Filter criteria for querying past events.
+///
+/// All fields are optional; unset fields do not restrict the result.
+/// Results are returned newest first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryQuery {
+    /// Only return events at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only return events at or before this time
+    pub until: Option<DateTime<Utc>>,
+    /// USB Vendor ID in hexadecimal format (case-insensitive)
+    pub vendor_id: Option<String>,
+    /// USB Product ID in hexadecimal format (case-insensitive)
+    pub product_id: Option<String>,
+    /// Exact serial number
+    pub serial_number: Option<String>,
+    /// Event type to match
+    pub event_type: Option<DeviceEventType>,
+    /// Maximum number of events to return
+    pub limit: Option<usize>,
+}
+
+impl HistoryQuery {
+    /// Returns whether an event matches the query's criteria (ignoring `limit`).
+    ///
+    /// A collapsed hub event matches if the hub or one of the devices behind it
+    /// matches the device criteria.
+    pub fn matches(&self, event: &UsbDeviceInfo) -> bool {
+        self.since.is_none_or(|since| event.timestamp >= since)
+            && self.until.is_none_or(|until| event.timestamp <= until)
+            && self
+                .event_type
+                .as_ref()
+                .is_none_or(|event_type| &event.event_type == event_type)
+            && std::iter::once(event)
+                .chain(&event.children)
+                .any(|device| self.matches_device(device))
+    }
+
+    fn matches_device(&self, device: &UsbDeviceInfo) -> bool {
+        self.vendor_id
+            .as_ref()
+            .is_none_or(|vid| device.vendor_id.eq_ignore_ascii_case(vid))
+            && self
+                .product_id
+                .as_ref()
+                .is_none_or(|pid| device.product_id.eq_ignore_ascii_case(pid))
+            && self
+                .serial_number
+                .as_ref()
+                .is_none_or(|serial| device.serial_number.as_ref() == Some(serial))
+    }
+
+    /// Selects the matching events from a list in chronological order,
+    /// returning them newest first and at most `limit` of them.
+    pub fn apply<'a, I>(&self, events: I) -> Vec<UsbDeviceInfo>
+    where
+        I: DoubleEndedIterator<Item = &'a UsbDeviceInfo>,
+    {
+        events
+            .rev()
+            .filter(|event| self.matches(event))
+            .take(self.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Parses a time bound given on the command line.
+///
+/// Accepts an RFC 3339 timestamp (`2025-07-27T10:30:00Z`), a date (`2025-07-27`,
+/// interpreted as midnight UTC) or a relative age such as `30m`, `24h` or `7d`
+/// (interpreted as that long before now).
+///
+/// # Errors
+///
+/// Returns an error if the value matches none of the accepted forms.
+///
+/// # Examples
+///
+/// ```
+/// use usbwatch_rs::history::parse_time_spec;
+///
+/// assert!(parse_time_spec("2025-07-27T10:30:00Z").is_ok());
+/// assert!(parse_time_spec("2025-07-27").is_ok());
+/// assert!(parse_time_spec("24h").is_ok());
+/// assert!(parse_time_spec("yesterday").is_err());
+/// assert!(parse_time_spec("-5h").is_err());
+/// ```
+pub fn parse_time_spec(spec: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(spec) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(spec, "%Y-%m-%d") {
+        if let Some(midnight) = date.and_hms_opt(0, 0, 0) {
+            return Ok(midnight.and_utc());
+        }
+    }
+
+    let invalid =
+        || format!("Invalid time '{spec}': expected RFC 3339, YYYY-MM-DD or an age like 24h");
+    let (split, _) = spec.char_indices().last().ok_or_else(invalid)?;
+    let (amount, unit) = spec.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .ok()
+        .filter(|amount| *amount > 0)
+        .ok_or_else(invalid)?;
+    let age = match unit {
+        "s" => Duration::try_seconds(amount),
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => None,
+    };
+    age.and_then(|age| Utc::now().checked_sub_signed(age))
+        .ok_or_else(invalid)
+}
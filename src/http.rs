@@ -0,0 +1,272 @@
+//! Embedded HTTP API with a Server-Sent Events stream.
+//!
+//! `usbwatch serve` runs the monitoring pipeline like the daemon and serves its [`DaemonState`] over HTTP. Devices and
+//! events use the same JSON serialization as `--json` output; errors are objects with an `error` message.
+//!
+//! | Endpoint             | Response                                                                            |
+//! |----------------------|-------------------------------------------------------------------------------------|
+//! | `GET /devices`       | Currently attached devices                                                          |
+//! | `GET /devices/{id}`  | One device by tracking key, port path, `VID:PID` or serial (404 if none, 409 if several) |
+//! | `GET /events`        | Recent events, newest first, filtered by `since`, `until`, `vid`, `pid`, `serial`, `event` and `limit` as in `usbwatch history` |
+//! | `GET /events/stream` | Server-Sent Events: each event as JSON `data`, named after its event type (`Connected`, ...) |
+//! | `GET /metrics`       | Prometheus metrics (see [`metrics`](crate::metrics))                                |
+//! | `GET /ws`            | WebSocket feed with a snapshot and client-side filters (see [`websocket`]) |
+//!
+//! When a token is configured, every request must carry `Authorization: Bearer <token>` or is answered with 401.
+//! Browsers cannot set that header on `EventSource` and `WebSocket` connections, so `/events/stream` and `/ws` also
+//! accept the token as an `access_token` query parameter (`/ws?access_token=<token>`).
+
+use crate::daemon::DaemonState;
+use crate::device_info::DeviceEventType;
+use crate::history::{parse_time_spec, HistoryQuery};
+use crate::websocket;
+use axum::extract::rejection::QueryRejection;
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+
+/// Default address for `usbwatch serve` to listen on.
+pub const DEFAULT_LISTEN: &str = "127.0.0.1:8080";
+
+/// Streaming endpoints that also take the token as a query parameter.
+const STREAM_PATHS: [&str; 2] = ["/events/stream", "/ws"];
+
+/// Options for the HTTP API.
+#[derive(Debug, Clone, Default)]
+pub struct HttpOptions {
+    /// Bearer token required on every request
+    pub token: Option<String>,
+    /// Only serve the WebSocket feed, not the REST API
+    pub websocket_only: bool,
+    /// Origins other than the server's own allowed to open the WebSocket feed
+    pub allowed_origins: Vec<String>,
+}
+
+/// Reads a bearer token from the first line of a file.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or its first line is empty.
+pub fn load_token(path: &std::path::Path) -> Result<String, Box<dyn std::error::Error>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read token file '{}': {e}", path.display()))?;
+    let token = content.lines().next().unwrap_or_default().trim();
+    if token.is_empty() {
+        return Err(format!("Token file '{}' is empty", path.display()).into());
+    }
+    Ok(token.to_string())
+}
+
+/// Builds the API's routes over `state`.
+pub fn router(state: Arc<DaemonState>, options: HttpOptions) -> Router {
+    let router = if options.websocket_only {
+        websocket::router(state, options.allowed_origins)
+    } else {
+        Router::new()
+            .route("/devices", get(list_devices))
+            .route("/devices/{id}", get(get_device))
+            .route("/events", get(list_events))
+            .route("/events/stream", get(stream_events))
+            .route("/metrics", get(metrics))
+            .with_state(state.clone())
+            .merge(websocket::router(state, options.allowed_origins))
+    }
+    .fallback(|| async { ApiError::new(StatusCode::NOT_FOUND, "Not found") });
+    match options.token {
+        Some(token) => router.layer(middleware::from_fn_with_state(
+            Arc::new(token),
+            require_token,
+        )),
+        None => router,
+    }
+}
+
+/// Serves the API on `listener` until the task is aborted.
+///
+/// # Errors
+///
+/// Returns an error if accepting connections fails.
+pub async fn serve(
+    listener: TcpListener,
+    state: Arc<DaemonState>,
+    options: HttpOptions,
+) -> std::io::Result<()> {
+    axum::serve(listener, router(state, options)).await
+}
+
+/// An error response: a status code and a JSON `error` message.
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(json!({ "error": self.message }))).into_response()
+    }
+}
+
+/// Query parameters carrying the token on streaming endpoints.
+#[derive(Debug, Default, Deserialize)]
+struct TokenParams {
+    access_token: Option<String>,
+}
+
+async fn require_token(State(token): State<Arc<String>>, request: Request, next: Next) -> Response {
+    let given = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let given_in_query = STREAM_PATHS
+        .contains(&request.uri().path())
+        .then(|| Query::<TokenParams>::try_from_uri(request.uri()).ok())
+        .flatten()
+        .and_then(|Query(params)| params.access_token);
+    if given.is_some_and(|given| token_matches(&token, given))
+        || given_in_query.is_some_and(|given| token_matches(&token, &given))
+    {
+        return next.run(request).await;
+    }
+    let mut response =
+        ApiError::new(StatusCode::UNAUTHORIZED, "Missing or invalid bearer token").into_response();
+    response.headers_mut().insert(
+        header::WWW_AUTHENTICATE,
+        header::HeaderValue::from_static("Bearer"),
+    );
+    response
+}
+
+/// Compares tokens in time independent of where they differ.
+fn token_matches(expected: &str, given: &str) -> bool {
+    let expected = Sha256::digest(expected.as_bytes());
+    let given = Sha256::digest(given.as_bytes());
+    expected
+        .iter()
+        .zip(given.iter())
+        .fold(0, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+async fn metrics(State(state): State<Arc<DaemonState>>) -> Response {
+    let text = state.metrics().render(&state.devices());
+    (
+        [(
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        text,
+    )
+        .into_response()
+}
+
+async fn list_devices(State(state): State<Arc<DaemonState>>) -> Response {
+    Json(state.devices()).into_response()
+}
+
+async fn get_device(
+    State(state): State<Arc<DaemonState>>,
+    Path(id): Path<String>,
+) -> Result<Response, ApiError> {
+    let mut devices = state.find_devices(&id);
+    match devices.len() {
+        0 => Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            format!("No attached device matches '{id}'"),
+        )),
+        1 => Ok(Json(devices.remove(0)).into_response()),
+        n => Err(ApiError::new(
+            StatusCode::CONFLICT,
+            format!("'{id}' matches {n} devices; use the tracking key or port path"),
+        )),
+    }
+}
+
+/// Query parameters of `GET /events`, named like the `history` options.
+#[derive(Debug, Default, Deserialize)]
+struct EventsParams {
+    since: Option<String>,
+    until: Option<String>,
+    vid: Option<String>,
+    pid: Option<String>,
+    serial: Option<String>,
+    event: Option<String>,
+    limit: Option<usize>,
+}
+
+impl EventsParams {
+    fn query(self) -> Result<HistoryQuery, String> {
+        Ok(HistoryQuery {
+            since: self.since.as_deref().map(parse_time_spec).transpose()?,
+            until: self.until.as_deref().map(parse_time_spec).transpose()?,
+            vendor_id: self.vid,
+            product_id: self.pid,
+            serial_number: self.serial,
+            event_type: self
+                .event
+                .as_deref()
+                .map(str::parse::<DeviceEventType>)
+                .transpose()?,
+            limit: self.limit,
+        })
+    }
+}
+
+async fn list_events(
+    State(state): State<Arc<DaemonState>>,
+    params: Result<Query<EventsParams>, QueryRejection>,
+) -> Result<Response, ApiError> {
+    let Query(params) = params.map_err(|e| ApiError::bad_request(e.body_text()))?;
+    let query = params.query().map_err(ApiError::bad_request)?;
+    let events = state
+        .history_blocking(query)
+        .await
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(events).into_response())
+}
+
+async fn stream_events(
+    State(state): State<Arc<DaemonState>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let metrics = state.metrics().clone();
+    let events = BroadcastStream::new(state.subscribe()).map(move |event| {
+        Ok(match event {
+            Ok(event) => Event::default()
+                .event(event.event_type.to_string())
+                .json_data(&event)
+                .unwrap_or_else(|e| Event::default().comment(format!("unserialisable event: {e}"))),
+            // Tell slow clients how many events they missed
+            Err(BroadcastStreamRecvError::Lagged(missed)) => {
+                metrics.record_dropped(missed);
+                Event::default().event("lagged").data(missed.to_string())
+            }
+        })
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,377 @@
+//! Detailed single-device reports (Linux).
+//!
+//! Collects everything sysfs knows about one USB device into a [`DeviceReport`]: the parsed device record with
+//! resolved names and interfaces, every readable sysfs attribute, device nodes, power management state, speed,
+//! authorisation and the decoded descriptors. Devices can be identified by port path, sysfs path, `VID:PID` or
+//! serial number with [`find_device`].
+
+use crate::descriptors::DescriptorTree;
+use crate::device_info::UsbDeviceInfo;
+use crate::watcher::linux::{list_device_paths, read_descriptors, read_device, read_link_info};
+use crate::watcher::WatcherOptions;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How deep to look below a device directory for device nodes.
+const MAX_NODE_DEPTH: usize = 8;
+
+/// Runtime power management state from the `power/` directory.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PowerInfo {
+    /// Runtime PM control: `auto` (autosuspend allowed) or `on`
+    pub control: Option<String>,
+    /// Runtime PM status, e.g. `active` or `suspended`
+    pub runtime_status: Option<String>,
+    /// Idle time before autosuspend, in milliseconds
+    pub autosuspend_delay_ms: Option<i64>,
+    /// Remote wakeup setting: `enabled` or `disabled`
+    pub wakeup: Option<String>,
+    /// Configured maximum power draw as reported by the kernel (e.g. `100mA`)
+    pub max_power: Option<String>,
+}
+
+/// Everything known about a single USB device.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceReport {
+    /// Parsed device record, with names and interfaces
+    pub device: UsbDeviceInfo,
+    /// sysfs directory of the device
+    pub sysfs_path: String,
+    /// Bus number
+    pub bus_number: Option<u32>,
+    /// Device number on the bus
+    pub device_number: Option<u32>,
+    /// USB version the device reports (e.g. `2.00`)
+    pub usb_version: Option<String>,
+    /// Negotiated speed in Mbit/s (e.g. `480`)
+    pub speed_mbps: Option<String>,
+    /// Why the link is slower than the device supports, if it is
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub degraded: Option<String>,
+    /// Whether the device is authorised to be used
+    pub authorized: Option<bool>,
+    /// Runtime power management state
+    pub power: PowerInfo,
+    /// Device nodes belonging to the device and its interfaces (e.g. `/dev/ttyUSB0`)
+    pub device_nodes: Vec<String>,
+    /// All readable sysfs attributes of the device directory
+    pub attributes: BTreeMap<String, String>,
+    /// Decoded descriptors, if they could be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub descriptors: Option<DescriptorTree>,
+}
+
+/// Resolves a device identifier to its sysfs directory.
+///
+/// `id` may be a sysfs path (anything containing `/`), a port path such as
+/// `1-1.2` or `usb1`, a `VID:PID` pair or a serial number. VID:PID and serial
+/// lookups must match exactly one connected device.
+///
+/// # Errors
+///
+/// Returns an error if no device, or more than one device, matches.
+pub fn find_device(usb_devices_path: &Path, id: &str) -> Result<PathBuf, String> {
+    if id.contains('/') {
+        let path = PathBuf::from(id);
+        return if path.join("idVendor").exists() || path.join("descriptors").exists() {
+            Ok(path)
+        } else {
+            Err(format!("'{id}' is not a USB device directory"))
+        };
+    }
+
+    let port = usb_devices_path.join(id);
+    if !id.contains(':') && port.is_dir() {
+        return Ok(port);
+    }
+
+    let read = |path: &Path, name: &str| {
+        fs::read_to_string(path.join(name))
+            .ok()
+            .map(|s| s.trim().to_string())
+    };
+    let vid_pid = id
+        .split_once(':')
+        .filter(|(vid, pid)| is_hex_id(vid) && is_hex_id(pid))
+        .map(|(vid, pid)| (vid.to_ascii_lowercase(), pid.to_ascii_lowercase()));
+
+    let matches: Vec<PathBuf> = list_device_paths(usb_devices_path)?
+        .into_iter()
+        .filter(|path| match &vid_pid {
+            Some((vid, pid)) => {
+                read(path, "idVendor").as_ref() == Some(vid)
+                    && read(path, "idProduct").as_ref() == Some(pid)
+            }
+            None => read(path, "serial").as_deref() == Some(id),
+        })
+        .collect();
+
+    match matches.len() {
+        0 => Err(format!("No connected USB device matches '{id}'")),
+        1 => Ok(matches.into_iter().next().unwrap_or_default()),
+        _ => Err(format!(
+            "'{id}' matches {} devices, use a port path instead: {}",
+            matches.len(),
+            matches
+                .iter()
+                .filter_map(|path| path.file_name())
+                .map(|name| name.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+fn is_hex_id(value: &str) -> bool {
+    value.len() == 4 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+impl DeviceReport {
+    /// Collects the report for the device in `device_path`.
+    ///
+    /// Names are resolved when `options.usb_ids` is set. Descriptors are
+    /// always read; a missing or unreadable descriptor file is not an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory is not a USB device.
+    pub fn collect(device_path: &Path, options: &WatcherOptions) -> Result<Self, String> {
+        let device = read_device(
+            device_path,
+            &WatcherOptions {
+                descriptors: false,
+                ..options.clone()
+            },
+        )?;
+        let attributes = read_attributes(device_path);
+        let attribute = |name: &str| attributes.get(name).cloned();
+
+        let power_path = device_path.join("power");
+        let power_attributes = read_attributes(&power_path);
+        let power_attribute = |name: &str| power_attributes.get(name).cloned();
+
+        let mut device_nodes = Vec::new();
+        collect_device_nodes(device_path, 0, &mut device_nodes);
+        device_nodes.sort();
+        device_nodes.dedup();
+
+        Ok(Self {
+            sysfs_path: device_path.to_string_lossy().to_string(),
+            bus_number: attribute("busnum").and_then(|v| v.parse().ok()),
+            device_number: attribute("devnum").and_then(|v| v.parse().ok()),
+            usb_version: attribute("version"),
+            speed_mbps: attribute("speed"),
+            degraded: read_link_info(device_path).degraded_reason(),
+            authorized: attribute("authorized").map(|v| v == "1"),
+            power: PowerInfo {
+                control: power_attribute("control"),
+                runtime_status: power_attribute("runtime_status"),
+                autosuspend_delay_ms: power_attribute("autosuspend_delay_ms")
+                    .and_then(|v| v.parse().ok()),
+                wakeup: power_attribute("wakeup"),
+                max_power: attribute("bMaxPower"),
+            },
+            device_nodes,
+            descriptors: read_descriptors(device_path).ok(),
+            attributes,
+            device,
+        })
+    }
+
+    /// Renders the report as plain text.
+    ///
+    /// The full descriptor tree is only included when `descriptors` is true;
+    /// otherwise a one-line summary of the active configuration is shown.
+    pub fn render_text(&self, descriptors: bool) -> String {
+        let mut out = String::new();
+        let device = &self.device;
+        let _ = writeln!(out, "{}", device.device_name);
+
+        let mut line = |label: &str, value: String| {
+            let _ = writeln!(out, "  {label:<14} {value}");
+        };
+        let or_dash = |value: Option<&str>| value.unwrap_or("-").to_string();
+
+        line("Port:", or_dash(device.port_path.as_deref()));
+        line("sysfs path:", self.sysfs_path.clone());
+        line(
+            "VID:PID:",
+            format!("{}:{}", device.vendor_id, device.product_id),
+        );
+        line("Vendor:", or_dash(device.vendor_name.as_deref()));
+        line("Product:", or_dash(device.product_name.as_deref()));
+        line("Serial:", or_dash(device.serial_number.as_deref()));
+        if let Some(reason) = device.suspicious_reason() {
+            line("Suspicious:", reason);
+        }
+        line(
+            "Class:",
+            or_dash(
+                device
+                    .class_name
+                    .clone()
+                    .or_else(|| device.class_summary())
+                    .as_deref(),
+            ),
+        );
+        line(
+            "Bus/Device:",
+            match (self.bus_number, self.device_number) {
+                (Some(bus), Some(dev)) => format!("{bus:03}/{dev:03}"),
+                _ => "-".to_string(),
+            },
+        );
+        line("USB version:", or_dash(self.usb_version.as_deref()));
+        line(
+            "Speed:",
+            self.speed_mbps
+                .as_ref()
+                .map(|speed| format!("{speed} Mbit/s"))
+                .unwrap_or_else(|| "-".to_string()),
+        );
+        if let Some(reason) = &self.degraded {
+            line("Degraded:", reason.clone());
+        }
+        line(
+            "Authorized:",
+            match self.authorized {
+                Some(true) => "yes".to_string(),
+                Some(false) => "no".to_string(),
+                None => "-".to_string(),
+            },
+        );
+        line(
+            "Power:",
+            format!(
+                "{} (control {}, wakeup {}, max {})",
+                or_dash(self.power.runtime_status.as_deref()),
+                or_dash(self.power.control.as_deref()),
+                or_dash(self.power.wakeup.as_deref()),
+                or_dash(self.power.max_power.as_deref())
+            ),
+        );
+        line(
+            "Device nodes:",
+            if self.device_nodes.is_empty() {
+                "-".to_string()
+            } else {
+                self.device_nodes.join(", ")
+            },
+        );
+
+        if !device.interfaces.is_empty() {
+            let _ = writeln!(out, "\nInterfaces:");
+            for interface in &device.interfaces {
+                let hid = if interface.hid_classes.is_empty() {
+                    String::new()
+                } else {
+                    let names: Vec<String> = interface
+                        .hid_classes
+                        .iter()
+                        .map(|c| c.to_string())
+                        .collect();
+                    format!("  HID: {}", names.join(", "))
+                };
+                let _ = writeln!(
+                    out,
+                    "  {:<14} {}  driver: {}{hid}",
+                    interface.id,
+                    interface
+                        .class_name
+                        .clone()
+                        .unwrap_or_else(|| interface.class.to_string()),
+                    interface.driver.as_deref().unwrap_or("none")
+                );
+            }
+        }
+
+        let _ = writeln!(out, "\nAttributes:");
+        for (name, value) in &self.attributes {
+            let _ = writeln!(out, "  {name:<22} {value}");
+        }
+
+        match &self.descriptors {
+            Some(tree) if descriptors => {
+                let _ = write!(out, "\n{}", tree.render_text());
+            }
+            Some(tree) => {
+                for config in &tree.configurations {
+                    let _ = writeln!(
+                        out,
+                        "\nConfiguration {}: {} interface(s), {}mA{} (use --descriptors for details)",
+                        config.value,
+                        config.interface_count(),
+                        config.max_power_ma,
+                        if config.self_powered {
+                            ", self powered"
+                        } else {
+                            ""
+                        }
+                    );
+                }
+            }
+            None if descriptors => {
+                let _ = writeln!(out, "\nDescriptors: not available");
+            }
+            None => {}
+        }
+        out
+    }
+}
+
+/// Reads every readable, single-line text attribute in a sysfs directory.
+pub(crate) fn read_attributes(path: &Path) -> BTreeMap<String, String> {
+    let mut attributes = BTreeMap::new();
+    let Ok(entries) = fs::read_dir(path) else {
+        return attributes;
+    };
+    for entry in entries.flatten() {
+        if !entry.file_type().is_ok_and(|t| t.is_file()) {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        if matches!(name.as_str(), "descriptors" | "bos_descriptors" | "uevent") {
+            continue;
+        }
+        // Binary, write-only and unreadable attributes are skipped
+        if let Ok(value) = fs::read_to_string(entry.path()) {
+            let value = value.trim();
+            if !value.contains('\n') {
+                attributes.insert(name, value.to_string());
+            }
+        }
+    }
+    attributes
+}
+
+/// Collects `/dev` paths from the `DEVNAME` of every `uevent` below a device
+/// directory, without descending into child USB devices or following symlinks.
+fn collect_device_nodes(path: &Path, depth: usize, nodes: &mut Vec<String>) {
+    if let Ok(uevent) = fs::read_to_string(path.join("uevent")) {
+        if let Some(name) = uevent
+            .lines()
+            .find_map(|line| line.strip_prefix("DEVNAME="))
+        {
+            nodes.push(format!("/dev/{name}"));
+        }
+    }
+    if depth >= MAX_NODE_DEPTH {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(path) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+            continue;
+        }
+        let child = entry.path();
+        // Devices behind a hub are reported on their own
+        if !child.join("idVendor").exists() {
+            collect_device_nodes(&child, depth + 1, nodes);
+        }
+    }
+}
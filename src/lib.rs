@@ -10,6 +10,7 @@
 //! - **Real-time monitoring**: Detect USB events as they happen
 //! - **Multiple output formats**: Plain text and JSON
 //! - **File logging**: Save events to log files
+//! - **Event history**: Optional SQLite store with time, VID/PID and serial queries
 //! - **Colored output**: Modern, readable CLI output
 //! - **Async/await support**: Built with Tokio for efficient I/O
 //! - **Device handle traits**: Access platform-specific device handles for advanced operations
@@ -32,6 +33,69 @@
 //! # Monitor with colored output (default if supported)
 //! usbwatch
 //!
+//! # Record events to an SQLite database and query them later
+//! usbwatch --db usb-events.db
+//! usbwatch history --db usb-events.db --serial 4C530001234567891234 --limit 1
+//!
+//! # Re-render recorded JSON logs (including rotated .gz files) at 10x speed
+//! usbwatch replay usb-events.json usb-events.json.1.gz --speed 10
+//!
+//! # Summarise recorded JSON logs
+//! usbwatch stats usb-events.json
+//!
+//! # Report devices only once stable for 3s, collapsing bursts into Flapping events
+//! usbwatch --debounce 3000 --collapse-flaps --flap-alert 10
+//!
+//! # Fill in vendor/product/class names from usb.ids
+//! usbwatch --resolve-names
+//!
+//! # Show everything about one device, by port path, sysfs path, VID:PID or serial
+//! usbwatch info 0781:5583
+//! usbwatch info 1-1.2 --descriptors
+//!
+//! # Enforce an allow/block policy (Linux, needs root)
+//! sudo usbwatch --policy /etc/usbwatch/policy.json
+//!
+//! # Capture the attached devices, then report drift (exits non-zero) or tag live events
+//! usbwatch baseline save baseline.json
+//! usbwatch baseline check baseline.json
+//! usbwatch --baseline baseline.json
+//!
+//! # Add sequence numbers, host name, machine/boot IDs and monotonic time to events
+//! usbwatch --json --metadata
+//!
+//! # Post events to chat and ticketing webhooks
+//! usbwatch --metadata --webhooks /etc/usbwatch/webhooks.json
+//!
+//! # Publish events to an MQTT broker under usbwatch/<host>/...
+//! usbwatch --mqtt mqtt://broker.local --mqtt-qos 1
+//!
+//! # Keep a tamper-evident audit log with signed checkpoints, and verify it later
+//! usbwatch audit keygen /etc/usbwatch/audit.key
+//! usbwatch --audit-log usb-audit.jsonl --audit-key /etc/usbwatch/audit.key
+//! usbwatch audit verify usb-audit.jsonl --public-key /etc/usbwatch/audit.key.pub
+//!
+//! # Run as a daemon and query it over its Unix socket
+//! sudo usbwatch daemon --socket-group plugdev
+//! usbwatch client list
+//! usbwatch client subscribe --json
+//!
+//! # Serve devices, events and Prometheus metrics over HTTP
+//! usbwatch serve --listen 127.0.0.1:8080
+//!
+//! # Show desktop notifications for device events (Linux)
+//! usbwatch --notify
+//!
+//! # Full-screen dashboard of connected devices and recent events
+//! usbwatch top
+//!
+//! # Stream events from every workstation to a central collector
+//! usbwatch collector --listen 0.0.0.0:7300 --json --logfile fleet.json
+//! usbwatch agent --upstream collector.corp.local:7300
+//!
+//! # Only report mass storage devices and keyboards
+//! usbwatch --class mass-storage,hid
+//!
 //! # Install or uninstall the CLI tool
 //! usbwatch install
 //! usbwatch uninstall
@@ -97,6 +161,24 @@
 //! - [`create_watcher`] - Convenience function for watcher creation
 //! - [`monitor_with_callback`] - High-level async monitoring with callback
 //! - [`monitor_for_duration`] - Collect events for a fixed duration
+//! - [`reader::EventReader`] - Read events back from JSON log files
+//! - [`template::render_line`] - `{{field}}` templates for `--template` output and webhook payloads
+//! - [`stats::EventStats`] - Summary statistics over recorded events
+//! - [`store::EventStore`] - SQLite event store (`sqlite` feature)
+//! - [`usb_ids::UsbIds`] - Vendor, product and class names from `usb.ids`
+//! - [`usb_class::UsbClass`] - USB-IF class codes with subclass/protocol refinements
+//! - [`descriptors::DescriptorTree`] - Decoded USB descriptors from sysfs
+//! - `info::DeviceReport` - Detailed single-device report from sysfs (Linux)
+//! - [`hid::classify_report_descriptor`] - Classify HID interfaces as keyboard, mouse, consumer control, ...
+//! - [`link::LinkInfo`] - Detect SuperSpeed devices running on a slower link
+//! - [`policy::Policy`] - Allow/block/log rules, enforced through sysfs `authorized` on Linux
+//! - [`filter::EventFilter`] - Select events by device or interface class
+//! - [`metadata::MetadataStamper`] - Sequence numbers, host identity, boot ID and monotonic time per event
+//! - [`audit::AuditLog`] - Hash-chained audit log with optional Ed25519 checkpoints (`signing` feature)
+//! - [`baseline::Baseline`] - Known-device snapshots, drift reports and known/unknown tagging
+//! - `notify::Notifier` - Desktop notifications over D-Bus, rate limited during bursts (`notify` feature, Linux)
+//! - [`tui::Dashboard`] - Full-screen device table and event log for `usbwatch top` (`tui` feature)
+//! - [`collector::agent_task`] / [`collector::serve`] - Stream events from agents to a central collector
 //!
 //! ## Platform Support
 //!
@@ -112,9 +194,44 @@
 #![warn(rust_2018_idioms)]
 #![deny(unsafe_op_in_unsafe_fn)]
 
+pub mod audit;
+pub mod baseline;
+pub mod collector;
+pub mod daemon;
+pub mod debounce;
+pub mod descriptors;
 pub mod device_info;
+pub mod filter;
+pub mod hid;
+pub mod history;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(target_os = "linux")]
+pub mod info;
+pub mod link;
 pub mod logger;
+pub mod metadata;
+pub mod metrics;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+#[cfg(all(feature = "notify", target_os = "linux"))]
+pub mod notify;
+pub mod policy;
+pub mod reader;
+pub mod report;
+pub mod stats;
+#[cfg(feature = "sqlite")]
+pub mod store;
+pub mod template;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod usb_class;
+pub mod usb_ids;
 pub mod watcher;
+#[cfg(feature = "webhooks")]
+pub mod webhook;
+#[cfg(feature = "http")]
+pub mod websocket;
 
 // Re-export commonly used types
 pub use device_info::{AsDeviceHandle, DeviceEventType, DeviceHandle, UsbDeviceInfo};
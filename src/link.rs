@@ -0,0 +1,152 @@
+//! Link speed checks.
+//!
+//! A USB 3 device in a USB 2 port, or behind a USB 2 cable or hub, silently falls back to High Speed (480 Mbit/s) and
+//! only shows it in slow transfers. [`LinkInfo`] gathers what the system reports about a device's link: the USB version
+//! and SuperSpeed capabilities it advertises, the speed it negotiated and the speed of the root hub it enumerated on.
+//! [`LinkInfo::degraded_reason`] compares them, and the Linux watcher raises a `Degraded` event for newly connected
+//! devices running below the speed they support.
+//!
+//! Hubs are not checked: every USB 3 hub also presents a USB 2 half that runs at 480 Mbit/s by design.
+
+use crate::descriptors::BosDescriptor;
+
+/// Speed of a SuperSpeed (USB 3 Gen 1) link, in Mbit/s.
+pub const SUPER_SPEED_MBPS: f64 = 5000.0;
+
+/// Speed of a SuperSpeedPlus (USB 3 Gen 2) link, in Mbit/s.
+pub const SUPER_SPEED_PLUS_MBPS: f64 = 10000.0;
+
+/// What is known about a device's USB link.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LinkInfo {
+    /// USB version the device reports (`bcdUSB`, e.g. `3.20`)
+    pub version: Option<String>,
+    /// Highest speed advertised in the device's BOS capabilities, in Mbit/s
+    pub capability_mbps: Option<f64>,
+    /// Negotiated speed, in Mbit/s
+    pub speed_mbps: Option<f64>,
+    /// Name of the root hub the device enumerated on (e.g. `usb1`)
+    pub root_hub: Option<String>,
+    /// Speed of that root hub, in Mbit/s
+    pub root_hub_speed_mbps: Option<f64>,
+    /// Whether the device is a hub
+    pub is_hub: bool,
+}
+
+impl LinkInfo {
+    /// Returns the highest speed advertised by SuperSpeed and SuperSpeedPlus
+    /// capability descriptors in a BOS, in Mbit/s.
+    pub fn capability_speed(bos: &BosDescriptor) -> Option<f64> {
+        bos.capabilities
+            .iter()
+            .filter_map(|capability| match capability.capability_type {
+                // wSpeedsSupported bit 3 is SuperSpeed; the descriptor is only
+                // given by SuperSpeed devices, so trust it if it is truncated
+                0x03 => match capability.data.get(1) {
+                    Some(speeds) if speeds & 0x08 == 0 => None,
+                    _ => Some(SUPER_SPEED_MBPS),
+                },
+                0x0a => Some(SUPER_SPEED_PLUS_MBPS),
+                _ => None,
+            })
+            .reduce(f64::max)
+    }
+
+    /// Returns the highest speed the device supports, judged by its USB
+    /// version and BOS capabilities, in Mbit/s.
+    ///
+    /// A USB 3 device connected over a USB 2 link reports version 2.10, so
+    /// only the capabilities reveal it; a version of 3.0 or later means at
+    /// least SuperSpeed.
+    pub fn supported_speed(&self) -> Option<f64> {
+        let from_version = self
+            .version
+            .as_deref()
+            .and_then(|version| version.trim().split('.').next()?.parse::<u8>().ok())
+            .filter(|major| *major >= 3)
+            .map(|_| SUPER_SPEED_MBPS);
+        match (from_version, self.capability_mbps) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Explains why the link is slower than the device supports, if it is.
+    ///
+    /// Only SuperSpeed-capable devices are checked, since a USB 2 device
+    /// cannot tell High Speed support apart from Full Speed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use usbwatch_rs::link::LinkInfo;
+    ///
+    /// let link = LinkInfo {
+    ///     version: Some("3.20".to_string()),
+    ///     speed_mbps: Some(480.0),
+    ///     root_hub: Some("usb1".to_string()),
+    ///     root_hub_speed_mbps: Some(480.0),
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(
+    ///     link.degraded_reason().unwrap(),
+    ///     "SuperSpeed device running at 480 Mbit/s (High Speed) on USB 2 root hub usb1; \
+    ///      use a USB 3 port and cable"
+    /// );
+    /// ```
+    pub fn degraded_reason(&self) -> Option<String> {
+        if self.is_hub {
+            return None;
+        }
+        let supported = self.supported_speed()?;
+        let speed = self.speed_mbps?;
+        if speed >= supported {
+            return None;
+        }
+
+        let mut reason = format!(
+            "{} device running at {} Mbit/s ({})",
+            speed_name(supported),
+            speed,
+            speed_name(speed)
+        );
+        match (&self.root_hub, self.root_hub_speed_mbps) {
+            (Some(hub), Some(hub_speed)) if hub_speed < SUPER_SPEED_MBPS => {
+                reason.push_str(&format!(
+                    " on USB 2 root hub {hub}; use a USB 3 port and cable"
+                ));
+            }
+            (Some(hub), Some(hub_speed)) if hub_speed < supported => {
+                reason.push_str(&format!(
+                    " on root hub {hub}, which only supports {hub_speed} Mbit/s; use a faster port"
+                ));
+            }
+            _ => reason.push_str("; check the port, cable and any hubs in between"),
+        }
+        Some(reason)
+    }
+}
+
+/// Returns the USB name of a link speed given in Mbit/s.
+///
+/// # Examples
+///
+/// ```
+/// use usbwatch_rs::link::speed_name;
+///
+/// assert_eq!(speed_name(12.0), "Full Speed");
+/// assert_eq!(speed_name(5000.0), "SuperSpeed");
+/// ```
+pub fn speed_name(mbps: f64) -> &'static str {
+    if mbps >= SUPER_SPEED_PLUS_MBPS {
+        "SuperSpeedPlus"
+    } else if mbps >= SUPER_SPEED_MBPS {
+        "SuperSpeed"
+    } else if mbps >= 480.0 {
+        "High Speed"
+    } else if mbps >= 12.0 {
+        "Full Speed"
+    } else {
+        "Low Speed"
+    }
+}
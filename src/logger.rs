@@ -6,12 +6,19 @@
 //! ## Features
 //!
 //! - Colored output using the `colored` crate
-//! - JSON and plain text output
+//! - JSON, plain text and `{{field}}` template output
 //! - File logging
+//! - Optional SQLite event store (`sqlite` feature)
+//! - Optional hash-chained audit log
 //! - Configurable via CLI options
 //! - Robust error handling
 
+use crate::audit::AuditLog;
 use crate::device_info::UsbDeviceInfo;
+use crate::report::format_duration;
+#[cfg(feature = "sqlite")]
+use crate::store::EventStore;
+use crate::template::render_line;
 use colored::*;
 use std::fs::OpenOptions;
 use std::io::Write;
@@ -22,8 +29,12 @@ use tokio::sync::mpsc;
 /// and log files simultaneously.
 pub struct Logger {
     output_json: bool,
+    template: Option<String>,
     log_file: Option<std::fs::File>,
     colorful: bool,
+    #[cfg(feature = "sqlite")]
+    store: Option<EventStore>,
+    audit_log: Option<AuditLog>,
 }
 
 impl Logger {
@@ -40,7 +51,7 @@ impl Logger {
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// use usbwatch_rs::logger::Logger;
     ///
     /// // Console-only logger with plain text
@@ -69,11 +80,65 @@ impl Logger {
 
         Ok(Self {
             output_json,
+            template: None,
             log_file,
             colorful,
+            #[cfg(feature = "sqlite")]
+            store: None,
+            audit_log: None,
         })
     }
 
+    /// Attaches an SQLite event store that receives every logged event.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use usbwatch_rs::logger::Logger;
+    /// use usbwatch_rs::store::EventStore;
+    ///
+    /// let logger = Logger::new(false, None, true)?.with_store(EventStore::open("usb-events.db")?);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "sqlite")]
+    pub fn with_store(mut self, store: EventStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Prints each event as a `{{field}}` template instead of plain text
+    /// (see [`template`](crate::template)). JSON output takes precedence.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use usbwatch_rs::logger::Logger;
+    ///
+    /// let logger = Logger::new(false, None, false)?
+    ///     .with_template("{{timestamp}} {{event_type}} {{device_name}} {{duration}}");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn with_template(mut self, template: impl Into<String>) -> Self {
+        self.template = Some(template.into());
+        self
+    }
+
+    /// Attaches a hash-chained audit log that receives every logged event.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use usbwatch_rs::audit::AuditLog;
+    /// use usbwatch_rs::logger::Logger;
+    ///
+    /// let logger = Logger::new(false, None, true)?.with_audit_log(AuditLog::open("usb-audit.jsonl")?);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn with_audit_log(mut self, audit_log: AuditLog) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
     /// Logs a USB device event to console and file (if configured).
     ///
     /// The output format depends on the `output_json` setting configured
@@ -85,7 +150,8 @@ impl Logger {
     ///
     /// # Errors
     ///
-    /// Returns an error if JSON serialisation fails or file writing fails.
+    /// Returns an error if JSON serialisation fails, file writing fails or the
+    /// event store or audit log rejects the event.
     pub fn log_device_event(
         &mut self,
         device_info: &UsbDeviceInfo,
@@ -97,10 +163,25 @@ impl Logger {
                 writeln!(file, "{json}")?;
                 file.flush()?;
             }
+        } else if let Some(template) = &self.template {
+            let line = render_line(template, device_info);
+            println!("{line}");
+            if let Some(file) = &mut self.log_file {
+                writeln!(file, "{line}")?;
+                file.flush()?;
+            }
         } else {
+            let blocked = device_info
+                .policy
+                .as_ref()
+                .is_some_and(|p| p.action == crate::policy::PolicyAction::Block);
             let event_icon = match device_info.event_type {
+                _ if blocked => "🚫",
                 crate::device_info::DeviceEventType::Connected => "🔌",
                 crate::device_info::DeviceEventType::Disconnected => "❌",
+                crate::device_info::DeviceEventType::Flapping => "🔁",
+                crate::device_info::DeviceEventType::SuspiciousDevice => "🚨",
+                crate::device_info::DeviceEventType::Degraded => "🐢",
             };
             let styled_name = if self.colorful {
                 match device_info.event_type {
@@ -110,11 +191,20 @@ impl Logger {
                     crate::device_info::DeviceEventType::Disconnected => {
                         device_info.device_name.red().bold()
                     }
+                    crate::device_info::DeviceEventType::Flapping => {
+                        device_info.device_name.yellow().bold()
+                    }
+                    crate::device_info::DeviceEventType::SuspiciousDevice => {
+                        device_info.device_name.magenta().bold()
+                    }
+                    crate::device_info::DeviceEventType::Degraded => {
+                        device_info.device_name.cyan().bold()
+                    }
                 }
             } else {
                 device_info.device_name.normal()
             };
-            let output = format!(
+            let mut output = format!(
                 "{} {} | VID: {} PID: {} | Serial: {} | Event: {:?} | {}",
                 event_icon,
                 styled_name,
@@ -124,12 +214,68 @@ impl Logger {
                 device_info.event_type,
                 device_info.timestamp
             );
+            if let (Some(connected_at), Some(duration)) =
+                (device_info.connected_at, device_info.duration)
+            {
+                output.push_str(&format!(
+                    " | Connected for: {} (since {})",
+                    format_duration(duration),
+                    connected_at
+                ));
+            }
+            if let Some(class_name) = device_info
+                .class_name
+                .clone()
+                .or_else(|| device_info.class_summary())
+            {
+                output.push_str(&format!(" | Class: {class_name}"));
+            }
+            let hid_classes = device_info.hid_classes();
+            if !hid_classes.is_empty() {
+                let names: Vec<String> = hid_classes.iter().map(|c| c.to_string()).collect();
+                output.push_str(&format!(" | HID: {}", names.join(", ")));
+            }
+            if let Some(policy) = &device_info.policy {
+                output.push_str(&format!(" | Policy: {policy}"));
+            }
+            if let Some(baseline) = device_info.baseline {
+                output.push_str(&format!(" | Baseline: {baseline}"));
+            }
+            if let Some(reason) = &device_info.reason {
+                output.push_str(&format!(" | Reason: {reason}"));
+            }
+            if let Some(metadata) = &device_info.metadata {
+                output.push_str(&format!(" | Seq: {}", metadata.seq));
+                if let Some(hostname) = &metadata.hostname {
+                    output.push_str(&format!(" | Host: {hostname}"));
+                }
+            }
+            if let Some(origin) = &device_info.origin {
+                output.push_str(&format!(" | Origin: {origin}"));
+            }
+            if let Some(flap_count) = device_info.flap_count {
+                output.push_str(&format!(" | Changes: {flap_count}"));
+            }
+            if !device_info.children.is_empty() {
+                output.push_str(&format!(
+                    " | Children ({}): {}",
+                    device_info.children.len(),
+                    device_info.children_summary()
+                ));
+            }
             println!("{output}");
             if let Some(file) = &mut self.log_file {
                 writeln!(file, "{output}")?;
                 file.flush()?;
             }
         }
+        #[cfg(feature = "sqlite")]
+        if let Some(store) = &self.store {
+            store.insert(device_info)?;
+        }
+        if let Some(audit_log) = &mut self.audit_log {
+            audit_log.append(device_info)?;
+        }
         Ok(())
     }
 }
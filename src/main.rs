@@ -4,25 +4,80 @@
 //!
 //! ## Subcommands
 //! - `monitor` (default): Monitor USB device events in real-time
+//! - `history`: Query events recorded in an SQLite database
+//! - `replay`: Re-emit events from JSON log files through the logger
+//! - `stats`: Summarise JSON log files
+//! - `info`: Show a detailed report for one connected device
+//! - `baseline`: Save the attached devices as a baseline, or check for drift against one
+//! - `audit`: Verify a hash-chained audit log, or generate a checkpoint signing key
+//! - `daemon`: Monitor in the background and answer queries on a Unix socket (Unix)
+//! - `client`: List devices, stream events, show statistics, reload or query history from a running daemon (Unix)
+//! - `serve`: Monitor and serve devices and events over HTTP, with Server-Sent Events and WebSocket feeds
+//! - `agent`: Monitor and stream events to a collector over TCP, optionally with (mutual) TLS
+//! - `collector`: Receive events from agents and log them tagged with their origin host
+//! - `top`: Full-screen dashboard of connected devices and recent events
 //! - `install`: Install usbwatch to system PATH
 //! - `uninstall`: Uninstall usbwatch from system PATH
 //!
 //! ## Options
 //! - `--json`: Output events in JSON format
 //! - `--logfile <PATH>`: Log events to the specified file
+//! - `--db <PATH>`: Record events to (or query them from) an SQLite database
+//! - `--class <CLASS>`: Only report devices of the given USB classes (e.g. `mass-storage`)
+//! - `--policy <FILE>`: Allow, block or log devices according to a JSON policy (Linux, needs root to enforce)
+//! - `--baseline [FILE]`: Tag events as known or unknown to a saved baseline
+//! - `--notify`: Show desktop notifications for device events (Linux, `notify` feature)
+//! - `--metadata`: Add a sequence number, host name, machine ID, boot ID and monotonic time to each event
+//! - `--audit-log <PATH>`: Append events to a tamper-evident, hash-chained audit log
+//! - `--audit-key <FILE>`: Sign audit log checkpoints with an Ed25519 key (`signing` feature)
 //!
 //! For installation and troubleshooting, see INSTALL.md.
-mod device_info;
-mod logger;
-mod watcher;
-
 use clap::{Parser, Subcommand};
-use logger::{logger_task, Logger};
 use std::env;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock};
 use tokio::sync::mpsc;
-use watcher::UsbWatcher;
+use usbwatch_rs::audit::{load_key, verify_log, AuditLog};
+use usbwatch_rs::baseline::{tag_task, Baseline};
+use usbwatch_rs::collector::{self, agent_task, AgentOptions, CollectorOptions};
+#[cfg(feature = "tls")]
+use usbwatch_rs::collector::{ClientTls, ServerTls};
+#[cfg(any(unix, feature = "http"))]
+use usbwatch_rs::daemon::{self, DaemonState};
+#[cfg(unix)]
+use usbwatch_rs::daemon::{DaemonClient, DaemonStats, SocketOptions};
+use usbwatch_rs::debounce::{debounce_task, DebounceConfig};
+#[cfg(any(unix, feature = "sqlite"))]
+use usbwatch_rs::device_info::DeviceEventType;
+use usbwatch_rs::device_info::UsbDeviceInfo;
+use usbwatch_rs::filter::{filter_task, EventFilter};
+#[cfg(any(unix, feature = "sqlite"))]
+use usbwatch_rs::history::{parse_time_spec, HistoryQuery};
+#[cfg(feature = "http")]
+use usbwatch_rs::http::{self, load_token, HttpOptions};
+use usbwatch_rs::logger::{logger_task, Logger};
+use usbwatch_rs::metadata::{metadata_task, HostIdentity, MetadataStamper};
+use usbwatch_rs::metrics::{metrics_task, Metrics};
+#[cfg(feature = "mqtt")]
+use usbwatch_rs::mqtt::{self, mqtt_task, MqttConfig, MqttPublisher};
+#[cfg(all(feature = "notify", target_os = "linux"))]
+use usbwatch_rs::notify::{notify_task, Notifier, RateLimit};
+use usbwatch_rs::policy::Policy;
+use usbwatch_rs::reader::{replay_events, EventReader};
+#[cfg(any(unix, feature = "sqlite"))]
+use usbwatch_rs::report::render_events;
+use usbwatch_rs::report::OutputFormat;
+use usbwatch_rs::stats::EventStats;
+#[cfg(feature = "sqlite")]
+use usbwatch_rs::store::EventStore;
+#[cfg(feature = "tui")]
+use usbwatch_rs::tui::{self, Dashboard};
+use usbwatch_rs::usb_class::UsbClass;
+use usbwatch_rs::usb_ids::UsbIds;
+use usbwatch_rs::watcher::{UsbWatcher, WatcherOptions};
+#[cfg(feature = "webhooks")]
+use usbwatch_rs::webhook::{webhook_task, WebhookConfig, Webhooks};
 
 #[derive(Parser)]
 #[command(name = "usbwatch")]
@@ -35,70 +90,564 @@ struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
 
-    /// Output events in JSON format (monitor mode only)
+    /// Output events in JSON format
     #[arg(long, global = true)]
     json: bool,
 
-    /// Log events to file (monitor mode only)
+    /// Print each event as a template, e.g. '{{timestamp}} {{event_type}} {{device_name}} {{duration}}'
+    #[arg(long, value_name = "TEMPLATE", global = true, conflicts_with = "json")]
+    template: Option<String>,
+
+    /// Log events to file (monitor and replay modes)
     #[arg(long, value_name = "PATH", global = true)]
     logfile: Option<String>,
+
+    /// Record events to, or query them from, an SQLite database
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "PATH", global = true)]
+    db: Option<String>,
+
+    /// Only report a device once it has been stable for MS milliseconds (monitor mode only)
+    #[arg(long, value_name = "MS", global = true)]
+    debounce: Option<u64>,
+
+    /// Collapse bursts of changes into a single Flapping event (requires --debounce)
+    #[arg(long, global = true, requires = "debounce")]
+    collapse_flaps: bool,
+
+    /// Alert when a device changes state more than N times per minute (monitor mode only)
+    #[arg(long, value_name = "N", global = true)]
+    flap_alert: Option<usize>,
+
+    /// Report a hub and the devices behind it as one event with a child list (Linux, monitor mode only)
+    #[arg(long, global = true)]
+    collapse_hubs: bool,
+
+    /// Fill in vendor, product and class names from the system usb.ids (Linux, monitor mode only)
+    #[arg(long, global = true)]
+    resolve_names: bool,
+
+    /// usb.ids file to resolve names from (implies --resolve-names)
+    #[arg(long, value_name = "PATH", global = true)]
+    usb_ids: Option<PathBuf>,
+
+    /// Only report devices of these USB classes, e.g. mass-storage,hid (monitor and replay modes)
+    #[arg(
+        long = "class",
+        value_name = "CLASS",
+        value_delimiter = ',',
+        global = true
+    )]
+    classes: Vec<UsbClass>,
+
+    /// Allow, block or log new devices according to a JSON policy file (Linux, monitor mode only)
+    #[arg(long, value_name = "FILE", global = true)]
+    policy: Option<PathBuf>,
+
+    /// Tag events as known or unknown to a baseline saved with `baseline save` (monitor mode only)
+    #[arg(
+        long = "baseline",
+        value_name = "FILE",
+        num_args = 0..=1,
+        default_missing_value = DEFAULT_BASELINE,
+        global = true
+    )]
+    baseline_file: Option<PathBuf>,
+
+    /// Add a sequence number, host name, machine ID, boot ID and monotonic time to each event (monitor mode only)
+    #[arg(long, global = true)]
+    metadata: bool,
+
+    /// Show a desktop notification for each event, through D-Bus (Linux, monitor mode only)
+    #[cfg(all(feature = "notify", target_os = "linux"))]
+    #[arg(long, global = true)]
+    notify: bool,
+
+    /// Post matching events to the HTTP endpoints in a JSON webhook configuration (monitor mode only)
+    #[cfg(feature = "webhooks")]
+    #[arg(long, value_name = "FILE", global = true)]
+    webhooks: Option<PathBuf>,
+
+    /// Publish events to an MQTT broker, `mqtt://[user[:password]@]host[:port]` or `mqtts://...` (monitor mode only)
+    #[cfg(feature = "mqtt")]
+    #[arg(long, value_name = "URL", global = true)]
+    mqtt: Option<String>,
+
+    /// Quality of service of MQTT messages
+    #[cfg(feature = "mqtt")]
+    #[arg(
+        long,
+        value_name = "QOS",
+        global = true,
+        default_value_t = mqtt::DEFAULT_QOS,
+        value_parser = clap::value_parser!(u8).range(0..=2)
+    )]
+    mqtt_qos: u8,
+
+    /// First level of the MQTT topics
+    #[cfg(feature = "mqtt")]
+    #[arg(long, value_name = "PREFIX", global = true, default_value = mqtt::DEFAULT_PREFIX)]
+    mqtt_prefix: String,
+
+    /// Append events to a hash-chained audit log (monitor and replay modes)
+    #[arg(long, value_name = "PATH", global = true)]
+    audit_log: Option<PathBuf>,
+
+    /// Sign audit log checkpoints with this Ed25519 secret key (see `audit keygen`)
+    #[cfg(feature = "signing")]
+    #[arg(long, value_name = "FILE", global = true, requires = "audit_log")]
+    audit_key: Option<PathBuf>,
+
+    /// Number of audit log records between signed checkpoints, when writing or verifying a log
+    #[cfg(feature = "signing")]
+    #[arg(
+        long,
+        value_name = "N",
+        global = true,
+        default_value_t = usbwatch_rs::audit::DEFAULT_CHECKPOINT_INTERVAL
+    )]
+    checkpoint_interval: u64,
 }
 
+/// Baseline file used when none is given.
+const DEFAULT_BASELINE: &str = "baseline.json";
+
+/// Exit status of `baseline check` when the devices drifted from the baseline.
+const EXIT_BASELINE_DRIFT: i32 = 1;
+
+/// Exit status of `baseline check` when the baseline could not be read or the
+/// devices could not be scanned.
+const EXIT_BASELINE_ERROR: i32 = 2;
+
 #[derive(Subcommand)]
 enum Commands {
     /// Monitor USB device events (default)
     Monitor,
+    /// Query events recorded with --db
+    #[cfg(feature = "sqlite")]
+    History(HistoryArgs),
+    /// Re-emit events from JSON log files (plain or gzipped)
+    Replay(ReplayArgs),
+    /// Summarise JSON log files (plain or gzipped)
+    Stats(StatsArgs),
+    /// Show details of a connected device (Linux)
+    Info(InfoArgs),
+    /// Save the attached devices as a baseline, or check them against one
+    #[command(subcommand)]
+    Baseline(BaselineCommand),
+    /// Verify audit logs written with --audit-log
+    #[command(subcommand)]
+    Audit(AuditCommand),
+    /// Monitor in the background and answer queries on a Unix socket
+    #[cfg(unix)]
+    Daemon(DaemonArgs),
+    /// Query or control a running daemon
+    #[cfg(unix)]
+    Client(ClientArgs),
+    /// Monitor and serve devices and events over HTTP and WebSocket
+    #[cfg(feature = "http")]
+    Serve(ServeArgs),
+    /// Monitor and stream events to a collector
+    Agent(AgentArgs),
+    /// Receive events from agents and log them
+    Collector(CollectorArgs),
+    /// Full-screen dashboard of connected devices and recent events
+    #[cfg(feature = "tui")]
+    Top(TopArgs),
     /// Install usbwatch to system PATH
     Install,
     /// Uninstall usbwatch from system PATH
     Uninstall,
 }
 
+#[derive(clap::Args)]
+struct ReplayArgs {
+    /// JSON log files to replay; rotated files are merged in timestamp order
+    #[arg(value_name = "LOGFILE", required = true)]
+    files: Vec<PathBuf>,
+
+    /// Reproduce the original gaps between events, divided by FACTOR (1 = original pace)
+    #[arg(long, value_name = "FACTOR")]
+    speed: Option<f64>,
+}
+
+#[derive(clap::Args)]
+struct StatsArgs {
+    /// JSON log files to summarise; rotated files are merged in timestamp order
+    #[arg(value_name = "LOGFILE", required = true)]
+    files: Vec<PathBuf>,
+
+    /// Number of devices to list as most frequently plugged
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    top: usize,
+
+    /// Output format: table or json
+    #[arg(long, value_name = "FORMAT", default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+}
+
+#[derive(clap::Args)]
+struct InfoArgs {
+    /// Port path (e.g. 1-1.2), sysfs path, VID:PID or serial number
+    #[arg(value_name = "DEVICE")]
+    device: String,
+
+    /// Show the full decoded descriptor tree
+    #[arg(long)]
+    descriptors: bool,
+}
+
+#[derive(Subcommand)]
+enum BaselineCommand {
+    /// Save the currently attached devices
+    Save {
+        /// Baseline file to write
+        #[arg(value_name = "FILE", default_value = DEFAULT_BASELINE)]
+        file: PathBuf,
+    },
+    /// Report devices added, removed or changed since the baseline; exits with status 1 on drift and
+    /// 2 on errors
+    Check {
+        /// Baseline file to compare against
+        #[arg(value_name = "FILE", default_value = DEFAULT_BASELINE)]
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuditCommand {
+    /// Check the hash chain (and checkpoint signatures); exits with status 1 at the first broken link,
+    /// or when a public key is given but no signature covers the log
+    Verify {
+        /// Audit log to verify
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Public key to verify checkpoint signatures with; every `--checkpoint-interval`th record
+        /// must then be signed
+        #[arg(long, value_name = "FILE")]
+        public_key: Option<PathBuf>,
+    },
+    /// Generate a checkpoint signing key; the public key is written to FILE.pub
+    #[cfg(feature = "signing")]
+    Keygen {
+        /// Secret key file to create
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+    },
+}
+
+#[cfg(unix)]
+#[derive(clap::Args)]
+struct DaemonArgs {
+    /// Socket to listen on
+    #[arg(long, value_name = "PATH", default_value = daemon::DEFAULT_SOCKET_PATH)]
+    socket: PathBuf,
+
+    /// Permissions of the socket, in octal
+    #[arg(long, value_name = "MODE", default_value = "660", value_parser = daemon::parse_socket_mode)]
+    socket_mode: u32,
+
+    /// Group that may connect to the socket (name or GID)
+    #[arg(long, value_name = "GROUP")]
+    socket_group: Option<String>,
+
+    /// Number of recent events kept in memory for history and stats (without --db)
+    #[arg(long, value_name = "N", default_value_t = daemon::DEFAULT_HISTORY_SIZE)]
+    history_size: usize,
+}
+
+#[cfg(feature = "http")]
+#[derive(clap::Args)]
+struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, value_name = "ADDR", default_value = http::DEFAULT_LISTEN)]
+    listen: String,
+
+    /// Require this bearer token (first line of FILE) on every request
+    #[arg(long, value_name = "FILE")]
+    token_file: Option<PathBuf>,
+
+    /// Only serve the WebSocket feed at /ws, not the REST API
+    #[arg(long)]
+    websocket_only: bool,
+
+    /// Also accept WebSocket upgrades from this origin (e.g. `https://dashboard.example.com`)
+    #[arg(long = "allow-origin", value_name = "ORIGIN")]
+    allowed_origins: Vec<String>,
+
+    /// Number of recent events kept in memory for /events (without --db)
+    #[arg(long, value_name = "N", default_value_t = daemon::DEFAULT_HISTORY_SIZE)]
+    history_size: usize,
+}
+
+#[derive(clap::Args)]
+struct AgentArgs {
+    /// Collector to stream events to, `host:port`
+    #[arg(long, value_name = "HOST:PORT")]
+    upstream: String,
+
+    /// Host name to tag events with (defaults to the system host name)
+    #[arg(long, value_name = "NAME")]
+    host: Option<String>,
+
+    /// Number of events kept while the collector is unreachable
+    #[arg(long, value_name = "N", default_value_t = collector::DEFAULT_BUFFER_SIZE)]
+    buffer_size: usize,
+
+    /// Connect with TLS, trusting collector certificates issued by this CA (PEM)
+    #[cfg(feature = "tls")]
+    #[arg(long, value_name = "FILE")]
+    tls_ca: Option<PathBuf>,
+
+    /// Client certificate chain (PEM) for mutual TLS
+    #[cfg(feature = "tls")]
+    #[arg(long, value_name = "FILE", requires_all = ["tls_ca", "tls_key"])]
+    tls_cert: Option<PathBuf>,
+
+    /// Private key (PEM) of the client certificate
+    #[cfg(feature = "tls")]
+    #[arg(long, value_name = "FILE", requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+
+    /// Name expected in the collector's certificate (defaults to the upstream host)
+    #[cfg(feature = "tls")]
+    #[arg(long, value_name = "NAME", requires = "tls_ca")]
+    tls_server_name: Option<String>,
+}
+
+#[derive(clap::Args)]
+struct CollectorArgs {
+    /// Address to listen on for agents
+    #[arg(long, value_name = "ADDR")]
+    listen: String,
+
+    /// Serve TLS with this certificate chain (PEM)
+    #[cfg(feature = "tls")]
+    #[arg(long, value_name = "FILE", requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+
+    /// Private key (PEM) of the certificate
+    #[cfg(feature = "tls")]
+    #[arg(long, value_name = "FILE", requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+
+    /// Only accept agents presenting a certificate issued by this CA (PEM)
+    #[cfg(feature = "tls")]
+    #[arg(long, value_name = "FILE", requires = "tls_cert")]
+    tls_client_ca: Option<PathBuf>,
+}
+
+#[cfg(feature = "tui")]
+#[derive(clap::Args)]
+struct TopArgs {
+    /// Number of lines kept in the event log
+    #[arg(long, value_name = "N", default_value_t = tui::DEFAULT_LOG_SIZE)]
+    log_size: usize,
+}
+
+#[cfg(unix)]
+#[derive(clap::Args)]
+struct ClientArgs {
+    /// Socket of the daemon
+    #[arg(long, value_name = "PATH", default_value = daemon::DEFAULT_SOCKET_PATH)]
+    socket: PathBuf,
+
+    #[command(subcommand)]
+    command: ClientCommand,
+}
+
+#[cfg(unix)]
+#[derive(Subcommand)]
+enum ClientCommand {
+    /// List the currently attached devices
+    List,
+    /// Stream events as they happen
+    Subscribe,
+    /// Show daemon and event statistics
+    Stats {
+        /// Number of devices to list as most frequently plugged
+        #[arg(long, value_name = "N", default_value_t = 10)]
+        top: usize,
+    },
+    /// Re-read the policy and baseline files
+    Reload,
+    /// Query past events
+    History(HistoryArgs),
+}
+
+#[cfg(any(unix, feature = "sqlite"))]
+#[derive(clap::Args)]
+struct HistoryArgs {
+    /// Only show events at or after this time (RFC 3339, YYYY-MM-DD or an age like 24h)
+    #[arg(long, value_name = "TIME", value_parser = parse_time_spec)]
+    since: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Only show events at or before this time (RFC 3339, YYYY-MM-DD or an age like 24h)
+    #[arg(long, value_name = "TIME", value_parser = parse_time_spec)]
+    until: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Only show events for this vendor ID (hex)
+    #[arg(long, value_name = "VID")]
+    vid: Option<String>,
+
+    /// Only show events for this product ID (hex)
+    #[arg(long, value_name = "PID")]
+    pid: Option<String>,
+
+    /// Only show events for this serial number
+    #[arg(long)]
+    serial: Option<String>,
+
+    /// Only show events of this type (connected or disconnected)
+    #[arg(long, value_name = "TYPE")]
+    event: Option<DeviceEventType>,
+
+    /// Show at most this many events (newest first)
+    #[arg(long, value_name = "N")]
+    limit: Option<usize>,
+
+    /// Output format: table, json or csv
+    #[arg(long, value_name = "FORMAT", default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
 
-    match cli.command.unwrap_or(Commands::Monitor) {
-        Commands::Monitor => run_monitor(cli.json, cli.logfile).await,
+    match cli.command.take().unwrap_or(Commands::Monitor) {
+        Commands::Monitor => run_monitor(&cli).await,
+        #[cfg(feature = "sqlite")]
+        Commands::History(args) => run_history(args, &cli),
+        Commands::Replay(args) => run_replay(args, &cli).await,
+        Commands::Stats(args) => run_stats(args, &cli),
+        Commands::Info(args) => run_info(args, &cli),
+        Commands::Baseline(command) => {
+            let check = matches!(command, BaselineCommand::Check { .. });
+            let result = run_baseline(command, &cli).await;
+            // Like diff, `check` keeps status 1 for drift and reports trouble with 2
+            if let (true, Err(e)) = (check, &result) {
+                eprintln!("Error: {e}");
+                std::process::exit(EXIT_BASELINE_ERROR);
+            }
+            result
+        }
+        Commands::Audit(command) => run_audit(command, &cli),
+        #[cfg(unix)]
+        Commands::Daemon(args) => run_daemon(args, &cli).await,
+        #[cfg(unix)]
+        Commands::Client(args) => run_client(args, &cli).await,
+        #[cfg(feature = "http")]
+        Commands::Serve(args) => run_serve(args, &cli).await,
+        Commands::Agent(args) => run_agent(args, &cli).await,
+        Commands::Collector(args) => run_collector(args, &cli).await,
+        #[cfg(feature = "tui")]
+        Commands::Top(args) => run_top(args, &cli).await,
         Commands::Install => install_binary(),
         Commands::Uninstall => uninstall_binary(),
     }
 }
 
-async fn run_monitor(
-    json: bool,
-    logfile: Option<String>,
-) -> Result<(), Box<dyn std::error::Error>> {
+#[cfg(any(unix, feature = "sqlite"))]
+impl HistoryArgs {
+    /// Returns the query described by the arguments.
+    fn query(&self) -> HistoryQuery {
+        HistoryQuery {
+            since: self.since,
+            until: self.until,
+            vendor_id: self.vid.clone(),
+            product_id: self.pid.clone(),
+            serial_number: self.serial.clone(),
+            event_type: self.event.clone(),
+            limit: self.limit,
+        }
+    }
+}
+
+/// Policy and baseline shared with the running watcher and pipeline, so they
+/// can be re-read from their files without restarting.
+#[derive(Clone)]
+#[cfg_attr(not(any(unix, feature = "http")), allow(dead_code))]
+struct SharedConfig {
+    policy_path: Option<PathBuf>,
+    policy: Option<Arc<RwLock<Policy>>>,
+    baseline_path: Option<PathBuf>,
+    baseline: Option<Arc<RwLock<Baseline>>>,
+}
+
+impl SharedConfig {
+    /// Loads the files given with `--policy` and `--baseline`.
+    fn load(cli: &Cli) -> Result<Self, Box<dyn std::error::Error>> {
+        let policy = cli.policy.as_ref().map(Policy::load).transpose()?;
+        let baseline = cli.baseline_file.as_ref().map(Baseline::load).transpose()?;
+        Ok(Self {
+            policy_path: cli.policy.clone(),
+            policy: policy.map(|policy| Arc::new(RwLock::new(policy))),
+            baseline_path: cli.baseline_file.clone(),
+            baseline: baseline.map(|baseline| Arc::new(RwLock::new(baseline))),
+        })
+    }
+
+    /// Re-reads the policy and baseline files; nothing is replaced unless all
+    /// of them load.
+    #[cfg_attr(not(any(unix, feature = "http")), allow(dead_code))]
+    fn reload(&self) -> Result<String, String> {
+        let policy = self
+            .policy_path
+            .as_ref()
+            .map(Policy::load)
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        let baseline = self
+            .baseline_path
+            .as_ref()
+            .map(Baseline::load)
+            .transpose()
+            .map_err(|e| e.to_string())?;
+
+        let mut reloaded = Vec::new();
+        if let (Some(shared), Some(policy), Some(path)) = (&self.policy, policy, &self.policy_path)
+        {
+            *shared.write().unwrap_or_else(PoisonError::into_inner) = policy;
+            reloaded.push(format!("policy from '{}'", path.display()));
+        }
+        if let (Some(shared), Some(baseline), Some(path)) =
+            (&self.baseline, baseline, &self.baseline_path)
+        {
+            *shared.write().unwrap_or_else(PoisonError::into_inner) = baseline;
+            reloaded.push(format!("baseline from '{}'", path.display()));
+        }
+        if reloaded.is_empty() {
+            return Ok("Nothing to reload: no --policy or --baseline given".to_string());
+        }
+        Ok(format!("Reloaded {}", reloaded.join(" and ")))
+    }
+}
+
+async fn run_monitor(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
     println!(
         "🔌 USB Device Monitor - usbwatch v{}",
         env!("CARGO_PKG_VERSION")
     );
     println!("Press Ctrl+C to stop monitoring...");
 
+    let config = SharedConfig::load(cli)?;
+
     // Create channel for device events
     let (tx, rx) = mpsc::channel(100);
-
-    // Detect if terminal supports colour
-    let colourful = atty::is(atty::Stream::Stdout);
-    // Initialise logger
-    let logger = Logger::new(json, logfile.as_deref(), colourful)?;
+    let rx = apply_pipeline(cli, &config, rx, None)?;
 
     // Start logger task
+    let logger = build_logger(cli)?;
     let logger_handle = tokio::spawn(logger_task(rx, logger));
 
-    // Create and start USB watcher
-    let watcher = UsbWatcher::new(tx)?;
-
-    // Handle Ctrl+C gracefully
-    let watcher_handle = tokio::spawn(async move {
-        if let Err(e) = watcher.start_monitoring().await {
-            eprintln!("USB monitoring error: {e}");
-        }
-    });
+    let watcher_handle = spawn_watcher(cli, &config, tx, None)?;
 
     // Wait for Ctrl+C
     tokio::select! {
-        _ = tokio::signal::ctrl_c() => {
+        result = shutdown_signal() => {
+            result?;
             println!("\n📡 Shutting down USB monitor...");
         }
         _ = watcher_handle => {
@@ -112,6 +661,712 @@ async fn run_monitor(
     Ok(())
 }
 
+/// Creates the USB watcher and starts it on a background task, recording
+/// scan metrics in `metrics` if given.
+fn spawn_watcher(
+    cli: &Cli,
+    config: &SharedConfig,
+    tx: mpsc::Sender<UsbDeviceInfo>,
+    metrics: Option<Arc<Metrics>>,
+) -> Result<tokio::task::JoinHandle<()>, Box<dyn std::error::Error>> {
+    let options = WatcherOptions {
+        collapse_hubs: cli.collapse_hubs,
+        usb_ids: load_usb_ids(cli)?.map(Arc::new),
+        policy: config.policy.clone(),
+        metrics,
+        ..Default::default()
+    };
+    let watcher = UsbWatcher::with_options(tx, options)?;
+    Ok(tokio::spawn(async move {
+        if let Err(e) = watcher.start_monitoring().await {
+            eprintln!("USB monitoring error: {e}");
+        }
+    }))
+}
+
+/// Inserts the processing stages requested on the command line between the
+/// watcher and the logger, counting events in `metrics` before they are
+/// filtered if given.
+fn apply_pipeline(
+    cli: &Cli,
+    config: &SharedConfig,
+    mut rx: mpsc::Receiver<UsbDeviceInfo>,
+    metrics: Option<&Arc<Metrics>>,
+) -> Result<mpsc::Receiver<UsbDeviceInfo>, Box<dyn std::error::Error>> {
+    // Insert the debounce stage between the watcher and the logger if requested
+    if cli.debounce.is_some() || cli.flap_alert.is_some() {
+        let config = DebounceConfig {
+            stable_for: std::time::Duration::from_millis(cli.debounce.unwrap_or(0)),
+            collapse: cli.collapse_flaps,
+            alert_per_minute: cli.flap_alert,
+        };
+        let (debounced_tx, debounced_rx) = mpsc::channel(100);
+        tokio::spawn(debounce_task(rx, debounced_tx, config));
+        rx = debounced_rx;
+    }
+    if let Some(metrics) = metrics {
+        let (counted_tx, counted_rx) = mpsc::channel(100);
+        tokio::spawn(metrics_task(rx, counted_tx, metrics.clone()));
+        rx = counted_rx;
+    }
+    let rx = apply_filter(cli, rx);
+    let rx = apply_baseline(config, rx);
+    let rx = apply_metadata(cli, rx);
+    #[cfg(all(feature = "notify", target_os = "linux"))]
+    let rx = apply_notify(cli, rx);
+    #[cfg(feature = "webhooks")]
+    let rx = apply_webhooks(cli, rx)?;
+    #[cfg(feature = "mqtt")]
+    let rx = apply_mqtt(cli, rx)?;
+    Ok(rx)
+}
+
+#[cfg(unix)]
+async fn run_daemon(args: DaemonArgs, cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let config = SharedConfig::load(cli)?;
+    let socket_options = SocketOptions {
+        mode: args.socket_mode,
+        group: args.socket_group.clone(),
+    };
+    let listener = daemon::bind(&args.socket, &socket_options)?;
+    println!(
+        "🔌 USB Device Monitor daemon - usbwatch v{}",
+        env!("CARGO_PKG_VERSION")
+    );
+    println!("Listening on {}", args.socket.display());
+
+    let mut recorder = start_recording(cli, &config, args.history_size)?;
+    let server_handle = tokio::spawn(daemon::serve(listener, recorder.state.clone()));
+    wait_for_shutdown(&config, &mut recorder.watcher).await?;
+
+    server_handle.abort();
+    recorder.logger.abort();
+    if let Err(e) = fs::remove_file(&args.socket) {
+        eprintln!("Failed to remove socket '{}': {e}", args.socket.display());
+    }
+    Ok(())
+}
+
+#[cfg(feature = "http")]
+async fn run_serve(args: ServeArgs, cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let config = SharedConfig::load(cli)?;
+    let options = HttpOptions {
+        token: args.token_file.as_deref().map(load_token).transpose()?,
+        websocket_only: args.websocket_only,
+        allowed_origins: args.allowed_origins,
+    };
+    let listener = tokio::net::TcpListener::bind(&args.listen)
+        .await
+        .map_err(|e| format!("Failed to listen on '{}': {e}", args.listen))?;
+    let address = listener.local_addr()?;
+    println!(
+        "🔌 USB Device Monitor HTTP API - usbwatch v{}",
+        env!("CARGO_PKG_VERSION")
+    );
+    println!("Listening on http://{address}");
+    if options.token.is_none() && !address.ip().is_loopback() {
+        eprintln!("Warning: serving without --token-file on a non-loopback address");
+    }
+
+    let mut recorder = start_recording(cli, &config, args.history_size)?;
+    let server_handle = tokio::spawn(async move {
+        if let Err(e) = http::serve(listener, recorder.state, options).await {
+            eprintln!("HTTP server error: {e}");
+        }
+    });
+    wait_for_shutdown(&config, &mut recorder.watcher).await?;
+
+    server_handle.abort();
+    recorder.logger.abort();
+    Ok(())
+}
+
+async fn run_agent(args: AgentArgs, cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let config = SharedConfig::load(cli)?;
+    let host = args
+        .host
+        .or_else(|| HostIdentity::detect().hostname)
+        .unwrap_or_else(|| "localhost".to_string());
+    #[cfg(feature = "tls")]
+    let tls = match &args.tls_ca {
+        Some(ca) => {
+            let server_name = args
+                .tls_server_name
+                .clone()
+                .unwrap_or_else(|| upstream_host(&args.upstream).to_string());
+            let identity = args.tls_cert.as_deref().zip(args.tls_key.as_deref());
+            Some(ClientTls::new(ca, identity, &server_name)?)
+        }
+        None => None,
+    };
+    let options = AgentOptions {
+        buffer_size: args.buffer_size,
+        #[cfg(feature = "tls")]
+        tls,
+        ..AgentOptions::new(args.upstream, host)
+    };
+    println!(
+        "🔌 USB Device Monitor agent - usbwatch v{}",
+        env!("CARGO_PKG_VERSION")
+    );
+    println!("Streaming events to {}", options.upstream);
+
+    let (tx, rx) = mpsc::channel(100);
+    let rx = apply_pipeline(cli, &config, rx, None)?;
+    let (sent_tx, sent_rx) = mpsc::channel(100);
+    tokio::spawn(agent_task(rx, sent_tx, options));
+    let logger_handle = tokio::spawn(logger_task(sent_rx, build_logger(cli)?));
+    let mut watcher = spawn_watcher(cli, &config, tx, None)?;
+    wait_for_shutdown(&config, &mut watcher).await?;
+
+    logger_handle.abort();
+    Ok(())
+}
+
+/// Returns the host part of a `host:port` address.
+#[cfg(feature = "tls")]
+fn upstream_host(upstream: &str) -> &str {
+    let host = upstream.rsplit_once(':').map_or(upstream, |(host, _)| host);
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+async fn run_collector(args: CollectorArgs, cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let options = CollectorOptions {
+        #[cfg(feature = "tls")]
+        tls: match (&args.tls_cert, &args.tls_key) {
+            (Some(cert), Some(key)) => {
+                Some(ServerTls::new(cert, key, args.tls_client_ca.as_deref())?)
+            }
+            _ => None,
+        },
+    };
+    let listener = tokio::net::TcpListener::bind(&args.listen)
+        .await
+        .map_err(|e| format!("Failed to listen on '{}': {e}", args.listen))?;
+    let address = listener.local_addr()?;
+    println!(
+        "🔌 USB Device Monitor collector - usbwatch v{}",
+        env!("CARGO_PKG_VERSION")
+    );
+    println!("Listening for agents on {address}");
+    #[cfg(feature = "tls")]
+    let encrypted = options.tls.is_some();
+    #[cfg(not(feature = "tls"))]
+    let encrypted = false;
+    if !encrypted && !address.ip().is_loopback() {
+        eprintln!("Warning: accepting agents without TLS on a non-loopback address");
+    }
+
+    let (tx, rx) = mpsc::channel(100);
+    let logger_handle = tokio::spawn(logger_task(rx, build_logger(cli)?));
+    tokio::select! {
+        _ = collector::serve(listener, tx, options) => {}
+        _ = tokio::signal::ctrl_c() => {
+            println!("\n📡 Shutting down collector...");
+        }
+    }
+
+    logger_handle.abort();
+    Ok(())
+}
+
+#[cfg(feature = "tui")]
+async fn run_top(args: TopArgs, cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    if !atty::is(atty::Stream::Stdout) {
+        return Err("`top` needs an interactive terminal; use `monitor` to log events".into());
+    }
+    let config = SharedConfig::load(cli)?;
+    let (tx, rx) = mpsc::channel(100);
+    let rx = apply_pipeline(cli, &config, rx, None)?;
+    let watcher = spawn_watcher(cli, &config, tx, None)?;
+    let result = tui::run(rx, Dashboard::new(args.log_size)).await;
+    watcher.abort();
+    Ok(result?)
+}
+
+/// Tasks of a monitoring pipeline that records events into a [`DaemonState`].
+#[cfg(any(unix, feature = "http"))]
+struct Recorder {
+    state: Arc<DaemonState>,
+    logger: tokio::task::JoinHandle<()>,
+    watcher: tokio::task::JoinHandle<()>,
+}
+
+/// Starts the watcher and pipeline, recording each event in a new daemon
+/// state before it is logged.
+#[cfg(any(unix, feature = "http"))]
+fn start_recording(
+    cli: &Cli,
+    config: &SharedConfig,
+    history_size: usize,
+) -> Result<Recorder, Box<dyn std::error::Error>> {
+    let reload_config = config.clone();
+    #[allow(unused_mut)]
+    let mut state = DaemonState::new(history_size).with_reload(move || reload_config.reload());
+    #[cfg(feature = "sqlite")]
+    if let Some(db) = &cli.db {
+        state = state.with_store(EventStore::open(db)?);
+    }
+    let state = Arc::new(state);
+
+    let (tx, rx) = mpsc::channel(100);
+    let rx = apply_pipeline(cli, config, rx, Some(state.metrics()))?;
+    let (recorded_tx, recorded_rx) = mpsc::channel(100);
+    tokio::spawn(daemon::record_task(rx, recorded_tx, state.clone()));
+    let logger = tokio::spawn(logger_task(recorded_rx, build_logger(cli)?));
+    let watcher = spawn_watcher(cli, config, tx, Some(state.metrics().clone()))?;
+    Ok(Recorder {
+        state,
+        logger,
+        watcher,
+    })
+}
+
+/// Waits for Ctrl+C or, on Unix, SIGTERM, so that a service stopped by its
+/// supervisor still shuts down cleanly (restoring `authorized_default`).
+async fn shutdown_signal() -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        let mut terminate =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result,
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await
+}
+
+/// Waits for a [`shutdown_signal`] or the watcher to stop; on Unix, SIGHUP reloads the
+/// policy and baseline in the meantime.
+async fn wait_for_shutdown(
+    config: &SharedConfig,
+    watcher: &mut tokio::task::JoinHandle<()>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(unix)]
+    let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+    loop {
+        #[cfg(unix)]
+        let reload = hangup.recv();
+        #[cfg(not(unix))]
+        let reload = std::future::pending::<Option<()>>();
+        tokio::select! {
+            result = shutdown_signal() => {
+                result?;
+                println!("\n📡 Shutting down USB monitor...");
+                return Ok(());
+            }
+            _ = &mut *watcher => {
+                println!("📡 USB monitoring stopped");
+                return Ok(());
+            }
+            _ = reload => match config.reload() {
+                Ok(message) => println!("🔄 {message}"),
+                Err(e) => eprintln!("Reload failed: {e}"),
+            },
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn run_client(args: ClientArgs, cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let mut client = DaemonClient::connect(&args.socket).await?;
+    match args.command {
+        ClientCommand::List => {
+            let result = client.call("list", serde_json::Value::Null).await?;
+            if cli.json {
+                println!("{}", serde_json::to_string_pretty(&result)?);
+                return Ok(());
+            }
+            let devices: Vec<UsbDeviceInfo> = serde_json::from_value(result)?;
+            if devices.is_empty() {
+                println!("No devices attached");
+            }
+            for device in devices {
+                println!("{}", device.format_plain());
+            }
+        }
+        ClientCommand::Subscribe => {
+            let (tx, rx) = mpsc::channel(100);
+            let logger_handle = tokio::spawn(logger_task(rx, build_logger(cli)?));
+            tokio::select! {
+                result = client.subscribe(tx) => result?,
+                _ = tokio::signal::ctrl_c() => {}
+            }
+            // The logger finishes once the subscription's sender is dropped
+            logger_handle.await?;
+        }
+        ClientCommand::Stats { top } => {
+            let result = client.call("stats", serde_json::Value::Null).await?;
+            if cli.json {
+                println!("{}", serde_json::to_string_pretty(&result)?);
+                return Ok(());
+            }
+            let stats: DaemonStats = serde_json::from_value(result)?;
+            println!(
+                "Uptime: {}",
+                usbwatch_rs::report::format_duration(stats.uptime_secs as f64)
+            );
+            println!("Connected devices: {}", stats.connected_devices);
+            println!("Subscribers: {}", stats.subscribers);
+            println!("Events since start: {}", stats.events_total);
+            println!();
+            print!("{}", stats.history.render_text(top));
+        }
+        ClientCommand::Reload => {
+            let result = client.call("reload", serde_json::Value::Null).await?;
+            println!("{}", result.as_str().unwrap_or_default());
+        }
+        ClientCommand::History(args) => {
+            let result = client
+                .call("history", serde_json::to_value(args.query())?)
+                .await?;
+            let events: Vec<UsbDeviceInfo> = serde_json::from_value(result)?;
+            print_events(&events, args.format, cli)?;
+        }
+    }
+    Ok(())
+}
+
+/// Builds the event logger from the global output options.
+fn build_logger(cli: &Cli) -> Result<Logger, Box<dyn std::error::Error>> {
+    // Detect if terminal supports colour
+    let colourful = atty::is(atty::Stream::Stdout);
+    #[allow(unused_mut)]
+    let mut logger = Logger::new(cli.json, cli.logfile.as_deref(), colourful)?;
+    if let Some(template) = &cli.template {
+        logger = logger.with_template(template);
+    }
+    #[cfg(feature = "sqlite")]
+    if let Some(db) = &cli.db {
+        logger = logger.with_store(EventStore::open(db)?);
+    }
+    if let Some(path) = &cli.audit_log {
+        #[allow(unused_mut)]
+        let mut audit_log = AuditLog::open(path)?;
+        #[cfg(feature = "signing")]
+        if let Some(key) = &cli.audit_key {
+            audit_log = audit_log.with_signing_key(&load_key(key)?, cli.checkpoint_interval);
+        }
+        logger = logger.with_audit_log(audit_log);
+    }
+    Ok(logger)
+}
+
+/// Inserts a filter stage in front of `rx` when filters were given on the command line.
+fn apply_filter(cli: &Cli, rx: mpsc::Receiver<UsbDeviceInfo>) -> mpsc::Receiver<UsbDeviceInfo> {
+    let filter = EventFilter {
+        classes: cli.classes.clone(),
+        ..Default::default()
+    };
+    if filter.is_empty() {
+        return rx;
+    }
+    let (filtered_tx, filtered_rx) = mpsc::channel(100);
+    tokio::spawn(filter_task(rx, filtered_tx, filter));
+    filtered_rx
+}
+
+/// Inserts a stage tagging events as known or unknown when `--baseline` is given.
+fn apply_baseline(
+    config: &SharedConfig,
+    rx: mpsc::Receiver<UsbDeviceInfo>,
+) -> mpsc::Receiver<UsbDeviceInfo> {
+    let Some(baseline) = &config.baseline else {
+        return rx;
+    };
+    let (tagged_tx, tagged_rx) = mpsc::channel(100);
+    tokio::spawn(tag_task(rx, tagged_tx, baseline.clone()));
+    tagged_rx
+}
+
+/// Inserts a stage stamping events with sequence numbers and host identity when `--metadata` is given.
+fn apply_metadata(cli: &Cli, rx: mpsc::Receiver<UsbDeviceInfo>) -> mpsc::Receiver<UsbDeviceInfo> {
+    if !cli.metadata {
+        return rx;
+    }
+    let (stamped_tx, stamped_rx) = mpsc::channel(100);
+    let stamper = MetadataStamper::new(HostIdentity::detect());
+    tokio::spawn(metadata_task(rx, stamped_tx, stamper));
+    stamped_rx
+}
+
+/// Inserts a stage showing desktop notifications when `--notify` is given.
+///
+/// Without a session bus (e.g. under sudo) events pass through unnotified.
+#[cfg(all(feature = "notify", target_os = "linux"))]
+fn apply_notify(cli: &Cli, mut rx: mpsc::Receiver<UsbDeviceInfo>) -> mpsc::Receiver<UsbDeviceInfo> {
+    if !cli.notify {
+        return rx;
+    }
+    let (notified_tx, notified_rx) = mpsc::channel(100);
+    tokio::spawn(async move {
+        match Notifier::connect(None, RateLimit::default()).await {
+            Ok(notifier) => notify_task(rx, notified_tx, notifier).await,
+            Err(e) => {
+                eprintln!("⚠️  {e}; desktop notifications are disabled");
+                while let Some(event) = rx.recv().await {
+                    if notified_tx.send(event).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+    notified_rx
+}
+
+/// Inserts a stage posting events to webhooks when `--webhooks` is given.
+#[cfg(feature = "webhooks")]
+fn apply_webhooks(
+    cli: &Cli,
+    rx: mpsc::Receiver<UsbDeviceInfo>,
+) -> Result<mpsc::Receiver<UsbDeviceInfo>, Box<dyn std::error::Error>> {
+    let Some(path) = &cli.webhooks else {
+        return Ok(rx);
+    };
+    let webhooks = Webhooks::start(WebhookConfig::load(path)?)?;
+    let (notified_tx, notified_rx) = mpsc::channel(100);
+    tokio::spawn(webhook_task(rx, notified_tx, webhooks));
+    Ok(notified_rx)
+}
+
+/// Inserts a stage publishing events to MQTT when `--mqtt` is given.
+#[cfg(feature = "mqtt")]
+fn apply_mqtt(
+    cli: &Cli,
+    rx: mpsc::Receiver<UsbDeviceInfo>,
+) -> Result<mpsc::Receiver<UsbDeviceInfo>, Box<dyn std::error::Error>> {
+    let Some(url) = &cli.mqtt else {
+        return Ok(rx);
+    };
+    let host = HostIdentity::detect()
+        .hostname
+        .unwrap_or_else(|| "localhost".to_string());
+    let config = MqttConfig {
+        prefix: cli.mqtt_prefix.clone(),
+        qos: cli.mqtt_qos,
+        ..MqttConfig::new(url.clone(), host)
+    };
+    let publisher = MqttPublisher::connect(config)?;
+    let (published_tx, published_rx) = mpsc::channel(100);
+    tokio::spawn(mqtt_task(rx, published_tx, publisher));
+    Ok(published_rx)
+}
+
+/// Loads the USB ID database requested on the command line, if any.
+fn load_usb_ids(cli: &Cli) -> Result<Option<UsbIds>, Box<dyn std::error::Error>> {
+    if let Some(path) = &cli.usb_ids {
+        return Ok(Some(UsbIds::load(path)?));
+    }
+    if !cli.resolve_names {
+        return Ok(None);
+    }
+    match UsbIds::load_default() {
+        Some(usb_ids) => Ok(Some(usb_ids)),
+        None => {
+            eprintln!("⚠️  No usb.ids database found; device names will not be resolved");
+            Ok(None)
+        }
+    }
+}
+
+async fn run_replay(args: ReplayArgs, cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(speed) = args.speed {
+        if speed <= 0.0 || !speed.is_finite() {
+            return Err(format!("Invalid speed '{speed}': must be a positive number").into());
+        }
+    }
+
+    let events = read_logs(&args.files)?;
+    let logger = build_logger(cli)?;
+
+    let (tx, rx) = mpsc::channel(100);
+    let rx = apply_filter(cli, rx);
+    let logger_handle = tokio::spawn(logger_task(rx, logger));
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = replay_events(events, tx, args.speed) => {}
+    }
+
+    // The sender has been dropped, so the logger drains the channel and exits
+    logger_handle.await?;
+    Ok(())
+}
+
+/// Reads the given logs, warning about any records that had to be skipped.
+fn read_logs(files: &[PathBuf]) -> Result<Vec<UsbDeviceInfo>, Box<dyn std::error::Error>> {
+    let log = EventReader::read_all(files)?;
+    for error in &log.skipped {
+        eprintln!("Skipping record: {error}");
+    }
+    Ok(log.events)
+}
+
+fn run_stats(args: StatsArgs, cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let events = read_logs(&args.files)?;
+    let stats = EventStats::from_events(&events);
+
+    match if cli.json {
+        OutputFormat::Json
+    } else {
+        args.format
+    } {
+        OutputFormat::Table => println!("{}", stats.render_text(args.top)),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+        OutputFormat::Csv => return Err("CSV output is not supported for stats".into()),
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn run_info(args: InfoArgs, cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    use usbwatch_rs::info::{find_device, DeviceReport};
+
+    let path = find_device(&WatcherOptions::default().usb_devices_path(), &args.device)?;
+    // Names are always resolved for reports when a database is available
+    let usb_ids = match load_usb_ids(cli)? {
+        Some(usb_ids) => Some(usb_ids),
+        None => UsbIds::load_default(),
+    };
+    let options = WatcherOptions {
+        usb_ids: usb_ids.map(Arc::new),
+        ..Default::default()
+    };
+    let report = DeviceReport::collect(&path, &options)?;
+
+    if cli.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{}", report.render_text(args.descriptors));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn run_info(_args: InfoArgs, _cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    Err("The info command is only supported on Linux".into())
+}
+
+async fn run_baseline(
+    command: BaselineCommand,
+    cli: &Cli,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let options = WatcherOptions {
+        usb_ids: load_usb_ids(cli)?.map(Arc::new),
+        ..Default::default()
+    };
+    // Scanning emits nothing, so the receiver is not needed
+    let (tx, _rx) = mpsc::channel(1);
+    let devices = UsbWatcher::with_options(tx, options)?
+        .scan_devices()
+        .await?;
+
+    match command {
+        BaselineCommand::Save { file } => {
+            let baseline = Baseline::new(devices);
+            baseline.save(&file)?;
+            println!(
+                "Saved {} device(s) to {}",
+                baseline.devices.len(),
+                file.display()
+            );
+        }
+        BaselineCommand::Check { file } => {
+            let report = Baseline::load(&file)?.diff(&devices);
+            if cli.json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print!("{}", report.render_text());
+            }
+            if report.has_drift() {
+                std::process::exit(EXIT_BASELINE_DRIFT);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_audit(command: AuditCommand, cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        AuditCommand::Verify { file, public_key } => {
+            let public_key = public_key.map(load_key).transpose()?;
+            let reader = std::io::BufReader::new(
+                fs::File::open(&file)
+                    .map_err(|e| format!("Failed to open '{}': {e}", file.display()))?,
+            );
+            #[cfg(feature = "signing")]
+            let checkpoint_interval = cli.checkpoint_interval;
+            #[cfg(not(feature = "signing"))]
+            let checkpoint_interval = usbwatch_rs::audit::DEFAULT_CHECKPOINT_INTERVAL;
+            let report = verify_log(reader, public_key.as_ref(), checkpoint_interval)?;
+            if cli.json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print!("{}", report.render_text());
+            }
+            if !report.is_valid() {
+                std::process::exit(1);
+            }
+        }
+        #[cfg(feature = "signing")]
+        AuditCommand::Keygen { file } => {
+            use usbwatch_rs::audit::{generate_keypair, to_hex};
+
+            let (secret, public) = generate_keypair();
+            let public_file = PathBuf::from(format!("{}.pub", file.display()));
+            let mut options = fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+            let mut secret_file = options
+                .open(&file)
+                .map_err(|e| format!("Failed to create '{}': {e}", file.display()))?;
+            std::io::Write::write_all(
+                &mut secret_file,
+                format!("{}\n", to_hex(&secret)).as_bytes(),
+            )?;
+            fs::write(&public_file, format!("{}\n", to_hex(&public)))?;
+            println!("Secret key: {}", file.display());
+            println!(
+                "Public key: {} ({})",
+                public_file.display(),
+                to_hex(&public)
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+fn run_history(args: HistoryArgs, cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let db = cli
+        .db
+        .as_deref()
+        .ok_or("No database given: pass --db <PATH>")?;
+    let store = EventStore::open(db)?;
+    let events = store.query(&args.query())?;
+    print_events(&events, args.format, cli)
+}
+
+/// Prints queried events in the requested format (`--json` overrides it).
+#[cfg(any(unix, feature = "sqlite"))]
+fn print_events(
+    events: &[UsbDeviceInfo],
+    format: OutputFormat,
+    cli: &Cli,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let format = if cli.json { OutputFormat::Json } else { format };
+    if events.is_empty() && format == OutputFormat::Table {
+        println!("No matching events");
+        return Ok(());
+    }
+    println!("{}", render_events(events, format)?);
+    Ok(())
+}
+
 fn install_binary() -> Result<(), Box<dyn std::error::Error>> {
     let current_exe = env::current_exe()?;
     let exe_name = if cfg!(windows) {
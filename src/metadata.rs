@@ -0,0 +1,178 @@
+//! Per-event sequence numbers and host identity.
+//!
+//! When events from many machines are merged, the wall-clock `timestamp` alone cannot say where an event came from
+//! or how events within the same second were ordered. [`MetadataStamper`] attaches an [`EventMetadata`] to each event:
+//! a sequence number that increases by one per event, the host name, the machine ID (`/etc/machine-id`), the boot ID
+//! (`/proc/sys/kernel/random/boot_id`) and the event time on the monotonic clock (`CLOCK_MONOTONIC`), which is the
+//! clock the kernel log and the systemd journal use. Identity fields that a platform cannot provide are left out.
+
+use crate::device_info::UsbDeviceInfo;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+/// Where and in which order an event was recorded.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventMetadata {
+    /// Position of the event in this monitor's output, starting at 1
+    pub seq: u64,
+    /// Host name of the machine
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    /// Machine ID (`/etc/machine-id` on Linux)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub machine_id: Option<String>,
+    /// ID of the current boot (Linux); monotonic times are only comparable within one boot
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boot_id: Option<String>,
+    /// Event time on the monotonic clock, in nanoseconds since boot (Unix)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monotonic_ns: Option<u64>,
+}
+
+/// Identity of the machine events are recorded on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostIdentity {
+    /// Host name
+    pub hostname: Option<String>,
+    /// Machine ID
+    pub machine_id: Option<String>,
+    /// Boot ID
+    pub boot_id: Option<String>,
+}
+
+impl HostIdentity {
+    /// Detects the identity of the current machine.
+    pub fn detect() -> Self {
+        Self {
+            hostname: detect_hostname(),
+            machine_id: read_id(&["/etc/machine-id", "/var/lib/dbus/machine-id"]),
+            boot_id: read_id(&["/proc/sys/kernel/random/boot_id"]),
+        }
+    }
+}
+
+/// Attaches sequence numbers and host identity to events.
+#[derive(Debug, Clone)]
+pub struct MetadataStamper {
+    identity: HostIdentity,
+    next_seq: u64,
+}
+
+impl MetadataStamper {
+    /// Creates a stamper whose first event gets sequence number 1.
+    pub fn new(identity: HostIdentity) -> Self {
+        Self {
+            identity,
+            next_seq: 1,
+        }
+    }
+
+    /// Stamps an event with the next sequence number, the host identity and
+    /// its monotonic time.
+    ///
+    /// The monotonic time is the clock reading the watcher took when it created
+    /// the event, so events that were delayed in the pipeline (e.g. by
+    /// debouncing) still get the time they happened, however the wall clock
+    /// was stepped in between. Events read back from elsewhere have none and
+    /// get the current time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+    /// use usbwatch_rs::metadata::{HostIdentity, MetadataStamper};
+    ///
+    /// let mut stamper = MetadataStamper::new(HostIdentity {
+    ///     hostname: Some("kiosk-7".to_string()),
+    ///     ..Default::default()
+    /// });
+    /// let mut event = UsbDeviceInfo::new(
+    ///     "USB Storage".to_string(),
+    ///     "0781".to_string(),
+    ///     "5583".to_string(),
+    ///     None,
+    ///     DeviceEventType::Connected,
+    /// );
+    /// stamper.stamp(&mut event);
+    /// let metadata = event.metadata.unwrap();
+    /// assert_eq!(metadata.seq, 1);
+    /// assert_eq!(metadata.hostname.as_deref(), Some("kiosk-7"));
+    /// ```
+    pub fn stamp(&mut self, event: &mut UsbDeviceInfo) {
+        let monotonic_ns = event.monotonic_ns.or_else(monotonic_now_ns);
+        event.metadata = Some(EventMetadata {
+            seq: self.next_seq,
+            hostname: self.identity.hostname.clone(),
+            machine_id: self.identity.machine_id.clone(),
+            boot_id: self.identity.boot_id.clone(),
+            monotonic_ns,
+        });
+        self.next_seq += 1;
+    }
+}
+
+/// Async task that stamps each event from `rx` with metadata and forwards it to `tx`.
+///
+/// The task ends when `rx` closes or `tx` is dropped.
+pub async fn metadata_task(
+    mut rx: mpsc::Receiver<UsbDeviceInfo>,
+    tx: mpsc::Sender<UsbDeviceInfo>,
+    mut stamper: MetadataStamper,
+) {
+    while let Some(mut event) = rx.recv().await {
+        stamper.stamp(&mut event);
+        if tx.send(event).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Returns the current time on the monotonic clock, in nanoseconds.
+#[cfg(unix)]
+pub fn monotonic_now_ns() -> Option<u64> {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: clock_gettime only writes to the timespec we pass
+    let result = unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+    if result != 0 {
+        return None;
+    }
+    Some(ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64)
+}
+
+/// Returns the current time on the monotonic clock, in nanoseconds.
+#[cfg(not(unix))]
+pub fn monotonic_now_ns() -> Option<u64> {
+    None
+}
+
+#[cfg(unix)]
+fn detect_hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    // SAFETY: gethostname writes at most buf.len() bytes into buf
+    let result = unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) };
+    if result != 0 {
+        return None;
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    Some(String::from_utf8_lossy(&buf[..len]).to_string()).filter(|name| !name.is_empty())
+}
+
+#[cfg(not(unix))]
+fn detect_hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME")
+        .ok()
+        .filter(|name| !name.is_empty())
+}
+
+/// Reads the first non-empty ID from a list of files.
+fn read_id(paths: &[&str]) -> Option<String> {
+    paths.iter().find_map(|path| {
+        std::fs::read_to_string(path)
+            .ok()
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+    })
+}
@@ -0,0 +1,247 @@
+//! Prometheus metrics.
+//!
+//! [`Metrics`] collects counters while events flow through the daemon, and [`Metrics::render`] produces the
+//! Prometheus text exposition format served at `/metrics` by `usbwatch serve`:
+//!
+//! | Metric                           | Type      | Labels          | Meaning                                          |
+//! |----------------------------------|-----------|-----------------|--------------------------------------------------|
+//! | `usbwatch_devices_connected`     | gauge     | `class`, `vendor` | Attached devices, by primary class and vendor ID |
+//! | `usbwatch_events_total`          | counter   | `event_type`    | Events seen, including ones `--class` filters out |
+//! | `usbwatch_scan_duration_seconds` | histogram | -               | Duration of each sysfs scan (Linux)              |
+//! | `usbwatch_channel_dropped_total` | counter   | -               | Events lost to a closed channel or a subscriber that fell behind |
+//! | `usbwatch_policy_blocks_total`   | counter   | -               | Devices blocked by the policy (Linux)            |
+//!
+//! Events are counted by [`metrics_task`] right after debouncing, so filtered-out events are included, and policy
+//! blocks by the watcher as it enforces the policy. The pipeline's channels apply backpressure: a full channel makes
+//! the watcher wait rather than drop events, so `usbwatch_channel_dropped_total` only counts events sent to a
+//! channel that was already closed and events a WebSocket or SSE client missed because it read too slowly.
+
+use crate::device_info::{DeviceEventType, UsbDeviceInfo};
+use crate::policy::PolicyAction;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Upper bounds of the scan duration histogram buckets, in seconds.
+pub const SCAN_DURATION_BUCKETS: [f64; 11] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+];
+
+/// Event types in the order they are counted and rendered.
+const EVENT_TYPES: [DeviceEventType; 5] = [
+    DeviceEventType::Connected,
+    DeviceEventType::Disconnected,
+    DeviceEventType::Flapping,
+    DeviceEventType::SuspiciousDevice,
+    DeviceEventType::Degraded,
+];
+
+/// Counters and histograms for the Prometheus exporter.
+///
+/// All methods take `&self`, so one instance can be shared between the
+/// watcher and the event pipeline.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    events: [AtomicU64; EVENT_TYPES.len()],
+    scan_buckets: [AtomicU64; SCAN_DURATION_BUCKETS.len()],
+    scan_count: AtomicU64,
+    scan_sum_ns: AtomicU64,
+    channel_dropped: AtomicU64,
+    policy_blocks: AtomicU64,
+}
+
+impl Metrics {
+    /// Creates metrics with every counter at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts an event by its type.
+    pub fn record_event(&self, event: &UsbDeviceInfo) {
+        let index = EVENT_TYPES
+            .iter()
+            .position(|event_type| *event_type == event.event_type)
+            .unwrap_or(0);
+        self.events[index].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a policy decision, if it blocked the device.
+    pub fn record_policy(&self, action: &PolicyAction) {
+        if *action == PolicyAction::Block {
+            self.policy_blocks.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Records the duration of a device scan.
+    pub fn observe_scan(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        if let Some(bucket) = SCAN_DURATION_BUCKETS.iter().position(|le| secs <= *le) {
+            self.scan_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+        self.scan_count.fetch_add(1, Ordering::Relaxed);
+        self.scan_sum_ns
+            .fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Counts events that were dropped before reaching a consumer.
+    pub fn record_dropped(&self, count: u64) {
+        self.channel_dropped.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Returns the number of events counted, over all event types.
+    pub fn events_total(&self) -> u64 {
+        self.events
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// Renders the metrics in the Prometheus text format, with `devices` as
+    /// the currently attached devices.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+    /// use usbwatch_rs::metrics::Metrics;
+    ///
+    /// let device = UsbDeviceInfo::new(
+    ///     "USB Storage".to_string(),
+    ///     "0781".to_string(),
+    ///     "5583".to_string(),
+    ///     None,
+    ///     DeviceEventType::Connected,
+    /// );
+    /// let metrics = Metrics::new();
+    /// metrics.record_event(&device);
+    ///
+    /// let text = metrics.render(&[device]);
+    /// assert!(text.contains(r#"usbwatch_devices_connected{class="unknown",vendor="0781"} 1"#));
+    /// assert!(text.contains(r#"usbwatch_events_total{event_type="Connected"} 1"#));
+    /// ```
+    pub fn render(&self, devices: &[UsbDeviceInfo]) -> String {
+        let mut out = String::new();
+
+        let mut connected: BTreeMap<(String, String), u64> = BTreeMap::new();
+        for device in devices {
+            let class = device
+                .classes()
+                .first()
+                .map_or_else(|| "unknown".to_string(), ToString::to_string);
+            *connected
+                .entry((class, device.vendor_id.to_ascii_lowercase()))
+                .or_default() += 1;
+        }
+        header(
+            &mut out,
+            "usbwatch_devices_connected",
+            "gauge",
+            "Currently attached USB devices, by primary class and vendor ID.",
+        );
+        for ((class, vendor), count) in connected {
+            let _ = writeln!(
+                out,
+                "usbwatch_devices_connected{{class=\"{}\",vendor=\"{}\"}} {count}",
+                escape(&class),
+                escape(&vendor)
+            );
+        }
+
+        header(
+            &mut out,
+            "usbwatch_events_total",
+            "counter",
+            "USB device events seen before filtering, by event type.",
+        );
+        for (event_type, count) in EVENT_TYPES.iter().zip(&self.events) {
+            let _ = writeln!(
+                out,
+                "usbwatch_events_total{{event_type=\"{event_type}\"}} {}",
+                count.load(Ordering::Relaxed)
+            );
+        }
+
+        header(
+            &mut out,
+            "usbwatch_scan_duration_seconds",
+            "histogram",
+            "Duration of each sysfs device scan.",
+        );
+        let mut cumulative = 0;
+        for (le, count) in SCAN_DURATION_BUCKETS.iter().zip(&self.scan_buckets) {
+            cumulative += count.load(Ordering::Relaxed);
+            let _ = writeln!(
+                out,
+                "usbwatch_scan_duration_seconds_bucket{{le=\"{le}\"}} {cumulative}"
+            );
+        }
+        let scans = self.scan_count.load(Ordering::Relaxed);
+        let _ = writeln!(
+            out,
+            "usbwatch_scan_duration_seconds_bucket{{le=\"+Inf\"}} {scans}"
+        );
+        let _ = writeln!(
+            out,
+            "usbwatch_scan_duration_seconds_sum {}",
+            self.scan_sum_ns.load(Ordering::Relaxed) as f64 / 1e9
+        );
+        let _ = writeln!(out, "usbwatch_scan_duration_seconds_count {scans}");
+
+        header(
+            &mut out,
+            "usbwatch_channel_dropped_total",
+            "counter",
+            "Events sent to a closed channel or missed by a stream client that fell behind.",
+        );
+        let _ = writeln!(
+            out,
+            "usbwatch_channel_dropped_total {}",
+            self.channel_dropped.load(Ordering::Relaxed)
+        );
+
+        header(
+            &mut out,
+            "usbwatch_policy_blocks_total",
+            "counter",
+            "Devices blocked by the authorisation policy.",
+        );
+        let _ = writeln!(
+            out,
+            "usbwatch_policy_blocks_total {}",
+            self.policy_blocks.load(Ordering::Relaxed)
+        );
+        out
+    }
+}
+
+/// Async task that counts each event from `rx` in `metrics` and forwards it to `tx`.
+///
+/// The task ends when `rx` closes or `tx` is dropped.
+pub async fn metrics_task(
+    mut rx: mpsc::Receiver<UsbDeviceInfo>,
+    tx: mpsc::Sender<UsbDeviceInfo>,
+    metrics: Arc<Metrics>,
+) {
+    while let Some(event) = rx.recv().await {
+        metrics.record_event(&event);
+        if tx.send(event).await.is_err() {
+            return;
+        }
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+/// Escapes a label value for the text format.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,306 @@
+//! MQTT publisher.
+//!
+//! [`MqttPublisher`] publishes every event as JSON to `<prefix>/<host>/<vid>/<pid>/<event>`, where `<event>` is
+//! `connected`, `disconnected`, `flapping`, `suspicious-device` or `degraded`, at the configured QoS. Per host it also
+//! keeps two retained messages up to date:
+//!
+//! | Topic                     | Payload                                                                       |
+//! |---------------------------|-------------------------------------------------------------------------------|
+//! | `<prefix>/<host>/devices` | JSON array of the currently attached devices                                  |
+//! | `<prefix>/<host>/status`  | `online` while connected; `offline`, also set as the Last Will, once the watcher goes away |
+//!
+//! The broker is given as `mqtt://[user[:password]@]host[:port]` (port 1883) or `mqtts://...` for TLS with the
+//! system's root certificates (port 8883). The connection is re-established with backoff when it drops; messages
+//! published in the meantime are buffered up to a limit.
+
+use crate::daemon::track_devices;
+use crate::device_info::{DeviceEventType, UsbDeviceInfo};
+use rumqttc::{
+    AsyncClient, Event, EventLoop, LastWill, MqttOptions, Outgoing, Packet, QoS, Transport,
+};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Default first topic level.
+pub const DEFAULT_PREFIX: &str = "usbwatch";
+
+/// Default quality of service for event messages.
+pub const DEFAULT_QOS: u8 = 1;
+
+/// Payload of the status topic while the watcher is connected.
+pub const STATUS_ONLINE: &str = "online";
+
+/// Payload of the status topic once the watcher is gone.
+pub const STATUS_OFFLINE: &str = "offline";
+
+/// Messages buffered while the broker is unreachable.
+const BUFFER_SIZE: usize = 1_000;
+
+/// Largest message sent, large enough for device lists with descriptors.
+const MAX_PACKET_SIZE: usize = 1024 * 1024;
+
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// Broker, topics and quality of service of an [`MqttPublisher`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MqttConfig {
+    /// Broker URL, `mqtt://` or `mqtts://`
+    pub url: String,
+    /// Host name used in topics and the client ID
+    pub host: String,
+    /// First topic level
+    pub prefix: String,
+    /// Quality of service (0, 1 or 2) of all messages
+    pub qos: u8,
+}
+
+impl MqttConfig {
+    /// Creates a configuration with the default prefix and QoS.
+    pub fn new(url: impl Into<String>, host: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            host: host.into(),
+            prefix: DEFAULT_PREFIX.to_string(),
+            qos: DEFAULT_QOS,
+        }
+    }
+
+    /// Returns the topic an event is published to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+    /// use usbwatch_rs::mqtt::MqttConfig;
+    ///
+    /// let config = MqttConfig::new("mqtt://broker.local", "line-3");
+    /// let device = UsbDeviceInfo::new(
+    ///     "USB Storage".to_string(),
+    ///     "0781".to_string(),
+    ///     "5583".to_string(),
+    ///     None,
+    ///     DeviceEventType::Connected,
+    /// );
+    /// assert_eq!(config.event_topic(&device), "usbwatch/line-3/0781/5583/connected");
+    /// ```
+    pub fn event_topic(&self, event: &UsbDeviceInfo) -> String {
+        let event_type = match event.event_type {
+            DeviceEventType::Connected => "connected",
+            DeviceEventType::Disconnected => "disconnected",
+            DeviceEventType::Flapping => "flapping",
+            DeviceEventType::SuspiciousDevice => "suspicious-device",
+            DeviceEventType::Degraded => "degraded",
+        };
+        format!(
+            "{}/{}/{}/{event_type}",
+            self.host_topic(),
+            topic_level(&event.vendor_id.to_ascii_lowercase()),
+            topic_level(&event.product_id.to_ascii_lowercase())
+        )
+    }
+
+    /// Returns the retained topic listing the attached devices.
+    pub fn devices_topic(&self) -> String {
+        format!("{}/devices", self.host_topic())
+    }
+
+    /// Returns the retained topic holding [`STATUS_ONLINE`] or [`STATUS_OFFLINE`].
+    pub fn status_topic(&self) -> String {
+        format!("{}/status", self.host_topic())
+    }
+
+    fn host_topic(&self) -> String {
+        format!("{}/{}", self.prefix, topic_level(&self.host))
+    }
+
+    fn qos(&self) -> Result<QoS, String> {
+        match self.qos {
+            0 => Ok(QoS::AtMostOnce),
+            1 => Ok(QoS::AtLeastOnce),
+            2 => Ok(QoS::ExactlyOnce),
+            qos => Err(format!("Invalid MQTT QoS {qos}: use 0, 1 or 2")),
+        }
+    }
+
+    /// Builds the client options from the broker URL.
+    fn options(&self) -> Result<MqttOptions, String> {
+        let invalid = |reason: &str| format!("Invalid MQTT broker '{}': {reason}", self.url);
+        let (tls, rest) = if let Some(rest) = self.url.strip_prefix("mqtt://") {
+            (false, rest)
+        } else if let Some(rest) = self.url.strip_prefix("mqtts://") {
+            (true, rest)
+        } else {
+            return Err(invalid("use mqtt:// or mqtts://"));
+        };
+        let rest = rest.trim_end_matches('/');
+        let (credentials, address) = match rest.rsplit_once('@') {
+            Some((credentials, address)) => (Some(credentials), address),
+            None => (None, rest),
+        };
+        let default_port = if tls { 8883 } else { 1883 };
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => {
+                (host, port.parse().map_err(|_| invalid("invalid port"))?)
+            }
+            _ => (address, default_port),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(invalid("missing host"));
+        }
+
+        let mut options = MqttOptions::new(format!("usbwatch-{}", self.host), host, port);
+        options
+            .set_keep_alive(Duration::from_secs(30))
+            .set_max_packet_size(MAX_PACKET_SIZE, MAX_PACKET_SIZE)
+            .set_last_will(LastWill::new(
+                self.status_topic(),
+                STATUS_OFFLINE,
+                self.qos()?,
+                true,
+            ));
+        if let Some(credentials) = credentials {
+            let (username, password) = credentials.split_once(':').unwrap_or((credentials, ""));
+            options.set_credentials(username, password);
+        }
+        if tls {
+            options.set_transport(Transport::tls_with_default_config());
+        }
+        Ok(options)
+    }
+}
+
+/// Replaces the characters that MQTT does not allow within a topic level.
+fn topic_level(value: &str) -> String {
+    value.replace(['/', '+', '#', '\0'], "_")
+}
+
+/// Publishes events and the retained device list to an MQTT broker.
+pub struct MqttPublisher {
+    config: MqttConfig,
+    qos: QoS,
+    client: AsyncClient,
+    devices: BTreeMap<String, UsbDeviceInfo>,
+}
+
+impl MqttPublisher {
+    /// Starts connecting to the broker in the background and publishes an
+    /// empty device list, replacing the one left by a previous run.
+    ///
+    /// Must be called from within a Tokio runtime.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the broker URL or QoS is invalid.
+    pub fn connect(config: MqttConfig) -> Result<Self, String> {
+        let qos = config.qos()?;
+        let (client, eventloop) = AsyncClient::new(config.options()?, BUFFER_SIZE);
+        tokio::spawn(drive(
+            eventloop,
+            client.clone(),
+            config.url.clone(),
+            config.status_topic(),
+            qos,
+        ));
+        let publisher = Self {
+            config,
+            qos,
+            client,
+            devices: BTreeMap::new(),
+        };
+        publisher.publish_devices();
+        Ok(publisher)
+    }
+
+    /// Publishes `event`, and the device list if the event changed it.
+    pub fn publish(&mut self, event: &UsbDeviceInfo) {
+        match serde_json::to_vec(event) {
+            Ok(payload) => self.send(self.config.event_topic(event), false, payload),
+            Err(e) => eprintln!("Failed to serialise MQTT event: {e}"),
+        }
+        if event.event_type.is_state_change() {
+            track_devices(&mut self.devices, event);
+            self.publish_devices();
+        }
+    }
+
+    fn publish_devices(&self) {
+        let devices: Vec<&UsbDeviceInfo> = self.devices.values().collect();
+        match serde_json::to_vec(&devices) {
+            Ok(payload) => self.send(self.config.devices_topic(), true, payload),
+            Err(e) => eprintln!("Failed to serialise MQTT device list: {e}"),
+        }
+    }
+
+    fn send(&self, topic: String, retain: bool, payload: Vec<u8>) {
+        if let Err(e) = self.client.try_publish(&topic, self.qos, retain, payload) {
+            eprintln!("Failed to publish to MQTT topic '{topic}': {e}");
+        }
+    }
+
+    /// Sets the status to [`STATUS_OFFLINE`] and disconnects.
+    pub async fn shutdown(self) {
+        let status = self
+            .client
+            .publish(self.config.status_topic(), self.qos, true, STATUS_OFFLINE)
+            .await;
+        if let Err(e) = status.and(self.client.disconnect().await) {
+            eprintln!("Failed to disconnect from MQTT broker: {e}");
+        }
+    }
+}
+
+/// Runs the client's event loop, reconnecting with backoff, until it disconnects.
+async fn drive(
+    mut eventloop: EventLoop,
+    client: AsyncClient,
+    url: String,
+    status_topic: String,
+    qos: QoS,
+) {
+    let mut delay = MIN_RECONNECT_DELAY;
+    let mut warned = false;
+    loop {
+        match eventloop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                delay = MIN_RECONNECT_DELAY;
+                warned = false;
+                // Replaces the Last Will published if the previous connection dropped
+                if let Err(e) = client.try_publish(&status_topic, qos, true, STATUS_ONLINE) {
+                    eprintln!("Failed to publish to MQTT topic '{status_topic}': {e}");
+                }
+            }
+            Ok(Event::Outgoing(Outgoing::Disconnect)) => return,
+            Ok(_) => {}
+            Err(e) => {
+                // Report each outage once rather than every attempt
+                if !warned {
+                    eprintln!("MQTT connection to '{url}' failed, reconnecting: {e}");
+                    warned = true;
+                }
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+            }
+        }
+    }
+}
+
+/// Async task that publishes each event from `rx` to MQTT and forwards it to `tx`.
+///
+/// The task ends when `rx` closes or `tx` is dropped, setting the status to
+/// [`STATUS_OFFLINE`] first.
+pub async fn mqtt_task(
+    mut rx: mpsc::Receiver<UsbDeviceInfo>,
+    tx: mpsc::Sender<UsbDeviceInfo>,
+    mut publisher: MqttPublisher,
+) {
+    while let Some(event) = rx.recv().await {
+        publisher.publish(&event);
+        if tx.send(event).await.is_err() {
+            break;
+        }
+    }
+    publisher.shutdown().await;
+}
@@ -0,0 +1,335 @@
+//! Desktop notifications.
+//!
+//! [`Notifier`] shows a pop-up for each event through the freedesktop `org.freedesktop.Notifications` D-Bus
+//! interface on the session bus, which GNOME, KDE Plasma, XFCE and notification daemons such as dunst and mako
+//! implement. The summary names the event and the body the device and its VID:PID; the icon follows the device's class
+//! (see [`icon_name`]) and suspicious devices are shown with critical urgency.
+//!
+//! During bursts, such as a hub full of devices being plugged in, at most [`RateLimit::burst`] notifications are shown
+//! per [`RateLimit::window`]; the events beyond that are summarised in one "N more USB events" notification once the
+//! window has passed. Suspicious devices are always shown and do not count towards the limit.
+
+use crate::device_info::{DeviceEventType, UsbDeviceInfo};
+use crate::hid::HidClass;
+use crate::usb_class::UsbClass;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use zbus::zvariant::Value;
+use zbus::Connection;
+
+/// Default number of notifications shown per window.
+pub const DEFAULT_BURST: usize = 3;
+
+/// Default length of the rate limiting window.
+pub const DEFAULT_WINDOW: Duration = Duration::from_secs(10);
+
+/// Application name shown by the notification server.
+const APP_NAME: &str = "usbwatch";
+
+/// How long to wait for the notification server to answer.
+const CALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How many notifications are shown in a burst of events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Notifications shown per window
+    pub burst: usize,
+    /// Length of the sliding window
+    pub window: Duration,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            burst: DEFAULT_BURST,
+            window: DEFAULT_WINDOW,
+        }
+    }
+}
+
+/// Returns the freedesktop icon name for the device's class.
+///
+/// # Examples
+///
+/// ```
+/// use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+/// use usbwatch_rs::notify::icon_name;
+/// use usbwatch_rs::usb_class::ClassCode;
+///
+/// let mut device = UsbDeviceInfo::new(
+///     "USB Storage".to_string(),
+///     "0781".to_string(),
+///     "5583".to_string(),
+///     None,
+///     DeviceEventType::Connected,
+/// );
+/// assert_eq!(icon_name(&device), "drive-removable-media");
+/// device.device_class = Some(ClassCode::new(0x0e, 0x01, 0x00));
+/// assert_eq!(icon_name(&device), "camera-web");
+/// ```
+pub fn icon_name(event: &UsbDeviceInfo) -> &'static str {
+    for class in event.classes() {
+        let icon = match class {
+            UsbClass::Hid => {
+                let hid_classes = || event.interfaces.iter().flat_map(|i| &i.hid_classes);
+                if hid_classes().any(|c| *c == HidClass::Mouse) {
+                    "input-mouse"
+                } else if hid_classes().any(|c| matches!(c, HidClass::Joystick | HidClass::Gamepad))
+                {
+                    "input-gaming"
+                } else {
+                    "input-keyboard"
+                }
+            }
+            UsbClass::MassStorage => "drive-removable-media",
+            UsbClass::Audio | UsbClass::AudioVideo => "audio-card",
+            UsbClass::Video => "camera-web",
+            UsbClass::Image => "camera-photo",
+            UsbClass::Printer => "printer",
+            UsbClass::Communications | UsbClass::CdcData => "modem",
+            UsbClass::WirelessController => "network-wireless",
+            UsbClass::SmartCard => "media-flash",
+            UsbClass::BulkDisplay => "video-display",
+            _ => continue,
+        };
+        return icon;
+    }
+    "drive-removable-media"
+}
+
+/// Returns the notification summary for the event type.
+pub fn summary(event: &UsbDeviceInfo) -> &'static str {
+    match event.event_type {
+        DeviceEventType::Connected => "USB device connected",
+        DeviceEventType::Disconnected => "USB device disconnected",
+        DeviceEventType::Flapping => "USB device flapping",
+        DeviceEventType::SuspiciousDevice => "Suspicious USB device",
+        DeviceEventType::Degraded => "Slow USB connection",
+    }
+}
+
+/// Returns the notification body: the device name and VID:PID, followed by
+/// the reason for notices such as `Degraded`.
+///
+/// Notification servers may interpret the body as markup, so `&`, `<` and
+/// `>` in the device's strings are escaped.
+///
+/// # Examples
+///
+/// ```
+/// use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+/// use usbwatch_rs::notify::body;
+///
+/// let device = UsbDeviceInfo::new(
+///     "<b>Totally</b> a Keyboard & Mouse".to_string(),
+///     "046d".to_string(),
+///     "c31c".to_string(),
+///     None,
+///     DeviceEventType::Connected,
+/// );
+/// assert_eq!(
+///     body(&device),
+///     "&lt;b&gt;Totally&lt;/b&gt; a Keyboard &amp; Mouse (046d:c31c)"
+/// );
+/// ```
+pub fn body(event: &UsbDeviceInfo) -> String {
+    let mut body = format!(
+        "{} ({}:{})",
+        escape_markup(&event.device_name),
+        escape_markup(&event.vendor_id),
+        escape_markup(&event.product_id)
+    );
+    if let Some(reason) = &event.reason {
+        body.push('\n');
+        body.push_str(&escape_markup(reason));
+    }
+    body
+}
+
+fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Shows events as desktop notifications, rate limited during bursts.
+pub struct Notifier {
+    connection: Connection,
+    limit: RateLimit,
+    shown: VecDeque<Instant>,
+    suppressed: usize,
+}
+
+impl Notifier {
+    /// Connects to the D-Bus bus at `address`, or the session bus if `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bus cannot be reached.
+    pub async fn connect(address: Option<&str>, limit: RateLimit) -> Result<Self, String> {
+        let connection = match address {
+            Some(address) => match zbus::connection::Builder::address(address) {
+                Ok(builder) => builder.build().await,
+                Err(e) => Err(e),
+            },
+            None => Connection::session().await,
+        }
+        .map_err(|e| format!("Failed to connect to the D-Bus session bus: {e}"))?;
+        Ok(Self {
+            connection,
+            limit,
+            shown: VecDeque::new(),
+            suppressed: 0,
+        })
+    }
+
+    /// Shows `event`, or counts it towards the next summary if the rate
+    /// limit is reached. Suspicious devices are always shown.
+    pub async fn notify(&mut self, event: &UsbDeviceInfo) {
+        let critical = event.event_type == DeviceEventType::SuspiciousDevice;
+        if !critical && !self.allow() {
+            self.suppressed += 1;
+            return;
+        }
+        let (urgency, category) = match event.event_type {
+            DeviceEventType::Connected => (1, "device.added"),
+            DeviceEventType::Disconnected => (1, "device.removed"),
+            DeviceEventType::Flapping => (1, "device"),
+            DeviceEventType::SuspiciousDevice => (2, "device.error"),
+            DeviceEventType::Degraded => (1, "device"),
+        };
+        self.show(
+            summary(event),
+            &body(event),
+            icon_name(event),
+            urgency,
+            category,
+        )
+        .await;
+    }
+
+    /// Returns when the summary of the suppressed events is due, if any
+    /// were suppressed.
+    pub fn summary_due(&self) -> Option<Instant> {
+        if self.suppressed == 0 {
+            return None;
+        }
+        let oldest = self.shown.front().copied().unwrap_or_else(Instant::now);
+        Some(oldest + self.limit.window)
+    }
+
+    /// Shows one notification counting the suppressed events.
+    pub async fn flush(&mut self) {
+        if self.suppressed == 0 {
+            return;
+        }
+        let message = match self.suppressed {
+            1 => "1 more USB event".to_string(),
+            n => format!("{n} more USB events"),
+        };
+        self.suppressed = 0;
+        self.allow();
+        self.show(
+            &message,
+            "See the usbwatch log for details",
+            "drive-removable-media",
+            1,
+            "device",
+        )
+        .await;
+    }
+
+    /// Records a notification shown now, unless the window is full.
+    fn allow(&mut self) -> bool {
+        let now = Instant::now();
+        while self
+            .shown
+            .front()
+            .is_some_and(|shown| now.duration_since(*shown) >= self.limit.window)
+        {
+            self.shown.pop_front();
+        }
+        if self.shown.len() >= self.limit.burst {
+            return false;
+        }
+        self.shown.push_back(now);
+        true
+    }
+
+    async fn show(&self, summary: &str, body: &str, icon: &str, urgency: u8, category: &str) {
+        let hints = HashMap::from([
+            ("urgency", Value::U8(urgency)),
+            ("category", Value::from(category)),
+        ]);
+        let arguments = (
+            APP_NAME,
+            0u32,
+            icon,
+            summary,
+            body,
+            Vec::<&str>::new(),
+            hints,
+            -1i32,
+        );
+        let call = self.connection.call_method(
+            Some("org.freedesktop.Notifications"),
+            "/org/freedesktop/Notifications",
+            Some("org.freedesktop.Notifications"),
+            "Notify",
+            &arguments,
+        );
+        match tokio::time::timeout(CALL_TIMEOUT, call).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => eprintln!("Failed to show desktop notification: {e}"),
+            Err(_) => eprintln!(
+                "Failed to show desktop notification: no answer from the notification server"
+            ),
+        }
+    }
+}
+
+/// Async task that shows each event from `rx` as a desktop notification and
+/// forwards it to `tx`.
+///
+/// Notifications are shown by a separate task, so a slow notification server
+/// does not hold up the events; if it falls far behind, notifications are
+/// skipped. The task ends when `rx` closes or `tx` is dropped.
+pub async fn notify_task(
+    mut rx: mpsc::Receiver<UsbDeviceInfo>,
+    tx: mpsc::Sender<UsbDeviceInfo>,
+    notifier: Notifier,
+) {
+    let (shown_tx, shown_rx) = mpsc::channel(100);
+    tokio::spawn(show_notifications(shown_rx, notifier));
+    while let Some(event) = rx.recv().await {
+        let _ = shown_tx.try_send(event.clone());
+        if tx.send(event).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Shows each event from `rx`, and the summaries of suppressed events when
+/// they are due, until `rx` closes.
+async fn show_notifications(mut rx: mpsc::Receiver<UsbDeviceInfo>, mut notifier: Notifier) {
+    loop {
+        let due = notifier.summary_due();
+        let summary = async {
+            match due {
+                Some(due) => tokio::time::sleep_until(due).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            event = rx.recv() => {
+                let Some(event) = event else {
+                    break;
+                };
+                notifier.notify(&event).await;
+            }
+            _ = summary => notifier.flush().await,
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! Device authorisation policy.
+//!
+//! A [`Policy`] is an ordered list of rules loaded from JSON. Each rule matches devices on vendor ID, product
+//! ID, serial number, USB class and port path and decides whether to allow, block or only log them; the first
+//! matching rule wins and the policy default applies otherwise. The Linux watcher enforces decisions through
+//! the sysfs `authorized` attribute and records them on the connect event; devices already attached when it
+//! starts are only evaluated.
+//!
+//! ```json
+//! {
+//!   "default": "allow",
+//!   "rules": [
+//!     { "name": "trusted-keyboard", "vendor_id": "046d", "product_id": "c31c", "action": "allow" },
+//!     { "name": "no-storage", "class": "mass-storage", "action": "block" },
+//!     { "name": "front-panel", "port": "1-2*", "action": "log" }
+//!   ]
+//! }
+//! ```
+
+use crate::device_info::UsbDeviceInfo;
+use crate::usb_class::UsbClass;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+/// What to do with a matching device.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PolicyAction {
+    /// Allow the device (and authorise it when new devices are denied by default;
+    /// otherwise its `authorized` attribute is left alone)
+    #[default]
+    Allow,
+    /// Deauthorise the device so no driver binds to it
+    Block,
+    /// Record the decision without enforcing anything
+    Log,
+}
+
+impl fmt::Display for PolicyAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyAction::Allow => write!(f, "allow"),
+            PolicyAction::Block => write!(f, "block"),
+            PolicyAction::Log => write!(f, "log"),
+        }
+    }
+}
+
+/// A single policy rule. Criteria that are not given match any device.
+///
+/// String criteria are compared case-insensitively; a trailing `*` matches
+/// any suffix (e.g. `"port": "1-2*"` matches every device behind port 1-2).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRule {
+    /// Name reported in decisions; defaults to the rule's position
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Vendor ID in hexadecimal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor_id: Option<String>,
+    /// Product ID in hexadecimal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub product_id: Option<String>,
+    /// Serial number
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+    /// USB class of the device or any of its interfaces
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<UsbClass>,
+    /// Port path (e.g. "1-1.2")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<String>,
+    /// Decision for matching devices
+    pub action: PolicyAction,
+}
+
+/// Ordered set of rules with a default action.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    /// Action for devices that match no rule. `block` turns on default-deny:
+    /// new devices start unauthorised and only allowed devices are authorised.
+    #[serde(default)]
+    pub default: PolicyAction,
+    /// Rules, evaluated in order
+    #[serde(default)]
+    pub rules: Vec<PolicyRule>,
+}
+
+/// The outcome of evaluating a [`Policy`] for a device.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyDecision {
+    /// Decided action
+    pub action: PolicyAction,
+    /// Name of the matching rule, or `default`
+    pub rule: String,
+    /// Error encountered while enforcing the decision
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl fmt::Display for PolicyDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (rule '{}')", self.action, self.rule)?;
+        if let Some(error) = &self.error {
+            write!(f, " failed: {error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl PolicyRule {
+    /// Returns whether the rule matches a device.
+    pub fn matches(&self, device: &UsbDeviceInfo) -> bool {
+        pattern_matches(self.vendor_id.as_deref(), Some(&device.vendor_id))
+            && pattern_matches(self.product_id.as_deref(), Some(&device.product_id))
+            && pattern_matches(self.serial.as_deref(), device.serial_number.as_deref())
+            && pattern_matches(self.port.as_deref(), device.port_path.as_deref())
+            && self
+                .class
+                .is_none_or(|class| device.classes().contains(&class))
+    }
+}
+
+impl Policy {
+    /// Parses a policy from JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JSON is invalid or contains unknown fields.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid policy: {e}"))
+    }
+
+    /// Loads a policy from a JSON file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid policy.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read policy '{}': {e}", path.display()))?;
+        Ok(Self::from_json(&json)?)
+    }
+
+    /// Returns whether devices are denied unless a rule allows them.
+    pub fn default_deny(&self) -> bool {
+        self.default == PolicyAction::Block
+    }
+
+    /// Evaluates the policy for a device; the first matching rule wins.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+    /// use usbwatch_rs::policy::{Policy, PolicyAction};
+    ///
+    /// let policy = Policy::from_json(r#"{
+    ///     "default": "block",
+    ///     "rules": [{ "name": "sandisk", "vendor_id": "0781", "action": "allow" }]
+    /// }"#).unwrap();
+    ///
+    /// let device = UsbDeviceInfo::new(
+    ///     "USB Storage".to_string(),
+    ///     "0781".to_string(),
+    ///     "5583".to_string(),
+    ///     None,
+    ///     DeviceEventType::Connected,
+    /// );
+    /// let decision = policy.evaluate(&device);
+    /// assert_eq!(decision.action, PolicyAction::Allow);
+    /// assert_eq!(decision.rule, "sandisk");
+    /// ```
+    pub fn evaluate(&self, device: &UsbDeviceInfo) -> PolicyDecision {
+        self.rules
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| rule.matches(device))
+            .map(|(index, rule)| PolicyDecision {
+                action: rule.action,
+                rule: rule
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| format!("#{}", index + 1)),
+                error: None,
+            })
+            .unwrap_or(PolicyDecision {
+                action: self.default,
+                rule: "default".to_string(),
+                error: None,
+            })
+    }
+}
+
+/// Matches an optional pattern against an optional value. A missing pattern
+/// matches anything; a missing value matches only a missing pattern.
+fn pattern_matches(pattern: Option<&str>, value: Option<&str>) -> bool {
+    let Some(pattern) = pattern else {
+        return true;
+    };
+    let Some(value) = value else {
+        return false;
+    };
+    match pattern.strip_suffix('*') {
+        Some(prefix) => value
+            .to_ascii_lowercase()
+            .starts_with(&prefix.to_ascii_lowercase()),
+        None => value.eq_ignore_ascii_case(pattern),
+    }
+}
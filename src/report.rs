@@ -0,0 +1,195 @@
+//! Tabular and machine-readable rendering of event lists and reports.
+//!
+//! Used by the query-style subcommands (such as `history`) that print a batch of results rather than a live stream.
+//! Supports aligned plain-text tables, JSON and CSV.
+
+use crate::device_info::UsbDeviceInfo;
+use std::fmt;
+use std::str::FromStr;
+
+/// Output format for query-style subcommands.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Aligned plain-text table
+    #[default]
+    Table,
+    /// Pretty-printed JSON
+    Json,
+    /// Comma-separated values with a header row
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(format!(
+                "Unknown output format '{s}' (expected table, json or csv)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputFormat::Table => write!(f, "table"),
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Csv => write!(f, "csv"),
+        }
+    }
+}
+
+/// Renders a list of device events in the requested format.
+///
+/// # Errors
+///
+/// Returns an error if JSON serialisation fails.
+///
+/// # Examples
+///
+/// ```
+/// use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+/// use usbwatch_rs::report::{render_events, OutputFormat};
+///
+/// let events = vec![UsbDeviceInfo::new(
+///     "USB Storage".to_string(),
+///     "0781".to_string(),
+///     "5583".to_string(),
+///     None,
+///     DeviceEventType::Connected,
+/// )];
+///
+/// let csv = render_events(&events, OutputFormat::Csv)?;
+/// assert!(csv.starts_with("timestamp,event,vendor_id,product_id,serial,device"));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn render_events(
+    events: &[UsbDeviceInfo],
+    format: OutputFormat,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if format == OutputFormat::Json {
+        return Ok(serde_json::to_string_pretty(events)?);
+    }
+
+    let rows: Vec<Vec<String>> = events
+        .iter()
+        .map(|event| {
+            vec![
+                event.timestamp.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+                event.event_type.to_string(),
+                event.vendor_id.clone(),
+                event.product_id.clone(),
+                event
+                    .serial_number
+                    .clone()
+                    .unwrap_or_else(|| "-".to_string()),
+                event.device_name.clone(),
+            ]
+        })
+        .collect();
+
+    Ok(match format {
+        OutputFormat::Csv => render_csv(
+            &[
+                "timestamp",
+                "event",
+                "vendor_id",
+                "product_id",
+                "serial",
+                "device",
+            ],
+            &rows,
+        ),
+        _ => render_table(
+            &["TIMESTAMP", "EVENT", "VID", "PID", "SERIAL", "DEVICE"],
+            &rows,
+        ),
+    })
+}
+
+/// Renders rows as a left-aligned plain-text table with a header line.
+pub fn render_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let format_row = |cells: Vec<&str>| {
+        let line = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        line.trim_end().to_string()
+    };
+
+    let mut lines = vec![format_row(headers.to_vec())];
+    for row in rows {
+        lines.push(format_row(row.iter().map(String::as_str).collect()));
+    }
+    lines.join("\n")
+}
+
+/// Renders rows as CSV with a header line, quoting cells where needed.
+pub fn render_csv(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut lines = vec![headers
+        .iter()
+        .map(|h| csv_escape(h))
+        .collect::<Vec<_>>()
+        .join(",")];
+    for row in rows {
+        lines.push(
+            row.iter()
+                .map(|c| csv_escape(c))
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+    }
+    lines.join("\n")
+}
+
+fn csv_escape(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+/// Formats a number of seconds as a compact human-readable duration.
+///
+/// # Examples
+///
+/// ```
+/// use usbwatch_rs::report::format_duration;
+///
+/// assert_eq!(format_duration(42.0), "42s");
+/// assert_eq!(format_duration(3723.0), "1h 02m 03s");
+/// assert_eq!(format_duration(90061.0), "1d 01h 01m 01s");
+/// ```
+pub fn format_duration(secs: f64) -> String {
+    let total = secs.max(0.0).round() as u64;
+    let (days, hours, minutes, seconds) = (
+        total / 86_400,
+        total % 86_400 / 3_600,
+        total % 3_600 / 60,
+        total % 60,
+    );
+    if days > 0 {
+        format!("{days}d {hours:02}h {minutes:02}m {seconds:02}s")
+    } else if hours > 0 {
+        format!("{hours}h {minutes:02}m {seconds:02}s")
+    } else if minutes > 0 {
+        format!("{minutes}m {seconds:02}s")
+    } else {
+        format!("{seconds}s")
+    }
+}
@@ -0,0 +1,304 @@
+//! Summary statistics over recorded USB device events.
+//!
+//! Aggregates a list of events (typically read with [`EventReader`](crate::reader::EventReader)) into per-device
+//! counts, connected time and dwell time, the most frequently plugged devices, and hourly and daily histograms.
+//! Devices are identified by the same `VID:PID:serial` key the watcher uses; the devices behind a collapsed hub
+//! event are counted like events of their own.
+
+use crate::device_info::{DeviceEventType, UsbDeviceInfo};
+use crate::report::{format_duration, render_table};
+use chrono::{DateTime, NaiveDate, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Statistics for a single device.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceStats {
+    /// Tracking key (`VID:PID:serial`)
+    pub key: String,
+    /// Most recently reported device name
+    pub device_name: String,
+    /// USB Vendor ID in hexadecimal format
+    pub vendor_id: String,
+    /// USB Product ID in hexadecimal format
+    pub product_id: String,
+    /// Serial number, if reported
+    pub serial_number: Option<String>,
+    /// Total number of events for this device
+    pub events: usize,
+    /// Number of connect events
+    pub connects: usize,
+    /// Number of disconnect events
+    pub disconnects: usize,
+    /// Total time between matched connect and disconnect events, in seconds
+    pub connected_secs: f64,
+    /// Mean time between matched connect and disconnect events, in seconds
+    pub mean_dwell_secs: Option<f64>,
+    /// Timestamp of the first event
+    pub first_seen: DateTime<Utc>,
+    /// Timestamp of the last event
+    pub last_seen: DateTime<Utc>,
+    #[serde(skip)]
+    sessions: usize,
+}
+
+/// Aggregated statistics over a set of device events.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventStats {
+    /// Total number of events
+    pub total_events: usize,
+    /// Timestamp of the earliest event
+    pub first_event: Option<DateTime<Utc>>,
+    /// Timestamp of the latest event
+    pub last_event: Option<DateTime<Utc>>,
+    /// Mean dwell time across all matched connect/disconnect pairs, in seconds
+    pub mean_dwell_secs: Option<f64>,
+    /// Per-device statistics, most frequently plugged first
+    pub devices: Vec<DeviceStats>,
+    /// Keys of devices that were connected at most once
+    pub seen_once: Vec<String>,
+    /// Number of events per hour of the day (UTC), index 0 is 00:00-00:59
+    pub hourly: [usize; 24],
+    /// Number of events per calendar day (UTC)
+    pub daily: BTreeMap<NaiveDate, usize>,
+}
+
+impl EventStats {
+    /// Computes statistics over the given events.
+    ///
+    /// Events do not need to be sorted. Connected time is taken from the
+    /// `duration` recorded on `Disconnected` events, or otherwise measured
+    /// between a `Connected` event and the next `Disconnected` event for the
+    /// same device; unmatched events are counted but contribute no connected time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+    /// use usbwatch_rs::stats::EventStats;
+    ///
+    /// let connected = UsbDeviceInfo::new(
+    ///     "USB Storage".to_string(),
+    ///     "0781".to_string(),
+    ///     "5583".to_string(),
+    ///     None,
+    ///     DeviceEventType::Connected,
+    /// );
+    /// let mut disconnected = connected.clone();
+    /// disconnected.event_type = DeviceEventType::Disconnected;
+    /// disconnected.timestamp = connected.timestamp + chrono::Duration::seconds(90);
+    ///
+    /// let stats = EventStats::from_events(&[connected, disconnected]);
+    /// assert_eq!(stats.devices[0].connected_secs, 90.0);
+    /// ```
+    pub fn from_events(events: &[UsbDeviceInfo]) -> Self {
+        let mut sorted: Vec<&UsbDeviceInfo> = events.iter().collect();
+        sorted.sort_by_key(|event| event.timestamp);
+
+        let mut devices: HashMap<String, DeviceStats> = HashMap::new();
+        let mut open_sessions: HashMap<String, DateTime<Utc>> = HashMap::new();
+        let mut hourly = [0; 24];
+        let mut daily = BTreeMap::new();
+        let mut total_dwell = 0.0;
+        let mut total_sessions = 0;
+
+        for event in &sorted {
+            // Collapsed hub events carry the devices behind the hub as children
+            for device in std::iter::once(*event).chain(&event.children) {
+                let key = device.device_key();
+                let stats = devices.entry(key.clone()).or_insert_with(|| DeviceStats {
+                    key: key.clone(),
+                    device_name: device.device_name.clone(),
+                    vendor_id: device.vendor_id.clone(),
+                    product_id: device.product_id.clone(),
+                    serial_number: device.serial_number.clone(),
+                    events: 0,
+                    connects: 0,
+                    disconnects: 0,
+                    connected_secs: 0.0,
+                    mean_dwell_secs: None,
+                    first_seen: device.timestamp,
+                    last_seen: device.timestamp,
+                    sessions: 0,
+                });
+                stats.events += 1;
+                stats.device_name = device.device_name.clone();
+                stats.last_seen = device.timestamp;
+
+                match device.event_type {
+                    DeviceEventType::Connected => {
+                        stats.connects += 1;
+                        open_sessions.insert(key, device.timestamp);
+                    }
+                    DeviceEventType::Disconnected => {
+                        stats.disconnects += 1;
+                        let measured = open_sessions.remove(&key).map(|connected_at| {
+                            (device.timestamp - connected_at).num_milliseconds() as f64 / 1000.0
+                        });
+                        if let Some(dwell) = device.duration.or(measured) {
+                            stats.connected_secs += dwell;
+                            stats.sessions += 1;
+                            total_dwell += dwell;
+                            total_sessions += 1;
+                        }
+                    }
+                    DeviceEventType::Flapping
+                    | DeviceEventType::SuspiciousDevice
+                    | DeviceEventType::Degraded => {}
+                }
+            }
+
+            hourly[event.timestamp.hour() as usize] += 1;
+            *daily.entry(event.timestamp.date_naive()).or_insert(0) += 1;
+        }
+
+        let mut devices: Vec<DeviceStats> = devices
+            .into_values()
+            .map(|mut stats| {
+                if stats.sessions > 0 {
+                    stats.mean_dwell_secs = Some(stats.connected_secs / stats.sessions as f64);
+                }
+                stats
+            })
+            .collect();
+        devices.sort_by(|a, b| {
+            b.connects
+                .cmp(&a.connects)
+                .then(b.events.cmp(&a.events))
+                .then(a.key.cmp(&b.key))
+        });
+
+        let seen_once = devices
+            .iter()
+            .filter(|stats| stats.connects <= 1)
+            .map(|stats| stats.key.clone())
+            .collect();
+
+        Self {
+            total_events: sorted.len(),
+            first_event: sorted.first().map(|event| event.timestamp),
+            last_event: sorted.last().map(|event| event.timestamp),
+            mean_dwell_secs: (total_sessions > 0).then(|| total_dwell / total_sessions as f64),
+            devices,
+            seen_once,
+            hourly,
+            daily,
+        }
+    }
+
+    /// Renders the statistics as plain-text tables.
+    ///
+    /// `top` limits the "most frequently plugged" list.
+    pub fn render_text(&self, top: usize) -> String {
+        let mut sections = Vec::new();
+
+        let range = match (self.first_event, self.last_event) {
+            (Some(first), Some(last)) => format!(
+                "{} - {}",
+                first.format("%Y-%m-%d %H:%M:%S UTC"),
+                last.format("%Y-%m-%d %H:%M:%S UTC")
+            ),
+            _ => "-".to_string(),
+        };
+        sections.push(format!(
+            "Events: {}\nDevices: {}\nTime range: {}\nMean dwell time: {}",
+            self.total_events,
+            self.devices.len(),
+            range,
+            self.mean_dwell_secs
+                .map(format_duration)
+                .unwrap_or_else(|| "-".to_string())
+        ));
+
+        let device_rows: Vec<Vec<String>> = self
+            .devices
+            .iter()
+            .map(|stats| {
+                vec![
+                    stats.key.clone(),
+                    stats.device_name.clone(),
+                    stats.events.to_string(),
+                    stats.connects.to_string(),
+                    format_duration(stats.connected_secs),
+                    stats
+                        .mean_dwell_secs
+                        .map(format_duration)
+                        .unwrap_or_else(|| "-".to_string()),
+                ]
+            })
+            .collect();
+        sections.push(format!(
+            "Devices:\n{}",
+            render_table(
+                &[
+                    "DEVICE",
+                    "NAME",
+                    "EVENTS",
+                    "CONNECTS",
+                    "CONNECTED",
+                    "MEAN DWELL"
+                ],
+                &device_rows
+            )
+        ));
+
+        let top_rows: Vec<Vec<String>> = self
+            .devices
+            .iter()
+            .filter(|stats| stats.connects > 0)
+            .take(top)
+            .map(|stats| {
+                vec![
+                    stats.connects.to_string(),
+                    stats.key.clone(),
+                    stats.device_name.clone(),
+                ]
+            })
+            .collect();
+        sections.push(format!(
+            "Most frequently plugged:\n{}",
+            render_table(&["CONNECTS", "DEVICE", "NAME"], &top_rows)
+        ));
+
+        let seen_once = if self.seen_once.is_empty() {
+            "  (none)".to_string()
+        } else {
+            self.seen_once
+                .iter()
+                .map(|key| format!("  {key}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        sections.push(format!("Seen only once:\n{seen_once}"));
+
+        let hourly: Vec<(String, usize)> = self
+            .hourly
+            .iter()
+            .enumerate()
+            .map(|(hour, count)| (format!("{hour:02}:00"), *count))
+            .collect();
+        sections.push(format!("Events by hour (UTC):\n{}", histogram(&hourly)));
+
+        let daily: Vec<(String, usize)> = self
+            .daily
+            .iter()
+            .map(|(date, count)| (date.to_string(), *count))
+            .collect();
+        sections.push(format!("Events by day (UTC):\n{}", histogram(&daily)));
+
+        sections.join("\n\n")
+    }
+}
+
+/// Renders labelled counts as a horizontal bar chart scaled to 40 columns.
+fn histogram(buckets: &[(String, usize)]) -> String {
+    let max = buckets.iter().map(|(_, count)| *count).max().unwrap_or(0);
+    buckets
+        .iter()
+        .map(|(label, count)| {
+            let bar_len = (count * 40).checked_div(max).unwrap_or(0);
+            format!("  {label}  {:<40}  {count}", "█".repeat(bar_len))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
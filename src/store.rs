@@ -0,0 +1,194 @@
+//! SQLite event store for querying past USB device events.
+//!
+//! Every event written through the [`Logger`](crate::logger::Logger) can optionally be persisted to a local SQLite database.
+//! The indexed columns cover the common lookups (time, VID/PID, serial, event type) while the full JSON record is kept
+//! alongside them so that no field of [`UsbDeviceInfo`] is lost. The devices behind a collapsed hub event are indexed
+//! too, so looking up one of them returns the hub event it arrived with.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use usbwatch_rs::store::{EventStore, HistoryQuery};
+//!
+//! let store = EventStore::open("usb-events.db")?;
+//! let query = HistoryQuery {
+//!     serial_number: Some("4C530001234567891234".to_string()),
+//!     limit: Some(1),
+//!     ..Default::default()
+//! };
+//! for event in store.query(&query)? {
+//!     println!("Last seen: {}", event.timestamp);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::device_info::UsbDeviceInfo;
+pub use crate::history::{parse_time_spec, HistoryQuery};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, types::Value, Connection};
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS events (
+    id            INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp     TEXT NOT NULL,
+    event_type    TEXT NOT NULL,
+    device_name   TEXT NOT NULL,
+    vendor_id     TEXT NOT NULL,
+    product_id    TEXT NOT NULL,
+    serial_number TEXT,
+    event_json    TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_events_timestamp ON events (timestamp);
+CREATE INDEX IF NOT EXISTS idx_events_vid_pid ON events (vendor_id, product_id);
+CREATE INDEX IF NOT EXISTS idx_events_serial ON events (serial_number);
+CREATE TABLE IF NOT EXISTS event_children (
+    event_id      INTEGER NOT NULL REFERENCES events (id),
+    vendor_id     TEXT NOT NULL,
+    product_id    TEXT NOT NULL,
+    serial_number TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_event_children_vid_pid ON event_children (vendor_id, product_id);
+CREATE INDEX IF NOT EXISTS idx_event_children_serial ON event_children (serial_number);
+";
+
+/// Persistent store of USB device events backed by SQLite.
+pub struct EventStore {
+    conn: Connection,
+}
+
+impl EventStore {
+    /// Opens (or creates) an event store at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be opened or the schema cannot be created.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let conn = Connection::open(path)
+            .map_err(|e| format!("Failed to open database '{}': {e}", path.display()))?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// Records a device event, indexing the devices behind a collapsed hub
+    /// event along with it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialisation or the insert fails.
+    pub fn insert(&self, device_info: &UsbDeviceInfo) -> Result<(), Box<dyn std::error::Error>> {
+        let event_json = serde_json::to_string(device_info)?;
+        let transaction = self.conn.unchecked_transaction()?;
+        transaction.execute(
+            "INSERT INTO events (timestamp, event_type, device_name, vendor_id, product_id, serial_number, event_json)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                format_timestamp(&device_info.timestamp),
+                device_info.event_type.to_string(),
+                device_info.device_name,
+                device_info.vendor_id.to_lowercase(),
+                device_info.product_id.to_lowercase(),
+                device_info.serial_number,
+                event_json,
+            ],
+        )?;
+        let event_id = transaction.last_insert_rowid();
+        for child in &device_info.children {
+            transaction.execute(
+                "INSERT INTO event_children (event_id, vendor_id, product_id, serial_number)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    event_id,
+                    child.vendor_id.to_lowercase(),
+                    child.product_id.to_lowercase(),
+                    child.serial_number,
+                ],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Returns the stored events matching the query, newest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query fails or a stored record cannot be decoded.
+    pub fn query(
+        &self,
+        query: &HistoryQuery,
+    ) -> Result<Vec<UsbDeviceInfo>, Box<dyn std::error::Error>> {
+        let mut conditions = Vec::new();
+        let mut values: Vec<Value> = Vec::new();
+
+        if let Some(since) = &query.since {
+            conditions.push("timestamp >= ?".to_string());
+            values.push(Value::Text(format_timestamp(since)));
+        }
+        if let Some(until) = &query.until {
+            conditions.push("timestamp <= ?".to_string());
+            values.push(Value::Text(format_timestamp(until)));
+        }
+        if let Some(event_type) = &query.event_type {
+            conditions.push("event_type = ?".to_string());
+            values.push(Value::Text(event_type.to_string()));
+        }
+
+        // The device criteria match the event's own device or one device behind it
+        let device_values: Vec<(&str, Value)> = [
+            (
+                "vendor_id",
+                query.vendor_id.as_ref().map(|vid| vid.to_lowercase()),
+            ),
+            (
+                "product_id",
+                query.product_id.as_ref().map(|pid| pid.to_lowercase()),
+            ),
+            ("serial_number", query.serial_number.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(column, value)| Some((column, Value::Text(value?))))
+        .collect();
+        if !device_values.is_empty() {
+            let device_condition = device_values
+                .iter()
+                .map(|(column, _)| format!("{column} = ?"))
+                .collect::<Vec<_>>()
+                .join(" AND ");
+            conditions.push(format!(
+                "(({device_condition}) OR id IN \
+                 (SELECT event_id FROM event_children WHERE {device_condition}))"
+            ));
+            for _ in 0..2 {
+                values.extend(device_values.iter().map(|(_, value)| value.clone()));
+            }
+        }
+
+        let mut sql = "SELECT event_json FROM events".to_string();
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        sql.push_str(" ORDER BY timestamp DESC, id DESC");
+        if let Some(limit) = query.limit {
+            sql.push_str(&format!(" LIMIT {limit}"));
+        }
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(values), |row| {
+            row.get::<_, String>(0)
+        })?;
+
+        let mut events = Vec::new();
+        for row in rows {
+            events.push(serde_json::from_str(&row?)?);
+        }
+        Ok(events)
+    }
+}
+
+/// Formats a timestamp so that lexicographic order matches chronological order.
+fn format_timestamp(timestamp: &DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
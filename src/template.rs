@@ -0,0 +1,96 @@
+//! `{{field}}` templates over events.
+//!
+//! Placeholders name a field of the event's JSON form, with dots for nested fields (`{{metadata.hostname}}`,
+//! `{{policy.rule}}`) and indices for arrays (`{{interfaces.0.driver}}`); `{{event}}` is the whole event. Fields
+//! that are not set render as an empty string. Used for `--template` output lines and webhook payloads.
+
+use crate::device_info::UsbDeviceInfo;
+use serde_json::Value;
+
+/// Renders a one-line template for `event`.
+///
+/// # Examples
+///
+/// ```
+/// use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+/// use usbwatch_rs::template::render_line;
+///
+/// let mut device = UsbDeviceInfo::new(
+///     "USB Storage".to_string(),
+///     "0781".to_string(),
+///     "5583".to_string(),
+///     None,
+///     DeviceEventType::Disconnected,
+/// );
+/// device.duration = Some(30.5);
+/// assert_eq!(
+///     render_line("{{event_type}} {{device_name}} after {{duration}}s{{serial_number}}", &device),
+///     "Disconnected USB Storage after 30.5s"
+/// );
+/// ```
+pub fn render_line(template: &str, event: &UsbDeviceInfo) -> String {
+    let event = serde_json::to_value(event).unwrap_or(Value::Null);
+    interpolate(template, &event)
+}
+
+/// Renders every string in a JSON template, keeping its structure.
+///
+/// A placeholder that makes up a whole string is replaced by the JSON value
+/// it names, keeping its type; one inside a longer string by its text.
+pub fn render_value(template: &Value, event: &Value) -> Value {
+    match template {
+        Value::String(text) => render_string(text, event),
+        Value::Array(items) => {
+            Value::Array(items.iter().map(|item| render_value(item, event)).collect())
+        }
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), render_value(value, event)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn render_string(text: &str, event: &Value) -> Value {
+    // A lone placeholder keeps the type of the value it names
+    if let Some(path) = text
+        .strip_prefix("{{")
+        .and_then(|rest| rest.strip_suffix("}}"))
+        .filter(|path| !path.contains("{{") && !path.contains("}}"))
+    {
+        return lookup(event, path.trim()).cloned().unwrap_or(Value::Null);
+    }
+    Value::String(interpolate(text, event))
+}
+
+fn interpolate(text: &str, event: &Value) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        match lookup(event, rest[start + 2..start + end].trim()) {
+            Some(Value::String(value)) => out.push_str(value),
+            Some(Value::Null) | None => {}
+            Some(value) => out.push_str(&value.to_string()),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn lookup<'a>(event: &'a Value, path: &str) -> Option<&'a Value> {
+    if path == "event" {
+        return Some(event);
+    }
+    path.split('.').try_fold(event, |value, key| match value {
+        Value::Object(fields) => fields.get(key),
+        Value::Array(items) => key.parse::<usize>().ok().and_then(|index| items.get(index)),
+        _ => None,
+    })
+}
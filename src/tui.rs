@@ -0,0 +1,824 @@
+//! Full-screen terminal dashboard.
+//!
+//! `usbwatch top` shows the connected devices as a table (name, VID:PID, port, speed, driver and uptime) above a
+//! scrolling log of recent events. [`Dashboard`] holds the state and renders it with `ratatui`; it is driven by the
+//! normal event pipeline, so the table starts with the devices found by the watcher's first scan. [`run`] takes over
+//! the terminal and handles the keyboard:
+//!
+//! | Key              | Action                                                      |
+//! |------------------|-------------------------------------------------------------|
+//! | `↑`/`↓`, `k`/`j` | Select a device                                             |
+//! | `/`              | Filter devices by name, VID:PID, port, serial or driver     |
+//! | `s` / `r`        | Sort by the next column / reverse the order                 |
+//! | `Enter`          | Show every sysfs attribute of the selected device (Linux)   |
+//! | `c`              | Clear the event log                                         |
+//! | `q`, `Esc`       | Close the filter or detail pane, then quit                  |
+//!
+//! While the dashboard runs on Unix, anything the pipeline would print (warnings, alerts) is shown in the event log
+//! instead of being drawn over the screen.
+
+use crate::device_info::{DeviceEventType, UsbDeviceInfo};
+use crate::report::format_duration;
+use chrono::{DateTime, Utc};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::crossterm::ExecutableCommand;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState};
+use ratatui::{Frame, Terminal};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::io::{self, Write};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Default number of lines kept in the event log.
+pub const DEFAULT_LOG_SIZE: usize = 500;
+
+/// How often the screen is redrawn when nothing happens, to update uptimes.
+const TICK: Duration = Duration::from_secs(1);
+
+/// Column the device table is sorted by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortKey {
+    /// Device name
+    #[default]
+    Name,
+    /// Vendor and product ID
+    VidPid,
+    /// Port path
+    Port,
+    /// Negotiated speed
+    Speed,
+    /// Interface drivers
+    Driver,
+    /// Time since the device was connected
+    Uptime,
+}
+
+impl SortKey {
+    /// Returns the column after this one, wrapping around.
+    pub fn next(self) -> Self {
+        match self {
+            SortKey::Name => SortKey::VidPid,
+            SortKey::VidPid => SortKey::Port,
+            SortKey::Port => SortKey::Speed,
+            SortKey::Speed => SortKey::Driver,
+            SortKey::Driver => SortKey::Uptime,
+            SortKey::Uptime => SortKey::Name,
+        }
+    }
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SortKey::Name => write!(f, "Name"),
+            SortKey::VidPid => write!(f, "VID:PID"),
+            SortKey::Port => write!(f, "Port"),
+            SortKey::Speed => write!(f, "Speed"),
+            SortKey::Driver => write!(f, "Driver"),
+            SortKey::Uptime => write!(f, "Uptime"),
+        }
+    }
+}
+
+/// A connected device shown in the table.
+#[derive(Debug, Clone)]
+pub struct DeviceRow {
+    /// The device's latest connect event
+    pub device: UsbDeviceInfo,
+    /// When the device was connected, or first seen if it was already attached
+    pub connected_at: DateTime<Utc>,
+    /// sysfs attributes of the device (Linux; empty elsewhere)
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl DeviceRow {
+    fn new(device: UsbDeviceInfo) -> Self {
+        let mut row = Self {
+            connected_at: device.connected_at.unwrap_or(device.timestamp),
+            attributes: BTreeMap::new(),
+            device,
+        };
+        row.refresh();
+        row
+    }
+
+    /// Re-reads the device's sysfs attributes.
+    pub fn refresh(&mut self) {
+        #[cfg(target_os = "linux")]
+        if let crate::device_info::DeviceHandle::Linux { sysfs_path, .. } =
+            &self.device.device_handle
+        {
+            self.attributes = crate::info::read_attributes(std::path::Path::new(sysfs_path));
+        }
+    }
+
+    /// Returns the negotiated speed in Mbit/s, if known.
+    pub fn speed(&self) -> Option<&str> {
+        self.attributes.get("speed").map(String::as_str)
+    }
+
+    /// Returns the drivers bound to the device's interfaces, without duplicates.
+    pub fn drivers(&self) -> String {
+        let mut drivers: Vec<&str> = Vec::new();
+        for driver in self
+            .device
+            .interfaces
+            .iter()
+            .filter_map(|i| i.driver.as_deref())
+        {
+            if !drivers.contains(&driver) {
+                drivers.push(driver);
+            }
+        }
+        drivers.join(",")
+    }
+
+    /// Returns how long the device has been connected at `now`, in seconds.
+    pub fn uptime(&self, now: DateTime<Utc>) -> f64 {
+        (now - self.connected_at).num_milliseconds().max(0) as f64 / 1000.0
+    }
+
+    fn vid_pid(&self) -> String {
+        format!("{}:{}", self.device.vendor_id, self.device.product_id)
+    }
+
+    fn matches(&self, filter: &str) -> bool {
+        let filter = filter.to_lowercase();
+        let device = &self.device;
+        [
+            Some(device.device_name.as_str()),
+            Some(self.vid_pid().as_str()),
+            device.port_path.as_deref(),
+            device.serial_number.as_deref(),
+            device.vendor_name.as_deref(),
+            device.product_name.as_deref(),
+            Some(self.drivers().as_str()),
+        ]
+        .into_iter()
+        .flatten()
+        .any(|field| field.to_lowercase().contains(&filter))
+    }
+}
+
+/// A line of the event log.
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    /// When the event happened or the message was printed
+    pub timestamp: DateTime<Utc>,
+    /// Event type, or `None` for a message printed by the pipeline
+    pub event_type: Option<DeviceEventType>,
+    /// Text of the line
+    pub text: String,
+}
+
+/// What the user is doing with the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Normal,
+    Filter,
+    Details,
+}
+
+/// State of the terminal dashboard.
+pub struct Dashboard {
+    devices: BTreeMap<String, DeviceRow>,
+    log: VecDeque<LogLine>,
+    log_size: usize,
+    filter: String,
+    sort: SortKey,
+    reverse: bool,
+    selected: Option<String>,
+    mode: Mode,
+    quit: bool,
+}
+
+impl Dashboard {
+    /// Creates an empty dashboard keeping the last `log_size` log lines.
+    pub fn new(log_size: usize) -> Self {
+        Self {
+            devices: BTreeMap::new(),
+            log: VecDeque::new(),
+            log_size,
+            filter: String::new(),
+            sort: SortKey::default(),
+            reverse: false,
+            selected: None,
+            mode: Mode::Normal,
+            quit: false,
+        }
+    }
+
+    /// Records an event: connects and disconnects update the table, and
+    /// every event is added to the log.
+    pub fn record(&mut self, event: &UsbDeviceInfo) {
+        let devices = std::iter::once(event).chain(&event.children);
+        match event.event_type {
+            DeviceEventType::Connected => {
+                for device in devices {
+                    let mut device = device.clone();
+                    device.children.clear();
+                    self.devices
+                        .insert(device.device_key(), DeviceRow::new(device));
+                }
+            }
+            DeviceEventType::Disconnected => {
+                for device in devices {
+                    self.devices.remove(&device.device_key());
+                }
+            }
+            DeviceEventType::Flapping
+            | DeviceEventType::SuspiciousDevice
+            | DeviceEventType::Degraded => {}
+        }
+
+        let mut text = format!(
+            "{:<12} {} ({}:{})",
+            event.event_type.to_string().to_uppercase(),
+            event.device_name,
+            event.vendor_id,
+            event.product_id
+        );
+        if let Some(port) = &event.port_path {
+            text.push_str(&format!(" on {port}"));
+        }
+        if let Some(duration) = event.duration {
+            text.push_str(&format!(", connected for {}", format_duration(duration)));
+        }
+        if let Some(reason) = &event.reason {
+            text.push_str(&format!(", {reason}"));
+        }
+        if let Some(policy) = &event.policy {
+            text.push_str(&format!(", policy: {policy}"));
+        }
+        if !event.children.is_empty() {
+            text.push_str(&format!(", children: {}", event.children_summary()));
+        }
+        self.push_log(LogLine {
+            timestamp: event.timestamp,
+            event_type: Some(event.event_type.clone()),
+            text,
+        });
+    }
+
+    /// Adds a message, such as a warning printed by the pipeline, to the log.
+    pub fn message(&mut self, text: &str) {
+        self.push_log(LogLine {
+            timestamp: Utc::now(),
+            event_type: None,
+            text: text.to_string(),
+        });
+    }
+
+    fn push_log(&mut self, line: LogLine) {
+        self.log.push_back(line);
+        while self.log.len() > self.log_size {
+            self.log.pop_front();
+        }
+    }
+
+    /// Returns the devices matching the filter, in display order.
+    pub fn rows(&self) -> Vec<&DeviceRow> {
+        let now = Utc::now();
+        let mut rows: Vec<&DeviceRow> = self
+            .devices
+            .values()
+            .filter(|row| self.filter.is_empty() || row.matches(&self.filter))
+            .collect();
+        rows.sort_by(|a, b| {
+            let order = match self.sort {
+                SortKey::Name => a
+                    .device
+                    .device_name
+                    .to_lowercase()
+                    .cmp(&b.device.device_name.to_lowercase()),
+                SortKey::VidPid => a.vid_pid().cmp(&b.vid_pid()),
+                SortKey::Port => a.device.port_path.cmp(&b.device.port_path),
+                SortKey::Speed => {
+                    let speed = |row: &DeviceRow| row.speed().and_then(|s| s.parse::<f64>().ok());
+                    speed(a)
+                        .partial_cmp(&speed(b))
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                }
+                SortKey::Driver => a.drivers().cmp(&b.drivers()),
+                SortKey::Uptime => a.uptime(now).total_cmp(&b.uptime(now)),
+            };
+            // Ties keep a stable order
+            let order = order.then_with(|| a.device.device_key().cmp(&b.device.device_key()));
+            if self.reverse {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+        rows
+    }
+
+    /// Returns the lines of the event log, oldest first.
+    pub fn log(&self) -> &VecDeque<LogLine> {
+        &self.log
+    }
+
+    /// Returns the selected device, or the first one shown if the selected
+    /// device is filtered out or gone.
+    pub fn selected(&self) -> Option<&DeviceRow> {
+        let rows = self.rows();
+        let index = self.selected_index(&rows)?;
+        rows.into_iter().nth(index)
+    }
+
+    fn selected_index(&self, rows: &[&DeviceRow]) -> Option<usize> {
+        if rows.is_empty() {
+            return None;
+        }
+        let index = self
+            .selected
+            .as_ref()
+            .and_then(|key| rows.iter().position(|row| &row.device.device_key() == key));
+        Some(index.unwrap_or(0))
+    }
+
+    /// Returns the current filter text.
+    pub fn filter(&self) -> &str {
+        &self.filter
+    }
+
+    /// Returns the sort column and whether the order is reversed.
+    pub fn sort(&self) -> (SortKey, bool) {
+        (self.sort, self.reverse)
+    }
+
+    /// Returns whether the detail pane is open.
+    pub fn showing_details(&self) -> bool {
+        self.mode == Mode::Details
+    }
+
+    /// Returns whether the user asked to quit.
+    pub fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// Handles a key press.
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        if key.kind == KeyEventKind::Release {
+            return;
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+        if self.mode == Mode::Filter {
+            match key.code {
+                KeyCode::Enter => self.mode = Mode::Normal,
+                KeyCode::Esc => {
+                    self.filter.clear();
+                    self.mode = Mode::Normal;
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Char(c) => self.filter.push(c),
+                _ => {}
+            }
+            return;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc if self.mode == Mode::Details => {
+                self.mode = Mode::Normal
+            }
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Esc if !self.filter.is_empty() => self.filter.clear(),
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Char('/') => self.mode = Mode::Filter,
+            KeyCode::Char('s') => self.sort = self.sort.next(),
+            KeyCode::Char('r') => self.reverse = !self.reverse,
+            KeyCode::Char('c') => self.log.clear(),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Home | KeyCode::Char('g') => self.move_selection(isize::MIN),
+            KeyCode::End | KeyCode::Char('G') => self.move_selection(isize::MAX),
+            KeyCode::Enter | KeyCode::Char('d') => {
+                if self.mode == Mode::Details {
+                    self.mode = Mode::Normal;
+                } else if let Some(key) = self.selected().map(|row| row.device.device_key()) {
+                    if let Some(row) = self.devices.get_mut(&key) {
+                        row.refresh();
+                    }
+                    self.mode = Mode::Details;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Moves the selection by `delta` rows, clamped to the table.
+    fn move_selection(&mut self, delta: isize) {
+        let rows = self.rows();
+        let Some(index) = self.selected_index(&rows) else {
+            return;
+        };
+        let index = index.saturating_add_signed(delta).min(rows.len() - 1);
+        self.selected = Some(rows[index].device.device_key());
+    }
+
+    /// Draws the dashboard.
+    pub fn render(&self, frame: &mut Frame<'_>) {
+        let [main, log, status] = split(
+            Direction::Vertical,
+            frame.area(),
+            [
+                Constraint::Percentage(60),
+                Constraint::Min(3),
+                Constraint::Length(1),
+            ],
+        );
+        let details = self.mode == Mode::Details;
+        let [table, detail] = split(
+            Direction::Horizontal,
+            main,
+            if details {
+                [Constraint::Percentage(55), Constraint::Percentage(45)]
+            } else {
+                [Constraint::Percentage(100), Constraint::Length(0)]
+            },
+        );
+        self.render_table(frame, table);
+        if details {
+            self.render_details(frame, detail);
+        }
+        self.render_log(frame, log);
+        self.render_status(frame, status);
+    }
+
+    fn render_table(&self, frame: &mut Frame<'_>, area: Rect) {
+        let now = Utc::now();
+        let rows = self.rows();
+        let selected = self.selected_index(&rows);
+        let arrow = if self.reverse { " ▼" } else { " ▲" };
+        let header = Row::new(
+            [
+                SortKey::Name,
+                SortKey::VidPid,
+                SortKey::Port,
+                SortKey::Speed,
+                SortKey::Driver,
+                SortKey::Uptime,
+            ]
+            .map(|key| {
+                let title = if key == self.sort {
+                    format!("{key}{arrow}")
+                } else {
+                    key.to_string()
+                };
+                Cell::from(title)
+            }),
+        )
+        .style(Style::default().add_modifier(Modifier::BOLD));
+        let table_rows = rows.iter().map(|row| {
+            let style = if row.device.suspicious_reason().is_some() {
+                Style::default().fg(Color::Magenta)
+            } else {
+                Style::default()
+            };
+            Row::new([
+                Cell::from(row.device.device_name.clone()),
+                Cell::from(row.vid_pid()),
+                Cell::from(row.device.port_path.clone().unwrap_or_else(dash)),
+                Cell::from(row.speed().map(|s| format!("{s}M")).unwrap_or_else(dash)),
+                Cell::from(non_empty(row.drivers())),
+                Cell::from(format_duration(row.uptime(now))),
+            ])
+            .style(style)
+        });
+        let title = if self.filter.is_empty() {
+            format!(" Devices ({}) ", rows.len())
+        } else {
+            format!(
+                " Devices ({} of {}, filter: {}) ",
+                rows.len(),
+                self.devices.len(),
+                self.filter
+            )
+        };
+        let table = Table::new(
+            table_rows,
+            [
+                Constraint::Fill(3),
+                Constraint::Length(9),
+                Constraint::Length(10),
+                Constraint::Length(8),
+                Constraint::Fill(1),
+                Constraint::Length(12),
+            ],
+        )
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut state = TableState::default().with_selected(selected);
+        frame.render_stateful_widget(table, area, &mut state);
+    }
+
+    fn render_details(&self, frame: &mut Frame<'_>, area: Rect) {
+        let block = Block::default().borders(Borders::ALL);
+        let Some(row) = self.selected() else {
+            frame.render_widget(Paragraph::new("No device selected").block(block), area);
+            return;
+        };
+        let device = &row.device;
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let mut lines = vec![
+            Line::from(Span::styled(device.device_name.clone(), bold)),
+            Line::from(format!("Key        {}", device.device_key())),
+            Line::from(format!(
+                "Class      {}",
+                device
+                    .class_name
+                    .clone()
+                    .or_else(|| device.class_summary())
+                    .unwrap_or_else(dash)
+            )),
+        ];
+        if let Some(reason) = device.suspicious_reason() {
+            lines.push(Line::from(Span::styled(
+                format!("Suspicious {reason}"),
+                Style::default().fg(Color::Magenta),
+            )));
+        }
+        if !device.interfaces.is_empty() {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled("Interfaces", bold)));
+            for interface in &device.interfaces {
+                lines.push(Line::from(format!(
+                    "  {:<12} {}  {}",
+                    interface.id,
+                    interface
+                        .class_name
+                        .clone()
+                        .unwrap_or_else(|| interface.class.to_string()),
+                    interface.driver.as_deref().unwrap_or("-")
+                )));
+            }
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled("Attributes", bold)));
+        if row.attributes.is_empty() {
+            lines.push(Line::from("  not available"));
+        }
+        for (name, value) in &row.attributes {
+            lines.push(Line::from(format!("  {name:<22} {value}")));
+        }
+        frame.render_widget(Paragraph::new(lines).block(block.title(" Details ")), area);
+    }
+
+    fn render_log(&self, frame: &mut Frame<'_>, area: Rect) {
+        let height = area.height.saturating_sub(2) as usize;
+        let lines: Vec<Line<'_>> = self
+            .log
+            .iter()
+            .skip(self.log.len().saturating_sub(height))
+            .map(|line| {
+                let style = match &line.event_type {
+                    Some(DeviceEventType::Connected) => Style::default().fg(Color::Green),
+                    Some(DeviceEventType::Disconnected) => Style::default().fg(Color::Red),
+                    Some(DeviceEventType::Flapping) => Style::default().fg(Color::Yellow),
+                    Some(DeviceEventType::SuspiciousDevice) => Style::default().fg(Color::Magenta),
+                    Some(DeviceEventType::Degraded) => Style::default().fg(Color::Cyan),
+                    None => Style::default().add_modifier(Modifier::DIM),
+                };
+                Line::from(vec![
+                    Span::raw(format!(
+                        "{} ",
+                        line.timestamp
+                            .with_timezone(&chrono::Local)
+                            .format("%H:%M:%S")
+                    )),
+                    Span::styled(line.text.clone(), style),
+                ])
+            })
+            .collect();
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" Events ")),
+            area,
+        );
+    }
+
+    fn render_status(&self, frame: &mut Frame<'_>, area: Rect) {
+        let text = match self.mode {
+            Mode::Filter => format!("Filter: {}█  (Enter apply, Esc clear)", self.filter),
+            _ => format!(
+                "q quit  / filter  s sort ({})  r reverse  Enter details  ↑↓ select  c clear log",
+                self.sort
+            ),
+        };
+        frame.render_widget(
+            Paragraph::new(text).style(Style::default().add_modifier(Modifier::REVERSED)),
+            area,
+        );
+    }
+}
+
+fn split<const N: usize>(
+    direction: Direction,
+    area: Rect,
+    constraints: [Constraint; N],
+) -> [Rect; N] {
+    Layout::default()
+        .direction(direction)
+        .constraints(constraints)
+        .areas(area)
+}
+
+fn dash() -> String {
+    "-".to_string()
+}
+
+fn non_empty(value: String) -> String {
+    if value.is_empty() {
+        dash()
+    } else {
+        value
+    }
+}
+
+/// Runs the dashboard full-screen until the user quits, feeding it the
+/// events from `rx`.
+///
+/// # Errors
+///
+/// Returns an error if the terminal cannot be set up or drawn to.
+pub async fn run(
+    mut rx: mpsc::Receiver<UsbDeviceInfo>,
+    mut dashboard: Dashboard,
+) -> io::Result<()> {
+    #[cfg(unix)]
+    let (capture, terminal_output, mut messages) = OutputCapture::start()?;
+    #[cfg(unix)]
+    let screen = Screen::enter(Box::new(terminal_output.try_clone()?))?;
+    #[cfg(not(unix))]
+    let (terminal_output, mut messages) = (io::stdout(), mpsc::unbounded_channel::<String>().1);
+    #[cfg(not(unix))]
+    let screen = Screen::enter(Box::new(io::stdout()))?;
+
+    let mut terminal = Terminal::new(ratatui::backend::CrosstermBackend::new(terminal_output))?;
+    terminal.clear()?;
+    let mut keys = spawn_key_reader();
+    let mut tick = tokio::time::interval(TICK);
+
+    let result = loop {
+        if let Err(e) = terminal.draw(|frame| dashboard.render(frame)) {
+            break Err(e);
+        }
+        tokio::select! {
+            Some(event) = rx.recv() => dashboard.record(&event),
+            Some(message) = messages.recv() => dashboard.message(&message),
+            key = keys.recv() => match key {
+                Some(Event::Key(key)) if key.modifiers.contains(KeyModifiers::CONTROL)
+                    && key.code == KeyCode::Char('l') =>
+                {
+                    if let Err(e) = terminal.clear() {
+                        break Err(e);
+                    }
+                }
+                Some(Event::Key(key)) => dashboard.handle_key(key),
+                Some(_) => {}
+                None => break Ok(()),
+            },
+            _ = tick.tick() => {}
+        }
+        if dashboard.should_quit() {
+            break Ok(());
+        }
+    };
+
+    terminal.show_cursor()?;
+    drop(screen);
+    #[cfg(unix)]
+    drop(capture);
+    result
+}
+
+/// Reads terminal input on a separate thread until the receiver is dropped.
+fn spawn_key_reader() -> mpsc::UnboundedReceiver<Event> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        while !tx.is_closed() {
+            match event::poll(Duration::from_millis(200)) {
+                Ok(true) => match event::read() {
+                    Ok(event) => {
+                        if tx.send(event).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                },
+                Ok(false) => {}
+                Err(_) => break,
+            }
+        }
+    });
+    rx
+}
+
+/// Raw mode and the alternate screen, restored when dropped.
+struct Screen {
+    output: Box<dyn Write>,
+}
+
+impl Screen {
+    fn enter(mut output: Box<dyn Write>) -> io::Result<Self> {
+        enable_raw_mode()?;
+        output.execute(EnterAlternateScreen)?;
+        Ok(Self { output })
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = self.output.execute(LeaveAlternateScreen);
+        let _ = disable_raw_mode();
+    }
+}
+
+/// Redirects stdout and stderr into a pipe while the dashboard owns the
+/// terminal, restoring them when dropped.
+#[cfg(unix)]
+struct OutputCapture {
+    stdout: std::os::fd::OwnedFd,
+    stderr: std::os::fd::OwnedFd,
+}
+
+#[cfg(unix)]
+impl OutputCapture {
+    /// Starts capturing, returning the capture, a handle to the original
+    /// stdout for drawing and a receiver for the captured lines.
+    fn start() -> io::Result<(Self, std::fs::File, mpsc::UnboundedReceiver<String>)> {
+        use std::io::BufRead;
+        use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+        let dup = |fd: i32| -> io::Result<OwnedFd> {
+            // SAFETY: dup returns a new descriptor that nothing else owns
+            let new = unsafe { libc::dup(fd) };
+            if new < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // SAFETY: `new` is a valid, open descriptor owned by nobody else
+            Ok(unsafe { OwnedFd::from_raw_fd(new) })
+        };
+        let mut pipe = [0; 2];
+        // SAFETY: pipe writes two descriptors into the array
+        if unsafe { libc::pipe(pipe.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: both descriptors were just created by pipe
+        let (read, write) =
+            unsafe { (OwnedFd::from_raw_fd(pipe[0]), OwnedFd::from_raw_fd(pipe[1])) };
+
+        let capture = Self {
+            stdout: dup(libc::STDOUT_FILENO)?,
+            stderr: dup(libc::STDERR_FILENO)?,
+        };
+        let terminal = std::fs::File::from(dup(libc::STDOUT_FILENO)?);
+        io::stdout().flush()?;
+        for fd in [libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+            // SAFETY: both descriptors are open; dup2 atomically replaces `fd`
+            if unsafe { libc::dup2(write.as_raw_fd(), fd) } < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        drop(write);
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        std::thread::spawn(move || {
+            // Ends when the original stdout and stderr are restored
+            for line in io::BufReader::new(std::fs::File::from(read)).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if !line.trim().is_empty() && tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok((capture, terminal, rx))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for OutputCapture {
+    fn drop(&mut self) {
+        use std::os::fd::AsRawFd;
+
+        let _ = io::stdout().flush();
+        // SAFETY: the saved descriptors stay open until self is dropped
+        unsafe {
+            libc::dup2(self.stdout.as_raw_fd(), libc::STDOUT_FILENO);
+            libc::dup2(self.stderr.as_raw_fd(), libc::STDERR_FILENO);
+        }
+    }
+}
@@ -0,0 +1,414 @@
+//! USB class code decoding.
+//!
+//! Decodes the base class, subclass and protocol bytes reported in device and interface descriptors
+//! (`bDeviceClass`/`bInterfaceClass` and friends) into the categories defined by the USB-IF class code table,
+//! including common subclass/protocol refinements such as HID boot keyboards, mass storage SCSI over
+//! Bulk-Only Transport and CDC ACM modems.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// USB base class, as assigned by the USB-IF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UsbClass {
+    /// 0x00: class is defined per interface
+    PerInterface,
+    /// 0x01: audio
+    Audio,
+    /// 0x02: communications and CDC control
+    Communications,
+    /// 0x03: human interface device
+    Hid,
+    /// 0x05: physical interface device
+    Physical,
+    /// 0x06: still imaging
+    Image,
+    /// 0x07: printer
+    Printer,
+    /// 0x08: mass storage
+    MassStorage,
+    /// 0x09: hub
+    Hub,
+    /// 0x0a: CDC data
+    CdcData,
+    /// 0x0b: smart card
+    SmartCard,
+    /// 0x0d: content security
+    ContentSecurity,
+    /// 0x0e: video
+    Video,
+    /// 0x0f: personal healthcare
+    PersonalHealthcare,
+    /// 0x10: audio/video devices
+    AudioVideo,
+    /// 0x11: billboard device
+    Billboard,
+    /// 0x12: USB Type-C bridge
+    TypeCBridge,
+    /// 0x13: bulk display protocol
+    BulkDisplay,
+    /// 0x14: MCTP over USB
+    Mctp,
+    /// 0x3c: I3C
+    I3c,
+    /// 0xdc: diagnostic device
+    Diagnostic,
+    /// 0xe0: wireless controller (e.g. Bluetooth)
+    WirelessController,
+    /// 0xef: miscellaneous (including interface association)
+    Miscellaneous,
+    /// 0xfe: application specific (DFU, IrDA bridge, test and measurement)
+    ApplicationSpecific,
+    /// 0xff: vendor specific
+    VendorSpecific,
+    /// Reserved or unassigned code
+    Reserved,
+}
+
+const CLASS_TABLE: &[(u8, UsbClass, &str, &str)] = &[
+    (
+        0x00,
+        UsbClass::PerInterface,
+        "per-interface",
+        "Per Interface",
+    ),
+    (0x01, UsbClass::Audio, "audio", "Audio"),
+    (
+        0x02,
+        UsbClass::Communications,
+        "communications",
+        "Communications",
+    ),
+    (0x03, UsbClass::Hid, "hid", "HID"),
+    (0x05, UsbClass::Physical, "physical", "Physical"),
+    (0x06, UsbClass::Image, "image", "Image"),
+    (0x07, UsbClass::Printer, "printer", "Printer"),
+    (0x08, UsbClass::MassStorage, "mass-storage", "Mass Storage"),
+    (0x09, UsbClass::Hub, "hub", "Hub"),
+    (0x0a, UsbClass::CdcData, "cdc-data", "CDC Data"),
+    (0x0b, UsbClass::SmartCard, "smart-card", "Smart Card"),
+    (
+        0x0d,
+        UsbClass::ContentSecurity,
+        "content-security",
+        "Content Security",
+    ),
+    (0x0e, UsbClass::Video, "video", "Video"),
+    (
+        0x0f,
+        UsbClass::PersonalHealthcare,
+        "personal-healthcare",
+        "Personal Healthcare",
+    ),
+    (0x10, UsbClass::AudioVideo, "audio-video", "Audio/Video"),
+    (0x11, UsbClass::Billboard, "billboard", "Billboard"),
+    (
+        0x12,
+        UsbClass::TypeCBridge,
+        "type-c-bridge",
+        "Type-C Bridge",
+    ),
+    (0x13, UsbClass::BulkDisplay, "bulk-display", "Bulk Display"),
+    (0x14, UsbClass::Mctp, "mctp", "MCTP"),
+    (0x3c, UsbClass::I3c, "i3c", "I3C"),
+    (0xdc, UsbClass::Diagnostic, "diagnostic", "Diagnostic"),
+    (
+        0xe0,
+        UsbClass::WirelessController,
+        "wireless-controller",
+        "Wireless Controller",
+    ),
+    (
+        0xef,
+        UsbClass::Miscellaneous,
+        "miscellaneous",
+        "Miscellaneous",
+    ),
+    (
+        0xfe,
+        UsbClass::ApplicationSpecific,
+        "application-specific",
+        "Application Specific",
+    ),
+    (
+        0xff,
+        UsbClass::VendorSpecific,
+        "vendor-specific",
+        "Vendor Specific",
+    ),
+];
+
+impl UsbClass {
+    /// Decodes a base class byte.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use usbwatch_rs::usb_class::UsbClass;
+    ///
+    /// assert_eq!(UsbClass::from_code(0x08), UsbClass::MassStorage);
+    /// assert_eq!(UsbClass::from_code(0x04), UsbClass::Reserved);
+    /// ```
+    pub fn from_code(code: u8) -> Self {
+        CLASS_TABLE
+            .iter()
+            .find(|(c, ..)| *c == code)
+            .map(|(_, class, ..)| *class)
+            .unwrap_or(UsbClass::Reserved)
+    }
+
+    /// Returns the short kebab-case name used in filters and JSON (e.g. `mass-storage`).
+    pub fn name(&self) -> &'static str {
+        CLASS_TABLE
+            .iter()
+            .find(|(_, class, ..)| class == self)
+            .map(|(_, _, name, _)| *name)
+            .unwrap_or("reserved")
+    }
+
+    /// Returns a human-readable label (e.g. `Mass Storage`).
+    pub fn label(&self) -> &'static str {
+        CLASS_TABLE
+            .iter()
+            .find(|(_, class, ..)| class == self)
+            .map(|(.., label)| *label)
+            .unwrap_or("Reserved")
+    }
+}
+
+impl fmt::Display for UsbClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for UsbClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalised = s.trim().to_ascii_lowercase().replace(['_', ' '], "-");
+        if let Some((_, class, ..)) = CLASS_TABLE
+            .iter()
+            .find(|(_, _, name, _)| *name == normalised)
+        {
+            return Ok(*class);
+        }
+        match normalised.as_str() {
+            "storage" | "msc" => Ok(UsbClass::MassStorage),
+            "cdc" | "comm" => Ok(UsbClass::Communications),
+            "wireless" | "bluetooth" => Ok(UsbClass::WirelessController),
+            "misc" => Ok(UsbClass::Miscellaneous),
+            "vendor" => Ok(UsbClass::VendorSpecific),
+            "reserved" => Ok(UsbClass::Reserved),
+            _ => {
+                let hex = normalised.trim_start_matches("0x");
+                u8::from_str_radix(hex, 16)
+                    .map(UsbClass::from_code)
+                    .map_err(|_| format!("Unknown USB class '{s}'"))
+            }
+        }
+    }
+}
+
+/// Class, subclass and protocol bytes from a device or interface descriptor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ClassCode {
+    /// Base class code
+    pub class: u8,
+    /// Subclass code
+    pub subclass: u8,
+    /// Protocol code
+    pub protocol: u8,
+}
+
+impl ClassCode {
+    /// Creates a class code triple.
+    pub fn new(class: u8, subclass: u8, protocol: u8) -> Self {
+        Self {
+            class,
+            subclass,
+            protocol,
+        }
+    }
+
+    /// Returns the decoded base class.
+    pub fn usb_class(&self) -> UsbClass {
+        UsbClass::from_code(self.class)
+    }
+
+    /// Returns the subclass/protocol refinement, if the combination is a well-known one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use usbwatch_rs::usb_class::ClassCode;
+    ///
+    /// assert_eq!(ClassCode::new(0x03, 0x01, 0x01).refinement().as_deref(), Some("Boot Keyboard"));
+    /// assert_eq!(ClassCode::new(0x08, 0x06, 0x50).refinement().as_deref(), Some("SCSI, Bulk-Only"));
+    /// assert_eq!(ClassCode::new(0x02, 0x02, 0x01).refinement().as_deref(), Some("ACM, AT Commands"));
+    /// ```
+    pub fn refinement(&self) -> Option<String> {
+        let (subclass, protocol) = match self.usb_class() {
+            UsbClass::Hid => (
+                match self.subclass {
+                    0x01 => Some("Boot"),
+                    _ => None,
+                },
+                match self.protocol {
+                    0x01 => Some("Keyboard"),
+                    0x02 => Some("Mouse"),
+                    _ => None,
+                },
+            ),
+            UsbClass::MassStorage => (
+                match self.subclass {
+                    0x01 => Some("RBC"),
+                    0x02 => Some("MMC-5 (ATAPI)"),
+                    0x03 => Some("QIC-157"),
+                    0x04 => Some("UFI"),
+                    0x05 => Some("SFF-8070i"),
+                    0x06 => Some("SCSI"),
+                    0x07 => Some("LSD FS"),
+                    0x08 => Some("IEEE 1667"),
+                    _ => None,
+                },
+                match self.protocol {
+                    0x00 => Some("CBI with completion interrupt"),
+                    0x01 => Some("CBI"),
+                    0x50 => Some("Bulk-Only"),
+                    0x62 => Some("UAS"),
+                    _ => None,
+                },
+            ),
+            UsbClass::Communications => (
+                match self.subclass {
+                    0x01 => Some("Direct Line"),
+                    0x02 => Some("ACM"),
+                    0x03 => Some("Telephone"),
+                    0x04 => Some("Multi-Channel"),
+                    0x05 => Some("CAPI"),
+                    0x06 => Some("Ethernet (ECM)"),
+                    0x07 => Some("ATM"),
+                    0x08 => Some("Wireless Handset"),
+                    0x09 => Some("Device Management"),
+                    0x0a => Some("Mobile Direct Line"),
+                    0x0b => Some("OBEX"),
+                    0x0c => Some("Ethernet Emulation (EEM)"),
+                    0x0d => Some("Network Control (NCM)"),
+                    0x0e => Some("Mobile Broadband (MBIM)"),
+                    _ => None,
+                },
+                match self.protocol {
+                    0x01 => Some("AT Commands"),
+                    0xff => Some("Vendor Specific"),
+                    _ => None,
+                },
+            ),
+            UsbClass::Audio => (
+                match self.subclass {
+                    0x01 => Some("Control"),
+                    0x02 => Some("Streaming"),
+                    0x03 => Some("MIDI Streaming"),
+                    _ => None,
+                },
+                None,
+            ),
+            UsbClass::Video => (
+                match self.subclass {
+                    0x01 => Some("Control"),
+                    0x02 => Some("Streaming"),
+                    0x03 => Some("Interface Collection"),
+                    _ => None,
+                },
+                None,
+            ),
+            UsbClass::Printer => (
+                None,
+                match self.protocol {
+                    0x01 => Some("Unidirectional"),
+                    0x02 => Some("Bidirectional"),
+                    0x03 => Some("IEEE 1284.4"),
+                    0x04 => Some("IPP over USB"),
+                    _ => None,
+                },
+            ),
+            UsbClass::Image => (
+                match (self.subclass, self.protocol) {
+                    (0x01, 0x01) => Some("Still Image Capture (PTP)"),
+                    _ => None,
+                },
+                None,
+            ),
+            UsbClass::Hub => (
+                None,
+                match self.protocol {
+                    0x00 => Some("Full Speed"),
+                    0x01 => Some("Hi-Speed Single TT"),
+                    0x02 => Some("Hi-Speed Multi TT"),
+                    0x03 => Some("SuperSpeed"),
+                    _ => None,
+                },
+            ),
+            UsbClass::WirelessController => (
+                match (self.subclass, self.protocol) {
+                    (0x01, 0x01) => Some("Bluetooth"),
+                    (0x01, 0x02) => Some("UWB Radio Control"),
+                    (0x01, 0x03) => Some("RNDIS"),
+                    (0x01, 0x04) => Some("Bluetooth AMP"),
+                    (0x02, 0x01) => Some("Host Wire Adapter"),
+                    (0x02, 0x02) => Some("Device Wire Adapter"),
+                    _ => None,
+                },
+                None,
+            ),
+            UsbClass::Miscellaneous => (
+                match (self.subclass, self.protocol) {
+                    (0x01, 0x01) => Some("ActiveSync"),
+                    (0x01, 0x02) => Some("Palm Sync"),
+                    (0x02, 0x01) => Some("Interface Association"),
+                    (0x02, 0x02) => Some("Wire Adapter Multifunction Peripheral"),
+                    (0x03, 0x01) => Some("Cable Based Association"),
+                    (0x04, 0x01) => Some("RNDIS over Ethernet"),
+                    (0x04, 0x02) => Some("RNDIS over Wi-Fi"),
+                    (0x05, _) => Some("USB3 Vision"),
+                    _ => None,
+                },
+                None,
+            ),
+            UsbClass::ApplicationSpecific => (
+                match self.subclass {
+                    0x01 => Some("Device Firmware Upgrade"),
+                    0x02 => Some("IrDA Bridge"),
+                    0x03 => Some("Test and Measurement"),
+                    _ => None,
+                },
+                None,
+            ),
+            _ => (None, None),
+        };
+
+        match (subclass, protocol) {
+            (Some("Boot"), Some(protocol)) => Some(format!("Boot {protocol}")),
+            (Some(subclass), Some(protocol)) => Some(format!("{subclass}, {protocol}")),
+            (Some(refinement), None) | (None, Some(refinement)) => Some(refinement.to_string()),
+            (None, None) => None,
+        }
+    }
+
+    /// Returns whether this is a HID boot-protocol keyboard.
+    pub fn is_boot_keyboard(&self) -> bool {
+        self.class == 0x03 && self.subclass == 0x01 && self.protocol == 0x01
+    }
+}
+
+impl fmt::Display for ClassCode {
+    /// Formats as the class label with any refinement, e.g. `Mass Storage (SCSI, Bulk-Only)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.refinement() {
+            Some(refinement) => write!(f, "{} ({refinement})", self.usb_class().label()),
+            None => write!(f, "{}", self.usb_class().label()),
+        }
+    }
+}
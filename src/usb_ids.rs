@@ -0,0 +1,299 @@
+//! Vendor, product and class name lookup using the `usb.ids` database.
+//!
+//! Many devices do not report manufacturer or product strings. [`UsbIds`] parses the
+//! [`usb.ids`](http://www.linux-usb.org/usb.ids) format maintained by the linux-usb project and resolves
+//! VID/PID and class codes to human-readable names. The system copy is used when available; an embedded
+//! snapshot can be compiled in with the `embedded-usb-ids` cargo feature. The snapshot (`data/usb.ids`, BSD-3-Clause,
+//! see `data/usb.ids.LICENSE`) is only in the git repository, not the published package.
+
+use crate::device_info::UsbDeviceInfo;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+/// Locations of the system `usb.ids` file, in order of preference.
+pub const SYSTEM_PATHS: &[&str] = &["/usr/share/hwdata/usb.ids", "/usr/share/misc/usb.ids"];
+
+#[cfg(all(feature = "embedded-usb-ids", not(usb_ids_missing)))]
+const EMBEDDED: &[u8] = include_bytes!("../data/usb.ids");
+
+// Set by build.rs when the snapshot is not there, as in the published package
+#[cfg(all(feature = "embedded-usb-ids", usb_ids_missing))]
+compile_error!(
+    "the `embedded-usb-ids` feature needs data/usb.ids, which is not part of the published package; \
+     build from the git repository or leave the feature off"
+);
+#[cfg(all(feature = "embedded-usb-ids", usb_ids_missing))]
+const EMBEDDED: &[u8] = &[];
+
+#[derive(Default)]
+struct Vendor {
+    name: String,
+    products: HashMap<u16, String>,
+}
+
+#[derive(Default)]
+struct Class {
+    name: String,
+    subclasses: HashMap<u8, SubClass>,
+}
+
+#[derive(Default)]
+struct SubClass {
+    name: String,
+    protocols: HashMap<u8, String>,
+}
+
+/// Parsed `usb.ids` database.
+#[derive(Default)]
+pub struct UsbIds {
+    vendors: HashMap<u16, Vendor>,
+    classes: HashMap<u8, Class>,
+}
+
+enum Section {
+    Vendor(u16),
+    Class(u8, Option<u8>),
+    Other,
+}
+
+impl UsbIds {
+    /// Parses a database in `usb.ids` format.
+    ///
+    /// Only the vendor/product and class sections are used; other sections are
+    /// skipped. Invalid bytes are replaced, since the upstream file is not
+    /// strictly UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use usbwatch_rs::usb_ids::UsbIds;
+    ///
+    /// let ids = UsbIds::parse(b"0781  SanDisk Corp.\n\t5583  Ultra Fit\nC 08  Mass Storage\n\t06  SCSI\n\t\t50  Bulk-Only\n");
+    /// assert_eq!(ids.vendor_name("0781"), Some("SanDisk Corp."));
+    /// assert_eq!(ids.product_name("0781", "5583"), Some("Ultra Fit"));
+    /// assert_eq!(ids.class_name(0x08, 0x06, 0x50).as_deref(), Some("Mass Storage / SCSI / Bulk-Only"));
+    /// ```
+    pub fn parse(data: &[u8]) -> Self {
+        let mut ids = UsbIds::default();
+        let mut section = Section::Other;
+
+        for raw_line in data.split(|b| *b == b'\n') {
+            let line = String::from_utf8_lossy(raw_line);
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let depth = line.chars().take_while(|c| *c == '\t').count();
+            let entry = &line[depth..];
+
+            match depth {
+                0 => {
+                    section = if let Some(class) = entry.strip_prefix("C ") {
+                        match split_id(class) {
+                            Some((code, name)) => match u8::from_str_radix(code, 16) {
+                                Ok(code) => {
+                                    ids.classes.insert(
+                                        code,
+                                        Class {
+                                            name: name.to_string(),
+                                            ..Default::default()
+                                        },
+                                    );
+                                    Section::Class(code, None)
+                                }
+                                Err(_) => Section::Other,
+                            },
+                            None => Section::Other,
+                        }
+                    } else {
+                        match split_id(entry).filter(|(id, _)| id.len() == 4) {
+                            Some((id, name)) => match u16::from_str_radix(id, 16) {
+                                Ok(vid) => {
+                                    ids.vendors.insert(
+                                        vid,
+                                        Vendor {
+                                            name: name.to_string(),
+                                            ..Default::default()
+                                        },
+                                    );
+                                    Section::Vendor(vid)
+                                }
+                                Err(_) => Section::Other,
+                            },
+                            None => Section::Other,
+                        }
+                    };
+                }
+                1 => match section {
+                    Section::Vendor(vid) => {
+                        if let Some((pid, name)) = parse_entry::<u16>(entry) {
+                            if let Some(vendor) = ids.vendors.get_mut(&vid) {
+                                vendor.products.insert(pid, name);
+                            }
+                        }
+                    }
+                    Section::Class(class, _) => {
+                        if let Some((subclass, name)) = parse_entry::<u8>(entry) {
+                            if let Some(class_entry) = ids.classes.get_mut(&class) {
+                                class_entry.subclasses.insert(
+                                    subclass,
+                                    SubClass {
+                                        name,
+                                        ..Default::default()
+                                    },
+                                );
+                            }
+                            section = Section::Class(class, Some(subclass));
+                        }
+                    }
+                    Section::Other => {}
+                },
+                2 => {
+                    if let Section::Class(class, Some(subclass)) = section {
+                        if let Some((protocol, name)) = parse_entry::<u8>(entry) {
+                            if let Some(subclass_entry) = ids
+                                .classes
+                                .get_mut(&class)
+                                .and_then(|c| c.subclasses.get_mut(&subclass))
+                            {
+                                subclass_entry.protocols.insert(protocol, name);
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        ids
+    }
+
+    /// Loads and parses a `usb.ids` file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)
+            .map_err(|e| format!("Failed to read USB ID database '{}': {e}", path.display()))?;
+        Ok(Self::parse(&data))
+    }
+
+    /// Loads the system copy of `usb.ids` from [`SYSTEM_PATHS`], if present.
+    pub fn load_system() -> Option<Self> {
+        SYSTEM_PATHS
+            .iter()
+            .find(|path| Path::new(path).exists())
+            .and_then(|path| Self::load(path).ok())
+    }
+
+    /// Parses the snapshot embedded at compile time.
+    #[cfg(feature = "embedded-usb-ids")]
+    pub fn embedded() -> Self {
+        Self::parse(EMBEDDED)
+    }
+
+    /// Loads the system database, falling back to the embedded snapshot when
+    /// the `embedded-usb-ids` feature is enabled.
+    pub fn load_default() -> Option<Self> {
+        let system = Self::load_system();
+        #[cfg(feature = "embedded-usb-ids")]
+        let system = system.or_else(|| Some(Self::embedded()));
+        system
+    }
+
+    /// Returns the vendor name for a hexadecimal vendor ID.
+    pub fn vendor_name(&self, vendor_id: &str) -> Option<&str> {
+        let vid = u16::from_str_radix(vendor_id, 16).ok()?;
+        self.vendors.get(&vid).map(|vendor| vendor.name.as_str())
+    }
+
+    /// Returns the product name for a hexadecimal vendor and product ID.
+    pub fn product_name(&self, vendor_id: &str, product_id: &str) -> Option<&str> {
+        let vid = u16::from_str_radix(vendor_id, 16).ok()?;
+        let pid = u16::from_str_radix(product_id, 16).ok()?;
+        self.vendors
+            .get(&vid)?
+            .products
+            .get(&pid)
+            .map(String::as_str)
+    }
+
+    /// Returns the most specific name for a class triple, joining the class,
+    /// subclass and protocol names that are known with `" / "`.
+    pub fn class_name(&self, class: u8, subclass: u8, protocol: u8) -> Option<String> {
+        let class_entry = self.classes.get(&class)?;
+        let mut parts = vec![class_entry.name.as_str()];
+        if let Some(subclass_entry) = class_entry.subclasses.get(&subclass) {
+            parts.push(&subclass_entry.name);
+            if let Some(protocol_name) = subclass_entry.protocols.get(&protocol) {
+                parts.push(protocol_name);
+            }
+        }
+        Some(parts.join(" / "))
+    }
+
+    /// Fills in `vendor_name` and `product_name` on a device record, and
+    /// replaces an unknown device name with the resolved names.
+    pub fn resolve(&self, device: &mut UsbDeviceInfo) {
+        if device.vendor_name.is_none() {
+            device.vendor_name = self.vendor_name(&device.vendor_id).map(str::to_string);
+        }
+        if device.product_name.is_none() {
+            device.product_name = self
+                .product_name(&device.vendor_id, &device.product_id)
+                .map(str::to_string);
+        }
+
+        if device.device_name == "Unknown Device" {
+            let resolved = match (&device.vendor_name, &device.product_name) {
+                (Some(vendor), Some(product)) => Some(format!("{vendor} {product}")),
+                (None, Some(product)) => Some(product.clone()),
+                (Some(vendor), None) => Some(format!("{vendor} device")),
+                (None, None) => None,
+            };
+            if let Some(name) = resolved {
+                device.device_name = name;
+            }
+        }
+    }
+}
+
+impl fmt::Debug for UsbIds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UsbIds")
+            .field("vendors", &self.vendors.len())
+            .field("classes", &self.classes.len())
+            .finish()
+    }
+}
+
+/// Splits `"id  name"` into its parts.
+fn split_id(entry: &str) -> Option<(&str, &str)> {
+    let (id, name) = entry.split_once(char::is_whitespace)?;
+    Some((id, name.trim()))
+}
+
+fn parse_entry<T: FromHex>(entry: &str) -> Option<(T, String)> {
+    let (id, name) = split_id(entry)?;
+    Some((T::from_hex(id)?, name.to_string()))
+}
+
+trait FromHex: Sized {
+    fn from_hex(s: &str) -> Option<Self>;
+}
+
+impl FromHex for u8 {
+    fn from_hex(s: &str) -> Option<Self> {
+        u8::from_str_radix(s, 16).ok()
+    }
+}
+
+impl FromHex for u16 {
+    fn from_hex(s: &str) -> Option<Self> {
+        u16::from_str_radix(s, 16).ok()
+    }
+}
@@ -1,11 +1,31 @@
 #[cfg(target_os = "linux")]
-use crate::device_info::{DeviceEventType, DeviceHandle, UsbDeviceInfo};
+use super::{collapse_hub_events, WatcherOptions};
+#[cfg(target_os = "linux")]
+use crate::descriptors::{BosDescriptor, DescriptorTree};
+#[cfg(target_os = "linux")]
+use crate::device_info::{DeviceEventType, DeviceHandle, UsbDeviceInfo, UsbInterfaceInfo};
+#[cfg(target_os = "linux")]
+use crate::hid::{classify_report_descriptor, HidClass};
+#[cfg(target_os = "linux")]
+use crate::link::LinkInfo;
+#[cfg(target_os = "linux")]
+use crate::metrics::Metrics;
+#[cfg(target_os = "linux")]
+use crate::policy::{Policy, PolicyAction};
+#[cfg(target_os = "linux")]
+use crate::usb_class::{ClassCode, UsbClass};
+#[cfg(target_os = "linux")]
+use chrono::{DateTime, Utc};
 #[cfg(target_os = "linux")]
 use std::collections::HashMap;
 #[cfg(target_os = "linux")]
 use std::fs;
 #[cfg(target_os = "linux")]
-use std::path::Path;
+use std::path::{Path, PathBuf};
+#[cfg(target_os = "linux")]
+use std::sync::{Mutex, PoisonError};
+#[cfg(target_os = "linux")]
+use std::time::Instant;
 #[cfg(target_os = "linux")]
 use tokio::sync::mpsc;
 
@@ -20,6 +40,8 @@ use tokio::sync::mpsc;
 /// Future versions may detect device nodes (e.g., `/dev/ttyUSB0`).
 pub struct LinuxUsbWatcher {
     tx: mpsc::Sender<UsbDeviceInfo>,
+    options: WatcherOptions,
+    hid_classes: Mutex<HidClassCache>,
 }
 
 #[cfg(target_os = "linux")]
@@ -34,7 +56,21 @@ impl LinuxUsbWatcher {
     ///
     /// A new `LinuxUsbWatcher` instance
     pub fn new(tx: mpsc::Sender<UsbDeviceInfo>) -> Self {
-        Self { tx }
+        Self::with_options(tx, WatcherOptions::default())
+    }
+
+    /// Creates a new Linux USB watcher with the given options.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx` - Channel sender for broadcasting USB device events
+    /// * `options` - Watcher behaviour options
+    pub fn with_options(tx: mpsc::Sender<UsbDeviceInfo>, options: WatcherOptions) -> Self {
+        Self {
+            tx,
+            options,
+            hid_classes: Mutex::new(HidClassCache::default()),
+        }
     }
 
     /// Starts monitoring USB devices on Linux.
@@ -58,48 +94,108 @@ impl LinuxUsbWatcher {
     pub async fn start_monitoring(&self) -> Result<(), String> {
         println!("Starting USB device monitoring on Linux...");
 
+        // Whether the root hubs have been told to leave new devices unauthorised; put back
+        // when monitoring stops so the machine does not keep rejecting new devices
+        let mut default_deny = DefaultDenyGuard {
+            usb_devices_path: self.options.usb_devices_path(),
+            active: false,
+            saved: Vec::new(),
+        };
+
         // Simple polling approach - check /sys/bus/usb/devices periodically
         let mut known_devices: HashMap<String, UsbDeviceInfo> = HashMap::new();
+        // When each known device was first seen, for dwell time on disconnect. Devices found by
+        // the first scan were attached at some unknown earlier time, so they are left out
+        let mut connected_since: HashMap<String, DateTime<Utc>> = HashMap::new();
+        let mut first_scan = true;
 
         loop {
-            match self.scan_usb_devices().await {
+            // Read the policy once per scan, since it can be reloaded while running
+            let policy = self.options.policy.as_ref().map(|policy| {
+                policy
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .clone()
+            });
+
+            // In default-deny mode new devices start unauthorised; allowed ones are authorised below
+            if let Some(policy) = &policy {
+                default_deny.set(policy.default_deny());
+            }
+
+            let started = Instant::now();
+            let scan = self.scan_usb_devices().await;
+            if let Some(metrics) = &self.options.metrics {
+                metrics.observe_scan(started.elapsed());
+            }
+            match scan {
                 Ok(current_devices) => {
                     let current_map: HashMap<String, UsbDeviceInfo> = current_devices
                         .into_iter()
-                        .map(|d| {
-                            let key = format!(
-                                "{}:{}:{}",
-                                d.vendor_id,
-                                d.product_id,
-                                d.serial_number.as_deref().unwrap_or("unknown")
-                            );
-                            (key, d)
-                        })
+                        .map(|d| (d.device_key(), d))
                         .collect();
 
                     // Check for new devices (connected)
+                    let mut connected = Vec::new();
                     for (key, device) in &current_map {
                         if !known_devices.contains_key(key) {
                             let mut device_clone = device.clone();
                             device_clone.event_type = DeviceEventType::Connected;
-                            if let Err(e) = self.tx.send(device_clone).await {
-                                eprintln!("Failed to send device event: {e}");
+                            if !first_scan {
+                                connected_since.insert(key.clone(), device_clone.timestamp);
                             }
+                            connected.push(device_clone);
                         }
                     }
 
                     // Check for removed devices (disconnected)
+                    let mut disconnected = Vec::new();
                     for (key, device) in &known_devices {
                         if !current_map.contains_key(key) {
                             let mut device_clone = device.clone();
-                            device_clone.event_type = DeviceEventType::Disconnected;
-                            if let Err(e) = self.tx.send(device_clone).await {
-                                eprintln!("Failed to send device event: {e}");
-                            }
+                            device_clone.mark_disconnected(connected_since.remove(key));
+                            disconnected.push(device_clone);
                         }
                     }
 
+                    // Devices attached before the watcher started are only evaluated, so starting
+                    // under default-deny does not cut off the keyboard in use
+                    if let Some(policy) = &policy {
+                        for device in &mut connected {
+                            apply_policy(
+                                policy,
+                                device,
+                                self.options.metrics.as_deref(),
+                                !first_scan,
+                            );
+                        }
+                    }
+
+                    // Flag keystroke injectors posing as other devices
+                    let suspicious: Vec<UsbDeviceInfo> = connected
+                        .iter()
+                        .filter_map(UsbDeviceInfo::suspicious_event)
+                        .collect();
+
+                    // Warn about SuperSpeed devices that fell back to a slower link
+                    let degraded: Vec<UsbDeviceInfo> =
+                        connected.iter().filter_map(degraded_event).collect();
+
+                    if self.options.collapse_hubs {
+                        connected = collapse_hub_events(connected);
+                        disconnected = collapse_hub_events(disconnected);
+                    }
+                    for device in connected
+                        .into_iter()
+                        .chain(suspicious)
+                        .chain(degraded)
+                        .chain(disconnected)
+                    {
+                        self.emit(device).await;
+                    }
+
                     known_devices = current_map;
+                    first_scan = false;
                 }
                 Err(e) => {
                     eprintln!("Error scanning USB devices: {e}");
@@ -110,94 +206,540 @@ impl LinuxUsbWatcher {
         }
     }
 
-    async fn scan_usb_devices(&self) -> Result<Vec<UsbDeviceInfo>, String> {
-        let mut devices = Vec::new();
-        let usb_devices_path = Path::new("/sys/bus/usb/devices");
-
-        if !usb_devices_path.exists() {
-            return Err(
-                "USB devices path not found. Make sure you're running on Linux with USB support."
-                    .to_string(),
-            );
+    async fn emit(&self, device: UsbDeviceInfo) {
+        if let Err(e) = self.tx.send(device).await {
+            if let Some(metrics) = &self.options.metrics {
+                metrics.record_dropped(1);
+            }
+            eprintln!("Failed to send device event: {e}");
         }
+    }
 
-        let entries = fs::read_dir(usb_devices_path).map_err(|e| e.to_string())?;
-
-        for entry in entries {
-            let entry = entry.map_err(|e| e.to_string())?;
-            let path = entry.path();
+    /// Lists the currently attached USB devices, skipping entries without a
+    /// vendor or product ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the USB devices directory cannot be read.
+    pub async fn scan_usb_devices(&self) -> Result<Vec<UsbDeviceInfo>, String> {
+        let mut devices = Vec::new();
+        let mut hid_classes = self
+            .hid_classes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        hid_classes.start_scan();
 
-            // Skip entries that don't look like USB devices (e.g., usb1, usb2, etc.)
-            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                // Look for actual USB devices and root hubs, but skip interfaces
-                // USB devices: patterns like "1-1", "1-2", "2-1", etc. (devices connected to ports)
-                // USB root hubs: "usb1", "usb2", etc.
-                // Skip interfaces: "1-0:1.0", "2-0:1.0", etc.
-                let is_device = (name.matches('-').count() == 1 && !name.contains(':'))
-                    || name.starts_with("usb");
-                let is_interface = name.contains(':');
-
-                if is_device && !is_interface {
-                    if let Ok(device_info) = self.parse_usb_device(&path).await {
-                        // Skip devices with all zero VID/PID (typically means no actual device info)
-                        if device_info.vendor_id != "0000" || device_info.product_id != "0000" {
-                            devices.push(device_info);
-                        }
-                    } else {
-                        println!("Failed to parse device: {name}");
-                    }
+        for path in list_device_paths(&self.options.usb_devices_path())? {
+            if let Ok(device_info) = read_device_with(&path, &self.options, &mut hid_classes) {
+                // Skip devices with all zero VID/PID (typically means no actual device info)
+                if device_info.vendor_id != "0000" || device_info.product_id != "0000" {
+                    devices.push(device_info);
                 }
+            } else {
+                println!("Failed to parse device: {}", path.display());
             }
         }
 
         Ok(devices)
     }
+}
 
-    async fn parse_usb_device(&self, device_path: &Path) -> Result<UsbDeviceInfo, String> {
-        let vendor_id = self
-            .read_sys_file(device_path, "idVendor")
-            .unwrap_or_else(|| "0000".to_string());
-        let product_id = self
-            .read_sys_file(device_path, "idProduct")
-            .unwrap_or_else(|| "0000".to_string());
-
-        let product_name = self
-            .read_sys_file(device_path, "product")
-            .unwrap_or_else(|| "Unknown Device".to_string());
-        let manufacturer = self
-            .read_sys_file(device_path, "manufacturer")
-            .unwrap_or_default();
-        let serial_number = self.read_sys_file(device_path, "serial");
-
-        let device_name = if !manufacturer.is_empty() && !product_name.is_empty() {
-            format!("{manufacturer} {product_name}")
-        } else if !product_name.is_empty() {
-            product_name
+#[cfg(target_os = "linux")]
+/// Keeps `authorized_default` on the root hubs in line with the policy and
+/// puts back the values it replaced when dropped.
+struct DefaultDenyGuard {
+    usb_devices_path: PathBuf,
+    active: bool,
+    /// `authorized_default` attributes that were cleared, with their original values
+    saved: Vec<(PathBuf, String)>,
+}
+
+#[cfg(target_os = "linux")]
+impl DefaultDenyGuard {
+    fn set(&mut self, deny: bool) {
+        if deny == self.active {
+            return;
+        }
+        // Not retried on failure, which would repeat the error every scan
+        self.active = deny;
+        if deny {
+            if let Err(e) = self.clear() {
+                eprintln!("Failed to update default authorisation: {e}");
+            }
         } else {
-            "Unknown Device".to_string()
-        };
+            self.restore();
+        }
+    }
 
-        let device_handle = DeviceHandle::Linux {
-            sysfs_path: device_path.to_string_lossy().to_string(),
-            device_node: None, // Could be enhanced to detect device nodes
-        };
+    /// Clears `authorized_default` on every root hub, remembering the values
+    /// it had.
+    fn clear(&mut self) -> Result<(), String> {
+        for path in list_device_paths(&self.usb_devices_path)? {
+            if !is_root_hub(&path) {
+                continue;
+            }
+            let attribute = path.join("authorized_default");
+            // A value that cannot be read could not be put back
+            let original = fs::read_to_string(&attribute)
+                .map_err(|e| format!("Failed to read '{}': {e}", attribute.display()))?;
+            fs::write(&attribute, "0")
+                .map_err(|e| format!("Failed to write '{}': {e}", attribute.display()))?;
+            self.saved.push((attribute, original.trim().to_string()));
+        }
+        Ok(())
+    }
 
-        Ok(UsbDeviceInfo::with_handle(
-            device_name,
-            vendor_id,
-            product_id,
-            serial_number,
-            DeviceEventType::Connected, // Will be updated by caller
-            device_handle,
-        ))
-    }
-    fn read_sys_file(&self, device_path: &Path, filename: &str) -> Option<String> {
-        let file_path = device_path.join(filename);
-        fs::read_to_string(file_path)
+    fn restore(&mut self) {
+        for (attribute, original) in self.saved.drain(..) {
+            if let Err(e) = fs::write(&attribute, &original) {
+                eprintln!(
+                    "Failed to restore default authorisation '{}': {e}",
+                    attribute.display()
+                );
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for DefaultDenyGuard {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
+
+#[cfg(target_os = "linux")]
+/// Evaluates the policy for a newly connected device, enforces it through the
+/// device's `authorized` attribute and records the decision on the event.
+///
+/// Allowed devices are only authorised under default-deny, so devices that
+/// the administrator or another tool deauthorised stay that way. Without
+/// `enforce` the decision is only recorded.
+fn apply_policy(
+    policy: &Policy,
+    device: &mut UsbDeviceInfo,
+    metrics: Option<&Metrics>,
+    enforce: bool,
+) {
+    let mut decision = policy.evaluate(device);
+    if !enforce {
+        device.policy = Some(decision);
+        return;
+    }
+    if let Some(metrics) = metrics {
+        metrics.record_policy(&decision.action);
+    }
+    let authorize = match decision.action {
+        PolicyAction::Block => Some(false),
+        PolicyAction::Allow if policy.default_deny() => Some(true),
+        PolicyAction::Allow | PolicyAction::Log => None,
+    };
+
+    if let (Some(authorize), DeviceHandle::Linux { sysfs_path, .. }) =
+        (authorize, &device.device_handle)
+    {
+        let path = Path::new(sysfs_path);
+        // Deauthorising a root hub would cut off the whole bus
+        if is_root_hub(path) {
+            device.policy = Some(decision);
+            return;
+        }
+        // Only touch the attribute when it needs to change; one that cannot be
+        // read is written anyway, so a failure shows up as an error
+        let current = read_sys_file(path, "authorized").map(|value| value == "1");
+        if current != Some(authorize) {
+            if let Err(e) = write_authorized(path, authorize) {
+                eprintln!(
+                    "Failed to enforce policy for {} ({}): {e}",
+                    device.device_name,
+                    device.device_key()
+                );
+                decision.error = Some(e);
+            }
+        }
+    }
+    device.policy = Some(decision);
+}
+
+#[cfg(target_os = "linux")]
+/// Returns a `Degraded` event for a newly connected device whose link is
+/// slower than it supports.
+fn degraded_event(device: &UsbDeviceInfo) -> Option<UsbDeviceInfo> {
+    let DeviceHandle::Linux { sysfs_path, .. } = &device.device_handle else {
+        return None;
+    };
+    let reason = read_link_info(Path::new(sysfs_path)).degraded_reason()?;
+    Some(device.degraded_event(reason))
+}
+
+#[cfg(target_os = "linux")]
+/// Reads a device's USB version, negotiated speed and SuperSpeed capabilities,
+/// and the speed of the root hub on its bus.
+pub fn read_link_info(device_path: &Path) -> LinkInfo {
+    let speed = |path: &Path| read_sys_file(path, "speed").and_then(|s| s.parse::<f64>().ok());
+    let root_hub = read_sys_file(device_path, "busnum").map(|bus| format!("usb{bus}"));
+    // The root hub is a sibling in /sys/bus/usb/devices, or an ancestor in /sys/devices
+    let root_hub_speed = root_hub.as_ref().and_then(|hub| {
+        device_path
+            .ancestors()
+            .skip(1)
+            .map(|dir| match dir.file_name() {
+                Some(name) if name == hub.as_str() => dir.to_path_buf(),
+                _ => dir.join(hub),
+            })
+            .find(|path| path.is_dir())
+            .and_then(|path| speed(&path))
+    });
+    LinkInfo {
+        version: read_sys_file(device_path, "version"),
+        capability_mbps: read_descriptors(device_path)
             .ok()
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
+            .and_then(|tree| tree.bos)
+            .and_then(|bos| LinkInfo::capability_speed(&bos)),
+        speed_mbps: speed(device_path),
+        root_hub,
+        root_hub_speed_mbps: root_hub_speed,
+        is_hub: is_root_hub(device_path)
+            || read_sys_file(device_path, "bDeviceClass").as_deref() == Some("09"),
+    }
+}
+
+#[cfg(target_os = "linux")]
+/// Authorises or deauthorises a device by writing its `authorized` attribute.
+///
+/// # Errors
+///
+/// Returns an error if the attribute cannot be written (usually because the
+/// process is not running as root).
+pub fn write_authorized(device_path: &Path, authorized: bool) -> Result<(), String> {
+    let path = device_path.join("authorized");
+    fs::write(&path, if authorized { "1" } else { "0" })
+        .map_err(|e| format!("Failed to write '{}': {e}", path.display()))
+}
+
+#[cfg(target_os = "linux")]
+/// Returns whether a sysfs device directory is a root hub (`usb1`, `usb2`, ...).
+fn is_root_hub(device_path: &Path) -> bool {
+    device_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with("usb"))
+}
+
+#[cfg(target_os = "linux")]
+/// Lists the USB device directories under a sysfs `devices` directory,
+/// skipping interfaces.
+///
+/// # Errors
+///
+/// Returns an error if the directory does not exist or cannot be read.
+pub fn list_device_paths(usb_devices_path: &Path) -> Result<Vec<PathBuf>, String> {
+    if !usb_devices_path.exists() {
+        return Err(
+            "USB devices path not found. Make sure you're running on Linux with USB support."
+                .to_string(),
+        );
+    }
+
+    let mut paths = Vec::new();
+    let entries = fs::read_dir(usb_devices_path).map_err(|e| e.to_string())?;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let path = entry.path();
+
+        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+            // Look for actual USB devices and root hubs, but skip interfaces
+            // USB devices: patterns like "1-1", "1-2", "2-1", etc. (devices connected to ports)
+            // USB root hubs: "usb1", "usb2", etc.
+            // Skip interfaces: "1-0:1.0", "2-0:1.0", etc.
+            let is_device =
+                (name.matches('-').count() == 1 && !name.contains(':')) || name.starts_with("usb");
+            let is_interface = name.contains(':');
+
+            if is_device && !is_interface {
+                paths.push(path);
+            }
+        }
+    }
+
+    paths.sort();
+    Ok(paths)
+}
+
+#[cfg(target_os = "linux")]
+/// Reads a single USB device from its sysfs directory
+/// (e.g. `/sys/bus/usb/devices/1-1.2`).
+///
+/// The returned record has event type `Connected`. Names and class names
+/// are resolved when `options.usb_ids` is set, and the raw descriptors are
+/// decoded when `options.descriptors` is set.
+///
+/// # Errors
+///
+/// Returns an error if the directory does not exist or its descriptors
+/// cannot be decoded.
+pub fn read_device(device_path: &Path, options: &WatcherOptions) -> Result<UsbDeviceInfo, String> {
+    read_device_with(device_path, options, &mut HidClassCache::default())
+}
+
+#[cfg(target_os = "linux")]
+fn read_device_with(
+    device_path: &Path,
+    options: &WatcherOptions,
+    hid_classes: &mut HidClassCache,
+) -> Result<UsbDeviceInfo, String> {
+    if !device_path.is_dir() {
+        return Err(format!("USB device '{}' not found", device_path.display()));
+    }
+
+    let vendor_id = read_sys_file(device_path, "idVendor").unwrap_or_else(|| "0000".to_string());
+    let product_id = read_sys_file(device_path, "idProduct").unwrap_or_else(|| "0000".to_string());
+
+    let product_name =
+        read_sys_file(device_path, "product").unwrap_or_else(|| "Unknown Device".to_string());
+    let manufacturer = read_sys_file(device_path, "manufacturer").unwrap_or_default();
+    let serial_number = read_sys_file(device_path, "serial");
+
+    let device_name = if !manufacturer.is_empty() && !product_name.is_empty() {
+        format!("{manufacturer} {product_name}")
+    } else if !product_name.is_empty() {
+        product_name
+    } else {
+        "Unknown Device".to_string()
+    };
+
+    let device_handle = DeviceHandle::Linux {
+        sysfs_path: device_path.to_string_lossy().to_string(),
+        device_node: None, // Could be enhanced to detect device nodes
+    };
+
+    let device_class = read_class_code(
+        device_path,
+        ["bDeviceClass", "bDeviceSubClass", "bDeviceProtocol"],
+    );
+
+    let mut device_info = UsbDeviceInfo::with_handle(
+        device_name,
+        vendor_id,
+        product_id,
+        serial_number,
+        DeviceEventType::Connected, // Will be updated by caller
+        device_handle,
+    );
+    // The sysfs directory name is the port path (e.g. "1-1.2", or "usb1" for a root hub)
+    device_info.port_path = device_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string());
+    device_info.device_class = device_class;
+    device_info.interfaces = read_interfaces(device_path, hid_classes);
+    if device_info.interfaces.is_empty() {
+        // Unauthorised devices are never configured, so have no interface
+        // directories; the kernel still reads their descriptors
+        device_info.interfaces = descriptor_interfaces(device_path);
     }
+
+    if let Some(usb_ids) = &options.usb_ids {
+        usb_ids.resolve(&mut device_info);
+        device_info.class_name = device_class
+            .and_then(|code| usb_ids.class_name(code.class, code.subclass, code.protocol));
+        for interface in &mut device_info.interfaces {
+            let code = interface.class;
+            interface.class_name = usb_ids.class_name(code.class, code.subclass, code.protocol);
+        }
+    }
+    if options.descriptors {
+        device_info.descriptors = Some(read_descriptors(device_path)?);
+    }
+    Ok(device_info)
+}
+
+#[cfg(target_os = "linux")]
+/// Lists the interfaces of a device's first configuration from its
+/// descriptors, for devices that have not been configured.
+fn descriptor_interfaces(device_path: &Path) -> Vec<UsbInterfaceInfo> {
+    let Some(port) = device_path.file_name().map(|name| name.to_string_lossy()) else {
+        return Vec::new();
+    };
+    let Some(config) = read_descriptors(device_path)
+        .ok()
+        .and_then(|tree| tree.configurations.into_iter().next())
+    else {
+        return Vec::new();
+    };
+    config
+        .interfaces
+        .iter()
+        .filter(|interface| interface.alternate_setting == 0)
+        .map(|interface| UsbInterfaceInfo {
+            id: format!("{port}:{}.{}", config.value, interface.number),
+            number: interface.number,
+            class: interface.class,
+            class_name: None,
+            driver: None,
+            hid_classes: Vec::new(),
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+/// Decodes a device's `descriptors` file, adding `bos_descriptors` where the
+/// kernel provides it.
+///
+/// # Errors
+///
+/// Returns an error if the descriptors cannot be read or decoded.
+pub fn read_descriptors(device_path: &Path) -> Result<DescriptorTree, String> {
+    let path = device_path.join("descriptors");
+    let data = fs::read(&path)
+        .map_err(|e| format!("Failed to read descriptors '{}': {e}", path.display()))?;
+    let mut tree = DescriptorTree::parse(&data)?;
+    if tree.bos.is_none() {
+        if let Ok(bos) = fs::read(device_path.join("bos_descriptors")) {
+            tree.bos = Some(BosDescriptor::parse(&bos)?);
+        }
+    }
+    Ok(tree)
+}
+
+#[cfg(target_os = "linux")]
+/// Reads the interfaces of the active configuration.
+///
+/// Interfaces appear as subdirectories named `<port>:<config>.<interface>`
+/// (e.g. "1-1.2:1.0").
+fn read_interfaces(device_path: &Path, hid_classes: &mut HidClassCache) -> Vec<UsbInterfaceInfo> {
+    let Ok(entries) = fs::read_dir(device_path) else {
+        return Vec::new();
+    };
+
+    let mut interfaces: Vec<UsbInterfaceInfo> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let id = path.file_name()?.to_str()?.to_string();
+            if !id.contains(':') {
+                return None;
+            }
+            let class = read_class_code(
+                &path,
+                [
+                    "bInterfaceClass",
+                    "bInterfaceSubClass",
+                    "bInterfaceProtocol",
+                ],
+            )?;
+            let number = read_sys_file(&path, "bInterfaceNumber")
+                .and_then(|n| u8::from_str_radix(&n, 16).ok())
+                .unwrap_or_default();
+            let driver = fs::read_link(path.join("driver")).ok().and_then(|target| {
+                target
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+            });
+            let hid_classes = if class.usb_class() == UsbClass::Hid {
+                read_hid_classes(&path, hid_classes)
+            } else {
+                Vec::new()
+            };
+            Some(UsbInterfaceInfo {
+                id,
+                number,
+                class,
+                class_name: None,
+                driver,
+                hid_classes,
+            })
+        })
+        .collect();
+    interfaces.sort_by(|a, b| a.id.cmp(&b.id));
+    interfaces
+}
+
+#[cfg(target_os = "linux")]
+/// HID classes of the HID devices seen by the last two scans, keyed by their
+/// sysfs directory.
+///
+/// The directory name ends in an instance number the kernel never reuses
+/// (e.g. "0003:046D:C52B.0001"), so a report descriptor is read and parsed
+/// once per device rather than on every scan.
+#[derive(Default)]
+struct HidClassCache {
+    previous: HashMap<PathBuf, Vec<HidClass>>,
+    current: HashMap<PathBuf, Vec<HidClass>>,
+}
+
+#[cfg(target_os = "linux")]
+impl HidClassCache {
+    /// Forgets the devices that were not seen by the last scan.
+    fn start_scan(&mut self) {
+        self.previous = std::mem::take(&mut self.current);
+    }
+
+    fn classes(&mut self, hid_path: PathBuf) -> &[HidClass] {
+        let classes = match self.previous.remove(&hid_path) {
+            Some(classes) => classes,
+            None => classify_hid_device(&hid_path),
+        };
+        self.current.entry(hid_path).or_insert(classes)
+    }
+}
+
+#[cfg(target_os = "linux")]
+/// Classifies a HID interface from the `report_descriptor` of each HID device
+/// bound below it (e.g. "1-1:1.0/0003:046D:C52B.0001/report_descriptor").
+fn read_hid_classes(interface_path: &Path, cache: &mut HidClassCache) -> Vec<HidClass> {
+    let Ok(entries) = fs::read_dir(interface_path) else {
+        return Vec::new();
+    };
+
+    let mut classes = Vec::new();
+    for entry in entries.flatten() {
+        if !entry.path().join("report_descriptor").is_file() {
+            continue;
+        }
+        for class in cache.classes(entry.path()) {
+            if !classes.contains(class) {
+                classes.push(*class);
+            }
+        }
+    }
+    classes
+}
+
+#[cfg(target_os = "linux")]
+/// Classifies one HID device from its `report_descriptor`; a descriptor that
+/// cannot be parsed is reported and yields no classes.
+fn classify_hid_device(hid_path: &Path) -> Vec<HidClass> {
+    let Ok(descriptor) = fs::read(hid_path.join("report_descriptor")) else {
+        return Vec::new();
+    };
+    classify_report_descriptor(&descriptor).unwrap_or_else(|e| {
+        eprintln!(
+            "Failed to parse HID report descriptor of {}: {e}",
+            hid_path.display()
+        );
+        Vec::new()
+    })
+}
+
+#[cfg(target_os = "linux")]
+/// Reads a class/subclass/protocol triple from three hexadecimal sysfs attributes.
+fn read_class_code(path: &Path, files: [&str; 3]) -> Option<ClassCode> {
+    let code = |file| read_sys_file(path, file).and_then(|v| u8::from_str_radix(&v, 16).ok());
+    Some(ClassCode::new(
+        code(files[0])?,
+        code(files[1])?,
+        code(files[2])?,
+    ))
+}
+
+#[cfg(target_os = "linux")]
+fn read_sys_file(device_path: &Path, filename: &str) -> Option<String> {
+    let file_path = device_path.join(filename);
+    fs::read_to_string(file_path)
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
 }
 
 #[cfg(not(target_os = "linux"))]
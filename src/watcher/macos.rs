@@ -78,8 +78,25 @@ impl MacosUsbWatcher {
                     vendor_id: "unknown".to_string(),
                     product_id: "unknown".to_string(),
                     serial_number: None,
+                    vendor_name: None,
+                    product_name: None,
+                    class_name: None,
+                    device_class: None,
+                    interfaces: Vec::new(),
+                    descriptors: None,
                     timestamp: chrono::Utc::now(),
+                    monotonic_ns: crate::metadata::monotonic_now_ns(),
                     event_type: DeviceEventType::Connected,
+                    connected_at: None,
+                    duration: None,
+                    flap_count: None,
+                    port_path: None,
+                    policy: None,
+                    baseline: None,
+                    metadata: None,
+                    origin: None,
+                    reason: None,
+                    children: Vec::new(),
                     device_handle: DeviceHandle::Macos {
                         device_id: format!("{device}"),
                     },
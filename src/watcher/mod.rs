@@ -16,8 +16,53 @@ pub mod windows;
 pub mod macos;
 
 use crate::device_info::UsbDeviceInfo;
+use crate::metrics::Metrics;
+use crate::policy::Policy;
+use crate::usb_ids::UsbIds;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
 use tokio::sync::mpsc;
 
+/// Options controlling how the platform watchers detect and report events.
+///
+/// Options that a platform does not support are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct WatcherOptions {
+    /// Report a hub and the devices behind it that changed state in the same
+    /// scan as a single event on the hub, with the devices listed as children
+    /// (Linux only)
+    pub collapse_hubs: bool,
+    /// Database used to fill in vendor, product and class names (Linux only)
+    pub usb_ids: Option<Arc<UsbIds>>,
+    /// Decode the raw descriptors of each device and attach them to its
+    /// events (Linux only)
+    pub descriptors: bool,
+    /// Authorisation policy to evaluate and enforce for connected devices
+    /// (enforced on Linux only); replacing the policy behind the lock takes
+    /// effect from the next scan
+    pub policy: Option<Arc<RwLock<Policy>>>,
+    /// Root of the sysfs filesystem, defaulting to [`DEFAULT_SYSFS_ROOT`];
+    /// overridable so the Linux watcher can run against a test directory
+    pub sysfs_root: Option<PathBuf>,
+    /// Metrics to record scan durations and undeliverable events in (Linux only)
+    pub metrics: Option<Arc<Metrics>>,
+}
+
+/// Default mount point of sysfs.
+pub const DEFAULT_SYSFS_ROOT: &str = "/sys";
+
+impl WatcherOptions {
+    /// Returns the directory in which sysfs lists USB devices and interfaces
+    /// (`/sys/bus/usb/devices` by default).
+    pub fn usb_devices_path(&self) -> PathBuf {
+        self.sysfs_root
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_SYSFS_ROOT))
+            .join("bus/usb/devices")
+    }
+}
+
 /// Cross-platform USB device watcher.
 ///
 /// This enum provides a unified interface for USB monitoring across
@@ -63,6 +108,42 @@ impl UsbWatcher {
     /// # }
     /// ```
     pub fn new(sender: mpsc::Sender<UsbDeviceInfo>) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_options(sender, WatcherOptions::default())
+    }
+
+    /// Creates a new USB watcher for the current platform with the given options.
+    ///
+    /// # Arguments
+    ///
+    /// * `sender` - Channel sender for publishing device events
+    /// * `options` - Watcher behaviour options
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the platform-specific watcher cannot be initialised.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use usbwatch_rs::watcher::{UsbWatcher, WatcherOptions};
+    /// use tokio::sync::mpsc;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let (tx, rx) = mpsc::channel(100);
+    /// let options = WatcherOptions {
+    ///     collapse_hubs: true,
+    ///     ..Default::default()
+    /// };
+    /// let watcher = UsbWatcher::with_options(tx, options)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[allow(unused_variables)]
+    pub fn with_options(
+        sender: mpsc::Sender<UsbDeviceInfo>,
+        options: WatcherOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         #[cfg(target_os = "windows")]
         {
             let watcher = windows::WindowsUsbWatcher::new(sender);
@@ -71,7 +152,7 @@ impl UsbWatcher {
 
         #[cfg(target_os = "linux")]
         {
-            let watcher = linux::LinuxUsbWatcher::new(sender);
+            let watcher = linux::LinuxUsbWatcher::with_options(sender, options);
             Ok(UsbWatcher::Linux(watcher))
         }
 
@@ -144,4 +225,107 @@ impl UsbWatcher {
             UsbWatcher::Unsupported => Err("USB monitoring not supported on this platform".into()),
         }
     }
+
+    /// Lists the currently attached USB devices without emitting events.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the devices cannot be enumerated, or on platforms
+    /// without device enumeration (macOS).
+    pub async fn scan_devices(&self) -> Result<Vec<UsbDeviceInfo>, Box<dyn std::error::Error>> {
+        match self {
+            #[cfg(target_os = "windows")]
+            UsbWatcher::Windows(watcher) => Ok(watcher.scan_usb_devices().await?),
+            #[cfg(target_os = "linux")]
+            UsbWatcher::Linux(watcher) => Ok(watcher.scan_usb_devices().await?),
+            #[cfg(target_os = "macos")]
+            UsbWatcher::Macos(_) => Err("Device scanning is not supported on macOS".into()),
+            #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+            UsbWatcher::Unsupported => Err("USB monitoring not supported on this platform".into()),
+        }
+    }
+}
+
+/// Returns the port path of the hub a device is attached to.
+///
+/// `"1-1.2.3"` is behind `"1-1.2"`, `"1-1"` is behind the root hub `"usb1"`,
+/// and root hubs have no parent.
+fn parent_port(port_path: &str) -> Option<String> {
+    if let Some((parent, _)) = port_path.rsplit_once('.') {
+        return Some(parent.to_string());
+    }
+    let (bus, _) = port_path.split_once('-')?;
+    Some(format!("usb{bus}"))
+}
+
+/// Groups events from the same scan by port-path hierarchy.
+///
+/// Every event whose port path lies behind another event's port path in the
+/// same batch is moved into the `children` of the top-most such event, so
+/// unplugging a hub yields one event for the hub listing the devices behind it.
+/// Events without a port path are left untouched.
+///
+/// # Examples
+///
+/// ```
+/// use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+/// use usbwatch_rs::watcher::collapse_hub_events;
+///
+/// let device = |name: &str, port: &str| {
+///     let mut info = UsbDeviceInfo::new(
+///         name.to_string(),
+///         "05e3".to_string(),
+///         "0610".to_string(),
+///         None,
+///         DeviceEventType::Disconnected,
+///     );
+///     info.port_path = Some(port.to_string());
+///     info
+/// };
+///
+/// let events = collapse_hub_events(vec![
+///     device("Keyboard", "1-2.1"),
+///     device("Hub", "1-2"),
+///     device("Mouse", "1-2.3"),
+/// ]);
+/// assert_eq!(events.len(), 1);
+/// assert_eq!(events[0].device_name, "Hub");
+/// assert_eq!(events[0].children.len(), 2);
+/// ```
+pub fn collapse_hub_events(events: Vec<UsbDeviceInfo>) -> Vec<UsbDeviceInfo> {
+    let ports: HashSet<String> = events.iter().filter_map(|e| e.port_path.clone()).collect();
+
+    // Find the top-most ancestor of each event that is also in the batch
+    let root_of = |port_path: &str| {
+        let mut root = None;
+        let mut current = parent_port(port_path);
+        while let Some(port) = current {
+            if ports.contains(&port) {
+                root = Some(port.clone());
+            }
+            current = parent_port(&port);
+        }
+        root
+    };
+
+    let mut roots = Vec::new();
+    let mut children: HashMap<String, Vec<UsbDeviceInfo>> = HashMap::new();
+    for event in events {
+        match event.port_path.as_deref().and_then(root_of) {
+            Some(root) => children.entry(root).or_default().push(event),
+            None => roots.push(event),
+        }
+    }
+
+    for root in &mut roots {
+        if let Some(mut grouped) = root
+            .port_path
+            .as_ref()
+            .and_then(|port| children.remove(port))
+        {
+            grouped.sort_by(|a, b| a.port_path.cmp(&b.port_path));
+            root.children.extend(grouped);
+        }
+    }
+    roots
 }
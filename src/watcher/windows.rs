@@ -6,7 +6,9 @@
 #[cfg(target_os = "windows")]
 use crate::device_info::{DeviceEventType, DeviceHandle, UsbDeviceInfo};
 #[cfg(target_os = "windows")]
-use std::collections::HashSet;
+use chrono::{DateTime, Utc};
+#[cfg(target_os = "windows")]
+use std::collections::{HashMap, HashSet};
 #[cfg(target_os = "windows")]
 use tokio::sync::mpsc;
 #[cfg(target_os = "windows")]
@@ -28,7 +30,10 @@ impl WindowsUsbWatcher {
 
         // For this implementation, we'll use a simple polling approach
         // In a production environment, you'd want to use proper Windows notifications
-        let mut known_devices = HashSet::new();
+        // Known devices and when they were first seen, for dwell time on disconnect;
+        // unknown for devices that were already attached at the first scan
+        let mut known_devices: HashMap<String, Option<DateTime<Utc>>> = HashMap::new();
+        let mut first_scan = true;
 
         loop {
             match self.scan_usb_devices().await {
@@ -36,8 +41,11 @@ impl WindowsUsbWatcher {
                     // Check for new devices (connected)
                     for device in &current_devices {
                         let device_key = format!("{}:{}", device.vendor_id, device.product_id);
-                        if !known_devices.contains(&device_key) {
-                            known_devices.insert(device_key.clone());
+                        if !known_devices.contains_key(&device_key) {
+                            known_devices.insert(
+                                device_key.clone(),
+                                (!first_scan).then_some(device.timestamp),
+                            );
                             let mut device_clone = device.clone();
                             device_clone.event_type = DeviceEventType::Connected;
                             if let Err(e) = self.tx.send(device_clone).await {
@@ -52,25 +60,30 @@ impl WindowsUsbWatcher {
                         .map(|d| format!("{}:{}", d.vendor_id, d.product_id))
                         .collect();
 
-                    let removed_keys: Vec<String> =
-                        known_devices.difference(&current_keys).cloned().collect();
+                    let removed_keys: Vec<String> = known_devices
+                        .keys()
+                        .filter(|key| !current_keys.contains(*key))
+                        .cloned()
+                        .collect();
 
                     for key in removed_keys {
-                        known_devices.remove(&key);
+                        let connected_at = known_devices.remove(&key).flatten();
                         let parts: Vec<&str> = key.split(':').collect();
                         if parts.len() == 2 {
-                            let device_info = UsbDeviceInfo::new(
+                            let mut device_info = UsbDeviceInfo::new(
                                 "Unknown Device".to_string(),
                                 parts[0].to_string(),
                                 parts[1].to_string(),
                                 None,
                                 DeviceEventType::Disconnected,
                             );
+                            device_info.mark_disconnected(connected_at);
                             if let Err(e) = self.tx.send(device_info).await {
                                 eprintln!("Failed to send device event: {}", e);
                             }
                         }
                     }
+                    first_scan = false;
                 }
                 Err(e) => {
                     eprintln!("Error scanning USB devices: {}", e);
@@ -81,7 +94,8 @@ impl WindowsUsbWatcher {
         }
     }
 
-    async fn scan_usb_devices(&self) -> std::result::Result<Vec<UsbDeviceInfo>, String> {
+    /// Lists the currently attached USB devices.
+    pub async fn scan_usb_devices(&self) -> std::result::Result<Vec<UsbDeviceInfo>, String> {
         let mut devices = Vec::new();
 
         unsafe {
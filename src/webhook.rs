@@ -0,0 +1,661 @@
+//! Webhook notifications.
+//!
+//! A [`WebhookConfig`] loaded from JSON names HTTP targets and the rules that route events to them. Each matching
+//! event is posted to the target as a JSON body, rendered from the target's `template` (the event itself if none is
+//! given), with the target's headers and, if it has a secret, an HMAC-SHA256 signature of the body:
+//!
+//! ```json
+//! {
+//!   "targets": {
+//!     "chat": {
+//!       "url": "https://chat.example.com/hooks/T000/B000",
+//!       "template": { "text": "USB {{event_type}}: {{device_name}} ({{vendor_id}}:{{product_id}}) on {{metadata.hostname}}" }
+//!     },
+//!     "tickets": {
+//!       "url": "https://tickets.example.com/api/usb",
+//!       "headers": { "Authorization": "Bearer 0123456789" },
+//!       "secret_file": "/etc/usbwatch/tickets.secret",
+//!       "template": { "summary": "Blocked {{device_name}}", "device": "{{event}}", "rule": "{{policy.rule}}" }
+//!     }
+//!   },
+//!   "rules": [
+//!     { "name": "storage", "match": { "classes": ["mass-storage"] }, "targets": ["chat"] },
+//!     { "name": "blocked", "policy_action": "block", "targets": ["chat", "tickets"] }
+//!   ],
+//!   "queue_dir": "/var/lib/usbwatch/webhooks"
+//! }
+//! ```
+//!
+//! A placeholder that makes up a whole string is replaced by the JSON value it names (`{{event}}` is the whole event),
+//! and one inside a longer string by its text; fields that are not set render as `null` or an empty string. Without
+//! rules, every event goes to every target.
+//!
+//! Deliveries to each target are sent in order. Timeouts, connection errors and 408, 429 and 5xx responses are retried
+//! with exponential backoff, so a target that is down holds back later deliveries until it recovers; other responses
+//! drop the delivery. With `queue_dir`, pending deliveries are kept on disk and sent after a restart.
+
+use crate::audit::to_hex;
+use crate::device_info::UsbDeviceInfo;
+use crate::filter::EventFilter;
+use crate::policy::PolicyAction;
+use crate::template::render_value;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::{Client, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+/// Header carrying the body signature, `sha256=<hex HMAC-SHA256 of the body>`.
+pub const SIGNATURE_HEADER: &str = "X-Usbwatch-Signature";
+
+/// An HTTP endpoint that receives events.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookTarget {
+    /// URL the payload is posted to
+    pub url: String,
+    /// Extra request headers; `Content-Type` defaults to `application/json`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// Key used to sign the body
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// File whose first line is the signing key, read when the configuration is loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_file: Option<PathBuf>,
+    /// JSON payload with `{{field}}` placeholders; defaults to the event
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<Value>,
+    /// Request timeout in seconds
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    10
+}
+
+impl Default for WebhookTarget {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            headers: BTreeMap::new(),
+            secret: None,
+            secret_file: None,
+            template: None,
+            timeout_secs: default_timeout_secs(),
+        }
+    }
+}
+
+/// Routes events matching its criteria to a list of targets.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookRule {
+    /// Name used in error messages; defaults to the rule's position
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Events the rule applies to; matches every event if empty
+    #[serde(default, rename = "match")]
+    pub filter: EventFilter,
+    /// Only apply to events with a device that the policy decided this action for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_action: Option<PolicyAction>,
+    /// Names of the targets that receive matching events
+    pub targets: Vec<String>,
+}
+
+impl WebhookRule {
+    /// Returns whether the rule applies to `event`.
+    pub fn matches(&self, event: &UsbDeviceInfo) -> bool {
+        if !self.filter.matches(event) {
+            return false;
+        }
+        let Some(action) = self.policy_action else {
+            return true;
+        };
+        std::iter::once(event).chain(&event.children).any(|device| {
+            device
+                .policy
+                .as_ref()
+                .is_some_and(|decision| decision.action == action)
+        })
+    }
+}
+
+/// Retry schedule for failed deliveries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    /// Delay before the first retry, in milliseconds; doubled for each further retry
+    pub initial_backoff_ms: u64,
+    /// Upper limit of the delay, in milliseconds
+    pub max_backoff_ms: u64,
+    /// Attempts after which a delivery is dropped; retried until it succeeds if not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff_ms: 1_000,
+            max_backoff_ms: 300_000,
+            max_attempts: None,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Returns the delay after the given number of failed attempts.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use usbwatch_rs::webhook::RetryConfig;
+    ///
+    /// let retry = RetryConfig::default();
+    /// assert_eq!(retry.backoff(1), Duration::from_secs(1));
+    /// assert_eq!(retry.backoff(4), Duration::from_secs(8));
+    /// assert_eq!(retry.backoff(30), Duration::from_secs(300));
+    /// ```
+    pub fn backoff(&self, attempts: u32) -> Duration {
+        let factor = 1u64
+            .checked_shl(attempts.saturating_sub(1))
+            .unwrap_or(u64::MAX);
+        Duration::from_millis(
+            self.initial_backoff_ms
+                .saturating_mul(factor)
+                .min(self.max_backoff_ms),
+        )
+    }
+}
+
+/// Webhook targets, routing rules and delivery settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    /// Targets by name
+    pub targets: BTreeMap<String, WebhookTarget>,
+    /// Routing rules; every event goes to every target if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<WebhookRule>,
+    /// Retry schedule
+    #[serde(default)]
+    pub retry: RetryConfig,
+    /// Directory in which pending deliveries are kept, one subdirectory per target
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_dir: Option<PathBuf>,
+    /// Pending deliveries kept per target; the oldest are dropped beyond this
+    #[serde(default = "default_max_queue")]
+    pub max_queue: usize,
+}
+
+fn default_max_queue() -> usize {
+    10_000
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            targets: BTreeMap::new(),
+            rules: Vec::new(),
+            retry: RetryConfig::default(),
+            queue_dir: None,
+            max_queue: default_max_queue(),
+        }
+    }
+}
+
+impl WebhookConfig {
+    /// Parses and validates a configuration from JSON. Secret files are not
+    /// read; see [`WebhookConfig::load`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JSON is invalid, a target name, URL or header
+    /// is invalid, or a rule names an unknown target.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let config: Self = serde_json::from_str(json)
+            .map_err(|e| format!("Invalid webhook configuration: {e}"))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Loads a configuration from a JSON file and reads the targets' secret files.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file cannot be read or the configuration is invalid.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path).map_err(|e| {
+            format!(
+                "Failed to read webhook configuration '{}': {e}",
+                path.display()
+            )
+        })?;
+        let mut config = Self::from_json(&json)?;
+        for target in config.targets.values_mut() {
+            if let Some(secret_file) = &target.secret_file {
+                let secret = fs::read_to_string(secret_file).map_err(|e| {
+                    format!("Failed to read secret '{}': {e}", secret_file.display())
+                })?;
+                target.secret = Some(secret.lines().next().unwrap_or_default().to_string());
+            }
+        }
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (name, target) in &self.targets {
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(format!(
+                    "Invalid webhook target name '{name}': use letters, digits, '-' and '_'"
+                ));
+            }
+            Url::parse(&target.url)
+                .map_err(|e| format!("Invalid URL for webhook target '{name}': {e}"))?;
+            for (header, value) in &target.headers {
+                HeaderName::from_bytes(header.as_bytes())
+                    .map_err(|e| format!("Invalid header '{header}' for target '{name}': {e}"))?;
+                HeaderValue::from_str(value).map_err(|e| {
+                    format!("Invalid value of header '{header}' for target '{name}': {e}")
+                })?;
+            }
+            if target.secret.is_some() && target.secret_file.is_some() {
+                return Err(format!(
+                    "Webhook target '{name}' has both secret and secret_file"
+                ));
+            }
+        }
+        for (index, rule) in self.rules.iter().enumerate() {
+            for target in &rule.targets {
+                if !self.targets.contains_key(target) {
+                    let rule = rule
+                        .name
+                        .clone()
+                        .unwrap_or_else(|| format!("#{}", index + 1));
+                    return Err(format!(
+                        "Webhook rule '{rule}' names unknown target '{target}'"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the names of the targets that should receive `event`, each once.
+    pub fn targets_for(&self, event: &UsbDeviceInfo) -> Vec<&str> {
+        if self.rules.is_empty() {
+            return self.targets.keys().map(String::as_str).collect();
+        }
+        let mut targets: Vec<&str> = Vec::new();
+        for rule in self.rules.iter().filter(|rule| rule.matches(event)) {
+            for target in &rule.targets {
+                if !targets.contains(&target.as_str()) {
+                    targets.push(target);
+                }
+            }
+        }
+        targets
+    }
+}
+
+/// Renders a payload template for `event`.
+///
+/// # Examples
+///
+/// ```
+/// use serde_json::json;
+/// use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+/// use usbwatch_rs::webhook::render_template;
+///
+/// let device = UsbDeviceInfo::new(
+///     "USB Storage".to_string(),
+///     "0781".to_string(),
+///     "5583".to_string(),
+///     None,
+///     DeviceEventType::Connected,
+/// );
+/// let template = json!({
+///     "text": "{{device_name}} {{event_type}} ({{vendor_id}}:{{product_id}})",
+///     "serial": "{{serial_number}}",
+/// });
+/// assert_eq!(
+///     render_template(&template, &device),
+///     json!({ "text": "USB Storage Connected (0781:5583)", "serial": null })
+/// );
+/// ```
+pub fn render_template(template: &Value, event: &UsbDeviceInfo) -> Value {
+    let event = serde_json::to_value(event).unwrap_or(Value::Null);
+    render_value(template, &event)
+}
+
+/// Returns the value of the [`SIGNATURE_HEADER`] for `body` signed with `secret`.
+///
+/// # Examples
+///
+/// ```
+/// use usbwatch_rs::webhook::sign;
+///
+/// assert_eq!(
+///     sign("key", b"The quick brown fox jumps over the lazy dog"),
+///     "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+/// );
+/// ```
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={}", to_hex(&mac.finalize().into_bytes()))
+}
+
+/// A rendered payload waiting to be sent to one target.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Delivery {
+    id: String,
+    body: String,
+    attempts: u32,
+}
+
+/// Running webhook dispatcher with one delivery worker per target.
+///
+/// Cloning is cheap; all clones feed the same workers.
+#[derive(Clone)]
+pub struct Webhooks {
+    config: Arc<WebhookConfig>,
+    workers: HashMap<String, mpsc::UnboundedSender<Delivery>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Webhooks {
+    /// Starts a worker for each target, first queueing the deliveries left
+    /// in `queue_dir` by a previous run.
+    ///
+    /// Must be called from within a Tokio runtime.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the queue directory cannot be created or read, or
+    /// an HTTP client cannot be built.
+    pub fn start(config: WebhookConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let config = Arc::new(config);
+        let mut workers = HashMap::new();
+        for (name, target) in &config.targets {
+            let client = Client::builder()
+                .timeout(Duration::from_secs(target.timeout_secs))
+                .build()
+                .map_err(|e| format!("Failed to create HTTP client for '{name}': {e}"))?;
+            let queue_dir = config.queue_dir.as_ref().map(|dir| dir.join(name));
+            let pending = match &queue_dir {
+                Some(dir) => load_queue(dir)?,
+                None => VecDeque::new(),
+            };
+            let (tx, rx) = mpsc::unbounded_channel();
+            let worker = Worker {
+                name: name.clone(),
+                target: target.clone(),
+                retry: config.retry,
+                max_queue: config.max_queue,
+                queue_dir,
+                client,
+                queue: pending,
+            };
+            tokio::spawn(worker.run(rx));
+            workers.insert(name.clone(), tx);
+        }
+        Ok(Self {
+            config,
+            workers,
+            next_id: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    /// Queues `event` for every target whose rules match it.
+    pub fn dispatch(&self, event: &UsbDeviceInfo) {
+        for name in self.config.targets_for(event) {
+            let (Some(target), Some(worker)) =
+                (self.config.targets.get(name), self.workers.get(name))
+            else {
+                continue;
+            };
+            let payload = match &target.template {
+                Some(template) => render_template(template, event),
+                None => serde_json::to_value(event).unwrap_or(Value::Null),
+            };
+            let delivery = Delivery {
+                // Sortable, so a restarted worker sends queued deliveries in order
+                id: format!(
+                    "{:020}-{:06}",
+                    chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default(),
+                    self.next_id.fetch_add(1, Ordering::Relaxed) % 1_000_000
+                ),
+                body: payload.to_string(),
+                attempts: 0,
+            };
+            if worker.send(delivery).is_err() {
+                eprintln!("Webhook worker for '{name}' has stopped");
+            }
+        }
+    }
+}
+
+/// Async task that queues each event from `rx` for its webhook targets and forwards it to `tx`.
+///
+/// The task ends when `rx` closes or `tx` is dropped.
+pub async fn webhook_task(
+    mut rx: mpsc::Receiver<UsbDeviceInfo>,
+    tx: mpsc::Sender<UsbDeviceInfo>,
+    webhooks: Webhooks,
+) {
+    while let Some(event) = rx.recv().await {
+        webhooks.dispatch(&event);
+        if tx.send(event).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Reads the deliveries queued in `dir`, oldest first.
+fn load_queue(dir: &Path) -> Result<VecDeque<Delivery>, String> {
+    fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create webhook queue '{}': {e}", dir.display()))?;
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read webhook queue '{}': {e}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    let mut queue = VecDeque::new();
+    for path in paths {
+        match fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+        {
+            Ok(delivery) => queue.push_back(delivery),
+            Err(e) => eprintln!("Skipping queued webhook '{}': {e}", path.display()),
+        }
+    }
+    Ok(queue)
+}
+
+/// Why a delivery failed.
+enum Failure {
+    /// Worth trying again later
+    Retry(String),
+    /// Will fail again; the delivery is dropped
+    Permanent(String),
+}
+
+/// Sends the deliveries for one target in order.
+struct Worker {
+    name: String,
+    target: WebhookTarget,
+    retry: RetryConfig,
+    max_queue: usize,
+    queue_dir: Option<PathBuf>,
+    client: Client,
+    queue: VecDeque<Delivery>,
+}
+
+impl Worker {
+    async fn run(mut self, mut rx: mpsc::UnboundedReceiver<Delivery>) {
+        let mut open = true;
+        loop {
+            if self.queue.is_empty() {
+                match rx.recv().await {
+                    Some(delivery) => self.enqueue(delivery),
+                    None => return,
+                }
+            }
+            while let Ok(delivery) = rx.try_recv() {
+                self.enqueue(delivery);
+            }
+            let Some(body) = self.queue.front().map(|delivery| delivery.body.clone()) else {
+                continue;
+            };
+
+            let failure = match self.send(&body).await {
+                Ok(()) => {
+                    self.finish();
+                    continue;
+                }
+                Err(Failure::Permanent(e)) => {
+                    eprintln!("Dropping webhook for '{}': {e}", self.name);
+                    self.finish();
+                    continue;
+                }
+                Err(Failure::Retry(e)) => e,
+            };
+            let delivery = self.queue.front_mut().expect("delivery is queued");
+            delivery.attempts += 1;
+            let attempts = delivery.attempts;
+            if self.retry.max_attempts.is_some_and(|max| attempts >= max) {
+                eprintln!(
+                    "Dropping webhook for '{}' after {attempts} attempts: {failure}",
+                    self.name
+                );
+                self.finish();
+                continue;
+            }
+            let delay = self.retry.backoff(attempts);
+            eprintln!(
+                "Failed to send webhook to '{}' (attempt {attempts}), retrying in {}s: {failure}",
+                self.name,
+                delay.as_secs_f64()
+            );
+            let delivery = delivery.clone();
+            self.persist(&delivery);
+
+            // Keep accepting deliveries while waiting to retry
+            let deadline = Instant::now() + delay;
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep_until(deadline) => break,
+                    delivery = rx.recv(), if open => match delivery {
+                        Some(delivery) => self.enqueue(delivery),
+                        None => open = false,
+                    },
+                }
+            }
+        }
+    }
+
+    async fn send(&self, body: &str) -> Result<(), Failure> {
+        let mut request = self.client.post(&self.target.url);
+        if !self
+            .target
+            .headers
+            .keys()
+            .any(|header| header.eq_ignore_ascii_case(CONTENT_TYPE.as_str()))
+        {
+            request = request.header(CONTENT_TYPE, "application/json");
+        }
+        for (header, value) in &self.target.headers {
+            request = request.header(header, value);
+        }
+        if let Some(secret) = &self.target.secret {
+            request = request.header(SIGNATURE_HEADER, sign(secret, body.as_bytes()));
+        }
+
+        let response = request
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(|e| Failure::Retry(e.to_string()))?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else if status.is_server_error()
+            || status == StatusCode::REQUEST_TIMEOUT
+            || status == StatusCode::TOO_MANY_REQUESTS
+        {
+            Err(Failure::Retry(format!("HTTP {status}")))
+        } else {
+            Err(Failure::Permanent(format!("HTTP {status}")))
+        }
+    }
+
+    fn enqueue(&mut self, delivery: Delivery) {
+        self.persist(&delivery);
+        self.queue.push_back(delivery);
+        while self.queue.len() > self.max_queue {
+            if let Some(dropped) = self.queue.pop_front() {
+                eprintln!(
+                    "Webhook queue for '{}' is full; dropping the oldest delivery",
+                    self.name
+                );
+                self.remove(&dropped);
+            }
+        }
+    }
+
+    /// Removes the delivery at the front of the queue.
+    fn finish(&mut self) {
+        if let Some(delivery) = self.queue.pop_front() {
+            self.remove(&delivery);
+        }
+    }
+
+    fn path(&self, delivery: &Delivery) -> Option<PathBuf> {
+        self.queue_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.json", delivery.id)))
+    }
+
+    fn persist(&self, delivery: &Delivery) {
+        let Some(path) = self.path(delivery) else {
+            return;
+        };
+        let result = serde_json::to_string(delivery)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(&path, json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("Failed to queue webhook '{}': {e}", path.display());
+        }
+    }
+
+    fn remove(&self, delivery: &Delivery) {
+        if let Some(path) = self.path(delivery) {
+            if let Err(e) = fs::remove_file(&path) {
+                eprintln!("Failed to remove queued webhook '{}': {e}", path.display());
+            }
+        }
+    }
+}
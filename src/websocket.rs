@@ -0,0 +1,198 @@
+//! WebSocket live event feed.
+//!
+//! `GET /ws` upgrades to a WebSocket that pushes device events as JSON text messages. It is mounted by
+//! [`http::router`](crate::http::router) next to the REST API, or on its own with `usbwatch serve --websocket-only`.
+//!
+//! Every server message has a `type`:
+//!
+//! ```text
+//! {"type":"snapshot","devices":[{...}],"filter":{}}   sent on connect and after each filter change
+//! {"type":"event","event":{...}}                      a device event matching the filter
+//! {"type":"lagged","missed":3}                        the client read too slowly and missed events
+//! {"type":"error","message":"..."}                    the client sent a message that was not understood
+//! ```
+//!
+//! Clients narrow the feed with a filter message holding [`EventFilter`] fields, which replaces the previous filter;
+//! `{"type":"filter"}` alone clears it:
+//!
+//! ```text
+//! {"type":"filter","port":"1-2","vendor_id":"0483","event_types":["Connected","Disconnected"]}
+//! ```
+//!
+//! The snapshot lists the attached devices that match the filter's device criteria (its event types are ignored).
+//!
+//! Browsers send an `Origin` header with the upgrade request but let any page open a WebSocket, so an upgrade whose
+//! `Origin` differs from its `Host` is refused with 403 unless the origin is allowed explicitly. Clients that send no
+//! `Origin` (command-line tools, scripts) are not affected. With a token configured, browsers, which cannot set an
+//! `Authorization` header on a WebSocket, pass it as `/ws?access_token=<token>`.
+
+use crate::daemon::DaemonState;
+use crate::device_info::UsbDeviceInfo;
+use crate::filter::EventFilter;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// A message sent to WebSocket clients.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// Attached devices matching the current filter
+    Snapshot {
+        /// Matching devices
+        devices: Vec<UsbDeviceInfo>,
+        /// Filter in effect from now on
+        filter: EventFilter,
+    },
+    /// A device event
+    Event {
+        /// The event
+        event: Box<UsbDeviceInfo>,
+    },
+    /// Number of events the client missed because it read too slowly
+    Lagged {
+        /// Missed events
+        missed: u64,
+    },
+    /// The client's last message was not understood
+    Error {
+        /// What went wrong
+        message: String,
+    },
+}
+
+/// A message sent by WebSocket clients.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Replace the feed's filter
+    Filter(EventFilter),
+}
+
+/// State of the `/ws` route.
+#[derive(Clone)]
+struct FeedState {
+    daemon: Arc<DaemonState>,
+    allowed_origins: Arc<Vec<String>>,
+}
+
+/// Builds the `/ws` route over `state`, accepting upgrades from the server's
+/// own origin and from `allowed_origins` (e.g. `https://dashboard.example.com`).
+pub fn router(state: Arc<DaemonState>, allowed_origins: Vec<String>) -> Router {
+    Router::new()
+        .route("/ws", get(upgrade))
+        .with_state(FeedState {
+            daemon: state,
+            allowed_origins: Arc::new(allowed_origins),
+        })
+}
+
+async fn upgrade(
+    State(state): State<FeedState>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    if !origin_allowed(&headers, &state.allowed_origins) {
+        let message = "WebSocket upgrade from this origin is not allowed";
+        return (StatusCode::FORBIDDEN, Json(json!({ "error": message }))).into_response();
+    }
+    ws.on_upgrade(move |socket| feed(socket, state.daemon))
+}
+
+/// Returns whether the request has no `Origin`, or one that matches its
+/// `Host` or one of `allowed_origins`.
+fn origin_allowed(headers: &HeaderMap, allowed_origins: &[String]) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN) else {
+        return true;
+    };
+    let Ok(origin) = origin.to_str() else {
+        return false;
+    };
+    let origin = origin.trim_end_matches('/');
+    if allowed_origins
+        .iter()
+        .any(|allowed| allowed.trim_end_matches('/').eq_ignore_ascii_case(origin))
+    {
+        return true;
+    }
+    // Same origin: the scheme is dropped, the host and port must match
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok());
+    match (origin.split_once("://"), host) {
+        (Some((_, authority)), Some(host)) => authority.eq_ignore_ascii_case(host),
+        _ => false,
+    }
+}
+
+fn snapshot(state: &DaemonState, filter: &EventFilter) -> ServerMessage {
+    let device_filter = EventFilter {
+        event_types: Vec::new(),
+        ..filter.clone()
+    };
+    ServerMessage::Snapshot {
+        devices: state
+            .devices()
+            .into_iter()
+            .filter(|device| device_filter.matches(device))
+            .collect(),
+        filter: filter.clone(),
+    }
+}
+
+async fn send(socket: &mut WebSocket, message: &ServerMessage) -> Result<(), axum::Error> {
+    let text = serde_json::to_string(message).map_err(axum::Error::new)?;
+    socket.send(Message::Text(text.into())).await
+}
+
+/// Streams events to one client until it disconnects.
+async fn feed(mut socket: WebSocket, state: Arc<DaemonState>) {
+    // Subscribe before taking the snapshot so no event falls between the two
+    let mut events = state.subscribe();
+    let mut filter = EventFilter::default();
+    if send(&mut socket, &snapshot(&state, &filter)).await.is_err() {
+        return;
+    }
+
+    loop {
+        let message = tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) if filter.matches(&event) => ServerMessage::Event {
+                    event: Box::new(event),
+                },
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    state.metrics().record_dropped(missed);
+                    ServerMessage::Lagged { missed }
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    match serde_json::from_str::<ClientMessage>(text.as_str()) {
+                        Ok(ClientMessage::Filter(new_filter)) => {
+                            filter = new_filter;
+                            snapshot(&state, &filter)
+                        }
+                        Err(e) => ServerMessage::Error {
+                            message: format!("Invalid message: {e}"),
+                        },
+                    }
+                }
+                // Pings are answered by axum; binary messages are ignored
+                Some(Ok(Message::Binary(_) | Message::Ping(_) | Message::Pong(_))) => continue,
+                Some(Ok(Message::Close(_)) | Err(_)) | None => return,
+            },
+        };
+        if send(&mut socket, &message).await.is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,149 @@
+// Integration tests for the hash-chained audit log
+
+mod common;
+
+use common::event;
+use std::fs;
+use std::io::BufReader;
+use std::path::Path;
+use usbwatch_rs::audit::{verify_log, AuditLog, VerifyReport, DEFAULT_CHECKPOINT_INTERVAL};
+use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+
+fn named(name: &str, event_type: DeviceEventType) -> UsbDeviceInfo {
+    UsbDeviceInfo {
+        device_name: name.to_string(),
+        ..event("0781", "A1", event_type)
+    }
+}
+
+fn write_log(path: &Path, count: usize) {
+    let mut log = AuditLog::open(path).unwrap();
+    for i in 0..count {
+        log.append(&named(&format!("Drive {i}"), DeviceEventType::Connected))
+            .unwrap();
+    }
+}
+
+fn verify(path: &Path) -> VerifyReport {
+    verify_log(
+        BufReader::new(fs::File::open(path).unwrap()),
+        None,
+        DEFAULT_CHECKPOINT_INTERVAL,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_chain_verifies_and_continues_after_reopen() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("audit.jsonl");
+    write_log(&path, 3);
+    // A restarted monitor continues the existing chain
+    let record = AuditLog::open(&path)
+        .unwrap()
+        .append(&named("Drive", DeviceEventType::Disconnected))
+        .unwrap();
+    assert_eq!(record.seq, 4);
+
+    let report = verify(&path);
+    assert!(report.is_valid());
+    assert_eq!(report.records, 4);
+    assert_eq!(report.last_hash.as_deref(), Some(record.hash.as_str()));
+    assert!(report.render_text().starts_with("OK: 4 record(s)"));
+}
+
+#[test]
+fn test_tampering_is_detected_at_the_first_broken_link() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("audit.jsonl");
+    write_log(&path, 4);
+    let original = fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = original.lines().collect();
+
+    // Edited content
+    fs::write(&path, original.replace("Drive 1", "Drive X")).unwrap();
+    let report = verify(&path);
+    let broken = report.broken.unwrap();
+    assert_eq!((broken.line, broken.seq), (2, Some(2)));
+    assert!(broken.reason.contains("content"));
+    assert_eq!(report.records, 1);
+
+    // Removed record
+    fs::write(&path, [lines[0], lines[2], lines[3]].join("\n")).unwrap();
+    let broken = verify(&path).broken.unwrap();
+    assert_eq!(broken.seq, Some(3));
+    assert!(broken.reason.contains("sequence number 3"));
+
+    // Truncated from the front
+    fs::write(&path, lines[1..].join("\n")).unwrap();
+    assert_eq!(verify(&path).broken.unwrap().line, 1);
+
+    // Not a record at all
+    fs::write(&path, format!("{}\nnot json\n", lines[0])).unwrap();
+    assert!(verify(&path)
+        .broken
+        .unwrap()
+        .reason
+        .starts_with("not an audit record"));
+
+    // Appending after a foreign line is refused
+    assert!(AuditLog::open(&path).is_err());
+}
+
+#[cfg(feature = "signing")]
+#[test]
+fn test_signed_checkpoints() {
+    use usbwatch_rs::audit::{generate_keypair, load_key, to_hex};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("audit.jsonl");
+    let (secret, public) = generate_keypair();
+    let mut log = AuditLog::open(&path).unwrap().with_signing_key(&secret, 2);
+    for i in 0..5 {
+        let record = log
+            .append(&named(&format!("Drive {i}"), DeviceEventType::Connected))
+            .unwrap();
+        assert_eq!(record.signature.is_some(), record.seq.is_multiple_of(2));
+    }
+
+    let open = || BufReader::new(fs::File::open(&path).unwrap());
+    let report = verify_log(open(), Some(&public), 2).unwrap();
+    assert!(report.is_valid());
+    assert_eq!(report.signatures, 2);
+    assert_eq!(report.unsigned_tail, Some(1));
+    assert!(report
+        .render_text()
+        .contains("1 record(s) after the last checkpoint"));
+
+    let report = verify_log(open(), None, 2).unwrap();
+    assert!(report.is_valid());
+    assert_eq!(report.unchecked_signatures, 2);
+    assert_eq!(report.unsigned_tail, None);
+
+    let (_, other) = generate_keypair();
+    let report = verify_log(open(), Some(&other), 2).unwrap();
+    assert_eq!(report.broken.unwrap().seq, Some(2));
+
+    // A log rewritten without signatures has an unsigned checkpoint
+    let rewritten = dir.path().join("rewritten.jsonl");
+    write_log(&rewritten, 5);
+    let open_rewritten = || BufReader::new(fs::File::open(&rewritten).unwrap());
+    let report = verify_log(open_rewritten(), Some(&public), 2).unwrap();
+    assert!(!report.is_valid());
+    let broken = report.broken.unwrap();
+    assert_eq!(broken.seq, Some(2));
+    assert!(broken.reason.starts_with("checkpoint record is not signed"));
+
+    // Too short to reach a checkpoint, so nothing vouches for it
+    let report = verify_log(open_rewritten(), Some(&public), 10).unwrap();
+    assert!(report.broken.is_none());
+    assert!(!report.is_valid());
+    assert_eq!(report.unsigned_tail, Some(5));
+    assert!(report.render_text().starts_with("UNSIGNED: 5 record(s)"));
+
+    let key_path = dir.path().join("key.pub");
+    fs::write(&key_path, format!("{}\n", to_hex(&public))).unwrap();
+    assert_eq!(load_key(&key_path).unwrap(), public);
+    fs::write(&key_path, "abcd\n").unwrap();
+    assert!(load_key(&key_path).is_err());
+}
@@ -0,0 +1,133 @@
+// Integration tests for known-device baselines and drift reports
+
+mod common;
+
+use common::device;
+use std::sync::{Arc, RwLock};
+use tokio::sync::mpsc;
+use usbwatch_rs::baseline::{tag_task, Baseline, BaselineStatus};
+
+fn kiosk_baseline() -> Baseline {
+    Baseline::new(vec![
+        device("046d", "c31c", None, "1-1"),
+        device("0781", "5583", Some("A1"), "1-2"),
+        device("0403", "6001", Some("FT01"), "1-3"),
+    ])
+}
+
+#[test]
+fn test_diff_reports_added_removed_and_changed() {
+    let baseline = kiosk_baseline();
+
+    let unchanged = baseline.diff(&baseline.devices);
+    assert!(!unchanged.has_drift());
+    assert!(unchanged.render_text().starts_with("No drift"));
+
+    let current = vec![
+        // Same keyboard, same port
+        device("046d", "c31c", None, "1-1"),
+        // Different drive in the drive's port
+        device("0781", "5583", Some("B2"), "1-2"),
+        // Serial adapter moved to another port
+        device("0403", "6001", Some("FT01"), "1-4"),
+        // Something new
+        device("05ac", "12a8", Some("PHONE"), "2-1"),
+    ];
+    let report = baseline.diff(&current);
+    assert!(report.has_drift());
+    assert!(report.removed.is_empty());
+    assert_eq!(report.added.len(), 1);
+    assert_eq!(report.added[0].vendor_id, "05ac");
+    assert_eq!(report.changed.len(), 2);
+    assert_eq!(report.changed[0].fields, vec!["serial_number"]);
+    assert_eq!(report.changed[1].fields, vec!["port_path"]);
+
+    let report = baseline.diff(&current[..1]);
+    assert_eq!(report.removed.len(), 2);
+    assert!(report
+        .render_text()
+        .contains("Drift: 0 added, 2 removed, 0 changed"));
+}
+
+#[test]
+fn test_known_devices() {
+    let baseline = kiosk_baseline();
+    // Serial numbers identify a device in any port
+    assert!(baseline.is_known(&device("0781", "5583", Some("A1"), "2-7")));
+    assert!(!baseline.is_known(&device("0781", "5583", Some("B2"), "1-2")));
+    // Without a serial number the port must match too
+    assert!(baseline.is_known(&device("046d", "c31c", None, "1-1")));
+    assert!(!baseline.is_known(&device("046d", "c31c", None, "1-5")));
+}
+
+#[test]
+fn test_save_and_load_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("baseline.json");
+    let baseline = kiosk_baseline();
+    baseline.save(&path).unwrap();
+
+    let loaded = Baseline::load(&path).unwrap();
+    assert_eq!(loaded.created_at, baseline.created_at);
+    assert!(!loaded.diff(&baseline.devices).has_drift());
+    assert!(Baseline::load(dir.path().join("missing.json")).is_err());
+}
+
+#[tokio::test]
+async fn test_tag_task_marks_events() {
+    let (tx, rx) = mpsc::channel(10);
+    let (tagged_tx, mut tagged_rx) = mpsc::channel(10);
+    let handle = tokio::spawn(tag_task(
+        rx,
+        tagged_tx,
+        Arc::new(RwLock::new(kiosk_baseline())),
+    ));
+
+    tx.send(device("0781", "5583", Some("A1"), "1-2"))
+        .await
+        .unwrap();
+    tx.send(device("dead", "beef", None, "1-2")).await.unwrap();
+    drop(tx);
+
+    let known = tagged_rx.recv().await.unwrap();
+    assert_eq!(known.baseline, Some(BaselineStatus::Known));
+    let unknown = tagged_rx.recv().await.unwrap();
+    assert_eq!(unknown.baseline, Some(BaselineStatus::Unknown));
+    assert!(unknown.format_plain().contains("Baseline: unknown"));
+    assert!(tagged_rx.recv().await.is_none());
+    handle.await.unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_scan_devices_from_sysfs_root() {
+    use std::fs;
+    use usbwatch_rs::watcher::{UsbWatcher, WatcherOptions};
+
+    let root = tempfile::tempdir().unwrap();
+    let drive = root.path().join("bus/usb/devices/1-2");
+    fs::create_dir_all(&drive).unwrap();
+    for (name, value) in [
+        ("idVendor", "0781"),
+        ("idProduct", "5583"),
+        ("serial", "A1"),
+    ] {
+        fs::write(drive.join(name), format!("{value}\n")).unwrap();
+    }
+
+    let (tx, _rx) = mpsc::channel(1);
+    let watcher = UsbWatcher::with_options(
+        tx,
+        WatcherOptions {
+            sysfs_root: Some(root.path().to_path_buf()),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let devices = watcher.scan_devices().await.unwrap();
+    assert_eq!(devices.len(), 1);
+
+    let report = kiosk_baseline().diff(&devices);
+    assert_eq!(report.removed.len(), 2);
+    assert!(report.added.is_empty() && report.changed.is_empty());
+}
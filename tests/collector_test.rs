@@ -0,0 +1,257 @@
+// Integration tests for the agent/collector wire format, buffering and TLS
+
+mod common;
+
+use common::event;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use usbwatch_rs::collector::{
+    self, agent_task, read_frame, write_frame, AgentOptions, CollectorOptions, Frame,
+};
+use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+
+fn event_frame(seq: u64, serial: &str) -> Frame {
+    Frame::Event {
+        seq,
+        event: Box::new(event("0781", serial, DeviceEventType::Connected)),
+    }
+}
+
+async fn next<T>(rx: &mut mpsc::Receiver<T>) -> T {
+    tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("nothing received")
+        .unwrap()
+}
+
+/// Starts a collector on a free port, returning its address and events.
+async fn start_collector(
+    options: CollectorOptions,
+) -> (std::net::SocketAddr, mpsc::Receiver<UsbDeviceInfo>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::channel(100);
+    tokio::spawn(collector::serve(listener, tx, options));
+    (address, rx)
+}
+
+#[tokio::test]
+async fn test_frame_round_trip() {
+    let mut buffer = Vec::new();
+    let hello = Frame::Hello {
+        host: "ws-042".to_string(),
+        session: "1".to_string(),
+        version: "0.4.8".to_string(),
+    };
+    write_frame(&mut buffer, &hello).await.unwrap();
+    write_frame(&mut buffer, &event_frame(7, "A1"))
+        .await
+        .unwrap();
+    let length = u32::from_be_bytes(buffer[..4].try_into().unwrap()) as usize;
+    let json: serde_json::Value = serde_json::from_slice(&buffer[4..4 + length]).unwrap();
+    assert_eq!(json["type"], "hello");
+    assert_eq!(json["host"], "ws-042");
+
+    let mut reader = buffer.as_slice();
+    assert!(matches!(
+        read_frame(&mut reader).await.unwrap(),
+        Some(Frame::Hello { host, .. }) if host == "ws-042"
+    ));
+    let Some(Frame::Event { seq, event }) = read_frame(&mut reader).await.unwrap() else {
+        panic!("expected an event frame");
+    };
+    assert_eq!(seq, 7);
+    assert_eq!(event.serial_number.as_deref(), Some("A1"));
+    assert!(read_frame(&mut reader).await.unwrap().is_none());
+
+    let oversized = (collector::MAX_FRAME_SIZE as u32 + 1).to_be_bytes();
+    assert!(read_frame(&mut oversized.as_slice()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_collector_tags_origin_and_skips_resent_events() {
+    let (address, mut events) = start_collector(CollectorOptions::default()).await;
+    let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+    let hello = Frame::Hello {
+        host: "ws-042".to_string(),
+        session: "1".to_string(),
+        version: "0.4.8".to_string(),
+    };
+    write_frame(&mut stream, &hello).await.unwrap();
+    for (seq, serial) in [(1, "A1"), (1, "A1"), (2, "B2")] {
+        write_frame(&mut stream, &event_frame(seq, serial))
+            .await
+            .unwrap();
+        let ack = read_frame(&mut stream).await.unwrap();
+        assert!(matches!(ack, Some(Frame::Ack { seq: acked }) if acked == seq));
+    }
+
+    let first = next(&mut events).await;
+    assert_eq!(first.serial_number.as_deref(), Some("A1"));
+    assert_eq!(first.origin.as_deref(), Some("ws-042"));
+    assert_eq!(next(&mut events).await.serial_number.as_deref(), Some("B2"));
+    assert!(
+        tokio::time::timeout(Duration::from_millis(100), events.recv())
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_collector_drops_connections_without_hello() {
+    use tokio::io::AsyncReadExt;
+
+    let (address, _events) = start_collector(CollectorOptions::default()).await;
+    let mut idle = tokio::net::TcpStream::connect(address).await.unwrap();
+    // The paused clock runs ahead to the collector's timeout, which closes the connection
+    let mut buf = [0; 1];
+    let read = tokio::time::timeout(Duration::from_secs(60), idle.read(&mut buf)).await;
+    assert_eq!(read.expect("connection kept open").unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_agent_streams_to_collector() {
+    let (address, mut received) = start_collector(CollectorOptions::default()).await;
+    let (events_tx, events_rx) = mpsc::channel(10);
+    let (forward_tx, mut forward_rx) = mpsc::channel(10);
+    tokio::spawn(agent_task(
+        events_rx,
+        forward_tx,
+        AgentOptions::new(address.to_string(), "bench-1"),
+    ));
+    events_tx
+        .send(event("0781", "A1", DeviceEventType::Connected))
+        .await
+        .unwrap();
+
+    // Events are still passed on locally
+    assert_eq!(next(&mut forward_rx).await.origin, None);
+    let device = next(&mut received).await;
+    assert_eq!(device.serial_number.as_deref(), Some("A1"));
+    assert_eq!(device.origin.as_deref(), Some("bench-1"));
+}
+
+#[tokio::test]
+async fn test_agent_resends_unacknowledged_events_after_reconnect() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let (events_tx, events_rx) = mpsc::channel(10);
+    let (forward_tx, _forward_rx) = mpsc::channel(10);
+    tokio::spawn(agent_task(
+        events_rx,
+        forward_tx,
+        AgentOptions::new(address.to_string(), "bench-1"),
+    ));
+    events_tx
+        .send(event("0781", "A1", DeviceEventType::Connected))
+        .await
+        .unwrap();
+
+    // The first connection drops before acknowledging anything
+    let (mut stream, _) = listener.accept().await.unwrap();
+    let Some(Frame::Hello { session, .. }) = read_frame(&mut stream).await.unwrap() else {
+        panic!("expected a hello frame");
+    };
+    assert!(matches!(
+        read_frame(&mut stream).await.unwrap(),
+        Some(Frame::Event { seq: 1, .. })
+    ));
+    drop(stream);
+    events_tx
+        .send(event("0781", "B2", DeviceEventType::Connected))
+        .await
+        .unwrap();
+
+    let (mut stream, _) = listener.accept().await.unwrap();
+    let Some(Frame::Hello {
+        session: resumed, ..
+    }) = read_frame(&mut stream).await.unwrap()
+    else {
+        panic!("expected a hello frame");
+    };
+    assert_eq!(resumed, session);
+    let mut serials = Vec::new();
+    while serials.len() < 2 {
+        let Some(Frame::Event { seq, event }) = read_frame(&mut stream).await.unwrap() else {
+            panic!("expected an event frame");
+        };
+        write_frame(&mut stream, &Frame::Ack { seq }).await.unwrap();
+        serials.push((seq, event.serial_number.unwrap()));
+    }
+    assert_eq!(serials, [(1, "A1".to_string()), (2, "B2".to_string())]);
+}
+
+#[cfg(feature = "tls")]
+#[tokio::test]
+async fn test_mutual_tls() {
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, Issuer, KeyPair};
+    use usbwatch_rs::collector::{ClientTls, ServerTls};
+
+    let dir = tempfile::tempdir().unwrap();
+    let write = |name: &str, pem: String| {
+        let path = dir.path().join(name);
+        std::fs::write(&path, pem).unwrap();
+        path
+    };
+    let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let ca_key = KeyPair::generate().unwrap();
+    let ca = write("ca.pem", ca_params.self_signed(&ca_key).unwrap().pem());
+    let issuer = Issuer::new(ca_params, ca_key);
+    let issue = |name: &str| {
+        let key = KeyPair::generate().unwrap();
+        let cert = CertificateParams::new(vec![name.to_string()])
+            .unwrap()
+            .signed_by(&key, &issuer)
+            .unwrap();
+        (
+            write(&format!("{name}.pem"), cert.pem()),
+            write(&format!("{name}.key"), key.serialize_pem()),
+        )
+    };
+    let (server_cert, server_key) = issue("localhost");
+    let (client_cert, client_key) = issue("bench-1");
+
+    let options = CollectorOptions {
+        tls: Some(ServerTls::new(&server_cert, &server_key, Some(&ca)).unwrap()),
+    };
+    let (address, mut received) = start_collector(options).await;
+    let agent = |identity: Option<(&std::path::Path, &std::path::Path)>, host: &str| {
+        let (events_tx, events_rx) = mpsc::channel(10);
+        let (forward_tx, forward_rx) = mpsc::channel(10);
+        let options = AgentOptions {
+            tls: Some(ClientTls::new(&ca, identity, "localhost").unwrap()),
+            ..AgentOptions::new(address.to_string(), host)
+        };
+        tokio::spawn(agent_task(events_rx, forward_tx, options));
+        (events_tx, forward_rx)
+    };
+
+    // An agent without a client certificate is turned away
+    let (anonymous, _anonymous_rx) = agent(None, "anonymous");
+    anonymous
+        .send(event("0781", "X9", DeviceEventType::Connected))
+        .await
+        .unwrap();
+    // Nor is one whose hello names a host its certificate was not issued for
+    let (impostor, _impostor_rx) = agent(Some((&client_cert, &client_key)), "bench-2");
+    impostor
+        .send(event("0781", "Z7", DeviceEventType::Connected))
+        .await
+        .unwrap();
+    let (trusted, _trusted_rx) = agent(Some((&client_cert, &client_key)), "bench-1");
+    trusted
+        .send(event("0781", "A1", DeviceEventType::Connected))
+        .await
+        .unwrap();
+
+    let device = next(&mut received).await;
+    assert_eq!(device.serial_number.as_deref(), Some("A1"));
+    assert_eq!(device.origin.as_deref(), Some("bench-1"));
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), received.recv())
+            .await
+            .is_err()
+    );
+}
@@ -0,0 +1,39 @@
+// Fixtures shared by the integration tests
+// Each test binary uses only some of them
+#![allow(dead_code)]
+
+use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+
+/// Returns an event of `event_type` for a `5583` device from `vendor_id`.
+pub fn event(vendor_id: &str, serial: &str, event_type: DeviceEventType) -> UsbDeviceInfo {
+    UsbDeviceInfo::new(
+        "USB Storage".to_string(),
+        vendor_id.to_string(),
+        "5583".to_string(),
+        Some(serial.to_string()),
+        event_type,
+    )
+}
+
+/// Returns `device` as seen in `port`.
+pub fn in_port(mut device: UsbDeviceInfo, port: &str) -> UsbDeviceInfo {
+    device.port_path = Some(port.to_string());
+    device
+}
+
+/// Returns a connect event for a device in `port`.
+pub fn device(
+    vendor_id: &str,
+    product_id: &str,
+    serial: Option<&str>,
+    port: &str,
+) -> UsbDeviceInfo {
+    let device = UsbDeviceInfo::new(
+        "USB Storage".to_string(),
+        vendor_id.to_string(),
+        product_id.to_string(),
+        serial.map(str::to_string),
+        DeviceEventType::Connected,
+    );
+    in_port(device, port)
+}
@@ -0,0 +1,133 @@
+// Integration tests for the daemon's Unix socket API
+#![cfg(unix)]
+
+mod common;
+
+use common::event;
+use serde_json::{json, Value};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use usbwatch_rs::daemon::{
+    bind, parse_socket_mode, serve, DaemonClient, DaemonState, DaemonStats, SocketOptions,
+};
+use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+
+fn start(path: &Path, state: Arc<DaemonState>) {
+    let listener = bind(path, &SocketOptions::default()).unwrap();
+    tokio::spawn(serve(listener, state));
+}
+
+/// Records an event the way the pipeline does: counted in the metrics before
+/// filtering, then recorded in the state.
+fn record(state: &DaemonState, event: UsbDeviceInfo) {
+    state.metrics().record_event(&event);
+    state.record(&event);
+}
+
+#[test]
+fn test_state_tracks_attached_devices() {
+    let state = DaemonState::new(2);
+    record(&state, event("0781", "A1", DeviceEventType::Connected));
+    record(&state, event("046d", "B2", DeviceEventType::Connected));
+    record(&state, event("0781", "A1", DeviceEventType::Disconnected));
+
+    let devices = state.devices();
+    assert_eq!(devices.len(), 1);
+    assert_eq!(devices[0].vendor_id, "046d");
+
+    let stats = state.stats();
+    assert_eq!(stats.events_total, 3);
+    assert_eq!(stats.connected_devices, 1);
+    // Only the last two events are kept
+    assert_eq!(stats.history.total_events, 2);
+}
+
+#[tokio::test]
+async fn test_requests_over_socket() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("usbwatch.sock");
+    let state = Arc::new(DaemonState::new(100).with_reload(|| Ok("Reloaded policy".to_string())));
+    record(&state, event("0781", "A1", DeviceEventType::Connected));
+    record(&state, event("046d", "B2", DeviceEventType::Connected));
+    record(&state, event("046d", "B2", DeviceEventType::Disconnected));
+    start(&path, state);
+
+    let mut client = DaemonClient::connect(&path).await.unwrap();
+    let devices: Vec<UsbDeviceInfo> =
+        serde_json::from_value(client.call("list", Value::Null).await.unwrap()).unwrap();
+    assert_eq!(devices.len(), 1);
+    assert_eq!(devices[0].serial_number.as_deref(), Some("A1"));
+
+    let history: Vec<UsbDeviceInfo> = serde_json::from_value(
+        client
+            .call("history", json!({ "vendor_id": "046d", "limit": 1 }))
+            .await
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].event_type, DeviceEventType::Disconnected);
+
+    let stats: DaemonStats =
+        serde_json::from_value(client.call("stats", Value::Null).await.unwrap()).unwrap();
+    assert_eq!(stats.events_total, 3);
+    assert_eq!(stats.history.devices.len(), 2);
+
+    assert_eq!(
+        client.call("reload", Value::Null).await.unwrap(),
+        json!("Reloaded policy")
+    );
+    let error = client.call("frobnicate", Value::Null).await.unwrap_err();
+    assert!(error.to_string().contains("Unknown method"));
+}
+
+#[tokio::test]
+async fn test_subscribe_streams_new_events() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("usbwatch.sock");
+    let state = Arc::new(DaemonState::new(100));
+    start(&path, state.clone());
+
+    let client = DaemonClient::connect(&path).await.unwrap();
+    let (tx, mut rx) = mpsc::channel(10);
+    tokio::spawn(async move {
+        let _ = client.subscribe(tx).await;
+    });
+    // Wait until the subscription is registered before recording
+    while state.stats().subscribers == 0 {
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+
+    state.record(&event("0781", "A1", DeviceEventType::Connected));
+    let received = rx.recv().await.unwrap();
+    assert_eq!(received.serial_number.as_deref(), Some("A1"));
+}
+
+#[tokio::test]
+async fn test_bind_sets_mode_and_replaces_stale_socket() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("usbwatch.sock");
+    let options = SocketOptions {
+        mode: 0o600,
+        group: None,
+    };
+
+    let listener = bind(&path, &options).unwrap();
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    // A second daemon on the same socket is refused
+    assert!(bind(&path, &options)
+        .unwrap_err()
+        .to_string()
+        .contains("Another daemon"));
+
+    // Once the first one is gone, its socket file is replaced
+    drop(listener);
+    assert!(bind(&path, &options).is_ok());
+
+    assert_eq!(parse_socket_mode("660"), Ok(0o660));
+    assert_eq!(parse_socket_mode("0o600"), Ok(0o600));
+    assert!(parse_socket_mode("999").is_err());
+}
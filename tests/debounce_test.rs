@@ -0,0 +1,176 @@
+// Integration tests for debouncing and flap detection
+
+mod common;
+
+use common::event;
+use std::time::Duration;
+use tokio::time::Instant;
+use usbwatch_rs::debounce::{debounce_task, DebounceConfig, DebounceOutput, Debouncer};
+use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+
+fn events(outputs: Vec<DebounceOutput>) -> Vec<UsbDeviceInfo> {
+    outputs
+        .into_iter()
+        .filter_map(|output| match output {
+            DebounceOutput::Event(event) => Some(*event),
+            DebounceOutput::Alert(_) => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_burst_collapses_into_flapping_event() {
+    let mut debouncer = Debouncer::new(DebounceConfig {
+        stable_for: Duration::from_millis(500),
+        collapse: true,
+        alert_per_minute: None,
+    });
+    let start = Instant::now();
+
+    // Initial connect settles normally
+    assert!(debouncer
+        .push(event("05e3", "HUB1", DeviceEventType::Connected), start)
+        .is_empty());
+    let settled = events(debouncer.poll(start + Duration::from_millis(600)));
+    assert_eq!(settled.len(), 1);
+    assert_eq!(settled[0].event_type, DeviceEventType::Connected);
+
+    // Disconnect/connect/disconnect burst
+    let burst_start = start + Duration::from_secs(5);
+    for (i, event_type) in [
+        DeviceEventType::Disconnected,
+        DeviceEventType::Connected,
+        DeviceEventType::Disconnected,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let now = burst_start + Duration::from_millis(100 * i as u64);
+        debouncer.push(event("05e3", "HUB1", event_type), now);
+        assert!(debouncer.poll(now).is_empty(), "still unstable");
+    }
+
+    let settled = events(debouncer.poll(burst_start + Duration::from_secs(1)));
+    assert_eq!(settled.len(), 2);
+    assert_eq!(settled[0].event_type, DeviceEventType::Flapping);
+    assert_eq!(settled[0].flap_count, Some(3));
+    assert_eq!(settled[1].event_type, DeviceEventType::Disconnected);
+}
+
+#[test]
+fn test_bounce_back_to_same_state_is_suppressed() {
+    let mut debouncer = Debouncer::new(DebounceConfig {
+        stable_for: Duration::from_millis(500),
+        ..Default::default()
+    });
+    let start = Instant::now();
+
+    debouncer.push(event("05e3", "HUB1", DeviceEventType::Connected), start);
+    assert_eq!(events(debouncer.flush()).len(), 1);
+
+    debouncer.push(event("05e3", "HUB1", DeviceEventType::Disconnected), start);
+    debouncer.push(
+        event("05e3", "HUB1", DeviceEventType::Connected),
+        start + Duration::from_millis(100),
+    );
+    assert!(events(debouncer.poll(start + Duration::from_secs(1))).is_empty());
+}
+
+#[test]
+fn test_device_gone_before_it_was_reported_leaves_no_disconnect() {
+    let mut debouncer = Debouncer::new(DebounceConfig {
+        stable_for: Duration::from_millis(500),
+        collapse: true,
+        alert_per_minute: None,
+    });
+    let start = Instant::now();
+
+    debouncer.push(event("05e3", "HUB1", DeviceEventType::Connected), start);
+    debouncer.push(
+        event("05e3", "HUB1", DeviceEventType::Disconnected),
+        start + Duration::from_millis(100),
+    );
+    // Only the flapping notice, no disconnect without a connect
+    let settled = events(debouncer.poll(start + Duration::from_secs(1)));
+    assert_eq!(settled.len(), 1);
+    assert_eq!(settled[0].event_type, DeviceEventType::Flapping);
+
+    // Once it comes back and stays, its connect is reported
+    debouncer.push(
+        event("05e3", "HUB1", DeviceEventType::Connected),
+        start + Duration::from_secs(2),
+    );
+    let settled = events(debouncer.poll(start + Duration::from_secs(3)));
+    assert_eq!(settled.len(), 1);
+    assert_eq!(settled[0].event_type, DeviceEventType::Connected);
+}
+
+#[test]
+fn test_flap_rate_alert_fires_once() {
+    let mut debouncer = Debouncer::new(DebounceConfig {
+        alert_per_minute: Some(3),
+        ..Default::default()
+    });
+    let start = Instant::now();
+
+    let mut alerts = Vec::new();
+    for i in 0..6 {
+        let event_type = if i % 2 == 0 {
+            DeviceEventType::Connected
+        } else {
+            DeviceEventType::Disconnected
+        };
+        for output in debouncer.push(
+            event("05e3", "HUB1", event_type),
+            start + Duration::from_secs(i),
+        ) {
+            if let DebounceOutput::Alert(alert) = output {
+                alerts.push(*alert);
+            }
+        }
+    }
+    assert_eq!(alerts.len(), 1);
+    // The alert is an event, so it reaches the logger and notifiers
+    assert_eq!(alerts[0].event_type, DeviceEventType::Flapping);
+    assert_eq!(alerts[0].flap_count, Some(4));
+    assert_eq!(
+        alerts[0].reason.as_deref(),
+        Some("4 state changes in the last minute (threshold 3)")
+    );
+}
+
+#[tokio::test]
+async fn test_debounce_task_forwards_alerts() {
+    let (in_tx, in_rx) = tokio::sync::mpsc::channel(10);
+    let (out_tx, mut out_rx) = tokio::sync::mpsc::channel(10);
+    tokio::spawn(debounce_task(
+        in_rx,
+        out_tx,
+        DebounceConfig {
+            alert_per_minute: Some(1),
+            ..Default::default()
+        },
+    ));
+    in_tx
+        .send(event("05e3", "HUB1", DeviceEventType::Connected))
+        .await
+        .unwrap();
+    in_tx
+        .send(event("05e3", "HUB1", DeviceEventType::Disconnected))
+        .await
+        .unwrap();
+    drop(in_tx);
+
+    let mut events = Vec::new();
+    while let Some(event) = out_rx.recv().await {
+        events.push(event.event_type);
+    }
+    assert_eq!(
+        events,
+        [
+            DeviceEventType::Connected,
+            DeviceEventType::Flapping,
+            DeviceEventType::Disconnected
+        ]
+    );
+}
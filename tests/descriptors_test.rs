@@ -0,0 +1,149 @@
+// Integration tests for USB descriptor parsing
+
+use usbwatch_rs::descriptors::{BosDescriptor, DescriptorTree, Direction, TransferType};
+use usbwatch_rs::usb_class::UsbClass;
+
+/// Descriptors of a USB 2.0 composite device: a boot keyboard and a webcam
+/// grouped with an interface association.
+#[rustfmt::skip]
+fn composite_blob() -> Vec<u8> {
+    vec![
+        // Device: USB 2.00, miscellaneous/IAD class, 046d:085c
+        18, 0x01, 0x00, 0x02, 0xef, 0x02, 0x01, 64, 0x6d, 0x04, 0x5c, 0x08, 0x11, 0x00, 1, 2, 0, 1,
+        // Configuration 1: 3 interfaces, bus powered with remote wakeup, 500 mA
+        9, 0x02, 0x5b, 0x00, 3, 1, 0, 0xa0, 250,
+        // Interface 0: HID boot keyboard
+        9, 0x04, 0, 0, 1, 0x03, 0x01, 0x01, 0,
+        // HID 1.11, one report descriptor of 63 bytes
+        9, 0x21, 0x11, 0x01, 0, 1, 0x22, 63, 0,
+        // Interrupt IN 0x81, 8 bytes, interval 10
+        7, 0x05, 0x81, 0x03, 8, 0, 10,
+        // IAD: interfaces 1-2, video interface collection
+        8, 0x0b, 1, 2, 0x0e, 0x03, 0x00, 0,
+        // Interface 1: video control
+        9, 0x04, 1, 0, 0, 0x0e, 0x01, 0x00, 0,
+        // Class-specific VC header
+        13, 0x24, 0x01, 0x00, 0x01, 0x4d, 0x00, 0x80, 0xc3, 0xc9, 0x01, 0x01, 0x02,
+        // Interface 2 alt 0 and alt 1: video streaming
+        9, 0x04, 2, 0, 0, 0x0e, 0x02, 0x00, 0,
+        9, 0x04, 2, 1, 1, 0x0e, 0x02, 0x00, 0,
+        // Isochronous IN 0x82, 1024 bytes x3
+        7, 0x05, 0x82, 0x05, 0x00, 0x14, 1,
+    ]
+}
+
+#[test]
+fn test_parse_composite_device() {
+    let tree = DescriptorTree::parse(&composite_blob()).unwrap();
+
+    assert_eq!(tree.device.usb_version, "2.00");
+    assert_eq!(tree.device.device_version, "0.11");
+    assert_eq!(tree.device.vendor_id, 0x046d);
+    assert_eq!(tree.device.class.usb_class(), UsbClass::Miscellaneous);
+
+    let config = &tree.configurations[0];
+    assert_eq!(config.max_power_ma, 500);
+    assert!(config.remote_wakeup);
+    assert!(!config.self_powered);
+    assert_eq!(config.interfaces.len(), 4);
+    assert_eq!(config.interface_count(), 3);
+
+    let keyboard = &config.interfaces[0];
+    assert!(keyboard.class.is_boot_keyboard());
+    let hid = keyboard.hid.as_ref().unwrap();
+    assert_eq!(hid.hid_version, "1.11");
+    assert_eq!(hid.descriptors, vec![(0x22, 63)]);
+    assert_eq!(keyboard.endpoints[0].direction, Direction::In);
+    assert_eq!(keyboard.endpoints[0].transfer_type, TransferType::Interrupt);
+
+    assert_eq!(config.associations.len(), 1);
+    assert_eq!(config.associations[0].first_interface, 1);
+    assert_eq!(config.associations[0].interface_count, 2);
+    assert_eq!(config.interfaces[1].extra[0].descriptor_type, 0x24);
+
+    let streaming = &config.interfaces[3].endpoints[0];
+    assert_eq!(streaming.transfer_type, TransferType::Isochronous);
+    assert_eq!(streaming.max_packet_size, 1024);
+    assert_eq!(streaming.transactions_per_microframe, 2);
+
+    let text = tree.render_text();
+    assert!(text.contains("idVendor             0x046d"));
+    assert!(text.contains("Interface Association:"));
+    assert!(text.contains("0x01 Boot Keyboard"));
+}
+
+#[test]
+fn test_truncated_descriptor_is_an_error() {
+    let mut blob = composite_blob();
+    blob.truncate(blob.len() - 3);
+    assert!(DescriptorTree::parse(&blob).is_err());
+    assert!(DescriptorTree::parse(&[9, 0x02, 0, 0, 0, 0, 0, 0, 0]).is_err());
+}
+
+#[test]
+fn test_parse_bos() {
+    #[rustfmt::skip]
+    let data = [
+        // BOS header: 2 capabilities
+        5, 0x0f, 22, 0, 2,
+        // USB 2.0 extension with LPM
+        7, 0x10, 0x02, 0x02, 0, 0, 0,
+        // SuperSpeed: full, high and 5Gbps
+        10, 0x10, 0x03, 0x00, 0x0e, 0x00, 0x01, 0x0a, 0xff, 0x07,
+    ];
+    let bos = BosDescriptor::parse(&data).unwrap();
+    assert_eq!(bos.capabilities.len(), 2);
+    assert_eq!(bos.capabilities[0].name(), "USB 2.0 Extension");
+    assert_eq!(
+        bos.capabilities[1].describe().as_deref(),
+        Some("Speeds supported: Full Speed, High Speed, SuperSpeed (5Gbps)")
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_read_device_from_sysfs_directory() {
+    use std::fs;
+    use usbwatch_rs::watcher::{linux::read_device, WatcherOptions};
+
+    let dir = tempfile::tempdir().unwrap();
+    let device_dir = dir.path().join("1-1");
+    fs::create_dir(&device_dir).unwrap();
+    for (name, value) in [
+        ("idVendor", "046d"),
+        ("idProduct", "085c"),
+        ("product", "C922 Pro Stream Webcam"),
+        ("bDeviceClass", "ef"),
+        ("bDeviceSubClass", "02"),
+        ("bDeviceProtocol", "01"),
+    ] {
+        fs::write(device_dir.join(name), format!("{value}\n")).unwrap();
+    }
+    fs::write(device_dir.join("descriptors"), composite_blob()).unwrap();
+    let interface_dir = device_dir.join("1-1:1.0");
+    fs::create_dir(&interface_dir).unwrap();
+    for (name, value) in [
+        ("bInterfaceNumber", "00"),
+        ("bInterfaceClass", "03"),
+        ("bInterfaceSubClass", "01"),
+        ("bInterfaceProtocol", "01"),
+    ] {
+        fs::write(interface_dir.join(name), format!("{value}\n")).unwrap();
+    }
+
+    let device = read_device(
+        &device_dir,
+        &WatcherOptions {
+            descriptors: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(device.port_path.as_deref(), Some("1-1"));
+    assert_eq!(device.classes(), vec![UsbClass::Hid]);
+    assert_eq!(device.descriptors.unwrap().configurations.len(), 1);
+
+    let device = read_device(&device_dir, &WatcherOptions::default()).unwrap();
+    assert!(device.descriptors.is_none());
+    assert!(read_device(&dir.path().join("9-9"), &WatcherOptions::default()).is_err());
+}
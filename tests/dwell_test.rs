@@ -0,0 +1,126 @@
+// Integration tests for connection time and dwell duration on disconnect events
+
+mod common;
+
+use chrono::Duration;
+use common::event;
+use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+use usbwatch_rs::template::render_line;
+
+#[test]
+fn test_mark_disconnected_records_dwell_time() {
+    let mut event = event("0781", "4C530001", DeviceEventType::Connected);
+    let connected_at = event.timestamp - Duration::seconds(90);
+    event.mark_disconnected(Some(connected_at));
+
+    assert_eq!(event.event_type, DeviceEventType::Disconnected);
+    assert_eq!(event.connected_at, Some(connected_at));
+    let duration = event.duration.unwrap();
+    assert!((90.0..91.0).contains(&duration), "{duration}");
+    assert!(event.timestamp > connected_at);
+
+    let json: serde_json::Value = serde_json::to_value(&event).unwrap();
+    assert_eq!(json["duration"], duration);
+    assert!(json["connected_at"].is_string());
+    assert!(event.format_plain().contains("Connected for: 1m 30s"));
+}
+
+#[test]
+fn test_unknown_connection_time_is_left_out() {
+    let mut event = event("0781", "4C530001", DeviceEventType::Connected);
+    event.mark_disconnected(None);
+
+    assert_eq!(event.event_type, DeviceEventType::Disconnected);
+    assert_eq!(event.connected_at, None);
+    assert_eq!(event.duration, None);
+    assert!(!event.format_plain().contains("Connected for"));
+
+    let json: serde_json::Value = serde_json::to_value(&event).unwrap();
+    assert!(json.get("connected_at").is_none());
+    assert!(json.get("duration").is_none());
+}
+
+#[test]
+fn test_template_output_includes_dwell_time() {
+    let mut event = event("0781", "4C530001", DeviceEventType::Connected);
+    event.connected_at = Some(event.timestamp - Duration::seconds(30));
+    event.duration = Some(30.0);
+    event.event_type = DeviceEventType::Disconnected;
+
+    let line = render_line(
+        "{{event_type}} {{vendor_id}}:{{product_id}} {{serial_number}} for {{duration}}s since {{connected_at}}",
+        &event,
+    );
+    let since = serde_json::to_value(event.connected_at).unwrap();
+    assert_eq!(
+        line,
+        format!(
+            "Disconnected 0781:5583 4C530001 for 30.0s since {}",
+            since.as_str().unwrap()
+        )
+    );
+
+    // Unset fields render empty
+    event.duration = None;
+    assert_eq!(render_line("[{{duration}}]", &event), "[]");
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_watcher_only_times_devices_it_saw_connect() {
+    use std::fs;
+    use std::path::Path;
+    use tokio::sync::mpsc;
+    use usbwatch_rs::watcher::{UsbWatcher, WatcherOptions};
+
+    fn add_device(path: &Path, product_id: &str) {
+        fs::create_dir_all(path).unwrap();
+        fs::write(path.join("idVendor"), "0781\n").unwrap();
+        fs::write(path.join("idProduct"), format!("{product_id}\n")).unwrap();
+    }
+
+    async fn next(rx: &mut mpsc::Receiver<UsbDeviceInfo>) -> UsbDeviceInfo {
+        tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    let root = tempfile::tempdir().unwrap();
+    let devices = root.path().join("bus/usb/devices");
+    // Already attached when the watcher starts
+    add_device(&devices.join("1-1"), "5583");
+
+    let (tx, mut rx) = mpsc::channel(10);
+    let watcher = UsbWatcher::with_options(
+        tx,
+        WatcherOptions {
+            sysfs_root: Some(root.path().to_path_buf()),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let handle =
+        tokio::spawn(async move { watcher.start_monitoring().await.map_err(|e| e.to_string()) });
+
+    let initial = next(&mut rx).await;
+    assert_eq!(initial.event_type, DeviceEventType::Connected);
+    fs::remove_dir_all(devices.join("1-1")).unwrap();
+    add_device(&devices.join("1-2"), "5591");
+
+    let mut events = [next(&mut rx).await, next(&mut rx).await];
+    events.sort_by_key(|event| event.product_id.clone());
+    // Its connection time is unknown, so no dwell time is reported
+    assert_eq!(events[0].event_type, DeviceEventType::Disconnected);
+    assert_eq!(events[0].connected_at, None);
+    assert_eq!(events[0].duration, None);
+    assert_eq!(events[1].event_type, DeviceEventType::Connected);
+
+    fs::remove_dir_all(devices.join("1-2")).unwrap();
+    let removed = next(&mut rx).await;
+    handle.abort();
+
+    assert_eq!(removed.event_type, DeviceEventType::Disconnected);
+    assert_eq!(removed.connected_at, Some(events[1].timestamp));
+    assert!(removed.duration.unwrap() > 0.0);
+}
@@ -0,0 +1,203 @@
+// Integration tests for HID report descriptor classification and suspicious device detection
+
+use usbwatch_rs::debounce::{DebounceConfig, DebounceOutput, Debouncer};
+use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo, UsbInterfaceInfo};
+use usbwatch_rs::hid::{classify_report_descriptor, HidClass};
+use usbwatch_rs::usb_class::ClassCode;
+
+/// Report descriptor of a wireless receiver with keyboard, mouse, consumer
+/// control and vendor-defined collections.
+#[rustfmt::skip]
+const RECEIVER: &[u8] = &[
+    // Keyboard
+    0x05, 0x01, 0x09, 0x06, 0xa1, 0x01, 0x85, 0x01, 0x05, 0x07, 0x19, 0xe0, 0x29, 0xe7,
+    0x15, 0x00, 0x25, 0x01, 0x75, 0x01, 0x95, 0x08, 0x81, 0x02, 0xc0,
+    // Mouse, with a nested physical pointer collection
+    0x05, 0x01, 0x09, 0x02, 0xa1, 0x01, 0x85, 0x02, 0x09, 0x01, 0xa1, 0x00, 0x05, 0x09,
+    0x19, 0x01, 0x29, 0x10, 0x81, 0x02, 0xc0, 0xc0,
+    // Consumer control
+    0x05, 0x0c, 0x09, 0x01, 0xa1, 0x01, 0x85, 0x03, 0x75, 0x10, 0x95, 0x02, 0x81, 0x00, 0xc0,
+    // Vendor-defined, usage given with an extended (4-byte) usage
+    0x0b, 0x01, 0x00, 0x00, 0xff, 0xa1, 0x01, 0x85, 0x10, 0x75, 0x08, 0x95, 0x06, 0x81, 0x00, 0xc0,
+];
+
+fn interface(number: u8, class: ClassCode, hid_classes: Vec<HidClass>) -> UsbInterfaceInfo {
+    UsbInterfaceInfo {
+        id: format!("1-1:1.{number}"),
+        number,
+        class,
+        class_name: None,
+        driver: None,
+        hid_classes,
+    }
+}
+
+fn device(interfaces: Vec<UsbInterfaceInfo>) -> UsbDeviceInfo {
+    let mut device = UsbDeviceInfo::new(
+        "Cruzer Blade".to_string(),
+        "0781".to_string(),
+        "5567".to_string(),
+        None,
+        DeviceEventType::Connected,
+    );
+    device.device_class = Some(ClassCode::new(0x00, 0x00, 0x00));
+    device.interfaces = interfaces;
+    device
+}
+
+#[test]
+fn test_classify_composite_receiver() {
+    assert_eq!(
+        classify_report_descriptor(RECEIVER).unwrap(),
+        vec![
+            HidClass::Keyboard,
+            HidClass::Mouse,
+            HidClass::ConsumerControl,
+            HidClass::VendorDefined
+        ]
+    );
+}
+
+#[test]
+fn test_classify_handles_push_pop_and_errors() {
+    // Push, switch to the vendor page, pop back to generic desktop, then a joystick
+    let descriptor = [
+        0x05, 0x01, 0xa4, 0x06, 0x00, 0xff, 0xb4, 0x09, 0x04, 0xa1, 0x01, 0xc0,
+    ];
+    assert_eq!(
+        classify_report_descriptor(&descriptor).unwrap(),
+        vec![HidClass::Joystick]
+    );
+    assert!(classify_report_descriptor(&[0x05]).is_err());
+    assert!(classify_report_descriptor(&[]).unwrap().is_empty());
+}
+
+#[test]
+fn test_storage_with_keyboard_is_suspicious() {
+    let storage = interface(0, ClassCode::new(0x08, 0x06, 0x50), vec![]);
+
+    // Report descriptor says keyboard, even though the boot protocol does not
+    let injector = device(vec![
+        storage.clone(),
+        interface(
+            1,
+            ClassCode::new(0x03, 0x00, 0x00),
+            vec![HidClass::Keyboard],
+        ),
+    ]);
+    let event = injector.suspicious_event().unwrap();
+    assert_eq!(event.event_type, DeviceEventType::SuspiciousDevice);
+    assert!(event.reason.unwrap().contains("mass storage"));
+
+    // Without a parsed report descriptor, fall back to the boot keyboard protocol
+    let boot = device(vec![
+        storage.clone(),
+        interface(1, ClassCode::new(0x03, 0x01, 0x01), vec![]),
+    ]);
+    assert!(boot.suspicious_reason().is_some());
+
+    // A boot keyboard interface whose report descriptor is only a mouse
+    let mouse = device(vec![
+        storage.clone(),
+        interface(1, ClassCode::new(0x03, 0x01, 0x01), vec![HidClass::Mouse]),
+    ]);
+    assert!(mouse.suspicious_reason().is_none());
+
+    assert!(device(vec![storage]).suspicious_reason().is_none());
+}
+
+#[test]
+fn test_suspicious_events_bypass_debounce() {
+    let mut debouncer = Debouncer::new(DebounceConfig {
+        stable_for: std::time::Duration::from_secs(5),
+        ..Default::default()
+    });
+    let mut event = device(vec![]);
+    event.event_type = DeviceEventType::SuspiciousDevice;
+
+    let outputs = debouncer.push(event, tokio::time::Instant::now());
+    assert!(matches!(
+        outputs.as_slice(),
+        [DebounceOutput::Event(event)] if event.event_type == DeviceEventType::SuspiciousDevice
+    ));
+    assert!(debouncer.next_deadline().is_none());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_read_device_classifies_hid_interfaces() {
+    use std::fs;
+    use usbwatch_rs::watcher::{linux::read_device, WatcherOptions};
+
+    let dir = tempfile::tempdir().unwrap();
+    let device_dir = dir.path().join("1-1");
+    for (interface, class) in [("1-1:1.0", "08"), ("1-1:1.1", "03")] {
+        let path = device_dir.join(interface);
+        fs::create_dir_all(&path).unwrap();
+        fs::write(path.join("bInterfaceClass"), class).unwrap();
+        fs::write(path.join("bInterfaceSubClass"), "00").unwrap();
+        fs::write(path.join("bInterfaceProtocol"), "00").unwrap();
+    }
+    let hid_dir = device_dir.join("1-1:1.1").join("0003:0781:5567.0001");
+    fs::create_dir_all(&hid_dir).unwrap();
+    fs::write(hid_dir.join("report_descriptor"), &RECEIVER[..25]).unwrap();
+    fs::write(device_dir.join("idVendor"), "0781").unwrap();
+    fs::write(device_dir.join("idProduct"), "5567").unwrap();
+
+    let device = read_device(&device_dir, &WatcherOptions::default()).unwrap();
+    assert_eq!(device.interfaces[1].hid_classes, vec![HidClass::Keyboard]);
+    assert_eq!(device.hid_classes(), vec![HidClass::Keyboard]);
+    assert!(device.suspicious_reason().is_some());
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_watcher_parses_report_descriptors_once() {
+    use std::fs;
+    use usbwatch_rs::watcher::linux::LinuxUsbWatcher;
+    use usbwatch_rs::watcher::WatcherOptions;
+
+    let root = tempfile::tempdir().unwrap();
+    let device_dir = root.path().join("bus/usb/devices/1-1");
+    let interface = device_dir.join("1-1:1.0");
+    fs::create_dir_all(&interface).unwrap();
+    fs::write(interface.join("bInterfaceClass"), "03").unwrap();
+    fs::write(interface.join("bInterfaceSubClass"), "01").unwrap();
+    fs::write(interface.join("bInterfaceProtocol"), "01").unwrap();
+    fs::write(device_dir.join("idVendor"), "046d").unwrap();
+    fs::write(device_dir.join("idProduct"), "c52b").unwrap();
+    let hid_dir = interface.join("0003:046D:C52B.0001");
+    fs::create_dir_all(&hid_dir).unwrap();
+    fs::write(hid_dir.join("report_descriptor"), &RECEIVER[..25]).unwrap();
+
+    let (tx, _rx) = tokio::sync::mpsc::channel(1);
+    let watcher = LinuxUsbWatcher::with_options(
+        tx,
+        WatcherOptions {
+            sysfs_root: Some(root.path().to_path_buf()),
+            ..Default::default()
+        },
+    );
+    let scan = watcher.scan_usb_devices().await.unwrap();
+    assert_eq!(scan[0].hid_classes(), vec![HidClass::Keyboard]);
+
+    // Later scans reuse the classes of a HID device they have seen
+    fs::write(hid_dir.join("report_descriptor"), [0xff]).unwrap();
+    let scan = watcher.scan_usb_devices().await.unwrap();
+    assert_eq!(scan[0].hid_classes(), vec![HidClass::Keyboard]);
+
+    // A device bound again gets a new instance number and is read afresh
+    fs::rename(&hid_dir, interface.join("0003:046D:C52B.0002")).unwrap();
+    let scan = watcher.scan_usb_devices().await.unwrap();
+    assert!(scan[0].hid_classes().is_empty());
+}
+
+#[test]
+fn test_event_type_round_trip() {
+    let event_type: DeviceEventType = "suspicious".parse().unwrap();
+    assert_eq!(event_type, DeviceEventType::SuspiciousDevice);
+    assert_eq!(
+        event_type.to_string().parse::<DeviceEventType>(),
+        Ok(DeviceEventType::SuspiciousDevice)
+    );
+}
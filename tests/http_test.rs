@@ -0,0 +1,196 @@
+// Integration tests for the HTTP API and Server-Sent Events stream
+#![cfg(feature = "http")]
+
+mod common;
+
+use common::{event, in_port};
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use usbwatch_rs::daemon::DaemonState;
+use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+use usbwatch_rs::http::{serve, HttpOptions};
+
+async fn start(state: Arc<DaemonState>, token: Option<&str>) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let options = HttpOptions {
+        token: token.map(str::to_string),
+        ..Default::default()
+    };
+    tokio::spawn(serve(listener, state, options));
+    address
+}
+
+fn request(path: &str, token: Option<&str>) -> String {
+    let auth = token
+        .map(|token| format!("Authorization: Bearer {token}\r\n"))
+        .unwrap_or_default();
+    format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n{auth}Connection: close\r\n\r\n")
+}
+
+/// Sends a GET request and returns the status code and JSON body.
+async fn get(address: SocketAddr, path: &str, token: Option<&str>) -> (u16, Value) {
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    stream
+        .write_all(request(path, token).as_bytes())
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+
+    let status = response[9..12].parse().unwrap();
+    let (_, body) = response.split_once("\r\n\r\n").unwrap();
+    (status, serde_json::from_str(body).unwrap())
+}
+
+/// Records an event the way the pipeline does: counted in the metrics before
+/// filtering, then recorded in the state.
+fn record(state: &DaemonState, event: UsbDeviceInfo) {
+    state.metrics().record_event(&event);
+    state.record(&event);
+}
+
+fn recorded_state() -> Arc<DaemonState> {
+    let state = Arc::new(DaemonState::new(100));
+    record(&state, event("0781", "A1", DeviceEventType::Connected));
+    record(
+        &state,
+        in_port(event("0781", "B2", DeviceEventType::Connected), "1-B2"),
+    );
+    record(&state, event("046d", "C3", DeviceEventType::Connected));
+    record(&state, event("046d", "C3", DeviceEventType::Disconnected));
+    state
+}
+
+#[tokio::test]
+async fn test_devices_endpoints() {
+    let address = start(recorded_state(), None).await;
+
+    let (status, devices) = get(address, "/devices", None).await;
+    assert_eq!(status, 200);
+    assert_eq!(devices.as_array().unwrap().len(), 2);
+
+    let (status, device) = get(address, "/devices/0781:5583:A1", None).await;
+    assert_eq!(status, 200);
+    assert_eq!(device["serial_number"], "A1");
+    let (status, device) = get(address, "/devices/1-B2", None).await;
+    assert_eq!(status, 200);
+    assert_eq!(device["serial_number"], "B2");
+
+    let (status, error) = get(address, "/devices/0781:5583", None).await;
+    assert_eq!(status, 409);
+    assert!(error["error"].as_str().unwrap().contains("2 devices"));
+    let (status, _) = get(address, "/devices/046d:5583:C3", None).await;
+    assert_eq!(status, 404);
+}
+
+#[tokio::test]
+async fn test_events_filters() {
+    let address = start(recorded_state(), None).await;
+
+    let (status, events) = get(address, "/events?since=1h&vid=046d", None).await;
+    assert_eq!(status, 200);
+    let events = events.as_array().unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["event_type"], "Disconnected");
+
+    let (_, events) = get(address, "/events?event=connected&limit=1", None).await;
+    assert_eq!(events.as_array().unwrap().len(), 1);
+
+    let (status, error) = get(address, "/events?since=yesterdayish", None).await;
+    assert_eq!(status, 400);
+    assert!(error["error"].is_string());
+    let (status, _) = get(address, "/events?limit=many", None).await;
+    assert_eq!(status, 400);
+}
+
+#[tokio::test]
+async fn test_bearer_token() {
+    let address = start(recorded_state(), Some("s3cret")).await;
+
+    let (status, _) = get(address, "/devices", None).await;
+    assert_eq!(status, 401);
+    let (status, _) = get(address, "/devices", Some("wrong")).await;
+    assert_eq!(status, 401);
+    let (status, _) = get(address, "/devices", Some("s3cret")).await;
+    assert_eq!(status, 200);
+    // Only streaming endpoints take the token from the query
+    let (status, _) = get(address, "/devices?access_token=s3cret", None).await;
+    assert_eq!(status, 401);
+}
+
+#[tokio::test]
+async fn test_event_stream_token_in_query() {
+    let address = start(Arc::new(DaemonState::new(100)), Some("s3cret")).await;
+
+    // EventSource cannot send headers, so the token comes in the query
+    for (path, expected) in [
+        ("/events/stream?access_token=wrong", "401"),
+        ("/events/stream?access_token=s3cret", "200"),
+    ] {
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        stream
+            .write_all(request(path, None).as_bytes())
+            .await
+            .unwrap();
+        let mut lines = BufReader::new(stream).lines();
+        let status = lines.next_line().await.unwrap().unwrap();
+        assert!(status.contains(expected), "{path}: {status}");
+    }
+}
+
+#[tokio::test]
+async fn test_event_stream() {
+    let state = Arc::new(DaemonState::new(100));
+    let address = start(state.clone(), None).await;
+
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    stream
+        .write_all(request("/events/stream", None).as_bytes())
+        .await
+        .unwrap();
+    let mut lines = BufReader::new(stream).lines();
+    let status = lines.next_line().await.unwrap().unwrap();
+    assert!(status.contains("200"), "{status}");
+    // Wait until the stream is subscribed before recording
+    while state.stats().subscribers == 0 {
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+
+    state.record(&event("0781", "A1", DeviceEventType::Connected));
+    let mut name = None;
+    loop {
+        let line = lines.next_line().await.unwrap().unwrap();
+        if let Some(event) = line.strip_prefix("event: ") {
+            name = Some(event.to_string());
+        }
+        if let Some(data) = line.strip_prefix("data: ") {
+            let device: UsbDeviceInfo = serde_json::from_str(data).unwrap();
+            assert_eq!(device.serial_number.as_deref(), Some("A1"));
+            break;
+        }
+    }
+    assert_eq!(name.as_deref(), Some("Connected"));
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint() {
+    let address = start(recorded_state(), None).await;
+
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    stream
+        .write_all(request("/metrics", None).as_bytes())
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    assert!(response.contains("content-type: text/plain; version=0.0.4"));
+    assert!(response.contains(r#"usbwatch_devices_connected{class="unknown",vendor="0781"} 2"#));
+    assert!(response.contains(r#"usbwatch_events_total{event_type="Connected"} 3"#));
+    assert!(response.contains(r#"usbwatch_events_total{event_type="Disconnected"} 1"#));
+}
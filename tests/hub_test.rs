@@ -0,0 +1,101 @@
+// Integration tests for collapsing hub events, run against a fake sysfs tree
+#![cfg(target_os = "linux")]
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+use usbwatch_rs::watcher::{UsbWatcher, WatcherOptions};
+
+/// Replaces the devices directory under `root` in one step, so a scan never
+/// sees a hub without the devices behind it.
+fn install(root: &Path, devices: &[(&str, &str, &str)]) {
+    let usb = root.join("bus/usb");
+    let next = usb.join("devices.next");
+    for (port, vendor_id, product_id) in devices {
+        let dir = next.join(port);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("idVendor"), format!("{vendor_id}\n")).unwrap();
+        fs::write(dir.join("idProduct"), format!("{product_id}\n")).unwrap();
+    }
+    fs::create_dir_all(&next).unwrap();
+    let current = usb.join("devices");
+    if current.exists() {
+        fs::rename(&current, usb.join("devices.old")).unwrap();
+    }
+    fs::rename(&next, &current).unwrap();
+    let _ = fs::remove_dir_all(usb.join("devices.old"));
+}
+
+async fn next(rx: &mut mpsc::Receiver<UsbDeviceInfo>) -> UsbDeviceInfo {
+    tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+fn ports(events: &[UsbDeviceInfo]) -> Vec<&str> {
+    events
+        .iter()
+        .map(|event| event.port_path.as_deref().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_watcher_collapses_hub_connects_and_disconnects() {
+    let root = tempfile::tempdir().unwrap();
+    let root_hub = ("usb1", "1d6b", "0002");
+    install(root.path(), &[root_hub]);
+
+    let (tx, mut rx) = mpsc::channel(10);
+    let watcher = UsbWatcher::with_options(
+        tx,
+        WatcherOptions {
+            collapse_hubs: true,
+            sysfs_root: Some(root.path().to_path_buf()),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let handle =
+        tokio::spawn(async move { watcher.start_monitoring().await.map_err(|e| e.to_string()) });
+    assert_eq!(next(&mut rx).await.port_path.as_deref(), Some("usb1"));
+
+    // A hub with a keyboard and a mouse behind it, and a drive on another port
+    install(
+        root.path(),
+        &[
+            root_hub,
+            ("1-2", "05e3", "0610"),
+            ("1-2.1", "046d", "c31c"),
+            ("1-2.3", "046d", "c077"),
+            ("1-1", "0781", "5583"),
+        ],
+    );
+    let mut connected = [next(&mut rx).await, next(&mut rx).await];
+    connected.sort_by(|a, b| a.port_path.cmp(&b.port_path));
+    assert_eq!(ports(&connected), ["1-1", "1-2"]);
+    assert!(connected
+        .iter()
+        .all(|event| event.event_type == DeviceEventType::Connected));
+    assert!(connected[0].children.is_empty());
+    assert_eq!(ports(&connected[1].children), ["1-2.1", "1-2.3"]);
+
+    // Unplugging the hub takes the devices behind it along
+    install(root.path(), &[root_hub, ("1-1", "0781", "5583")]);
+    let disconnected = next(&mut rx).await;
+    assert_eq!(disconnected.event_type, DeviceEventType::Disconnected);
+    assert_eq!(disconnected.port_path.as_deref(), Some("1-2"));
+    assert_eq!(ports(&disconnected.children), ["1-2.1", "1-2.3"]);
+    assert!(disconnected
+        .children
+        .iter()
+        .all(|child| child.event_type == DeviceEventType::Disconnected));
+
+    // Nothing else changed
+    assert!(tokio::time::timeout(Duration::from_secs(3), rx.recv())
+        .await
+        .is_err());
+    handle.abort();
+}
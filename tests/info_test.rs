@@ -0,0 +1,143 @@
+// Integration tests for single-device reports against a fake sysfs tree
+#![cfg(target_os = "linux")]
+
+use std::fs;
+use std::path::Path;
+use usbwatch_rs::info::{find_device, DeviceReport};
+use usbwatch_rs::watcher::WatcherOptions;
+
+fn write_attributes(dir: &Path, attributes: &[(&str, &str)]) {
+    fs::create_dir_all(dir).unwrap();
+    for (name, value) in attributes {
+        fs::write(dir.join(name), format!("{value}\n")).unwrap();
+    }
+}
+
+/// Builds a sysfs-like `devices` directory with two identical serial
+/// adapters and a flash drive.
+fn fake_sysfs(root: &Path) {
+    for (port, devnum, serial) in [("1-1", "5", "A1"), ("1-2", "6", "A2")] {
+        let device = root.join(port);
+        write_attributes(
+            &device,
+            &[
+                ("idVendor", "0403"),
+                ("idProduct", "6001"),
+                ("product", "FT232R USB UART"),
+                ("manufacturer", "FTDI"),
+                ("serial", serial),
+                ("busnum", "1"),
+                ("devnum", devnum),
+                ("speed", "12"),
+                ("version", " 2.00"),
+                ("authorized", "1"),
+                ("bMaxPower", "90mA"),
+                ("bDeviceClass", "00"),
+                ("bDeviceSubClass", "00"),
+                ("bDeviceProtocol", "00"),
+                (
+                    "uevent",
+                    &format!("DEVTYPE=usb_device\nDEVNAME=bus/usb/001/00{devnum}"),
+                ),
+            ],
+        );
+        write_attributes(
+            &device.join("power"),
+            &[("control", "auto"), ("runtime_status", "active")],
+        );
+        let interface = device.join(format!("{port}:1.0"));
+        write_attributes(
+            &interface,
+            &[
+                ("bInterfaceNumber", "00"),
+                ("bInterfaceClass", "ff"),
+                ("bInterfaceSubClass", "ff"),
+                ("bInterfaceProtocol", "ff"),
+            ],
+        );
+        write_attributes(
+            &interface
+                .join(format!("ttyUSB{devnum}"))
+                .join("tty")
+                .join(format!("ttyUSB{devnum}")),
+            &[("uevent", &format!("MAJOR=188\nDEVNAME=ttyUSB{devnum}"))],
+        );
+    }
+
+    write_attributes(
+        &root.join("2-1"),
+        &[
+            ("idVendor", "0781"),
+            ("idProduct", "5583"),
+            ("serial", "4C530001234567891234"),
+            ("authorized", "0"),
+        ],
+    );
+    write_attributes(&root.join("2-1:1.0"), &[("bInterfaceClass", "08")]);
+}
+
+#[test]
+fn test_find_device_by_each_identifier() {
+    let dir = tempfile::tempdir().unwrap();
+    fake_sysfs(dir.path());
+
+    let port = find_device(dir.path(), "1-2").unwrap();
+    assert_eq!(port, dir.path().join("1-2"));
+    assert_eq!(
+        find_device(dir.path(), "0781:5583").unwrap(),
+        dir.path().join("2-1")
+    );
+    assert_eq!(
+        find_device(dir.path(), "A2").unwrap(),
+        dir.path().join("1-2")
+    );
+    assert_eq!(
+        find_device(dir.path(), port.to_str().unwrap()).unwrap(),
+        port
+    );
+
+    let ambiguous = find_device(dir.path(), "0403:6001").unwrap_err();
+    assert!(ambiguous.contains("1-1, 1-2"), "{ambiguous}");
+    assert!(find_device(dir.path(), "ffff:ffff").is_err());
+    assert!(find_device(dir.path(), "2-1:1.0").is_err());
+}
+
+#[test]
+fn test_collect_report() {
+    let dir = tempfile::tempdir().unwrap();
+    fake_sysfs(dir.path());
+
+    let report =
+        DeviceReport::collect(&dir.path().join("1-1"), &WatcherOptions::default()).unwrap();
+    assert_eq!(report.device.device_name, "FTDI FT232R USB UART");
+    assert_eq!(report.bus_number, Some(1));
+    assert_eq!(report.device_number, Some(5));
+    assert_eq!(report.usb_version.as_deref(), Some("2.00"));
+    assert_eq!(report.speed_mbps.as_deref(), Some("12"));
+    assert_eq!(report.authorized, Some(true));
+    assert_eq!(report.power.runtime_status.as_deref(), Some("active"));
+    assert_eq!(report.power.max_power.as_deref(), Some("90mA"));
+    assert_eq!(
+        report.device_nodes,
+        vec![
+            "/dev/bus/usb/001/005".to_string(),
+            "/dev/ttyUSB5".to_string()
+        ]
+    );
+    assert_eq!(
+        report.attributes.get("serial").map(String::as_str),
+        Some("A1")
+    );
+    assert!(!report.attributes.contains_key("uevent"));
+    assert!(report.descriptors.is_none());
+
+    let text = report.render_text(false);
+    assert!(text.contains("Speed:         12 Mbit/s"));
+    assert!(text.contains("1-1:1.0"));
+
+    let report =
+        DeviceReport::collect(&dir.path().join("2-1"), &WatcherOptions::default()).unwrap();
+    assert_eq!(report.authorized, Some(false));
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["device"]["vendor_id"], "0781");
+}
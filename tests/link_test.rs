@@ -0,0 +1,164 @@
+// Integration tests for link speed checks
+use usbwatch_rs::descriptors::{BosDescriptor, DeviceCapability};
+use usbwatch_rs::link::{speed_name, LinkInfo, SUPER_SPEED_MBPS, SUPER_SPEED_PLUS_MBPS};
+
+fn link(version: &str, speed: f64, hub_speed: f64) -> LinkInfo {
+    LinkInfo {
+        version: Some(version.to_string()),
+        speed_mbps: Some(speed),
+        root_hub: Some("usb2".to_string()),
+        root_hub_speed_mbps: Some(hub_speed),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_capability_speed() {
+    let capability = |capability_type: u8, data: &[u8]| DeviceCapability {
+        capability_type,
+        data: data.to_vec(),
+    };
+    let usb2 = BosDescriptor {
+        capabilities: vec![capability(0x02, &[0x06, 0, 0, 0])],
+    };
+    assert_eq!(LinkInfo::capability_speed(&usb2), None);
+
+    let super_speed = BosDescriptor {
+        capabilities: vec![
+            capability(0x02, &[0x06, 0, 0, 0]),
+            capability(0x03, &[0x00, 0x0e, 0x00, 0x01, 0x0a, 0xff, 0x07]),
+        ],
+    };
+    assert_eq!(
+        LinkInfo::capability_speed(&super_speed),
+        Some(SUPER_SPEED_MBPS)
+    );
+
+    // wSpeedsSupported without SuperSpeed
+    let high_speed_only = BosDescriptor {
+        capabilities: vec![capability(0x03, &[0x00, 0x06, 0x00])],
+    };
+    assert_eq!(LinkInfo::capability_speed(&high_speed_only), None);
+
+    let mut plus = super_speed.clone();
+    plus.capabilities.push(capability(0x0a, &[0; 12]));
+    assert_eq!(
+        LinkInfo::capability_speed(&plus),
+        Some(SUPER_SPEED_PLUS_MBPS)
+    );
+}
+
+#[test]
+fn test_degraded_reason() {
+    // Running at the supported speed
+    assert_eq!(link("3.20", 5000.0, 10000.0).degraded_reason(), None);
+    assert_eq!(link("3.10", 10000.0, 10000.0).degraded_reason(), None);
+    // USB 2 devices are not checked
+    assert_eq!(link("2.00", 12.0, 480.0).degraded_reason(), None);
+
+    assert_eq!(
+        link("3.20", 480.0, 480.0).degraded_reason().unwrap(),
+        "SuperSpeed device running at 480 Mbit/s (High Speed) on USB 2 root hub usb2; \
+         use a USB 3 port and cable"
+    );
+    assert_eq!(
+        link("3.20", 480.0, 10000.0).degraded_reason().unwrap(),
+        "SuperSpeed device running at 480 Mbit/s (High Speed); \
+         check the port, cable and any hubs in between"
+    );
+
+    let mut plus = link("3.20", 5000.0, 5000.0);
+    plus.capability_mbps = Some(SUPER_SPEED_PLUS_MBPS);
+    assert_eq!(
+        plus.degraded_reason().unwrap(),
+        "SuperSpeedPlus device running at 5000 Mbit/s (SuperSpeed) on root hub usb2, \
+         which only supports 5000 Mbit/s; use a faster port"
+    );
+
+    // A USB 3 device on a USB 2 link reports version 2.10
+    let mut fallback = link("2.10", 480.0, 480.0);
+    assert_eq!(fallback.degraded_reason(), None);
+    fallback.capability_mbps = Some(SUPER_SPEED_MBPS);
+    assert!(fallback.degraded_reason().is_some());
+
+    // The USB 2 half of a USB 3 hub runs at 480 Mbit/s by design
+    let mut hub = link("3.20", 480.0, 480.0);
+    hub.is_hub = true;
+    assert_eq!(hub.degraded_reason(), None);
+}
+
+#[test]
+fn test_speed_name() {
+    assert_eq!(speed_name(1.5), "Low Speed");
+    assert_eq!(speed_name(12.0), "Full Speed");
+    assert_eq!(speed_name(480.0), "High Speed");
+    assert_eq!(speed_name(5000.0), "SuperSpeed");
+    assert_eq!(speed_name(20000.0), "SuperSpeedPlus");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_read_link_info_from_sysfs() {
+    use std::fs;
+    use std::path::Path;
+    use usbwatch_rs::watcher::linux::read_link_info;
+
+    fn write_attributes(dir: &Path, attributes: &[(&str, &str)]) {
+        fs::create_dir_all(dir).unwrap();
+        for (name, value) in attributes {
+            fs::write(dir.join(name), format!("{value}\n")).unwrap();
+        }
+    }
+
+    // Laid out like /sys/devices: the device below its root hub
+    let dir = tempfile::tempdir().unwrap();
+    let hub = dir.path().join("usb1");
+    write_attributes(
+        &hub,
+        &[
+            ("busnum", "1"),
+            ("devnum", "1"),
+            ("speed", "480"),
+            ("version", " 2.00"),
+            ("bDeviceClass", "09"),
+        ],
+    );
+    let device = hub.join("1-1");
+    write_attributes(
+        &device,
+        &[
+            ("busnum", "1"),
+            ("devnum", "4"),
+            ("speed", "480"),
+            ("version", " 3.20"),
+            ("bDeviceClass", "00"),
+        ],
+    );
+
+    let link = read_link_info(&device);
+    assert_eq!(link.version.as_deref(), Some("3.20"));
+    assert_eq!(link.speed_mbps, Some(480.0));
+    assert_eq!(link.root_hub.as_deref(), Some("usb1"));
+    assert_eq!(link.root_hub_speed_mbps, Some(480.0));
+    assert!(!link.is_hub);
+    assert!(link
+        .degraded_reason()
+        .unwrap()
+        .contains("on USB 2 root hub usb1"));
+
+    assert!(read_link_info(&hub).is_hub);
+    assert_eq!(read_link_info(&hub).degraded_reason(), None);
+
+    // An external hub below the root hub is skipped as well
+    let external = hub.join("1-2");
+    write_attributes(
+        &external,
+        &[
+            ("busnum", "1"),
+            ("speed", "480"),
+            ("version", " 3.20"),
+            ("bDeviceClass", "09"),
+        ],
+    );
+    assert_eq!(read_link_info(&external).degraded_reason(), None);
+}
@@ -0,0 +1,107 @@
+// Integration tests for per-event sequence numbers and host identity
+
+mod common;
+
+use chrono::Duration;
+use common::event;
+use tokio::sync::mpsc;
+use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+use usbwatch_rs::metadata::{metadata_task, HostIdentity, MetadataStamper};
+
+fn identity() -> HostIdentity {
+    HostIdentity {
+        hostname: Some("kiosk-7".to_string()),
+        machine_id: Some("0123456789abcdef0123456789abcdef".to_string()),
+        boot_id: Some("6f2c1a4e-8d3b-4a5c-9e7f-1b2d3c4e5f60".to_string()),
+    }
+}
+
+#[test]
+fn test_stamper_numbers_events_and_copies_identity() {
+    let mut stamper = MetadataStamper::new(identity());
+    let mut first = event("0781", "A1", DeviceEventType::Connected);
+    let mut second = event("0781", "A1", DeviceEventType::Connected);
+    stamper.stamp(&mut first);
+    stamper.stamp(&mut second);
+
+    let first = first.metadata.unwrap();
+    let second = second.metadata.unwrap();
+    assert_eq!((first.seq, second.seq), (1, 2));
+    assert_eq!(second.machine_id, identity().machine_id);
+    assert_eq!(second.boot_id, identity().boot_id);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_monotonic_time_is_taken_when_the_event_is_created() {
+    let mut stamper = MetadataStamper::new(HostIdentity::default());
+    let mut old = event("0781", "A1", DeviceEventType::Connected);
+    // A wall-clock step does not move the monotonic time
+    old.timestamp -= Duration::hours(1);
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    let mut new = event("0781", "A1", DeviceEventType::Connected);
+    stamper.stamp(&mut new);
+    stamper.stamp(&mut old);
+
+    let old = old.metadata.unwrap().monotonic_ns.unwrap();
+    let new = new.metadata.unwrap().monotonic_ns.unwrap();
+    // Allow for scheduling delays between the two clock readings
+    let gap = new - old;
+    assert!((200_000_000..1_000_000_000).contains(&gap), "gap {gap}");
+
+    // Events read back from JSON are stamped with the current time
+    let mut parsed: UsbDeviceInfo = serde_json::from_str(
+        &serde_json::to_string(&event("0781", "A1", DeviceEventType::Connected)).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(parsed.monotonic_ns, None);
+    stamper.stamp(&mut parsed);
+    assert!(parsed.metadata.unwrap().monotonic_ns.unwrap() > new);
+}
+
+#[test]
+fn test_metadata_serialisation() {
+    let mut device = event("0781", "A1", DeviceEventType::Connected);
+    assert!(!serde_json::to_string(&device).unwrap().contains("metadata"));
+
+    MetadataStamper::new(identity()).stamp(&mut device);
+    let json = serde_json::to_string(&device).unwrap();
+    assert!(json.contains(r#""seq":1"#));
+    assert!(json.contains(r#""hostname":"kiosk-7""#));
+    let parsed: UsbDeviceInfo = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.metadata, device.metadata);
+    assert!(device.format_plain().contains("Seq: 1 Host: kiosk-7"));
+}
+
+#[tokio::test]
+async fn test_metadata_task_stamps_in_order() {
+    let (tx, rx) = mpsc::channel(10);
+    let (stamped_tx, mut stamped_rx) = mpsc::channel(10);
+    tokio::spawn(metadata_task(
+        rx,
+        stamped_tx,
+        MetadataStamper::new(identity()),
+    ));
+
+    for _ in 0..3 {
+        tx.send(event("0781", "A1", DeviceEventType::Connected))
+            .await
+            .unwrap();
+    }
+    drop(tx);
+    let mut seqs = Vec::new();
+    while let Some(event) = stamped_rx.recv().await {
+        seqs.push(event.metadata.unwrap().seq);
+    }
+    assert_eq!(seqs, vec![1, 2, 3]);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_detect_reads_boot_id() {
+    let identity = HostIdentity::detect();
+    if std::path::Path::new("/proc/sys/kernel/random/boot_id").exists() {
+        assert_eq!(identity.boot_id.map(|id| id.len()), Some(36));
+    }
+    assert!(identity.hostname.is_some());
+}
@@ -0,0 +1,151 @@
+// Integration tests for the Prometheus metrics exporter
+
+mod common;
+
+use common::event;
+use std::time::Duration;
+use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+use usbwatch_rs::metrics::Metrics;
+use usbwatch_rs::policy::PolicyAction;
+use usbwatch_rs::usb_class::ClassCode;
+
+fn with_class(mut device: UsbDeviceInfo, class: u8) -> UsbDeviceInfo {
+    device.device_class = Some(ClassCode::new(class, 0x00, 0x00));
+    device
+}
+
+#[test]
+fn test_render_counters_and_gauges() {
+    let metrics = Metrics::new();
+    let storage = with_class(event("0781", "A1", DeviceEventType::Connected), 0x08);
+    let hub = with_class(event("05E3", "A1", DeviceEventType::Connected), 0x09);
+    metrics.record_event(&storage);
+    metrics.record_event(&hub);
+    metrics.record_event(&with_class(
+        event("046d", "A1", DeviceEventType::Disconnected),
+        0x03,
+    ));
+    metrics.record_policy(&PolicyAction::Block);
+    metrics.record_policy(&PolicyAction::Allow);
+    metrics.record_policy(&PolicyAction::Block);
+    metrics.record_dropped(3);
+    assert_eq!(metrics.events_total(), 3);
+
+    let text = metrics.render(&[storage.clone(), storage, hub]);
+    assert!(text.contains(r#"usbwatch_devices_connected{class="mass-storage",vendor="0781"} 2"#));
+    assert!(text.contains(r#"usbwatch_devices_connected{class="hub",vendor="05e3"} 1"#));
+    assert!(text.contains(r#"usbwatch_events_total{event_type="Connected"} 2"#));
+    assert!(text.contains(r#"usbwatch_events_total{event_type="Disconnected"} 1"#));
+    assert!(text.contains(r#"usbwatch_events_total{event_type="Flapping"} 0"#));
+    assert!(text.contains("usbwatch_channel_dropped_total 3\n"));
+    assert!(text.contains("usbwatch_policy_blocks_total 2\n"));
+    assert!(text.contains("# TYPE usbwatch_scan_duration_seconds histogram\n"));
+}
+
+#[test]
+fn test_scan_duration_histogram() {
+    let metrics = Metrics::new();
+    metrics.observe_scan(Duration::from_micros(500));
+    metrics.observe_scan(Duration::from_millis(20));
+    metrics.observe_scan(Duration::from_secs(10));
+
+    let text = metrics.render(&[]);
+    assert!(text.contains(r#"usbwatch_scan_duration_seconds_bucket{le="0.001"} 1"#));
+    assert!(text.contains(r#"usbwatch_scan_duration_seconds_bucket{le="0.025"} 2"#));
+    assert!(text.contains(r#"usbwatch_scan_duration_seconds_bucket{le="2.5"} 2"#));
+    assert!(text.contains(r#"usbwatch_scan_duration_seconds_bucket{le="+Inf"} 3"#));
+    assert!(text.contains("usbwatch_scan_duration_seconds_count 3\n"));
+    assert!(text.contains("usbwatch_scan_duration_seconds_sum 10.0205\n"));
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_watcher_records_scans() {
+    use std::fs;
+    use std::sync::{Arc, RwLock};
+    use tokio::sync::mpsc;
+    use usbwatch_rs::policy::Policy;
+    use usbwatch_rs::watcher::{UsbWatcher, WatcherOptions};
+
+    let root = tempfile::tempdir().unwrap();
+    let devices = root.path().join("bus/usb/devices");
+    fs::create_dir_all(&devices).unwrap();
+
+    let metrics = Arc::new(Metrics::new());
+    let policy = Policy::from_json(r#"{ "default": "block", "rules": [] }"#).unwrap();
+    let (tx, mut rx) = mpsc::channel(10);
+    let watcher = UsbWatcher::with_options(
+        tx,
+        WatcherOptions {
+            sysfs_root: Some(root.path().to_path_buf()),
+            metrics: Some(metrics.clone()),
+            policy: Some(Arc::new(RwLock::new(policy))),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let handle =
+        tokio::spawn(async move { watcher.start_monitoring().await.map_err(|e| e.to_string()) });
+
+    // Devices already attached at startup are not enforced, so plug the drive in afterwards
+    while !metrics
+        .render(&[])
+        .contains("usbwatch_scan_duration_seconds_count 1\n")
+    {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let drive = devices.join("1-1");
+    fs::create_dir_all(&drive).unwrap();
+    fs::write(drive.join("idVendor"), "0781\n").unwrap();
+    fs::write(drive.join("idProduct"), "5583\n").unwrap();
+
+    tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    handle.abort();
+
+    let text = metrics.render(&[]);
+    assert!(
+        text.contains("usbwatch_scan_duration_seconds_count 2\n"),
+        "{text}"
+    );
+    // Blocks are counted by the watcher, before any later stage can drop the event
+    assert!(text.contains("usbwatch_policy_blocks_total 1\n"), "{text}");
+}
+
+#[tokio::test]
+async fn test_metrics_task_counts_and_forwards() {
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+    use usbwatch_rs::metrics::metrics_task;
+
+    let metrics = Arc::new(Metrics::new());
+    let (tx, rx) = mpsc::channel(10);
+    let (counted_tx, mut counted_rx) = mpsc::channel(10);
+    tokio::spawn(metrics_task(rx, counted_tx, metrics.clone()));
+
+    tx.send(with_class(
+        event("0781", "A1", DeviceEventType::Connected),
+        0x08,
+    ))
+    .await
+    .unwrap();
+    tx.send(with_class(
+        event("0781", "A1", DeviceEventType::Disconnected),
+        0x08,
+    ))
+    .await
+    .unwrap();
+    drop(tx);
+
+    let mut forwarded = Vec::new();
+    while let Some(event) = counted_rx.recv().await {
+        forwarded.push(event.event_type);
+    }
+    assert_eq!(
+        forwarded,
+        [DeviceEventType::Connected, DeviceEventType::Disconnected]
+    );
+    assert_eq!(metrics.events_total(), 2);
+}
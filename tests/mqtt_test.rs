@@ -0,0 +1,234 @@
+// Integration tests for the MQTT publisher
+#![cfg(feature = "mqtt")]
+
+mod common;
+
+use common::event;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+use usbwatch_rs::mqtt::{mqtt_task, MqttConfig, MqttPublisher, STATUS_OFFLINE, STATUS_ONLINE};
+
+/// A packet received by the stand-in broker.
+#[derive(Debug)]
+enum Received {
+    Connect {
+        client_id: String,
+        will_topic: String,
+        will_message: String,
+        will_retain: bool,
+    },
+    Publish {
+        topic: String,
+        payload: String,
+        qos: u8,
+        retain: bool,
+    },
+    Disconnect,
+}
+
+async fn read_packet(stream: &mut TcpStream) -> Option<(u8, Vec<u8>)> {
+    let header = stream.read_u8().await.ok()?;
+    let mut length = 0usize;
+    for shift in (0..28).step_by(7) {
+        let byte = stream.read_u8().await.ok()?;
+        length |= usize::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    let mut body = vec![0; length];
+    stream.read_exact(&mut body).await.ok()?;
+    Some((header, body))
+}
+
+fn take_string(body: &mut &[u8]) -> String {
+    let length = usize::from(u16::from_be_bytes([body[0], body[1]]));
+    let value = String::from_utf8(body[2..2 + length].to_vec()).unwrap();
+    *body = &body[2 + length..];
+    value
+}
+
+/// Starts a stand-in MQTT 3.1.1 broker that acknowledges everything and
+/// reports each packet it receives. Acknowledgements may fail once the
+/// client has disconnected.
+async fn broker() -> (SocketAddr, mpsc::UnboundedReceiver<Received>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        while let Some((header, body)) = read_packet(&mut stream).await {
+            let mut rest = body.as_slice();
+            let received = match header >> 4 {
+                1 => {
+                    take_string(&mut rest); // Protocol name
+                    let flags = rest[1];
+                    rest = &rest[4..];
+                    let client_id = take_string(&mut rest);
+                    let will_topic = take_string(&mut rest);
+                    let will_message = take_string(&mut rest);
+                    let _ = stream.write_all(&[0x20, 0x02, 0x00, 0x00]).await;
+                    Received::Connect {
+                        client_id,
+                        will_topic,
+                        will_message,
+                        will_retain: flags & 0x20 != 0,
+                    }
+                }
+                3 => {
+                    let qos = (header >> 1) & 0x03;
+                    let topic = take_string(&mut rest);
+                    if qos > 0 {
+                        let ack = if qos == 1 { 0x40 } else { 0x50 };
+                        let _ = stream.write_all(&[ack, 0x02, rest[0], rest[1]]).await;
+                        rest = &rest[2..];
+                    }
+                    Received::Publish {
+                        topic,
+                        payload: String::from_utf8(rest.to_vec()).unwrap(),
+                        qos,
+                        retain: header & 0x01 != 0,
+                    }
+                }
+                6 => {
+                    // PUBREL of a QoS 2 publish
+                    let _ = stream.write_all(&[0x70, 0x02, body[0], body[1]]).await;
+                    continue;
+                }
+                12 => {
+                    let _ = stream.write_all(&[0xd0, 0x00]).await;
+                    continue;
+                }
+                14 => Received::Disconnect,
+                _ => continue,
+            };
+            let _ = tx.send(received);
+        }
+    });
+    (address, rx)
+}
+
+async fn next(rx: &mut mpsc::UnboundedReceiver<Received>) -> Received {
+    tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("nothing received from the publisher")
+        .unwrap()
+}
+
+/// Waits for the next message published to `topic`, skipping others.
+async fn next_on(rx: &mut mpsc::UnboundedReceiver<Received>, wanted: &str) -> (String, u8, bool) {
+    loop {
+        if let Received::Publish {
+            topic,
+            payload,
+            qos,
+            retain,
+        } = next(rx).await
+        {
+            if topic == wanted {
+                return (payload, qos, retain);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_topics() {
+    let config = MqttConfig {
+        prefix: "factory".to_string(),
+        ..MqttConfig::new("mqtt://broker.local", "line/3")
+    };
+    let mut device = event("0781", "A1", DeviceEventType::SuspiciousDevice);
+    device.vendor_id = "046D".to_string();
+    assert_eq!(
+        config.event_topic(&device),
+        "factory/line_3/046d/5583/suspicious-device"
+    );
+    assert_eq!(config.devices_topic(), "factory/line_3/devices");
+    assert_eq!(config.status_topic(), "factory/line_3/status");
+}
+
+#[tokio::test]
+async fn test_invalid_configuration() {
+    let invalid = MqttPublisher::connect(MqttConfig::new("http://broker.local", "host"));
+    assert!(invalid.err().unwrap().contains("mqtt://"));
+    let invalid = MqttPublisher::connect(MqttConfig::new("mqtt://broker.local:port", "host"));
+    assert!(invalid.err().unwrap().contains("invalid port"));
+    let invalid = MqttPublisher::connect(MqttConfig {
+        qos: 3,
+        ..MqttConfig::new("mqtt://broker.local", "host")
+    });
+    assert!(invalid.is_err());
+}
+
+#[tokio::test]
+async fn test_publishes_events_devices_and_status() {
+    let (address, mut received) = broker().await;
+    let config = MqttConfig::new(format!("mqtt://user:secret@{address}"), "bench-1");
+    let publisher = MqttPublisher::connect(config).unwrap();
+
+    let Received::Connect {
+        client_id,
+        will_topic,
+        will_message,
+        will_retain,
+    } = next(&mut received).await
+    else {
+        panic!("expected CONNECT");
+    };
+    assert_eq!(client_id, "usbwatch-bench-1");
+    assert_eq!(will_topic, "usbwatch/bench-1/status");
+    assert_eq!(will_message, STATUS_OFFLINE);
+    assert!(will_retain);
+    assert_eq!(
+        next_on(&mut received, "usbwatch/bench-1/devices").await,
+        ("[]".to_string(), 1, true)
+    );
+    assert_eq!(
+        next_on(&mut received, "usbwatch/bench-1/status").await,
+        (STATUS_ONLINE.to_string(), 1, true)
+    );
+
+    let (events_tx, events_rx) = mpsc::channel(10);
+    let (forward_tx, mut forward_rx) = mpsc::channel(10);
+    let task = tokio::spawn(mqtt_task(events_rx, forward_tx, publisher));
+    events_tx
+        .send(event("0781", "A1", DeviceEventType::Connected))
+        .await
+        .unwrap();
+    events_tx
+        .send(event("0781", "B2", DeviceEventType::Connected))
+        .await
+        .unwrap();
+    events_tx
+        .send(event("0781", "A1", DeviceEventType::Disconnected))
+        .await
+        .unwrap();
+
+    let (payload, qos, retain) =
+        next_on(&mut received, "usbwatch/bench-1/0781/5583/disconnected").await;
+    let device: UsbDeviceInfo = serde_json::from_str(&payload).unwrap();
+    assert_eq!(device.serial_number.as_deref(), Some("A1"));
+    assert_eq!((qos, retain), (1, false));
+    let (payload, _, retain) = next_on(&mut received, "usbwatch/bench-1/devices").await;
+    assert!(retain);
+    let devices: Vec<UsbDeviceInfo> = serde_json::from_str(&payload).unwrap();
+    assert_eq!(devices.len(), 1);
+    assert_eq!(devices[0].serial_number.as_deref(), Some("B2"));
+    for _ in 0..3 {
+        forward_rx.recv().await.unwrap();
+    }
+
+    // Stopping the pipeline marks the host offline and disconnects
+    drop(events_tx);
+    task.await.unwrap();
+    assert_eq!(
+        next_on(&mut received, "usbwatch/bench-1/status").await,
+        (STATUS_OFFLINE.to_string(), 1, true)
+    );
+    assert!(matches!(next(&mut received).await, Received::Disconnect));
+}
@@ -0,0 +1,216 @@
+// Integration tests for desktop notifications against a private D-Bus session
+#![cfg(all(feature = "notify", target_os = "linux"))]
+
+mod common;
+
+use common::event;
+use futures_util::StreamExt;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+use usbwatch_rs::notify::{body, icon_name, notify_task, summary, Notifier, RateLimit};
+use usbwatch_rs::usb_class::ClassCode;
+use zbus::zvariant::OwnedValue;
+use zbus::{Connection, MessageStream, MessageType};
+
+fn storage(serial: &str, event_type: DeviceEventType) -> UsbDeviceInfo {
+    let mut device = event("0781", serial, event_type);
+    device.device_class = Some(ClassCode::new(0x08, 0x06, 0x50));
+    device
+}
+
+/// A private `dbus-daemon`, stopped when dropped.
+struct Bus {
+    daemon: Child,
+    address: String,
+}
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+/// Starts a private session bus, or `None` if `dbus-daemon` is not installed.
+fn start_bus() -> Option<Bus> {
+    let mut daemon = Command::new("dbus-daemon")
+        .args(["--session", "--nofork", "--print-address=1"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let mut address = String::new();
+    BufReader::new(daemon.stdout.take()?)
+        .read_line(&mut address)
+        .ok()?;
+    Some(Bus {
+        daemon,
+        address: address.trim().to_string(),
+    })
+}
+
+/// A `Notify` call received by the stand-in notification server.
+#[derive(Debug)]
+struct Notification {
+    app_name: String,
+    icon: String,
+    summary: String,
+    body: String,
+    urgency: u8,
+}
+
+/// Starts a stand-in notification server on the bus that answers every
+/// `Notify` call and reports it.
+async fn notification_server(address: &str) -> mpsc::UnboundedReceiver<Notification> {
+    let connection = zbus::connection::Builder::address(address)
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    connection
+        .request_name("org.freedesktop.Notifications")
+        .await
+        .unwrap();
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(serve_notifications(connection, tx));
+    rx
+}
+
+async fn serve_notifications(connection: Connection, tx: mpsc::UnboundedSender<Notification>) {
+    let mut messages = MessageStream::from(&connection);
+    let mut id = 0u32;
+    while let Some(Ok(message)) = messages.next().await {
+        let header = message.header();
+        if header.message_type() != MessageType::MethodCall
+            || header.member().map(|m| m.as_str()) != Some("Notify")
+        {
+            continue;
+        }
+        type Arguments = (
+            String,
+            u32,
+            String,
+            String,
+            String,
+            Vec<String>,
+            HashMap<String, OwnedValue>,
+            i32,
+        );
+        let (app_name, _, icon, summary, body, _, hints, _): Arguments =
+            message.body().deserialize().unwrap();
+        id += 1;
+        connection.reply(&message, &(id,)).await.unwrap();
+        let _ = tx.send(Notification {
+            app_name,
+            icon,
+            summary,
+            body,
+            urgency: u8::try_from(&hints["urgency"]).unwrap(),
+        });
+    }
+}
+
+async fn next(rx: &mut mpsc::UnboundedReceiver<Notification>) -> Notification {
+    tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("no notification received")
+        .unwrap()
+}
+
+#[test]
+fn test_notification_text() {
+    let device = storage("A1", DeviceEventType::Disconnected);
+    assert_eq!(summary(&device), "USB device disconnected");
+    assert_eq!(body(&device), "USB Storage (0781:5583)");
+    assert_eq!(icon_name(&device), "drive-removable-media");
+
+    let mut keyboard = storage("B2", DeviceEventType::SuspiciousDevice);
+    keyboard.device_class = Some(ClassCode::new(0x03, 0x01, 0x01));
+    assert_eq!(summary(&keyboard), "Suspicious USB device");
+    assert_eq!(icon_name(&keyboard), "input-keyboard");
+
+    // Device strings cannot inject markup
+    keyboard.device_name = "<a href=\"https://example.com\">Update</a>".to_string();
+    keyboard.reason = Some("HID & storage <interfaces>".to_string());
+    assert_eq!(
+        body(&keyboard),
+        "&lt;a href=\"https://example.com\"&gt;Update&lt;/a&gt; (0781:5583)\n\
+         HID &amp; storage &lt;interfaces&gt;"
+    );
+}
+
+#[tokio::test]
+async fn test_notifications_over_dbus() {
+    let Some(bus) = start_bus() else {
+        eprintln!("dbus-daemon not found; skipping");
+        return;
+    };
+    let mut received = notification_server(&bus.address).await;
+    let mut notifier = Notifier::connect(Some(&bus.address), RateLimit::default())
+        .await
+        .unwrap();
+
+    notifier
+        .notify(&storage("A1", DeviceEventType::Connected))
+        .await;
+    let notification = next(&mut received).await;
+    assert_eq!(notification.app_name, "usbwatch");
+    assert_eq!(notification.icon, "drive-removable-media");
+    assert_eq!(notification.summary, "USB device connected");
+    assert_eq!(notification.body, "USB Storage (0781:5583)");
+    assert_eq!(notification.urgency, 1);
+
+    notifier
+        .notify(&storage("A1", DeviceEventType::SuspiciousDevice))
+        .await;
+    let notification = next(&mut received).await;
+    assert_eq!(notification.summary, "Suspicious USB device");
+    assert_eq!(notification.urgency, 2);
+}
+
+#[tokio::test]
+async fn test_burst_is_rate_limited() {
+    let Some(bus) = start_bus() else {
+        eprintln!("dbus-daemon not found; skipping");
+        return;
+    };
+    let mut received = notification_server(&bus.address).await;
+    let limit = RateLimit {
+        burst: 2,
+        window: Duration::from_millis(300),
+    };
+    let notifier = Notifier::connect(Some(&bus.address), limit).await.unwrap();
+    let (events_tx, events_rx) = mpsc::channel(10);
+    let (forward_tx, mut forward_rx) = mpsc::channel(10);
+    tokio::spawn(notify_task(events_rx, forward_tx, notifier));
+    for serial in ["A1", "B2", "C3", "D4", "E5"] {
+        events_tx
+            .send(storage(serial, DeviceEventType::Connected))
+            .await
+            .unwrap();
+    }
+
+    // Every event is still forwarded
+    for _ in 0..5 {
+        forward_rx.recv().await.unwrap();
+    }
+    assert_eq!(next(&mut received).await.summary, "USB device connected");
+    assert_eq!(next(&mut received).await.summary, "USB device connected");
+    // Suspicious devices are shown even when the window is full
+    events_tx
+        .send(storage("F6", DeviceEventType::SuspiciousDevice))
+        .await
+        .unwrap();
+    forward_rx.recv().await.unwrap();
+    assert_eq!(next(&mut received).await.summary, "Suspicious USB device");
+    assert_eq!(next(&mut received).await.summary, "3 more USB events");
+    assert!(
+        tokio::time::timeout(Duration::from_millis(500), received.recv())
+            .await
+            .is_err()
+    );
+}
@@ -0,0 +1,244 @@
+// Integration tests for the device authorisation policy
+
+mod common;
+
+use common::device;
+use usbwatch_rs::policy::{Policy, PolicyAction};
+
+#[test]
+fn test_first_matching_rule_wins() {
+    let policy = Policy::from_json(
+        r#"{
+            "rules": [
+                { "name": "trusted", "vendor_id": "0781", "serial": "ABC*", "action": "allow" },
+                { "vendor_id": "0781", "action": "block" },
+                { "name": "front-panel", "port": "1-2*", "action": "log" }
+            ]
+        }"#,
+    )
+    .unwrap();
+
+    let decision = policy.evaluate(&device("0781", "5583", Some("abc123"), "1-1"));
+    assert_eq!(decision.action, PolicyAction::Allow);
+    assert_eq!(decision.rule, "trusted");
+
+    let decision = policy.evaluate(&device("0781", "5583", Some("XYZ"), "1-1"));
+    assert_eq!(decision.action, PolicyAction::Block);
+    assert_eq!(decision.rule, "#2");
+
+    let decision = policy.evaluate(&device("046d", "c31c", None, "1-2.4"));
+    assert_eq!(decision.action, PolicyAction::Log);
+    assert_eq!(decision.to_string(), "log (rule 'front-panel')");
+
+    let decision = policy.evaluate(&device("046d", "c31c", None, "3-1"));
+    assert_eq!(decision.action, PolicyAction::Allow);
+    assert_eq!(decision.rule, "default");
+    assert!(!policy.default_deny());
+}
+
+#[test]
+fn test_invalid_policy_is_rejected() {
+    assert!(
+        Policy::from_json(r#"{ "rules": [{ "vendor": "0781", "action": "block" }] }"#).is_err()
+    );
+    assert!(Policy::from_json(r#"{ "default": "deny" }"#).is_err());
+    assert!(Policy::load("/nonexistent/policy.json").is_err());
+}
+
+/// What happened to a device under a policy: its event, its `authorized`
+/// attribute once the watcher stopped and `authorized_default` on the root
+/// hub while the watcher ran and after it stopped.
+#[cfg(target_os = "linux")]
+struct Enforced {
+    event: usbwatch_rs::device_info::UsbDeviceInfo,
+    authorized: String,
+    default_while_running: String,
+    default_after: String,
+}
+
+/// Runs the watcher with `policy` over a root hub whose `authorized_default`
+/// is `2`, then plugs in a device with the given attributes. With `present`
+/// the device is already attached when the watcher starts.
+#[cfg(target_os = "linux")]
+async fn enforce(policy: &str, attributes: &[(&str, &[u8])], present: bool) -> Enforced {
+    use std::fs;
+    use std::sync::{Arc, RwLock};
+    use tokio::sync::mpsc;
+    use usbwatch_rs::watcher::{UsbWatcher, WatcherOptions};
+
+    let root = tempfile::tempdir().unwrap();
+    let devices = root.path().join("bus/usb/devices");
+    let hub = devices.join("usb1");
+    let device = devices.join("1-1");
+    fs::create_dir_all(&hub).unwrap();
+    fs::write(hub.join("idVendor"), "1d6b\n").unwrap();
+    fs::write(hub.join("idProduct"), "0002\n").unwrap();
+    fs::write(hub.join("authorized"), "1\n").unwrap();
+    fs::write(hub.join("authorized_default"), "2\n").unwrap();
+    // Written elsewhere and moved in, so a scan never sees half a device
+    let staged = root.path().join("1-1");
+    fs::create_dir_all(&staged).unwrap();
+    for (name, value) in attributes {
+        fs::write(staged.join(name), value).unwrap();
+    }
+    if present {
+        fs::rename(&staged, &device).unwrap();
+    }
+
+    let (tx, mut rx) = mpsc::channel(10);
+    let watcher = UsbWatcher::with_options(
+        tx,
+        WatcherOptions {
+            policy: Some(Arc::new(RwLock::new(Policy::from_json(policy).unwrap()))),
+            sysfs_root: Some(root.path().to_path_buf()),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let handle =
+        tokio::spawn(async move { watcher.start_monitoring().await.map_err(|e| e.to_string()) });
+    let event = loop {
+        let event = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        if event.port_path.as_deref() == Some("usb1") && !present {
+            fs::rename(&staged, &device).unwrap();
+        }
+        if event.port_path.as_deref() == Some("1-1") {
+            break event;
+        }
+    };
+    let default_while_running = fs::read_to_string(hub.join("authorized_default")).unwrap();
+    handle.abort();
+    let _ = handle.await;
+
+    // Root hubs are never deauthorised
+    assert_eq!(
+        fs::read_to_string(hub.join("authorized")).unwrap().trim(),
+        "1"
+    );
+    Enforced {
+        event,
+        authorized: fs::read_to_string(device.join("authorized"))
+            .unwrap()
+            .trim()
+            .to_string(),
+        default_while_running: default_while_running.trim().to_string(),
+        default_after: fs::read_to_string(hub.join("authorized_default"))
+            .unwrap()
+            .trim()
+            .to_string(),
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_watcher_enforces_policy_through_sysfs() {
+    let enforced = enforce(
+        r#"{ "default": "block", "rules": [{ "vendor_id": "1d6b", "action": "allow" }] }"#,
+        &[
+            ("idVendor", b"0781\n"),
+            ("idProduct", b"5583\n"),
+            ("authorized", b"1\n"),
+        ],
+        false,
+    )
+    .await;
+
+    let decision = enforced.event.policy.unwrap();
+    assert_eq!(decision.action, PolicyAction::Block);
+    assert_eq!(decision.rule, "default");
+    assert!(decision.error.is_none());
+    assert_eq!(enforced.authorized, "0");
+    // Root hubs stop authorising new devices while the policy is enforced and
+    // get their own setting back once the watcher stops
+    assert_eq!(enforced.default_while_running, "0");
+    assert_eq!(enforced.default_after, "2");
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_devices_present_at_startup_are_not_enforced() {
+    let enforced = enforce(
+        r#"{ "default": "block", "rules": [] }"#,
+        &[
+            ("idVendor", b"046d\n"),
+            ("idProduct", b"c31c\n"),
+            ("authorized", b"1\n"),
+        ],
+        true,
+    )
+    .await;
+
+    // The decision is reported, but the keyboard in use keeps working
+    let decision = enforced.event.policy.unwrap();
+    assert_eq!(decision.action, PolicyAction::Block);
+    assert!(decision.error.is_none());
+    assert_eq!(enforced.authorized, "1");
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_unreadable_authorized_attribute_is_still_enforced() {
+    let enforced = enforce(
+        r#"{ "default": "block", "rules": [] }"#,
+        &[("idVendor", b"0781\n"), ("idProduct", b"5583\n")],
+        false,
+    )
+    .await;
+
+    assert_eq!(enforced.event.policy.unwrap().action, PolicyAction::Block);
+    assert_eq!(enforced.authorized, "0");
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_class_rules_match_descriptors_of_unauthorised_devices() {
+    // Device, configuration and HID interface descriptors; an unauthorised
+    // device has no interface directories to read the class from
+    let mut descriptors = vec![
+        18, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 64, 0x6d, 0x04, 0x1c, 0xc3, 0x00, 0x01, 0, 0, 0, 1,
+    ];
+    descriptors.extend([9, 0x02, 18, 0, 1, 1, 0, 0xa0, 50]);
+    descriptors.extend([9, 0x04, 0, 0, 0, 0x03, 0x01, 0x01, 0]);
+
+    let enforced = enforce(
+        r#"{ "default": "block", "rules": [{ "name": "hid", "class": "hid", "action": "allow" }] }"#,
+        &[
+            ("idVendor", b"046d\n"),
+            ("idProduct", b"c31c\n"),
+            ("authorized", b"0\n"),
+            ("descriptors", &descriptors),
+        ],
+        false,
+    )
+    .await;
+
+    let decision = enforced.event.policy.unwrap();
+    assert_eq!(decision.action, PolicyAction::Allow);
+    assert_eq!(decision.rule, "hid");
+    assert_eq!(enforced.authorized, "1");
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_allow_leaves_deauthorised_devices_alone() {
+    // Without default-deny, allowing a device does not undo another tool's
+    // decision to deauthorise it
+    let enforced = enforce(
+        r#"{ "default": "allow", "rules": [] }"#,
+        &[
+            ("idVendor", b"0781\n"),
+            ("idProduct", b"5583\n"),
+            ("authorized", b"0\n"),
+        ],
+        false,
+    )
+    .await;
+
+    assert_eq!(enforced.event.policy.unwrap().action, PolicyAction::Allow);
+    assert_eq!(enforced.authorized, "0");
+    // Left alone without default-deny
+    assert_eq!(enforced.default_while_running, "2");
+}
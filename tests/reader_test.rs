@@ -0,0 +1,48 @@
+// Integration tests for reading JSON event logs back
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::Write;
+use usbwatch_rs::device_info::DeviceEventType;
+use usbwatch_rs::reader::EventReader;
+
+const CONNECTED: &str = r#"{"device_name":"SanDisk Ultra","vendor_id":"0781","product_id":"5583","serial_number":"AAA","timestamp":"2025-07-27T10:30:15Z","event_type":"Connected"}"#;
+const DISCONNECTED: &str = r#"{"device_name":"SanDisk Ultra","vendor_id":"0781","product_id":"5583","serial_number":"AAA","timestamp":"2025-07-27T10:31:15Z","event_type":"Disconnected"}"#;
+
+#[test]
+fn test_reader_skips_non_json_and_reports_bad_records() {
+    let log = format!("🔌 USB Device Monitor\n{CONNECTED}\n\n{{\"broken\": true}}\n");
+    let events: Vec<_> = EventReader::new(log.as_bytes()).collect();
+
+    assert_eq!(events.len(), 2);
+    assert!(events[0].is_ok());
+    let error = events[1].as_ref().unwrap_err();
+    assert!(
+        error.contains(":4:"),
+        "error should carry the line number: {error}"
+    );
+}
+
+#[test]
+fn test_read_all_merges_rotated_gzip_logs() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let current = dir.path().join("events.json");
+    std::fs::write(&current, format!("{DISCONNECTED}\n{{\"truncated\"\n")).unwrap();
+
+    let rotated = dir.path().join("events.json.1.gz");
+    let mut encoder = GzEncoder::new(
+        std::fs::File::create(&rotated).unwrap(),
+        Compression::default(),
+    );
+    writeln!(encoder, "{CONNECTED}").unwrap();
+    encoder.finish().unwrap();
+
+    let log = EventReader::read_all(&[current, rotated]).unwrap();
+    assert_eq!(log.events.len(), 2);
+    assert_eq!(log.events[0].event_type, DeviceEventType::Connected);
+    assert_eq!(log.events[1].event_type, DeviceEventType::Disconnected);
+    // The corrupt record is reported rather than silently dropped
+    assert_eq!(log.skipped.len(), 1);
+    assert!(log.skipped[0].contains("events.json:2:"));
+}
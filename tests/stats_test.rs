@@ -0,0 +1,63 @@
+// Integration tests for summary statistics over event logs
+
+mod common;
+
+use chrono::{Duration, TimeZone, Utc};
+use common::event;
+use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+use usbwatch_rs::stats::EventStats;
+
+fn at_minute(mut device: UsbDeviceInfo, minute: i64) -> UsbDeviceInfo {
+    device.timestamp =
+        Utc.with_ymd_and_hms(2025, 7, 27, 10, 0, 0).unwrap() + Duration::minutes(minute);
+    device
+}
+
+#[test]
+fn test_stats_dwell_and_frequency() {
+    let events = vec![
+        at_minute(event("0781", "AAA", DeviceEventType::Connected), 0),
+        at_minute(event("0781", "AAA", DeviceEventType::Disconnected), 10),
+        at_minute(event("0781", "AAA", DeviceEventType::Connected), 20),
+        at_minute(event("0781", "AAA", DeviceEventType::Disconnected), 50),
+        at_minute(event("0781", "BBB", DeviceEventType::Connected), 70),
+    ];
+
+    let stats = EventStats::from_events(&events);
+    assert_eq!(stats.total_events, 5);
+    assert_eq!(stats.devices[0].key, "0781:5583:AAA", "most plugged first");
+    assert_eq!(stats.devices[0].connected_secs, 40.0 * 60.0);
+    assert_eq!(stats.devices[0].mean_dwell_secs, Some(20.0 * 60.0));
+    assert_eq!(stats.devices[1].mean_dwell_secs, None);
+    assert_eq!(stats.seen_once, vec!["0781:5583:BBB".to_string()]);
+    assert_eq!(stats.hourly[10], 4);
+    assert_eq!(stats.hourly[11], 1);
+    assert_eq!(stats.daily.len(), 1);
+}
+
+#[test]
+fn test_devices_behind_collapsed_hubs_are_counted() {
+    // A hub plugged in with a drive behind it, then unplugged with it
+    let mut connected = at_minute(event("0781", "HUB", DeviceEventType::Connected), 0);
+    connected.children.push(at_minute(
+        event("0781", "AAA", DeviceEventType::Connected),
+        0,
+    ));
+    let mut disconnected = at_minute(event("0781", "HUB", DeviceEventType::Disconnected), 30);
+    disconnected.children.push(at_minute(
+        event("0781", "AAA", DeviceEventType::Disconnected),
+        30,
+    ));
+
+    let stats = EventStats::from_events(&[connected, disconnected]);
+    assert_eq!(stats.total_events, 2);
+    let drive = stats
+        .devices
+        .iter()
+        .find(|device| device.key == "0781:5583:AAA")
+        .unwrap();
+    assert_eq!(drive.connects, 1);
+    assert_eq!(drive.disconnects, 1);
+    assert_eq!(drive.connected_secs, 30.0 * 60.0);
+    assert_eq!(stats.mean_dwell_secs, Some(30.0 * 60.0));
+}
@@ -0,0 +1,131 @@
+// Integration tests for the SQLite event store
+
+#![cfg(feature = "sqlite")]
+
+mod common;
+
+use chrono::{Duration, Utc};
+use common::event;
+use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+use usbwatch_rs::store::{EventStore, HistoryQuery};
+
+fn minutes_ago(mut device: UsbDeviceInfo, minutes: i64) -> UsbDeviceInfo {
+    device.timestamp = Utc::now() - Duration::minutes(minutes);
+    device
+}
+
+#[test]
+fn test_store_query_filters() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = EventStore::open(dir.path().join("events.db")).unwrap();
+
+    store
+        .insert(&minutes_ago(
+            event("0781", "AAA", DeviceEventType::Connected),
+            90,
+        ))
+        .unwrap();
+    store
+        .insert(&minutes_ago(
+            event("0781", "AAA", DeviceEventType::Disconnected),
+            60,
+        ))
+        .unwrap();
+    store
+        .insert(&minutes_ago(
+            event("0781", "BBB", DeviceEventType::Connected),
+            30,
+        ))
+        .unwrap();
+
+    let all = store.query(&HistoryQuery::default()).unwrap();
+    assert_eq!(all.len(), 3);
+    assert_eq!(all[0].serial_number.as_deref(), Some("BBB"), "newest first");
+
+    let last_seen = store
+        .query(&HistoryQuery {
+            serial_number: Some("AAA".to_string()),
+            limit: Some(1),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(last_seen.len(), 1);
+    assert_eq!(last_seen[0].event_type, DeviceEventType::Disconnected);
+
+    let recent = store
+        .query(&HistoryQuery {
+            since: Some(Utc::now() - Duration::minutes(45)),
+            vendor_id: Some("0781".to_string()),
+            product_id: Some("5583".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(recent.len(), 1);
+
+    let connects = store
+        .query(&HistoryQuery {
+            event_type: Some(DeviceEventType::Connected),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(connects.len(), 2);
+}
+
+#[test]
+fn test_parse_time_spec_ages() {
+    use usbwatch_rs::store::parse_time_spec;
+
+    let age = Utc::now() - parse_time_spec("90m").unwrap();
+    assert!((89..=90).contains(&age.num_minutes()), "{age}");
+    // Ages that would overflow or point into the future are refused
+    for spec in ["99999999999d", "-5h", "0s", "h"] {
+        assert!(
+            parse_time_spec(spec)
+                .unwrap_err()
+                .starts_with("Invalid time"),
+            "{spec}"
+        );
+    }
+}
+
+#[test]
+fn test_devices_behind_collapsed_hubs_are_found() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = EventStore::open(dir.path().join("events.db")).unwrap();
+
+    let mut hub = minutes_ago(event("0781", "HUB", DeviceEventType::Connected), 10);
+    hub.vendor_id = "05e3".to_string();
+    hub.children.push(minutes_ago(
+        event("0781", "AAA", DeviceEventType::Connected),
+        10,
+    ));
+    store.insert(&hub).unwrap();
+    store
+        .insert(&minutes_ago(
+            event("0781", "BBB", DeviceEventType::Connected),
+            5,
+        ))
+        .unwrap();
+
+    // Looking up the drive returns the hub event it arrived with
+    let query = HistoryQuery {
+        vendor_id: Some("0781".to_string()),
+        serial_number: Some("AAA".to_string()),
+        ..Default::default()
+    };
+    let found = store.query(&query).unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].serial_number.as_deref(), Some("HUB"));
+    assert_eq!(found[0].children.len(), 1);
+    // The daemon's in-memory history matches the same way
+    assert_eq!(query.apply([hub.clone()].iter()).len(), 1);
+
+    // Criteria must all match the same device
+    let mixed = HistoryQuery {
+        vendor_id: Some("05e3".to_string()),
+        serial_number: Some("AAA".to_string()),
+        ..Default::default()
+    };
+    assert!(store.query(&mixed).unwrap().is_empty());
+    assert!(mixed.apply([hub].iter()).is_empty());
+}
@@ -0,0 +1,255 @@
+// Integration tests for the terminal dashboard, rendered to a test backend
+#![cfg(feature = "tui")]
+
+use ratatui::backend::TestBackend;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::Terminal;
+use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo, UsbInterfaceInfo};
+use usbwatch_rs::tui::{Dashboard, SortKey};
+use usbwatch_rs::usb_class::ClassCode;
+
+fn event(
+    name: &str,
+    vid_pid: &str,
+    port: &str,
+    driver: &str,
+    event_type: DeviceEventType,
+) -> UsbDeviceInfo {
+    let (vid, pid) = vid_pid.split_once(':').unwrap();
+    let mut device = UsbDeviceInfo::new(
+        name.to_string(),
+        vid.to_string(),
+        pid.to_string(),
+        Some(format!("SN-{port}")),
+        event_type,
+    );
+    device.port_path = Some(port.to_string());
+    device.interfaces.push(UsbInterfaceInfo {
+        id: format!("{port}:1.0"),
+        number: 0,
+        class: ClassCode::new(0x08, 0x06, 0x50),
+        class_name: None,
+        driver: Some(driver.to_string()),
+        hid_classes: Vec::new(),
+    });
+    device
+}
+
+fn key(code: KeyCode) -> KeyEvent {
+    KeyEvent::new(code, KeyModifiers::NONE)
+}
+
+fn type_text(dashboard: &mut Dashboard, text: &str) {
+    for c in text.chars() {
+        dashboard.handle_key(key(KeyCode::Char(c)));
+    }
+}
+
+fn names(dashboard: &Dashboard) -> Vec<String> {
+    dashboard
+        .rows()
+        .iter()
+        .map(|row| row.device.device_name.clone())
+        .collect()
+}
+
+/// Renders the dashboard and returns the screen as lines of text.
+fn render(dashboard: &Dashboard) -> Vec<String> {
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    terminal.draw(|frame| dashboard.render(frame)).unwrap();
+    let buffer = terminal.backend().buffer();
+    (0..buffer.area.height)
+        .map(|y| {
+            (0..buffer.area.width)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect::<String>()
+        })
+        .collect()
+}
+
+fn sample() -> Dashboard {
+    let mut dashboard = Dashboard::new(100);
+    dashboard.record(&event(
+        "SanDisk Ultra",
+        "0781:5583",
+        "1-2",
+        "usb-storage",
+        DeviceEventType::Connected,
+    ));
+    dashboard.record(&event(
+        "Logitech Receiver",
+        "046d:c52b",
+        "1-1",
+        "usbhid",
+        DeviceEventType::Connected,
+    ));
+    dashboard.record(&event(
+        "FTDI Serial",
+        "0403:6001",
+        "3-1",
+        "ftdi_sio",
+        DeviceEventType::Connected,
+    ));
+    dashboard
+}
+
+#[test]
+fn test_record_tracks_connected_devices() {
+    let mut dashboard = sample();
+    assert_eq!(
+        names(&dashboard),
+        ["FTDI Serial", "Logitech Receiver", "SanDisk Ultra"]
+    );
+
+    dashboard.record(&event(
+        "Logitech Receiver",
+        "046d:c52b",
+        "1-1",
+        "usbhid",
+        DeviceEventType::Disconnected,
+    ));
+    dashboard.message("Failed to send webhook");
+    assert_eq!(names(&dashboard), ["FTDI Serial", "SanDisk Ultra"]);
+
+    let log: Vec<&str> = dashboard.log().iter().map(|l| l.text.as_str()).collect();
+    assert_eq!(log.len(), 5);
+    assert!(log[3].starts_with("DISCONNECTED"));
+    assert!(log[3].contains("Logitech Receiver (046d:c52b) on 1-1"));
+    assert_eq!(log[4], "Failed to send webhook");
+    assert!(dashboard.log()[4].event_type.is_none());
+}
+
+#[test]
+fn test_filter_and_sort_keys() {
+    let mut dashboard = sample();
+
+    dashboard.handle_key(key(KeyCode::Char('/')));
+    type_text(&mut dashboard, "usb");
+    // Matches the usb-storage and usbhid drivers
+    assert_eq!(names(&dashboard), ["Logitech Receiver", "SanDisk Ultra"]);
+    // Typing in the filter does not trigger commands
+    assert_eq!(dashboard.sort(), (SortKey::Name, false));
+    dashboard.handle_key(key(KeyCode::Backspace));
+    dashboard.handle_key(key(KeyCode::Backspace));
+    dashboard.handle_key(key(KeyCode::Backspace));
+    type_text(&mut dashboard, "0403:");
+    dashboard.handle_key(key(KeyCode::Enter));
+    assert_eq!(dashboard.filter(), "0403:");
+    assert_eq!(names(&dashboard), ["FTDI Serial"]);
+    dashboard.handle_key(key(KeyCode::Esc));
+    assert_eq!(dashboard.filter(), "");
+    assert_eq!(names(&dashboard).len(), 3);
+
+    // Name -> VID:PID -> Port
+    dashboard.handle_key(key(KeyCode::Char('s')));
+    assert_eq!(
+        names(&dashboard),
+        ["FTDI Serial", "Logitech Receiver", "SanDisk Ultra"]
+    );
+    dashboard.handle_key(key(KeyCode::Char('s')));
+    assert_eq!(dashboard.sort(), (SortKey::Port, false));
+    assert_eq!(
+        names(&dashboard),
+        ["Logitech Receiver", "SanDisk Ultra", "FTDI Serial"]
+    );
+    dashboard.handle_key(key(KeyCode::Char('r')));
+    assert_eq!(
+        names(&dashboard),
+        ["FTDI Serial", "SanDisk Ultra", "Logitech Receiver"]
+    );
+
+    assert!(!dashboard.should_quit());
+    dashboard.handle_key(key(KeyCode::Char('q')));
+    assert!(dashboard.should_quit());
+}
+
+#[test]
+fn test_selection_and_detail_pane() {
+    let mut dashboard = sample();
+    // The first row is selected until the user moves
+    assert_eq!(
+        dashboard.selected().unwrap().device.device_name,
+        "FTDI Serial"
+    );
+    dashboard.handle_key(key(KeyCode::Down));
+    dashboard.handle_key(key(KeyCode::Down));
+    dashboard.handle_key(key(KeyCode::Down));
+    assert_eq!(
+        dashboard.selected().unwrap().device.device_name,
+        "SanDisk Ultra"
+    );
+    dashboard.handle_key(key(KeyCode::Char('k')));
+    assert_eq!(
+        dashboard.selected().unwrap().device.device_name,
+        "Logitech Receiver"
+    );
+
+    let screen = render(&dashboard);
+    assert!(screen[1].contains("Name ▲"));
+    assert!(screen[1].contains("VID:PID"));
+    assert!(screen
+        .iter()
+        .any(|line| line.contains("046d:c52b") && line.contains("1-1") && line.contains("usbhid")));
+    assert!(screen.iter().any(|line| line.contains("CONNECTED")));
+    assert!(!screen.iter().any(|line| line.contains("Details")));
+
+    dashboard.handle_key(key(KeyCode::Enter));
+    assert!(dashboard.showing_details());
+    let screen = render(&dashboard);
+    assert!(screen.iter().any(|line| line.contains("Details")));
+    assert!(screen
+        .iter()
+        .any(|line| line.contains("Key        046d:c52b:SN-1-1")));
+
+    // Esc and q close the detail pane before quitting
+    dashboard.handle_key(key(KeyCode::Esc));
+    assert!(!dashboard.showing_details());
+    assert!(!dashboard.should_quit());
+    dashboard.handle_key(key(KeyCode::Enter));
+    dashboard.handle_key(key(KeyCode::Char('q')));
+    assert!(!dashboard.showing_details());
+    assert!(!dashboard.should_quit());
+    dashboard.handle_key(key(KeyCode::Char('q')));
+    assert!(dashboard.should_quit());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_speed_and_attributes_from_sysfs() {
+    use usbwatch_rs::device_info::DeviceHandle;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut dashboard = Dashboard::new(100);
+    for (port, speed) in [("1-1", "480"), ("2-1", "5000"), ("1-3", "12")] {
+        let path = dir.path().join(port);
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(path.join("speed"), format!("{speed}\n")).unwrap();
+        std::fs::write(path.join("version"), " 3.20\n").unwrap();
+        let mut device = event(
+            &format!("Drive {port}"),
+            "0781:5583",
+            port,
+            "uas",
+            DeviceEventType::Connected,
+        );
+        device.device_handle = DeviceHandle::Linux {
+            sysfs_path: path.to_string_lossy().to_string(),
+            device_node: None,
+        };
+        dashboard.record(&device);
+    }
+
+    for _ in 0..3 {
+        dashboard.handle_key(key(KeyCode::Char('s')));
+    }
+    assert_eq!(dashboard.sort(), (SortKey::Speed, false));
+    assert_eq!(names(&dashboard), ["Drive 1-3", "Drive 1-1", "Drive 2-1"]);
+    assert_eq!(dashboard.rows()[2].speed(), Some("5000"));
+
+    dashboard.handle_key(key(KeyCode::Enter));
+    let screen = render(&dashboard);
+    assert!(screen.iter().any(|line| line.contains("5000M")));
+    assert!(screen
+        .iter()
+        .any(|line| line.contains("version") && line.contains("3.20")));
+}
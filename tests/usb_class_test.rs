@@ -0,0 +1,123 @@
+// Integration tests for USB class decoding and class filters
+
+use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo, UsbInterfaceInfo};
+use usbwatch_rs::filter::EventFilter;
+use usbwatch_rs::usb_class::{ClassCode, UsbClass};
+
+fn interface(number: u8, class: ClassCode) -> UsbInterfaceInfo {
+    UsbInterfaceInfo {
+        id: format!("1-1:1.{number}"),
+        number,
+        class,
+        class_name: None,
+        driver: None,
+        hid_classes: Vec::new(),
+    }
+}
+
+fn composite_device() -> UsbDeviceInfo {
+    let mut device = UsbDeviceInfo::new(
+        "Composite Device".to_string(),
+        "1234".to_string(),
+        "5678".to_string(),
+        None,
+        DeviceEventType::Connected,
+    );
+    device.device_class = Some(ClassCode::new(0x00, 0x00, 0x00));
+    device.interfaces = vec![
+        interface(0, ClassCode::new(0x08, 0x06, 0x50)),
+        interface(1, ClassCode::new(0x03, 0x01, 0x01)),
+    ];
+    device
+}
+
+#[test]
+fn test_class_names_round_trip() {
+    for code in 0..=u8::MAX {
+        let class = UsbClass::from_code(code);
+        assert_eq!(class.name().parse::<UsbClass>(), Ok(class));
+    }
+    assert_eq!("Mass Storage".parse(), Ok(UsbClass::MassStorage));
+    assert_eq!("0x03".parse(), Ok(UsbClass::Hid));
+    assert!("not-a-class".parse::<UsbClass>().is_err());
+}
+
+#[test]
+fn test_class_code_display() {
+    assert_eq!(
+        ClassCode::new(0x08, 0x06, 0x50).to_string(),
+        "Mass Storage (SCSI, Bulk-Only)"
+    );
+    assert_eq!(
+        ClassCode::new(0x03, 0x01, 0x02).to_string(),
+        "HID (Boot Mouse)"
+    );
+    assert_eq!(ClassCode::new(0x03, 0x00, 0x00).to_string(), "HID");
+    assert_eq!(ClassCode::new(0x42, 0x00, 0x00).to_string(), "Reserved");
+}
+
+#[test]
+fn test_composite_device_classes() {
+    let device = composite_device();
+    assert_eq!(device.classes(), vec![UsbClass::MassStorage, UsbClass::Hid]);
+    assert_eq!(
+        device.class_summary().as_deref(),
+        Some("Mass Storage (SCSI, Bulk-Only), HID (Boot Keyboard)")
+    );
+
+    let json = serde_json::to_string(&device).unwrap();
+    let parsed: UsbDeviceInfo = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.interfaces, device.interfaces);
+}
+
+#[test]
+fn test_filter_by_interface_class() {
+    let device = composite_device();
+    let storage = EventFilter {
+        classes: vec![UsbClass::MassStorage],
+        ..Default::default()
+    };
+    let audio = EventFilter {
+        classes: vec![UsbClass::Audio],
+        ..Default::default()
+    };
+    assert!(storage.matches(&device));
+    assert!(!audio.matches(&device));
+    assert!(EventFilter::default().matches(&device));
+
+    let mut hub = UsbDeviceInfo::new(
+        "USB Hub".to_string(),
+        "05e3".to_string(),
+        "0610".to_string(),
+        None,
+        DeviceEventType::Connected,
+    );
+    hub.device_class = Some(ClassCode::new(0x09, 0x00, 0x02));
+    assert!(!storage.matches(&hub));
+    hub.children.push(device);
+    assert!(storage.matches(&hub));
+}
+
+#[test]
+fn test_filter_by_id_port_and_event_type() {
+    let mut device = composite_device();
+    device.port_path = Some("1-2.4".to_string());
+    let filter: EventFilter = serde_json::from_str(
+        r#"{"vendor_id":"1234","port":"1-2","event_types":["Connected"],"classes":["mass-storage"]}"#,
+    )
+    .unwrap();
+    assert!(filter.matches(&device));
+
+    device.port_path = Some("1-20".to_string());
+    assert!(!filter.matches(&device));
+    device.port_path = Some("1-2".to_string());
+    assert!(filter.matches(&device));
+
+    device.event_type = DeviceEventType::Disconnected;
+    assert!(!filter.matches(&device));
+    let other_vendor = EventFilter {
+        vendor_id: Some("046D".to_string()),
+        ..Default::default()
+    };
+    assert!(!other_vendor.matches(&device));
+}
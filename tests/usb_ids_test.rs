@@ -0,0 +1,38 @@
+// Integration tests for usb.ids name resolution against the bundled snapshot
+
+use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+use usbwatch_rs::usb_ids::UsbIds;
+
+fn snapshot() -> UsbIds {
+    UsbIds::load(concat!(env!("CARGO_MANIFEST_DIR"), "/data/usb.ids")).unwrap()
+}
+
+#[test]
+fn test_snapshot_lookups() {
+    let ids = snapshot();
+    assert_eq!(ids.vendor_name("1d6b"), Some("Linux Foundation"));
+    assert_eq!(ids.vendor_name("1D6B"), Some("Linux Foundation"));
+    assert_eq!(ids.product_name("1d6b", "0002"), Some("2.0 root hub"));
+    assert_eq!(
+        ids.class_name(0x03, 0x01, 0x01).as_deref(),
+        Some("Human Interface Device / Boot Interface Subclass / Keyboard")
+    );
+    assert_eq!(ids.class_name(0x09, 0xff, 0xff).as_deref(), Some("Hub"));
+}
+
+#[test]
+fn test_resolve_replaces_unknown_device_name() {
+    let ids = snapshot();
+    let mut device = UsbDeviceInfo::new(
+        "Unknown Device".to_string(),
+        "1d6b".to_string(),
+        "0003".to_string(),
+        None,
+        DeviceEventType::Connected,
+    );
+    ids.resolve(&mut device);
+
+    assert_eq!(device.vendor_name.as_deref(), Some("Linux Foundation"));
+    assert_eq!(device.product_name.as_deref(), Some("3.0 root hub"));
+    assert_eq!(device.device_name, "Linux Foundation 3.0 root hub");
+}
@@ -0,0 +1,252 @@
+// Integration tests for webhook routing, templating, signing, retries and the on-disk queue
+#![cfg(feature = "webhooks")]
+
+mod common;
+
+use common::event;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use usbwatch_rs::device_info::{DeviceEventType, UsbDeviceInfo};
+use usbwatch_rs::policy::{PolicyAction, PolicyDecision};
+use usbwatch_rs::usb_class::ClassCode;
+use usbwatch_rs::webhook::{
+    render_template, sign, RetryConfig, WebhookConfig, WebhookTarget, Webhooks, SIGNATURE_HEADER,
+};
+
+/// A request received by the stand-in endpoint.
+struct Received {
+    headers: HashMap<String, String>,
+    body: String,
+}
+
+/// Starts a stand-in HTTP endpoint that answers with `statuses` in turn
+/// (200 once they run out) and reports each request it receives.
+async fn endpoint(statuses: Vec<u16>) -> (SocketAddr, mpsc::UnboundedReceiver<Received>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut statuses = statuses.into_iter();
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut headers = HashMap::new();
+            let mut line = String::new();
+            reader.read_line(&mut line).await.unwrap();
+            loop {
+                line.clear();
+                reader.read_line(&mut line).await.unwrap();
+                let Some((name, value)) = line.trim_end().split_once(": ") else {
+                    break;
+                };
+                headers.insert(name.to_ascii_lowercase(), value.to_string());
+            }
+            let length: usize = headers["content-length"].parse().unwrap();
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).await.unwrap();
+
+            let status = statuses.next().unwrap_or(200);
+            let response = format!(
+                "HTTP/1.1 {status} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            );
+            reader
+                .get_mut()
+                .write_all(response.as_bytes())
+                .await
+                .unwrap();
+            let _ = tx.send(Received {
+                headers,
+                body: String::from_utf8(body).unwrap(),
+            });
+        }
+    });
+    (address, rx)
+}
+
+async fn next(rx: &mut mpsc::UnboundedReceiver<Received>) -> Received {
+    tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("no webhook received")
+        .unwrap()
+}
+
+fn config(address: SocketAddr, target: WebhookTarget) -> WebhookConfig {
+    let mut config = WebhookConfig {
+        retry: RetryConfig {
+            initial_backoff_ms: 10,
+            max_backoff_ms: 50,
+            max_attempts: None,
+        },
+        ..Default::default()
+    };
+    config.targets.insert(
+        "hook".to_string(),
+        WebhookTarget {
+            url: format!("http://{address}/hook"),
+            ..target
+        },
+    );
+    config
+}
+
+#[test]
+fn test_config_routing() {
+    let config = WebhookConfig::from_json(
+        r#"{
+            "targets": {
+                "chat": { "url": "https://chat.example.com/hook" },
+                "tickets": { "url": "https://tickets.example.com/api", "secret": "s3cret" }
+            },
+            "rules": [
+                { "name": "storage", "match": { "classes": ["mass-storage"] }, "targets": ["chat"] },
+                { "name": "blocked", "policy_action": "block", "targets": ["chat", "tickets"] }
+            ]
+        }"#,
+    )
+    .unwrap();
+
+    let mut storage = event("0781", "A1", DeviceEventType::Connected);
+    storage.device_class = Some(ClassCode::new(0x08, 0x06, 0x50));
+    assert_eq!(config.targets_for(&storage), vec!["chat"]);
+    storage.policy = Some(PolicyDecision {
+        action: PolicyAction::Block,
+        rule: "no-storage".to_string(),
+        error: None,
+    });
+    assert_eq!(config.targets_for(&storage), vec!["chat", "tickets"]);
+    assert!(config
+        .targets_for(&event("046d", "B2", DeviceEventType::Connected))
+        .is_empty());
+
+    let unknown = WebhookConfig::from_json(
+        r#"{ "targets": { "chat": { "url": "https://chat.example.com" } }, "rules": [{ "targets": ["pager"] }] }"#,
+    );
+    assert!(unknown.unwrap_err().contains("unknown target 'pager'"));
+    assert!(
+        WebhookConfig::from_json(r#"{ "targets": { "a/b": { "url": "https://x" } } }"#).is_err()
+    );
+    assert!(
+        WebhookConfig::from_json(r#"{ "targets": { "chat": { "url": "not a url" } } }"#).is_err()
+    );
+}
+
+#[test]
+fn test_render_template() {
+    let mut device = event("0781", "A1", DeviceEventType::Connected);
+    device.port_path = Some("1-2".to_string());
+    let template = json!({
+        "text": "{{device_name}} on port {{port_path}} ({{missing}})",
+        "device": "{{event}}",
+        "ids": ["{{vendor_id}}", "{{product_id}}"],
+        "urgent": true,
+    });
+    let payload = render_template(&template, &device);
+    assert_eq!(payload["text"], "USB Storage on port 1-2 ()");
+    assert_eq!(payload["device"]["serial_number"], "A1");
+    assert_eq!(payload["ids"], json!(["0781", "5583"]));
+    assert_eq!(payload["urgent"], true);
+}
+
+#[tokio::test]
+async fn test_delivery_with_headers_and_signature() {
+    let (address, mut received) = endpoint(Vec::new()).await;
+    let target = WebhookTarget {
+        headers: [("X-Team".to_string(), "platform".to_string())].into(),
+        secret: Some("s3cret".to_string()),
+        template: Some(
+            json!({ "text": "{{device_name}} {{event_type}}", "serial": "{{serial_number}}" }),
+        ),
+        ..Default::default()
+    };
+    let webhooks = Webhooks::start(config(address, target)).unwrap();
+    webhooks.dispatch(&event("0781", "A1", DeviceEventType::Connected));
+
+    let request = next(&mut received).await;
+    let body: Value = serde_json::from_str(&request.body).unwrap();
+    assert_eq!(
+        body,
+        json!({ "text": "USB Storage Connected", "serial": "A1" })
+    );
+    assert_eq!(request.headers["content-type"], "application/json");
+    assert_eq!(request.headers["x-team"], "platform");
+    assert_eq!(
+        request.headers[&SIGNATURE_HEADER.to_ascii_lowercase()],
+        sign("s3cret", request.body.as_bytes())
+    );
+}
+
+#[tokio::test]
+async fn test_retries_in_order() {
+    // Two server errors are retried; the client error drops the second event
+    let (address, mut received) = endpoint(vec![503, 503, 200, 400]).await;
+    let webhooks = Webhooks::start(config(address, WebhookTarget::default())).unwrap();
+    webhooks.dispatch(&event("0781", "A1", DeviceEventType::Connected));
+    webhooks.dispatch(&event("0781", "B2", DeviceEventType::Connected));
+    webhooks.dispatch(&event("0781", "C3", DeviceEventType::Connected));
+
+    let serials: Vec<String> = [
+        next(&mut received).await,
+        next(&mut received).await,
+        next(&mut received).await,
+        next(&mut received).await,
+        next(&mut received).await,
+    ]
+    .iter()
+    .map(|request| {
+        let device: UsbDeviceInfo = serde_json::from_str(&request.body).unwrap();
+        device.serial_number.unwrap()
+    })
+    .collect();
+    assert_eq!(serials, ["A1", "A1", "A1", "B2", "C3"]);
+    assert!(
+        tokio::time::timeout(Duration::from_millis(100), received.recv())
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_queue_survives_restart() {
+    let queue = tempfile::tempdir().unwrap();
+    let down = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let down_address = down.local_addr().unwrap();
+    drop(down);
+
+    let mut first = config(down_address, WebhookTarget::default());
+    first.queue_dir = Some(queue.path().to_path_buf());
+    // Keep the first worker waiting so it does not touch the queue again
+    first.retry.initial_backoff_ms = 60_000;
+    let webhooks = Webhooks::start(first).unwrap();
+    webhooks.dispatch(&event("0781", "A1", DeviceEventType::Connected));
+
+    let pending = queue.path().join("hook");
+    let queued = || std::fs::read_dir(&pending).unwrap().count();
+    for _ in 0..100 {
+        if queued() == 1 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(queued(), 1);
+
+    let (address, mut received) = endpoint(Vec::new()).await;
+    let mut second = config(address, WebhookTarget::default());
+    second.queue_dir = Some(queue.path().to_path_buf());
+    let _restarted = Webhooks::start(second).unwrap();
+    let request = next(&mut received).await;
+    let device: UsbDeviceInfo = serde_json::from_str(&request.body).unwrap();
+    assert_eq!(device.serial_number.as_deref(), Some("A1"));
+
+    for _ in 0..100 {
+        if queued() == 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(queued(), 0);
+}
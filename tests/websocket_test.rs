@@ -0,0 +1,221 @@
+// Integration tests for the WebSocket live event feed
+#![cfg(feature = "http")]
+
+mod common;
+
+use common::{device, event, in_port};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::json;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use usbwatch_rs::daemon::DaemonState;
+use usbwatch_rs::device_info::DeviceEventType;
+use usbwatch_rs::http::{serve, HttpOptions};
+use usbwatch_rs::websocket::ServerMessage;
+
+type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+async fn start(state: Arc<DaemonState>, options: HttpOptions) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(serve(listener, state, options));
+    address
+}
+
+async fn connect(address: SocketAddr) -> Client {
+    tokio_tungstenite::connect_async(format!("ws://{address}/ws"))
+        .await
+        .unwrap()
+        .0
+}
+
+async fn receive(client: &mut Client) -> ServerMessage {
+    loop {
+        match client.next().await.unwrap().unwrap() {
+            Message::Text(text) => return serde_json::from_str(text.as_str()).unwrap(),
+            _ => continue,
+        }
+    }
+}
+
+/// Waits until `count` clients are subscribed to the state's events.
+async fn wait_for_subscribers(state: &DaemonState, count: usize) {
+    while state.stats().subscribers < count {
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_snapshot_then_events() {
+    let state = Arc::new(DaemonState::new(100));
+    state.record(&device("0483", "5583", Some("A1"), "1-2.1"));
+    let address = start(state.clone(), HttpOptions::default()).await;
+
+    let mut client = connect(address).await;
+    match receive(&mut client).await {
+        ServerMessage::Snapshot { devices, filter } => {
+            assert_eq!(devices.len(), 1);
+            assert!(filter.is_empty());
+        }
+        other => panic!("expected a snapshot, got {other:?}"),
+    }
+
+    wait_for_subscribers(&state, 1).await;
+    state.record(&in_port(
+        event("0483", "A1", DeviceEventType::Disconnected),
+        "1-2.1",
+    ));
+    match receive(&mut client).await {
+        ServerMessage::Event { event } => {
+            assert_eq!(event.event_type, DeviceEventType::Disconnected)
+        }
+        other => panic!("expected an event, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_filter_narrows_feed() {
+    let state = Arc::new(DaemonState::new(100));
+    state.record(&device("0483", "5583", Some("A1"), "1-2.1"));
+    state.record(&device("0483", "5583", Some("B2"), "1-3"));
+    let address = start(state.clone(), HttpOptions::default()).await;
+
+    let mut client = connect(address).await;
+    receive(&mut client).await;
+    let filter = json!({ "type": "filter", "port": "1-2", "event_types": ["Connected"] });
+    client
+        .send(Message::Text(filter.to_string().into()))
+        .await
+        .unwrap();
+    match receive(&mut client).await {
+        ServerMessage::Snapshot { devices, filter } => {
+            assert_eq!(devices.len(), 1);
+            assert_eq!(devices[0].port_path.as_deref(), Some("1-2.1"));
+            assert_eq!(filter.port.as_deref(), Some("1-2"));
+        }
+        other => panic!("expected a snapshot, got {other:?}"),
+    }
+
+    wait_for_subscribers(&state, 1).await;
+    // Neither on the rig's port nor a connect event
+    state.record(&device("0483", "5583", Some("B2"), "1-3"));
+    state.record(&in_port(
+        event("0483", "A1", DeviceEventType::Disconnected),
+        "1-2.1",
+    ));
+    state.record(&device("1366", "5583", Some("C3"), "1-2.4"));
+    match receive(&mut client).await {
+        ServerMessage::Event { event } => assert_eq!(event.vendor_id, "1366"),
+        other => panic!("expected an event, got {other:?}"),
+    }
+
+    client
+        .send(Message::Text("{\"type\":\"subscribe\"}".into()))
+        .await
+        .unwrap();
+    assert!(matches!(
+        receive(&mut client).await,
+        ServerMessage::Error { .. }
+    ));
+}
+
+#[tokio::test]
+async fn test_websocket_only_with_token() {
+    let state = Arc::new(DaemonState::new(100));
+    let options = HttpOptions {
+        token: Some("s3cret".to_string()),
+        websocket_only: true,
+        ..Default::default()
+    };
+    let address = start(state, options).await;
+
+    // Without the token the upgrade is refused
+    assert!(
+        tokio_tungstenite::connect_async(format!("ws://{address}/ws"))
+            .await
+            .is_err()
+    );
+
+    let mut request =
+        tokio_tungstenite::tungstenite::client::IntoClientRequest::into_client_request(format!(
+            "ws://{address}/ws"
+        ))
+        .unwrap();
+    request
+        .headers_mut()
+        .insert("Authorization", "Bearer s3cret".parse().unwrap());
+    let (mut client, _) = tokio_tungstenite::connect_async(request).await.unwrap();
+    assert!(matches!(
+        receive(&mut client).await,
+        ServerMessage::Snapshot { .. }
+    ));
+
+    // Browsers cannot set headers on a WebSocket, so the token can come in the query
+    let (mut client, _) =
+        tokio_tungstenite::connect_async(format!("ws://{address}/ws?access_token=s3cret"))
+            .await
+            .unwrap();
+    assert!(matches!(
+        receive(&mut client).await,
+        ServerMessage::Snapshot { .. }
+    ));
+    assert!(
+        tokio_tungstenite::connect_async(format!("ws://{address}/ws?access_token=wrong"))
+            .await
+            .is_err()
+    );
+
+    // The REST API is not served
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    stream
+        .write_all(
+            b"GET /devices HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer s3cret\r\nConnection: close\r\n\r\n",
+        )
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 404"), "{response}");
+}
+
+#[tokio::test]
+async fn test_cross_origin_upgrades_are_refused() {
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+    use tokio_tungstenite::tungstenite::Error;
+
+    let state = Arc::new(DaemonState::new(100));
+    let options = HttpOptions {
+        allowed_origins: vec!["https://dashboard.example.com".to_string()],
+        ..Default::default()
+    };
+    let address = start(state, options).await;
+    let connect_from = |origin: String| async move {
+        let mut request = format!("ws://{address}/ws").into_client_request().unwrap();
+        request
+            .headers_mut()
+            .insert("Origin", origin.parse().unwrap());
+        tokio_tungstenite::connect_async(request).await
+    };
+
+    // A page on another site cannot read the feed
+    match connect_from("https://evil.example.com".to_string()).await {
+        Err(Error::Http(response)) => assert_eq!(response.status(), 403),
+        other => panic!("expected a refused upgrade, got {other:?}"),
+    }
+
+    // The server's own origin and allowed origins can
+    for origin in [
+        format!("http://{address}"),
+        "https://dashboard.example.com".to_string(),
+    ] {
+        let (mut client, _) = connect_from(origin).await.unwrap();
+        assert!(matches!(
+            receive(&mut client).await,
+            ServerMessage::Snapshot { .. }
+        ));
+    }
+}